@@ -0,0 +1,19 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="tiles" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+ <image source="tiles.png" width="64" height="32"/>
+ <tile id="2">
+  <properties>
+   <property name="collision" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="4">
+  <properties>
+   <property name="collision" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="7" type="crate">
+  <objectgroup draworder="index" id="2">
+   <object id="1" x="2" y="2" width="12" height="12"/>
+  </objectgroup>
+ </tile>
+</tileset>
@@ -0,0 +1,13 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main() {
+    vec4 center = texture(scene, vertex_texture_coords);
+    color = vec4(center.rgb + texture(bloom, vertex_texture_coords).rgb * intensity, center.a);
+}
@@ -0,0 +1,36 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D source;
+// The first downsample keeps only what is brighter than the threshold.
+uniform bool prefilter;
+uniform float threshold;
+
+// 13 taps weighted so the result doesn't flicker when bright pixels move, drawn at half the source size.
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(source, 0));
+    vec2 uv = vertex_texture_coords;
+
+    vec3 a = texture(source, uv + texel * vec2(-2.0, 2.0)).rgb;
+    vec3 b = texture(source, uv + texel * vec2(0.0, 2.0)).rgb;
+    vec3 c = texture(source, uv + texel * vec2(2.0, 2.0)).rgb;
+    vec3 d = texture(source, uv + texel * vec2(-2.0, 0.0)).rgb;
+    vec3 e = texture(source, uv).rgb;
+    vec3 f = texture(source, uv + texel * vec2(2.0, 0.0)).rgb;
+    vec3 g = texture(source, uv + texel * vec2(-2.0, -2.0)).rgb;
+    vec3 h = texture(source, uv + texel * vec2(0.0, -2.0)).rgb;
+    vec3 i = texture(source, uv + texel * vec2(2.0, -2.0)).rgb;
+    vec3 j = texture(source, uv + texel * vec2(-1.0, 1.0)).rgb;
+    vec3 k = texture(source, uv + texel * vec2(1.0, 1.0)).rgb;
+    vec3 l = texture(source, uv + texel * vec2(-1.0, -1.0)).rgb;
+    vec3 m = texture(source, uv + texel * vec2(1.0, -1.0)).rgb;
+
+    vec3 result = e * 0.125 + (a + c + g + i) * 0.03125 + (b + d + f + h) * 0.0625 + (j + k + l + m) * 0.125;
+    if (prefilter) {
+        float brightness = max(result.r, max(result.g, result.b));
+        result *= max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+    }
+    color = vec4(max(result, vec3(0.0)), 1.0);
+}
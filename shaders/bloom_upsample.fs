@@ -0,0 +1,20 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+// The next smaller level, added on top of the level drawn to.
+uniform sampler2D source;
+
+// 3x3 tent filter.
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(source, 0));
+    vec2 uv = vertex_texture_coords;
+
+    vec3 result = texture(source, uv).rgb * 4.0;
+    result += (texture(source, uv + vec2(texel.x, 0.0)).rgb + texture(source, uv - vec2(texel.x, 0.0)).rgb
+             + texture(source, uv + vec2(0.0, texel.y)).rgb + texture(source, uv - vec2(0.0, texel.y)).rgb) * 2.0;
+    result += texture(source, uv + texel).rgb + texture(source, uv - texel).rgb
+            + texture(source, uv + vec2(texel.x, -texel.y)).rgb + texture(source, uv + vec2(-texel.x, texel.y)).rgb;
+    color = vec4(result / 16.0, 1.0);
+}
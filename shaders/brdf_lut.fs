@@ -0,0 +1,44 @@
+#version 330
+
+#define SAMPLES 1024u
+
+in vec2 vertex_position;
+out vec4 color;
+
+float geometry_schlick_ggx(float n_dot_v, float roughness) {
+    // Image based lighting remaps roughness differently than direct lights.
+    float k = roughness * roughness / 2.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+// Scale and bias to the Fresnel reflectance at normal incidence, x along the angle between normal and
+// viewer, y along the roughness. Split sum approximation of the specular BRDF.
+void main() {
+    vec2 coords = vertex_position * 0.5 + 0.5;
+    float n_dot_v = max(coords.x, 0.001);
+    float roughness = coords.y;
+    vec3 to_camera = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+
+    float scale = 0.0;
+    float bias = 0.0;
+    for (uint index = 0u; index < SAMPLES; index++) {
+        vec2 xi = vec2(float(index) / float(SAMPLES), radical_inverse(index));
+        vec3 halfway = importance_sample_ggx(xi, vec3(0.0, 0.0, 1.0), roughness);
+        vec3 to_light = normalize(2.0 * dot(to_camera, halfway) * halfway - to_camera);
+
+        float n_dot_l = max(to_light.z, 0.0);
+        float n_dot_h = max(halfway.z, 0.0);
+        float v_dot_h = max(dot(to_camera, halfway), 0.0);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+
+        float geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+        float visibility = geometry * v_dot_h / (n_dot_h * n_dot_v);
+        float fresnel = pow(1.0 - v_dot_h, 5.0);
+        scale += (1.0 - fresnel) * visibility;
+        bias += fresnel * visibility;
+    }
+
+    color = vec4(scale / float(SAMPLES), bias / float(SAMPLES), 0.0, 1.0);
+}
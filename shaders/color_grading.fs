@@ -0,0 +1,36 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+// Strip of square slices, one per blue value: red along each slice, green from the bottom row up.
+uniform sampler2D lut;
+// 0 keeps the colors, 1 applies the LUT fully.
+uniform float strength;
+
+vec3 to_srgb(vec3 linear) {
+    return mix(linear * 12.92, 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055, step(vec3(0.0031308), linear));
+}
+
+vec3 to_linear(vec3 srgb) {
+    return mix(srgb / 12.92, pow((srgb + 0.055) / 1.055, vec3(2.4)), step(vec3(0.04045), srgb));
+}
+
+vec3 lookup(vec3 srgb, float slice, float size) {
+    vec2 coords = vec2((slice * size + srgb.r * (size - 1.0) + 0.5) / (size * size), (srgb.g * (size - 1.0) + 0.5) / size);
+    return texture(lut, coords).rgb;
+}
+
+// LUTs are made in sRGB, so colors are looked up sRGB encoded. Blue blends between the two nearest slices.
+void main() {
+    vec4 center = texture(scene, vertex_texture_coords);
+    vec3 srgb = to_srgb(clamp(center.rgb, 0.0, 1.0));
+
+    float size = float(textureSize(lut, 0).y);
+    float blue = srgb.b * (size - 1.0);
+    float slice = floor(blue);
+    vec3 graded = mix(lookup(srgb, slice, size), lookup(srgb, min(slice + 1.0, size - 1.0), size), blue - slice);
+
+    color = vec4(mix(center.rgb, to_linear(graded), strength), center.a);
+}
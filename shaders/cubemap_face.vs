@@ -0,0 +1,9 @@
+#version 330
+
+in vec2 position;
+out vec2 vertex_position;
+
+void main() {
+    vertex_position = position;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
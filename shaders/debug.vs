@@ -0,0 +1,13 @@
+#version 330
+
+uniform mat4 view_projection;
+
+in vec3 position;
+in vec4 color;
+
+out vec4 vertex_color;
+
+void main() {
+    vertex_color = color;
+    gl_Position = view_projection * vec4(position, 1.0);
+}
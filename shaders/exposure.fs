@@ -0,0 +1,13 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+// Factor the HDR colors are scaled by, 2 to the power of the exposure in stops.
+uniform float exposure;
+
+void main() {
+    vec4 center = texture(scene, vertex_texture_coords);
+    color = vec4(center.rgb * exposure, center.a);
+}
@@ -0,0 +1,43 @@
+#version 330
+
+#define REDUCE_MIN (1.0 / 128.0)
+#define REDUCE_MUL (1.0 / 8.0)
+#define SPAN_MAX 8.0
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+
+float luma(vec3 rgb) {
+    // Perceptual brightness, so edges are found where they are visible.
+    return sqrt(dot(clamp(rgb, 0.0, 1.0), vec3(0.299, 0.587, 0.114)));
+}
+
+// Blurs along edges found from the luma of the neighbours, for anti-aliasing after tonemapping.
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(scene, 0));
+    vec2 uv = vertex_texture_coords;
+    vec4 center = texture(scene, uv);
+
+    float luma_nw = luma(texture(scene, uv + vec2(-1.0, 1.0) * texel).rgb);
+    float luma_ne = luma(texture(scene, uv + vec2(1.0, 1.0) * texel).rgb);
+    float luma_sw = luma(texture(scene, uv + vec2(-1.0, -1.0) * texel).rgb);
+    float luma_se = luma(texture(scene, uv + vec2(1.0, -1.0) * texel).rgb);
+    float luma_m = luma(center.rgb);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Along the edge, perpendicular to the luma gradient.
+    vec2 direction = vec2((luma_sw + luma_se) - (luma_nw + luma_ne), (luma_ne + luma_se) - (luma_nw + luma_sw));
+    float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+    vec3 near = 0.5 * (texture(scene, uv + direction * (1.0 / 3.0 - 0.5)).rgb + texture(scene, uv + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 far = near * 0.5 + 0.25 * (texture(scene, uv - direction * 0.5).rgb + texture(scene, uv + direction * 0.5).rgb);
+
+    // The wider blur is only kept when it stays within the local contrast, otherwise it crossed an edge.
+    float luma_far = luma(far);
+    color = vec4(luma_far < luma_min || luma_far > luma_max ? near : far, center.a);
+}
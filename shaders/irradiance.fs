@@ -0,0 +1,37 @@
+#version 330
+
+#define PI 3.14159265359
+
+in vec2 vertex_position;
+out vec4 color;
+
+uniform samplerCube environment;
+// Basis of the cubemap face being drawn.
+uniform vec3 face_forward;
+uniform vec3 face_right;
+uniform vec3 face_up;
+// Mip level of the environment that is about as detailed as the steps below.
+uniform float source_level;
+
+// Averages the light arriving from the hemisphere around each direction, weighted by the cosine, so
+// diffuse surfaces can look up their lighting with one sample.
+void main() {
+    vec3 normal = normalize(face_forward + vertex_position.x * face_right + vertex_position.y * face_up);
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    const float step_size = 0.05;
+    vec3 sum = vec3(0.0);
+    float samples = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += step_size) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += step_size) {
+            vec3 tangent = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 direction = tangent.x * right + tangent.y * up + tangent.z * normal;
+            sum += textureLod(environment, direction, source_level).rgb * cos(theta) * sin(theta);
+            samples += 1.0;
+        }
+    }
+
+    color = vec4(PI * sum / samples, 1.0);
+}
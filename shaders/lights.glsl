@@ -0,0 +1,106 @@
+// Lights and shadows shared by simple.fs and pbr.fs, inserted after their #version line when their
+// programs are created.
+
+// Have to match MAX_LIGHTS in src/graphics/lighting.rs and the ones in src/graphics/shadow.rs.
+#define MAX_LIGHTS 32
+#define MAX_CASCADES 4
+#define MAX_SPOT_SHADOWS 4
+#define DIRECTIONAL 0
+#define POINT 1
+#define SPOT 2
+
+struct Light {
+    vec4 position;
+    vec4 direction;
+    vec4 color;
+    vec4 cone;
+};
+
+uniform vec3 camera_position;
+layout(std140) uniform Lights {
+    Light lights[MAX_LIGHTS];
+    vec4 ambient;
+    int light_count;
+};
+layout(std140) uniform Shadows {
+    // The cascades' view projections, then the spot lights'.
+    mat4 light_matrices[MAX_CASCADES + MAX_SPOT_SHADOWS];
+    vec4 cascade_splits;
+    vec4 camera_forward;
+    // Depth bias, normal bias and PCF radius.
+    vec4 shadow_filter;
+    // Cascades and spot light maps rendered this frame.
+    ivec4 shadow_counts;
+};
+uniform sampler2DArrayShadow cascade_maps;
+uniform sampler2DArrayShadow spot_maps;
+uniform bool receive_shadows;
+
+// Quads are seen from both sides, so the back is lit as if it faced the viewer too.
+vec3 facing_normal(vec3 normal) {
+    return normalize(gl_FrontFacing ? normal : -normal);
+}
+
+// Fades smoothly to 0 at the light's range and falls off with the square of the distance before that.
+float attenuation(float distance, float range) {
+    float window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return window * window / (distance * distance + 1.0);
+}
+
+// How strongly `light` shines on `position` before shadows, and the direction towards it.
+float light_strength(Light light, int kind, vec3 position, out vec3 to_light) {
+    if (kind == DIRECTIONAL) {
+        to_light = -light.direction.xyz;
+        return 1.0;
+    }
+    vec3 offset = light.position.xyz - position;
+    float distance = length(offset);
+    to_light = offset / max(distance, 0.0001);
+    float strength = attenuation(distance, light.direction.w);
+    if (kind == SPOT) {
+        strength *= smoothstep(light.cone.y, light.cone.x, dot(-to_light, light.direction.xyz));
+    }
+    return strength;
+}
+
+// Percentage closer filtering, the fraction of the texels around `position` in `layer` that see the light.
+float filtered_shadow(sampler2DArrayShadow maps, int layer, mat4 light_view_projection, vec3 position) {
+    vec4 clip = light_view_projection * vec4(position, 1.0);
+    vec3 coords = clip.xyz / clip.w * 0.5 + 0.5;
+    if (any(lessThan(coords, vec3(0.0))) || any(greaterThan(coords, vec3(1.0)))) {
+        return 1.0;
+    }
+
+    vec2 texel = 1.0 / vec2(textureSize(maps, 0).xy);
+    int radius = int(shadow_filter.z);
+    float lit = 0.0;
+    for (int x = -radius; x <= radius; x++) {
+        for (int y = -radius; y <= radius; y++) {
+            lit += texture(maps, vec4(coords.xy + vec2(x, y) * texel, float(layer), coords.z - shadow_filter.x));
+        }
+    }
+    return lit / float((2 * radius + 1) * (2 * radius + 1));
+}
+
+// How much of a light with the shadow map `slot` reaches `position`, 1 where nothing is in the way.
+float shadow(int kind, int slot, vec3 position, vec3 normal, vec3 to_light) {
+    if (!receive_shadows || slot < 0) {
+        return 1.0;
+    }
+    // Moving the position off the surface keeps it from shadowing itself, more so at grazing angles.
+    vec3 offset_position = position + normal * shadow_filter.y * (1.0 - max(dot(normal, to_light), 0.0));
+
+    if (kind == DIRECTIONAL) {
+        float depth = dot(position - camera_position, camera_forward.xyz);
+        for (int cascade = 0; cascade < min(shadow_counts.x, MAX_CASCADES); cascade++) {
+            if (depth < cascade_splits[cascade]) {
+                return filtered_shadow(cascade_maps, cascade, light_matrices[cascade], offset_position);
+            }
+        }
+        return 1.0;
+    }
+    if (slot >= shadow_counts.y) {
+        return 1.0;
+    }
+    return filtered_shadow(spot_maps, slot, light_matrices[MAX_CASCADES + slot], offset_position);
+}
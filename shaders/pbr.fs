@@ -0,0 +1,108 @@
+#version 430
+
+layout(location = 0) in vec3 vertex_position;
+layout(location = 1) in vec3 vertex_normal;
+layout(location = 2) in vec2 vertex_texture_coords;
+layout(location = 0) out vec4 color;
+
+// Maps and factors with the meaning they have in glTF.
+uniform sampler2D base_color_texture;
+uniform sampler2D metallic_roughness_texture;
+uniform sampler2D normal_texture;
+uniform sampler2D occlusion_texture;
+uniform sampler2D emissive_texture;
+uniform vec4 base_color_factor;
+uniform float metallic_factor;
+uniform float roughness_factor;
+uniform vec3 emissive_factor;
+uniform float normal_scale;
+uniform float occlusion_strength;
+
+uniform samplerCube irradiance_map;
+uniform samplerCube prefiltered_map;
+uniform sampler2D brdf_lut;
+// Highest mip level of the prefiltered map, the one for roughness 1.
+uniform float prefiltered_max_level;
+uniform float environment_intensity;
+
+// Tangent frame from the screen space derivatives of position and texture coordinates, so meshes don't
+// need tangents for normal maps.
+mat3 cotangent_frame(vec3 normal, vec3 position, vec2 coords) {
+    vec3 dp1 = dFdx(position);
+    vec3 dp2 = dFdy(position);
+    vec2 duv1 = dFdx(coords);
+    vec2 duv2 = dFdy(coords);
+
+    vec3 dp2_perp = cross(dp2, normal);
+    vec3 dp1_perp = cross(normal, dp1);
+    vec3 tangent = dp2_perp * duv1.x + dp1_perp * duv2.x;
+    vec3 bitangent = dp2_perp * duv1.y + dp1_perp * duv2.y;
+    float scale = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+    return mat3(tangent * scale, bitangent * scale, normal);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main() {
+    vec4 base_color = texture(base_color_texture, vertex_texture_coords) * base_color_factor;
+    vec4 metallic_roughness = texture(metallic_roughness_texture, vertex_texture_coords);
+    float metallic = clamp(metallic_roughness.b * metallic_factor, 0.0, 1.0);
+    // Perfectly smooth surfaces turn highlights into single pixels.
+    float roughness = clamp(metallic_roughness.g * roughness_factor, 0.04, 1.0);
+    float occlusion = mix(1.0, texture(occlusion_texture, vertex_texture_coords).r, occlusion_strength);
+    vec3 emissive = texture(emissive_texture, vertex_texture_coords).rgb * emissive_factor;
+
+    vec3 geometry_normal = facing_normal(vertex_normal);
+    vec3 mapped = texture(normal_texture, vertex_texture_coords).xyz * 2.0 - 1.0;
+    mapped.xy *= normal_scale;
+    vec3 normal = normalize(cotangent_frame(geometry_normal, vertex_position, vertex_texture_coords) * mapped);
+
+    vec3 to_camera = normalize(camera_position - vertex_position);
+    float n_dot_v = max(dot(normal, to_camera), 0.0001);
+    vec3 f0 = mix(vec3(0.04), base_color.rgb, metallic);
+    vec3 diffuse_color = base_color.rgb * (1.0 - metallic);
+
+    vec3 direct = vec3(0.0);
+    for (int index = 0; index < min(light_count, MAX_LIGHTS); index++) {
+        Light light = lights[index];
+        int kind = int(light.position.w);
+
+        vec3 to_light;
+        float strength = light_strength(light, kind, vertex_position, to_light);
+        strength *= shadow(kind, int(light.cone.z), vertex_position, geometry_normal, to_light);
+
+        float n_dot_l = dot(normal, to_light);
+        if (n_dot_l <= 0.0 || strength <= 0.0) {
+            continue;
+        }
+        vec3 halfway = normalize(to_light + to_camera);
+        vec3 fresnel = fresnel_schlick(max(dot(halfway, to_camera), 0.0), f0);
+        vec3 specular = fresnel * distribution_ggx(max(dot(normal, halfway), 0.0), roughness) * geometry_smith(n_dot_v, n_dot_l, roughness)
+            / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        vec3 diffuse = (1.0 - fresnel) * diffuse_color / PI;
+        direct += (diffuse + specular) * light.color.rgb * strength * n_dot_l;
+    }
+
+    // Light from the environment, split into a diffuse part from the irradiance map and a specular part
+    // from the prefiltered map and the BRDF lookup table.
+    vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    vec3 irradiance = texture(irradiance_map, normal).rgb;
+    vec3 reflected = textureLod(prefiltered_map, reflect(-to_camera, normal), roughness * prefiltered_max_level).rgb;
+    vec2 brdf = texture(brdf_lut, vec2(n_dot_v, roughness)).rg;
+    vec3 indirect = ((1.0 - fresnel) * diffuse_color * irradiance + reflected * (fresnel * brdf.x + brdf.y)) * environment_intensity;
+    indirect += ambient.rgb * diffuse_color;
+
+    vec3 lit = direct + indirect * occlusion + emissive;
+    color = vec4(lit * base_color.a, base_color.a);
+}
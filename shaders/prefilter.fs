@@ -0,0 +1,47 @@
+#version 330
+
+#define SAMPLES 512u
+
+in vec2 vertex_position;
+out vec4 color;
+
+uniform samplerCube environment;
+uniform vec3 face_forward;
+uniform vec3 face_right;
+uniform vec3 face_up;
+uniform float roughness;
+// Face size of the environment's base level.
+uniform float source_size;
+
+// Blurs the environment by the GGX lobe of `roughness`, assuming the viewer looks along the normal.
+void main() {
+    vec3 normal = normalize(face_forward + vertex_position.x * face_right + vertex_position.y * face_up);
+    if (roughness <= 0.0) {
+        color = vec4(textureLod(environment, normal, 0.0).rgb, 1.0);
+        return;
+    }
+
+    vec3 sum = vec3(0.0);
+    float weight = 0.0;
+    for (uint index = 0u; index < SAMPLES; index++) {
+        vec2 xi = vec2(float(index) / float(SAMPLES), radical_inverse(index));
+        vec3 halfway = importance_sample_ggx(xi, normal, roughness);
+        vec3 to_light = normalize(2.0 * dot(normal, halfway) * halfway - normal);
+        float n_dot_l = dot(normal, to_light);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+
+        // Samples that stand for a large solid angle read from a blurrier level, which avoids bright dots.
+        float n_dot_h = max(dot(normal, halfway), 0.0);
+        float pdf = distribution_ggx(n_dot_h, roughness) / 4.0 + 0.0001;
+        float sample_angle = 1.0 / (float(SAMPLES) * pdf + 0.0001);
+        float texel_angle = 4.0 * PI / (6.0 * source_size * source_size);
+        float level = 0.5 * log2(sample_angle / texel_angle) + 1.0;
+
+        sum += textureLod(environment, to_light, max(level, 0.0)).rgb * n_dot_l;
+        weight += n_dot_l;
+    }
+
+    color = vec4(sum / weight, 1.0);
+}
@@ -0,0 +1,5 @@
+#version 330
+
+// Only depth is written.
+void main() {
+}
@@ -0,0 +1,9 @@
+#version 330
+
+in vec3 position;
+
+uniform mat4 light_view_projection;
+
+void main() {
+    gl_Position = light_view_projection * vec4(position, 1.0);
+}
@@ -1,19 +1,43 @@
 #version 430
 
+layout(location = 0) in vec3 vertex_position;
+layout(location = 1) in vec3 vertex_normal;
 layout(location = 2) in vec2 vertex_texture_coords;
 layout(location = 0) out vec4 color;
 
 uniform sampler2D texture_2d;
 uniform Material {
     vec3 color_override;
+    float shininess;
+    float specular;
 };
 
 void main() {
     vec4 texture_color = texture(texture_2d, vertex_texture_coords);
+    vec3 albedo = color_override.r < 0.0 ? texture_color.rgb : color_override;
 
-    if (color_override.r < 0.0) {
-        color = vec4(texture_color.rgb * texture_color.a, texture_color.a);
-    } else {
-        color = vec4(color_override * texture_color.a, texture_color.a);
+    vec3 normal = facing_normal(vertex_normal);
+    vec3 to_camera = normalize(camera_position - vertex_position);
+
+    vec3 diffuse = ambient.rgb;
+    vec3 highlights = vec3(0.0);
+    for (int index = 0; index < min(light_count, MAX_LIGHTS); index++) {
+        Light light = lights[index];
+        int kind = int(light.position.w);
+
+        vec3 to_light;
+        float strength = light_strength(light, kind, vertex_position, to_light);
+        strength *= shadow(kind, int(light.cone.z), vertex_position, normal, to_light);
+
+        float lambert = max(dot(normal, to_light), 0.0);
+        if (lambert <= 0.0 || strength <= 0.0) {
+            continue;
+        }
+        vec3 halfway = normalize(to_light + to_camera);
+        diffuse += light.color.rgb * lambert * strength;
+        highlights += light.color.rgb * pow(max(dot(normal, halfway), 0.0), shininess) * specular * strength;
     }
-}
\ No newline at end of file
+
+    vec3 lit = albedo * diffuse + highlights;
+    color = vec4(lit * texture_color.a, texture_color.a);
+}
@@ -0,0 +1,11 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+in vec4 vertex_color;
+out vec4 color;
+
+uniform sampler2D sprite_texture;
+
+void main() {
+    color = texture(sprite_texture, vertex_texture_coords) * vertex_color;
+}
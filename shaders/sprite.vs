@@ -0,0 +1,28 @@
+#version 330
+
+uniform mat4 view_projection;
+
+// Corner of the unit quad, (0, 0) is the bottom left.
+in vec2 corner;
+
+in vec2 sprite_position;
+in vec2 sprite_size;
+in vec2 sprite_origin;
+in float sprite_rotation;
+in vec2 sprite_uv_min;
+in vec2 sprite_uv_max;
+in vec4 sprite_color;
+
+out vec2 vertex_texture_coords;
+out vec4 vertex_color;
+
+void main() {
+    vec2 local = (corner - sprite_origin) * sprite_size;
+    float c = cos(sprite_rotation);
+    float s = sin(sprite_rotation);
+    vec2 world = sprite_position + vec2(c * local.x - s * local.y, s * local.x + c * local.y);
+
+    vertex_texture_coords = mix(sprite_uv_min, sprite_uv_max, corner);
+    vertex_color = sprite_color;
+    gl_Position = view_projection * vec4(world, 0.0, 1.0);
+}
@@ -0,0 +1,18 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+in vec4 vertex_color;
+out vec4 color;
+
+uniform sampler2D glyphs;
+// Outline glyphs are coverage in the red channel, bitmap font pages are colored images.
+uniform bool coverage;
+
+void main() {
+    vec4 texel = texture(glyphs, vertex_texture_coords);
+    if (coverage) {
+        color = vec4(vertex_color.rgb, vertex_color.a * texel.r);
+    } else {
+        color = texel * vertex_color;
+    }
+}
@@ -0,0 +1,16 @@
+#version 330
+
+uniform mat4 view_projection;
+
+in vec2 position;
+in vec2 texture_coords;
+in vec4 color;
+
+out vec2 vertex_texture_coords;
+out vec4 vertex_color;
+
+void main() {
+    vertex_texture_coords = texture_coords;
+    vertex_color = color;
+    gl_Position = view_projection * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,12 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D tileset;
+uniform float opacity;
+
+void main() {
+    vec4 texel = texture(tileset, vertex_texture_coords);
+    color = vec4(texel.rgb, texel.a * opacity);
+}
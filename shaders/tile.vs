@@ -0,0 +1,13 @@
+#version 330
+
+uniform mat4 view_projection;
+
+in vec2 position;
+in vec2 texture_coords;
+
+out vec2 vertex_texture_coords;
+
+void main() {
+    vertex_texture_coords = texture_coords;
+    gl_Position = view_projection * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,23 @@
+#version 330
+
+#define REINHARD 0
+#define ACES 1
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+uniform int operator;
+
+// Narkowicz's fit of the ACES filmic curve.
+vec3 aces(vec3 x) {
+    return clamp(x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// Maps HDR colors into the 0 to 1 range the window shows.
+void main() {
+    vec4 center = texture(scene, vertex_texture_coords);
+    vec3 hdr = max(center.rgb, vec3(0.0));
+    vec3 mapped = operator == ACES ? aces(hdr) : hdr / (1.0 + hdr);
+    color = vec4(mapped, center.a);
+}
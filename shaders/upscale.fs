@@ -0,0 +1,30 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+// 0 leaves the bilinear result as it is, 1 sharpens the most.
+uniform float sharpness;
+
+void main() {
+    vec4 center = texture(scene, vertex_texture_coords);
+    if (sharpness <= 0.0) {
+        color = center;
+        return;
+    }
+
+    // Unsharp mask over the source texel neighbours, clamped to their range so edges don't ring.
+    vec2 texel = 1.0 / vec2(textureSize(scene, 0));
+    vec3 left = texture(scene, vertex_texture_coords - vec2(texel.x, 0.0)).rgb;
+    vec3 right = texture(scene, vertex_texture_coords + vec2(texel.x, 0.0)).rgb;
+    vec3 up = texture(scene, vertex_texture_coords + vec2(0.0, texel.y)).rgb;
+    vec3 down = texture(scene, vertex_texture_coords - vec2(0.0, texel.y)).rgb;
+
+    vec3 blurred = (left + right + up + down) * 0.25;
+    vec3 sharpened = center.rgb + (center.rgb - blurred) * sharpness * 2.0;
+    vec3 low = min(center.rgb, min(min(left, right), min(up, down)));
+    vec3 high = max(center.rgb, max(max(left, right), max(up, down)));
+
+    color = vec4(clamp(sharpened, low, high), center.a);
+}
@@ -0,0 +1,17 @@
+#version 330
+
+in vec2 vertex_texture_coords;
+out vec4 color;
+
+uniform sampler2D scene;
+// How dark the corners get, 0 to 1.
+uniform float intensity;
+// Fraction of the way from the center to the corners the darkening fades in over.
+uniform float smoothness;
+
+void main() {
+    vec4 center = texture(scene, vertex_texture_coords);
+    float distance = length(vertex_texture_coords - 0.5) * sqrt(2.0);
+    float darkening = smoothstep(1.0 - smoothness, 1.0, distance) * intensity;
+    color = vec4(center.rgb * (1.0 - darkening), center.a);
+}
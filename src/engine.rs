@@ -0,0 +1,162 @@
+use std::time::Instant;
+use glium::Surface;
+use winit::event::{Event, WindowEvent};
+
+use crate::graphics::capture::Capture;
+use crate::graphics::post::{PostProcessor, PostStack};
+use crate::graphics::scaling::Scaler;
+use crate::graphics::window::{Window, WindowConfig};
+use crate::graphics::window_manager::{WindowHandle, WindowManager};
+use crate::input::state::Input;
+
+/// State owned by the engine and handed to every `App` callback.
+pub struct Context {
+    /// The main window, closing it ends the game.
+    pub window: Window,
+    pub windows: WindowManager,
+    pub input: Input,
+    pub capture: Capture,
+    /// Post-processing applied to what `App::render` draws, empty to render straight to the window.
+    pub post: PostStack,
+    exit_requested: bool
+}
+
+impl Context {
+    /// Asks the engine to stop after the current event, `App::shutdown` is still called.
+    pub fn exit(&mut self) { self.exit_requested = true; }
+}
+
+/// Implemented by games to plug into the engine loop, every callback has an empty default.
+pub trait App {
+    fn init(&mut self, _context: &mut Context) {}
+    fn update(&mut self, _context: &mut Context, _delta_time: f32) {}
+    fn render<S: Surface>(&mut self, _context: &mut Context, _target: &mut S) {}
+    /// Draws on top of the post-processed frame once it is scaled to the window, for UI that shouldn't be
+    /// tonemapped, bloomed or drawn at a lower render resolution.
+    fn render_overlay<S: Surface>(&mut self, _context: &mut Context, _target: &mut S) {}
+    fn on_event(&mut self, _context: &mut Context, _event: &WindowEvent) {}
+    /// Events of secondary windows, they don't feed `Context::input`.
+    fn on_window_event(&mut self, _context: &mut Context, _window: WindowHandle, _event: &WindowEvent) {}
+    /// Draws a secondary window, called after `render` whenever that window needs a redraw.
+    fn render_window<S: Surface>(&mut self, _context: &mut Context, _window: WindowHandle, _target: &mut S) {}
+    fn shutdown(&mut self, _context: &mut Context) {}
+}
+
+pub struct Engine;
+
+/// Renders the frame into `target`, through the HDR target of `post` when the stack has passes.
+fn render_frame<A: App, S: Surface>(app: &mut A, context: &mut Context, post: &mut PostProcessor, target: &mut S) {
+    if context.post.passes.is_empty() {
+        app.render(context, target);
+    } else {
+        let display = context.window.display.clone();
+        let (width, height) = target.get_dimensions();
+        post.prepare(&display, [width, height]);
+        app.render(context, &mut post.framebuffer(&display));
+        post.apply(&display, &context.post, target);
+    }
+}
+
+impl Engine {
+    /// Opens the window described by `config` and drives `app` until the window closes.
+    pub fn run<A: App + 'static>(config: WindowConfig, mut app: A) -> Result<(), winit::error::EventLoopError> {
+        let event_loop = winit::event_loop::EventLoopBuilder::new().build()?;
+
+        let window = Window::new(config, &event_loop, None);
+        let mut context = Context {
+            windows: WindowManager::new(window.display.clone()),
+            window,
+            input: Input::default(),
+            capture: Capture::default(),
+            post: PostStack::default(),
+            exit_requested: false
+        };
+
+        let mut scaler = Scaler::new(&context.window.display);
+        let mut post = PostProcessor::new(&context.window.display);
+
+        app.init(&mut context);
+
+        let mut last_frame_update = Instant::now();
+
+        return event_loop.run(move |event, control_flow| {
+            match event {
+                Event::AboutToWait => {
+                    context.window.winit_window.request_redraw();
+                    context.windows.request_redraw();
+                },
+                Event::WindowEvent { window_id, event } if window_id != context.window.winit_window.id() => {
+                    let Some(handle) = context.windows.handle_of(window_id) else { return; };
+
+                    context.windows.handle_event(handle, &event);
+                    app.on_window_event(&mut context, handle, &event);
+
+                    match event {
+                        WindowEvent::CloseRequested => context.windows.close(handle),
+                        WindowEvent::RedrawRequested => {
+                            let Some(window) = context.windows.get(handle) else { return; };
+
+                            let mut frame = window.display.draw();
+                            app.render_window(&mut context, handle, &mut frame);
+                            frame.finish().unwrap();
+                        },
+                        _ => ()
+                    }
+                },
+                Event::WindowEvent { event, .. } => {
+                    context.window.handle_event(&event);
+                    context.input.handle_event(&event);
+                    app.on_event(&mut context, &event);
+
+                    match event {
+                        WindowEvent::CloseRequested => context.exit(),
+                        WindowEvent::Resized(window_size) => { context.window.display.resize(window_size.into()); },
+                        WindowEvent::RedrawRequested => {
+                            let delta_time = context.capture.timestep().unwrap_or(last_frame_update.elapsed().as_secs_f32());
+                            last_frame_update = Instant::now();
+
+                            context.input.gamepads.update();
+                            app.update(&mut context, delta_time);
+
+                            let display = context.window.display.clone();
+                            let mut frame = display.draw();
+                            let window_size = frame.get_dimensions();
+                            let render_size = scaler.render_size(context.window.render_resolution, [window_size.0, window_size.1]);
+
+                            scaler.prepare(&display, render_size, [window_size.0, window_size.1]);
+                            match scaler.framebuffer(&display) {
+                                Some(mut target) => render_frame(&mut app, &mut context, &mut post, &mut target),
+                                None => render_frame(&mut app, &mut context, &mut post, &mut frame)
+                            }
+                            scaler.present(&mut frame, context.window.upscale_filter);
+                            app.render_overlay(&mut context, &mut frame);
+
+                            if context.capture.screenshot_action.as_ref().is_some_and(|action| context.input.action_pressed(action)) {
+                                context.capture.take_screenshot();
+                            }
+                            context.capture.capture(&display, &frame);
+
+                            // Waiting for the GPU leaves the vsync wait out of the measured frame time.
+                            if context.window.dynamic_resolution.is_some() { display.finish(); }
+                            scaler.update(context.window.dynamic_resolution.as_ref(), last_frame_update.elapsed().as_secs_f32());
+                            frame.finish().unwrap();
+
+                            context.input.end_frame();
+                        },
+                        _ => ()
+                    }
+                },
+                Event::DeviceEvent { event, .. } => context.input.handle_device_event(&event),
+                Event::LoopExiting => {
+                    // Images still being written finish first, so `shutdown` sees every failed save.
+                    context.capture.finish();
+                    app.shutdown(&mut context);
+                },
+                _ => ()
+            }
+
+            context.windows.open_pending(control_flow);
+            if context.exit_requested { control_flow.exit(); }
+        });
+    }
+}
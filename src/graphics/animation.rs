@@ -0,0 +1,317 @@
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+
+use super::atlas::{Atlas, AtlasRegion};
+use super::sprite::Sprite;
+
+/// One picture of a clip.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct AnimationFrame {
+    /// Part of the texture shown, bottom left and top right like `Sprite`.
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    /// Seconds the frame stays on screen.
+    pub duration: f32,
+    /// Reported by `Animator::events` when the frame is reached, e.g. "footstep".
+    #[serde(default)]
+    pub event: Option<String>
+}
+
+impl AnimationFrame {
+    pub fn from_region(region: &AtlasRegion, duration: f32) -> AnimationFrame {
+        return AnimationFrame { uv_min: region.uv_min, uv_max: region.uv_max, duration, event: None };
+    }
+}
+
+/// What happens after the last frame.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PlayMode {
+    /// Stops on the last frame.
+    Once,
+    /// Starts over at the first frame.
+    Loop,
+    /// Plays backwards to the first frame, then forwards again.
+    PingPong
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>,
+    pub mode: PlayMode
+}
+
+impl AnimationClip {
+    /// Clip from a sprite sheet laid out as a grid of `frame_size` pixel cells, numbered left to right and
+    /// top to bottom starting at 0. Takes `count` cells starting at `first`, `None` if they run past the last cell.
+    pub fn from_grid(sheet_size: [u32; 2], frame_size: [u32; 2], first: u32, count: u32, frame_duration: f32, mode: PlayMode) -> Option<AnimationClip> {
+        let columns = (sheet_size[0] / frame_size[0].max(1)).max(1);
+        let rows = sheet_size[1] / frame_size[1].max(1);
+        let end = first.checked_add(count)?;
+        if end > columns.checked_mul(rows)? { return None; }
+        let (sheet_width, sheet_height) = (sheet_size[0] as f32, sheet_size[1] as f32);
+
+        let frames = (first..end).map(|cell| {
+            let (x, y) = ((cell % columns * frame_size[0]) as f32, (cell / columns * frame_size[1]) as f32);
+            return AnimationFrame {
+                uv_min: [x / sheet_width, 1.0 - (y + frame_size[1] as f32) / sheet_height],
+                uv_max: [(x + frame_size[0] as f32) / sheet_width, 1.0 - y / sheet_height],
+                duration: frame_duration,
+                event: None
+            };
+        }).collect();
+
+        return Some(AnimationClip { frames, mode });
+    }
+
+    /// Clip from named atlas regions in order, `None` if one of them is missing.
+    pub fn from_regions(atlas: &Atlas, names: &[&str], frame_duration: f32, mode: PlayMode) -> Option<AnimationClip> {
+        let frames = names.iter().map(|name| Some(AnimationFrame::from_region(atlas.get(name)?, frame_duration))).collect::<Option<_>>()?;
+        return Some(AnimationClip { frames, mode });
+    }
+
+    /// Clip from every atlas region named `prefix` followed by a number, ordered by that number so
+    /// `run_2` comes before `run_10`.
+    pub fn from_prefix(atlas: &Atlas, prefix: &str, frame_duration: f32, mode: PlayMode) -> AnimationClip {
+        let mut regions: Vec<_> = atlas.regions.iter()
+            .filter_map(|(name, region)| Some((name.strip_prefix(prefix)?.parse::<u32>().ok()?, region)))
+            .collect();
+        regions.sort_by_key(|(number, _)| *number);
+
+        let frames = regions.into_iter().map(|(_, region)| AnimationFrame::from_region(region, frame_duration)).collect();
+        return AnimationClip { frames, mode };
+    }
+
+    /// Attaches `event` to the frame at `index`.
+    pub fn with_event(mut self, index: usize, event: impl Into<String>) -> AnimationClip {
+        if let Some(frame) = self.frames.get_mut(index) { frame.event = Some(event.into()); }
+        return self;
+    }
+
+    /// Seconds for one pass through the frames.
+    pub fn duration(&self) -> f32 { self.frames.iter().map(|frame| frame.duration).sum() }
+}
+
+/// Plays an `AnimationClip`, advanced by `update` with the game's delta time.
+pub struct Animator {
+    clip: Option<Rc<AnimationClip>>,
+    frame: usize,
+    time: f32,
+    backwards: bool,
+    finished: bool,
+    /// The current frame's event still has to be reported, set when a clip starts.
+    entered: bool,
+    events: Vec<String>,
+    /// Playback rate, 2 plays twice as fast.
+    pub speed: f32,
+    pub paused: bool
+}
+
+impl Default for Animator {
+    fn default() -> Animator {
+        return Animator {
+            clip: None,
+            frame: 0,
+            time: 0.0,
+            backwards: false,
+            finished: false,
+            entered: false,
+            events: Vec::new(),
+            speed: 1.0,
+            paused: false
+        };
+    }
+}
+
+impl Animator {
+    pub fn new() -> Animator { Animator::default() }
+
+    /// Switches to `clip` from its first frame. Playing the clip that is already playing changes nothing,
+    /// use `restart` for that.
+    pub fn play(&mut self, clip: &Rc<AnimationClip>) {
+        if self.clip.as_ref().is_some_and(|current| Rc::ptr_eq(current, clip)) { return; }
+        self.clip = Some(clip.clone());
+        self.restart();
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.time = 0.0;
+        self.backwards = false;
+        self.finished = false;
+        self.entered = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.clip = None;
+        self.events.clear();
+    }
+
+    pub fn clip(&self) -> Option<&Rc<AnimationClip>> { self.clip.as_ref() }
+
+    pub fn frame_index(&self) -> usize { self.frame }
+
+    pub fn frame(&self) -> Option<&AnimationFrame> { self.clip.as_ref()?.frames.get(self.frame) }
+
+    /// A `PlayMode::Once` clip reached the end of its last frame.
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    /// Events of the frames reached during the last `update`, in order.
+    pub fn events(&self) -> &[String] { &self.events }
+
+    fn next_frame(&mut self, clip: &AnimationClip) -> Option<usize> {
+        let last = clip.frames.len() - 1;
+        return match clip.mode {
+            PlayMode::Once => (self.frame < last).then_some(self.frame + 1),
+            PlayMode::Loop => Some(if self.frame < last { self.frame + 1 } else { 0 }),
+            PlayMode::PingPong if last == 0 => Some(0),
+            PlayMode::PingPong => {
+                if self.backwards && self.frame == 0 || !self.backwards && self.frame == last { self.backwards = !self.backwards; }
+                Some(if self.backwards { self.frame - 1 } else { self.frame + 1 })
+            }
+        };
+    }
+
+    /// Advances by `delta_time` seconds, scaled by `speed`.
+    pub fn update(&mut self, delta_time: f32) {
+        self.events.clear();
+        let Some(clip) = self.clip.clone() else { return; };
+        if self.paused || self.finished || clip.frames.is_empty() { return; }
+
+        if self.entered {
+            self.entered = false;
+            self.events.extend(clip.frames[self.frame].event.clone());
+        }
+
+        // A clip without any duration would never let time run out.
+        if clip.duration() <= 0.0 { return; }
+
+        self.time += delta_time * self.speed.max(0.0);
+        while self.time >= clip.frames[self.frame].duration {
+            self.time -= clip.frames[self.frame].duration;
+            match self.next_frame(&clip) {
+                Some(frame) => {
+                    self.frame = frame;
+                    self.events.extend(clip.frames[frame].event.clone());
+                },
+                None => {
+                    self.time = clip.frames[self.frame].duration;
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Shows the current frame on `sprite`, leaves it alone when nothing is playing.
+    pub fn apply(&self, sprite: &mut Sprite) {
+        if let Some(frame) = self.frame() {
+            sprite.uv_min = frame.uv_min;
+            sprite.uv_max = frame.uv_max;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(frames: usize, mode: PlayMode) -> Rc<AnimationClip> {
+        let frame = AnimationFrame { uv_min: [0.0, 0.0], uv_max: [1.0, 1.0], duration: 1.0, event: None };
+        return Rc::new(AnimationClip { frames: vec![frame; frames], mode });
+    }
+
+    /// Frame index after each of `steps` one second updates.
+    fn sequence(clip: &Rc<AnimationClip>, steps: usize) -> Vec<usize> {
+        let mut animator = Animator::new();
+        animator.play(clip);
+        return (0..steps).map(|_| {
+            animator.update(1.0);
+            animator.frame_index()
+        }).collect();
+    }
+
+    #[test]
+    fn loop_starts_over() {
+        assert_eq!(sequence(&clip(3, PlayMode::Loop), 7), [1, 2, 0, 1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let clip = clip(3, PlayMode::Once);
+        assert_eq!(sequence(&clip, 5), [1, 2, 2, 2, 2]);
+
+        let mut animator = Animator::new();
+        animator.play(&clip);
+        animator.update(2.5);
+        assert!(!animator.is_finished());
+        animator.update(0.5);
+        assert!(animator.is_finished());
+    }
+
+    #[test]
+    fn ping_pong_turns_at_both_ends() {
+        assert_eq!(sequence(&clip(3, PlayMode::PingPong), 8), [1, 2, 1, 0, 1, 2, 1, 0]);
+        assert_eq!(sequence(&clip(1, PlayMode::PingPong), 3), [0, 0, 0]);
+    }
+
+    #[test]
+    fn long_updates_skip_frames_and_speed_scales_time() {
+        let clip = clip(4, PlayMode::Loop);
+        let mut animator = Animator::new();
+        animator.play(&clip);
+        animator.update(2.5);
+        assert_eq!(animator.frame_index(), 2);
+
+        animator.speed = 2.0;
+        animator.update(0.25);
+        assert_eq!(animator.frame_index(), 3);
+
+        animator.paused = true;
+        animator.update(10.0);
+        assert_eq!(animator.frame_index(), 3);
+    }
+
+    #[test]
+    fn events_are_reported_when_frames_are_reached() {
+        let clip = Rc::new(Rc::unwrap_or_clone(clip(3, PlayMode::Loop)).with_event(0, "start").with_event(2, "footstep"));
+        let mut animator = Animator::new();
+        animator.play(&clip);
+
+        animator.update(0.5);
+        assert_eq!(animator.events(), ["start"]);
+        animator.update(0.5);
+        assert!(animator.events().is_empty());
+        animator.update(1.0);
+        assert_eq!(animator.events(), ["footstep"]);
+        // Skipping over frames still reports every event in order.
+        animator.update(3.0);
+        assert_eq!(animator.events(), ["start", "footstep"]);
+    }
+
+    #[test]
+    fn playing_the_same_clip_does_not_restart_it() {
+        let clip = clip(3, PlayMode::Loop);
+        let mut animator = Animator::new();
+        animator.play(&clip);
+        animator.update(1.0);
+        animator.play(&clip);
+        assert_eq!(animator.frame_index(), 1);
+
+        animator.restart();
+        assert_eq!(animator.frame_index(), 0);
+    }
+
+    #[test]
+    fn grid_cells_run_left_to_right_then_down() {
+        let clip = AnimationClip::from_grid([64, 32], [16, 16], 3, 2, 0.1, PlayMode::Loop).unwrap();
+        assert_eq!(clip.frames.len(), 2);
+        assert_eq!((clip.frames[0].uv_min, clip.frames[0].uv_max), ([0.75, 0.5], [1.0, 1.0]));
+        assert_eq!((clip.frames[1].uv_min, clip.frames[1].uv_max), ([0.0, 0.0], [0.25, 0.5]));
+    }
+
+    #[test]
+    fn grid_cells_past_the_sheet_are_rejected() {
+        assert!(AnimationClip::from_grid([64, 32], [16, 16], 6, 3, 0.1, PlayMode::Loop).is_none());
+        assert!(AnimationClip::from_grid([64, 32], [16, 16], u32::MAX, 2, 0.1, PlayMode::Loop).is_none());
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::context::Display;
+use super::texture::{Texture, TextureError, TextureSettings};
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Io(std::io::Error),
+    Decode(image::ImageError),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// The named image doesn't fit even into a `max_size` atlas.
+    DoesNotFit(String),
+    Invalid(String),
+    Texture(TextureError)
+}
+
+impl std::fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AtlasError::Io(error) => write!(f, "failed to access atlas: {}", error),
+            AtlasError::Decode(error) => write!(f, "failed to decode atlas image: {}", error),
+            AtlasError::Parse(error) => write!(f, "invalid atlas layout: {}", error),
+            AtlasError::Serialize(error) => write!(f, "failed to serialize atlas layout: {}", error),
+            AtlasError::DoesNotFit(name) => write!(f, "image {:?} does not fit into the atlas", name),
+            AtlasError::Invalid(message) => write!(f, "invalid atlas: {}", message),
+            AtlasError::Texture(error) => write!(f, "failed to create atlas texture: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+/// Where one image ended up in the atlas.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct AtlasRegion {
+    /// Pixel rectangle in the atlas image, measured from its top left corner.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Texture coordinates of the bottom left and top right corners, as the texture is sampled after `Texture::from_image`.
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2]
+}
+
+/// Packed atlas on the CPU, what `AtlasBuilder::build` gives and what can be saved at build time.
+pub struct AtlasImage {
+    pub image: RgbaImage,
+    pub regions: BTreeMap<String, AtlasRegion>
+}
+
+/// Layout file saved next to the atlas image.
+#[derive(Serialize, Deserialize)]
+struct AtlasLayout {
+    width: u32,
+    height: u32,
+    regions: BTreeMap<String, AtlasRegion>
+}
+
+impl AtlasImage {
+    /// Writes the image as a PNG to `path` and the regions as TOML next to it, `sprites.png` gets `sprites.toml`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AtlasError> {
+        let path = path.as_ref();
+        self.image.save(path).map_err(AtlasError::Decode)?;
+
+        let layout = AtlasLayout { width: self.image.width(), height: self.image.height(), regions: self.regions.clone() };
+        let source = toml::to_string(&layout).map_err(AtlasError::Serialize)?;
+        return std::fs::write(path.with_extension("toml"), source).map_err(AtlasError::Io);
+    }
+
+    /// Loads an atlas written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<AtlasImage, AtlasError> {
+        let path = path.as_ref();
+        let image = image::open(path).map_err(AtlasError::Decode)?.into_rgba8();
+
+        let source = std::fs::read_to_string(path.with_extension("toml")).map_err(AtlasError::Io)?;
+        let layout: AtlasLayout = toml::from_str(&source).map_err(AtlasError::Parse)?;
+        if (layout.width, layout.height) != image.dimensions() {
+            return Err(AtlasError::Invalid(format!("layout is for {}x{} but the image is {}x{}",
+                layout.width, layout.height, image.width(), image.height())));
+        }
+
+        return Ok(AtlasImage { image, regions: layout.regions });
+    }
+}
+
+/// Packs many images into one. Every image gets `extrude` pixels of its own edge copied around it so
+/// filtering at the border doesn't pick up its neighbours, and `padding` transparent pixels between
+/// it and everything else.
+pub struct AtlasBuilder {
+    pub padding: u32,
+    pub extrude: u32,
+    /// Largest width and height the atlas may grow to.
+    pub max_size: u32,
+    images: Vec<(String, RgbaImage)>
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> AtlasBuilder {
+        return AtlasBuilder { padding: 2, extrude: 1, max_size: 4096, images: Vec::new() };
+    }
+}
+
+/// Free space of the skyline packer, the top edge of what has been placed so far.
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32
+}
+
+/// Bottom left skyline packing into `width` x `height`, gives the top left corner of every size or `Err`
+/// with the index of the first one that didn't fit.
+fn pack(sizes: &[(u32, u32)], order: &[usize], width: u32, height: u32) -> Result<Vec<(u32, u32)>, usize> {
+    let mut skyline = vec![Segment { x: 0, y: 0, width }];
+    let mut positions = vec![(0, 0); sizes.len()];
+
+    for &index in order {
+        let (item_width, item_height) = sizes[index];
+
+        let mut best: Option<(usize, u32, u32)> = None;
+        for start in 0..skyline.len() {
+            let x = skyline[start].x;
+            if x + item_width > width { break; }
+
+            let mut y = 0;
+            let mut covered = 0;
+            for segment in &skyline[start..] {
+                if covered >= item_width { break; }
+                y = y.max(segment.y);
+                covered += segment.width;
+            }
+            if y + item_height > height { continue; }
+            if best.is_none_or(|(_, best_x, best_y)| (y, x) < (best_y, best_x)) { best = Some((start, x, y)); }
+        }
+        let Some((start, x, y)) = best else { return Err(index); };
+        positions[index] = (x, y);
+
+        // Replace the segments under the new item with its top edge.
+        let mut end = start;
+        while end < skyline.len() && skyline[end].x < x + item_width { end += 1; }
+        let last = &skyline[end - 1];
+        let remainder = (last.x + last.width).saturating_sub(x + item_width);
+        let last_y = last.y;
+        skyline.splice(start..end, [Segment { x, y: y + item_height, width: item_width }]);
+        if remainder > 0 { skyline.insert(start + 1, Segment { x: x + item_width, y: last_y, width: remainder }); }
+        skyline.dedup_by(|next, previous| {
+            if next.y != previous.y { return false; }
+            previous.width += next.width;
+            return true;
+        });
+    }
+
+    return Ok(positions);
+}
+
+impl AtlasBuilder {
+    /// Adds an image under `name`, replacing any image added before with the same name.
+    pub fn add(&mut self, name: impl Into<String>, image: &DynamicImage) {
+        let name = name.into();
+        self.images.retain(|(existing, _)| *existing != name);
+        self.images.push((name, image.to_rgba8()));
+    }
+
+    /// Adds an image file named after its file stem, `img/player.png` is `player`.
+    pub fn add_file(&mut self, path: impl AsRef<Path>) -> Result<(), AtlasError> {
+        let path = path.as_ref();
+        let image = image::open(path).map_err(AtlasError::Decode)?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        self.add(name, &image);
+
+        return Ok(());
+    }
+
+    /// Adds every image in `directory` that the `image` crate can open, other files are skipped.
+    pub fn add_directory(&mut self, directory: impl AsRef<Path>) -> Result<(), AtlasError> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(directory).map_err(AtlasError::Io)? {
+            let path = entry.map_err(AtlasError::Io)?.path();
+            if path.is_file() && image::ImageFormat::from_path(&path).is_ok() { paths.push(path); }
+        }
+        paths.sort();
+
+        for path in paths { self.add_file(path)?; }
+        return Ok(());
+    }
+
+    /// Packs the images into the smallest power of two atlas that fits them, growing up to `max_size`.
+    pub fn build(&self) -> Result<AtlasImage, AtlasError> {
+        if let Some((name, image)) = self.images.iter().find(|(_, image)| image.width() == 0 || image.height() == 0) {
+            return Err(AtlasError::Invalid(format!("image {:?} is {}x{}", name, image.width(), image.height())));
+        }
+
+        let border = self.extrude * 2 + self.padding;
+        let sizes: Vec<_> = self.images.iter().map(|(_, image)| (image.width() + border, image.height() + border)).collect();
+
+        // Tallest first, then widest, keeps the skyline flat.
+        let mut order: Vec<_> = (0..sizes.len()).collect();
+        order.sort_by_key(|&index| (std::cmp::Reverse(sizes[index].1), std::cmp::Reverse(sizes[index].0)));
+
+        let area: u64 = sizes.iter().map(|&(width, height)| width as u64 * height as u64).sum();
+        let side = ((area as f64).sqrt().ceil() as u32).max(1).next_power_of_two().min(self.max_size);
+        let (mut width, mut height) = (side, side);
+
+        // The outer padding is left off the packing area so every image has it on all sides.
+        let positions = loop {
+            match pack(&sizes, &order, width.saturating_sub(self.padding), height.saturating_sub(self.padding)) {
+                Ok(positions) => break positions,
+                Err(_) if width < self.max_size && width <= height => width = (width * 2).min(self.max_size),
+                Err(_) if height < self.max_size => height = (height * 2).min(self.max_size),
+                Err(index) => return Err(AtlasError::DoesNotFit(self.images[index].0.clone()))
+            }
+        };
+
+        let mut atlas = RgbaImage::new(width, height);
+        let mut regions = BTreeMap::new();
+        for ((name, image), (x, y)) in self.images.iter().zip(positions) {
+            let (x, y) = (x + self.padding + self.extrude, y + self.padding + self.extrude);
+            let extrude = self.extrude as i64;
+
+            // Clamping the source coordinates repeats the edge pixels into the extruded border.
+            for target_y in -extrude..image.height() as i64 + extrude {
+                for target_x in -extrude..image.width() as i64 + extrude {
+                    let source_x = target_x.clamp(0, image.width() as i64 - 1) as u32;
+                    let source_y = target_y.clamp(0, image.height() as i64 - 1) as u32;
+                    atlas.put_pixel((x as i64 + target_x) as u32, (y as i64 + target_y) as u32, *image.get_pixel(source_x, source_y));
+                }
+            }
+
+            let (atlas_width, atlas_height) = (width as f32, height as f32);
+            regions.insert(name.clone(), AtlasRegion {
+                x,
+                y,
+                width: image.width(),
+                height: image.height(),
+                uv_min: [x as f32 / atlas_width, 1.0 - (y + image.height()) as f32 / atlas_height],
+                uv_max: [(x + image.width()) as f32 / atlas_width, 1.0 - y as f32 / atlas_height]
+            });
+        }
+
+        return Ok(AtlasImage { image: atlas, regions });
+    }
+}
+
+/// Many images in one texture, looked up by name.
+pub struct Atlas {
+    pub texture: Texture,
+    pub regions: BTreeMap<String, AtlasRegion>
+}
+
+impl Atlas {
+    /// Uploads a packed atlas. Mipmaps make neighbours bleed into each other once levels get smaller
+    /// than the padding, pixel art usually wants `mipmaps: false`.
+    pub fn new(display: &Display, atlas: AtlasImage, settings: &TextureSettings) -> Result<Atlas, AtlasError> {
+        let texture = Texture::from_image(display, &DynamicImage::ImageRgba8(atlas.image), settings).map_err(AtlasError::Texture)?;
+        return Ok(Atlas { texture, regions: atlas.regions });
+    }
+
+    /// Loads an atlas saved with `AtlasImage::save`.
+    pub fn load(display: &Display, path: impl AsRef<Path>, settings: &TextureSettings) -> Result<Atlas, AtlasError> {
+        return Atlas::new(display, AtlasImage::load(path)?, settings);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AtlasRegion> { self.regions.get(name) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn solid(width: u32, height: u32, color: [u8; 4]) -> DynamicImage {
+        return DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba(color)));
+    }
+
+    fn overlaps(a: &AtlasRegion, b: &AtlasRegion, margin: u32) -> bool {
+        return a.x < b.x + b.width + margin && b.x < a.x + a.width + margin && a.y < b.y + b.height + margin && b.y < a.y + a.height + margin;
+    }
+
+    #[test]
+    fn regions_keep_padding_and_extrude() {
+        let mut builder = AtlasBuilder { padding: 2, extrude: 1, ..AtlasBuilder::default() };
+        builder.add("red", &solid(10, 6, [255, 0, 0, 255]));
+        builder.add("green", &solid(4, 12, [0, 255, 0, 255]));
+        builder.add("blue", &solid(7, 7, [0, 0, 255, 255]));
+        let atlas = builder.build().unwrap();
+
+        let regions: Vec<_> = atlas.regions.values().collect();
+        for (index, a) in regions.iter().enumerate() {
+            // Every region is inside the outer padding and its extruded border.
+            assert!(a.x >= 3 && a.y >= 3);
+            assert!(a.x + a.width + 3 <= atlas.image.width() && a.y + a.height + 3 <= atlas.image.height());
+            for b in &regions[index + 1..] { assert!(!overlaps(a, b, 4), "{:?} and {:?} are too close", a, b); }
+        }
+
+        let red = atlas.regions["red"];
+        assert_eq!((red.width, red.height), (10, 6));
+        assert_eq!(atlas.image.get_pixel(red.x - 1, red.y - 1).0, [255, 0, 0, 255]);
+        assert_eq!(atlas.image.get_pixel(red.x + red.width, red.y + red.height).0, [255, 0, 0, 255]);
+        assert_eq!(atlas.image.get_pixel(red.x - 2, red.y).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn uvs_are_flipped_to_the_bottom_left() {
+        let mut builder = AtlasBuilder { padding: 0, extrude: 0, ..AtlasBuilder::default() };
+        builder.add("square", &solid(8, 8, [255; 4]));
+        let atlas = builder.build().unwrap();
+
+        assert_eq!(atlas.image.dimensions(), (8, 8));
+        assert_eq!(atlas.regions["square"].uv_min, [0.0, 0.0]);
+        assert_eq!(atlas.regions["square"].uv_max, [1.0, 1.0]);
+    }
+
+    #[test]
+    fn atlas_grows_until_everything_fits() {
+        // The area would fit into 32x32, the width doesn't.
+        let mut builder = AtlasBuilder { padding: 0, extrude: 0, ..AtlasBuilder::default() };
+        builder.add("wide", &solid(40, 8, [255; 4]));
+        builder.add("small", &solid(8, 8, [255; 4]));
+        let atlas = builder.build().unwrap();
+
+        assert_eq!(atlas.image.dimensions(), (64, 32));
+        assert!(!overlaps(&atlas.regions["wide"], &atlas.regions["small"], 0));
+    }
+
+    #[test]
+    fn adding_a_name_again_replaces_the_image() {
+        let mut builder = AtlasBuilder::default();
+        builder.add("sprite", &solid(4, 4, [255; 4]));
+        builder.add("sprite", &solid(2, 3, [255; 4]));
+        let atlas = builder.build().unwrap();
+
+        assert_eq!(atlas.regions.len(), 1);
+        assert_eq!((atlas.regions["sprite"].width, atlas.regions["sprite"].height), (2, 3));
+    }
+
+    #[test]
+    fn overflow_names_the_image_that_does_not_fit() {
+        let mut builder = AtlasBuilder { padding: 0, extrude: 0, max_size: 32, ..AtlasBuilder::default() };
+        builder.add("small", &solid(16, 16, [255; 4]));
+        builder.add("large", &solid(40, 8, [255; 4]));
+
+        assert!(matches!(builder.build(), Err(AtlasError::DoesNotFit(name)) if name == "large"));
+    }
+
+    #[test]
+    fn empty_images_are_rejected() {
+        for (width, height) in [(0, 4), (4, 0), (0, 0)] {
+            let mut builder = AtlasBuilder::default();
+            builder.add("empty", &DynamicImage::ImageRgba8(RgbaImage::new(width, height)));
+            assert!(matches!(builder.build(), Err(AtlasError::Invalid(_))));
+        }
+        assert!(AtlasBuilder::default().build().unwrap().regions.is_empty());
+    }
+}
@@ -0,0 +1,601 @@
+//! CPU decoders for BC1-BC7 blocks, used when the GL context can't sample a compressed format.
+//! Every function decodes one 4x4 block into 16 texels, row by row from the top left.
+
+/// Expands a 5 or 6 bit channel to 8 bits by repeating its high bits.
+fn expand(value: u32, bits: u32) -> u8 {
+    return ((value << (8 - bits)) | (value >> (2 * bits - 8))) as u8;
+}
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let color = color as u32;
+    return [expand(color >> 11, 5), expand((color >> 5) & 0x3f, 6), expand(color & 0x1f, 5)];
+}
+
+fn color_block(block: &[u8], four_colors: bool, out: &mut [[u8; 4]; 16]) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+
+    let mut palette = [[e0[0], e0[1], e0[2], 255], [e1[0], e1[1], e1[2], 255], [0; 4], [0; 4]];
+    for channel in 0..3 {
+        let (a, b) = (e0[channel] as u32, e1[channel] as u32);
+        if four_colors || c0 > c1 {
+            palette[2][channel] = ((2 * a + b) / 3) as u8;
+            palette[3][channel] = ((a + 2 * b) / 3) as u8;
+        } else {
+            palette[2][channel] = ((a + b) / 2) as u8;
+        }
+    }
+    palette[2][3] = 255;
+    palette[3][3] = if four_colors || c0 > c1 { 255 } else { 0 };
+
+    for (texel, pixel) in out.iter_mut().enumerate() {
+        let alpha = pixel[3];
+        *pixel = palette[((indices >> (2 * texel)) & 3) as usize];
+        // BC2 and BC3 bring their own alpha.
+        if four_colors { pixel[3] = alpha; }
+    }
+}
+
+/// Eight 8 bit values interpolated from two endpoints with 3 bit indices, the alpha of BC3 and channels of BC4/BC5.
+fn alpha_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 0];
+    if a0 > a1 {
+        for i in 1..7 { palette[i + 1] = ((7 - i as u32) * a0 + i as u32 * a1) / 7; }
+    } else {
+        for i in 1..5 { palette[i + 1] = ((5 - i as u32) * a0 + i as u32 * a1) / 5; }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut bits = 0u64;
+    for (i, byte) in block[2..8].iter().enumerate() { bits |= (*byte as u64) << (8 * i); }
+
+    let mut out = [0; 16];
+    for (texel, value) in out.iter_mut().enumerate() { *value = palette[((bits >> (3 * texel)) & 7) as usize] as u8; }
+    return out;
+}
+
+/// BC1 with 1 bit alpha, index 3 of three color blocks is transparent black.
+pub fn decode_bc1(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut out = [[0; 4]; 16];
+    color_block(block, false, &mut out);
+    return out;
+}
+
+/// BC2, explicit 4 bit alpha followed by a BC1 color block.
+pub fn decode_bc2(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut out = [[0; 4]; 16];
+    let alpha = u64::from_le_bytes(block[0..8].try_into().unwrap());
+    for (texel, pixel) in out.iter_mut().enumerate() { pixel[3] = expand(((alpha >> (4 * texel)) & 0xf) as u32, 4); }
+
+    color_block(&block[8..16], true, &mut out);
+    return out;
+}
+
+/// BC3, interpolated alpha followed by a BC1 color block.
+pub fn decode_bc3(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut out = [[0; 4]; 16];
+    for (pixel, alpha) in out.iter_mut().zip(alpha_block(&block[0..8])) { pixel[3] = alpha; }
+
+    color_block(&block[8..16], true, &mut out);
+    return out;
+}
+
+/// BC4, one channel returned as red like GL samples it.
+pub fn decode_bc4(block: &[u8]) -> [[u8; 4]; 16] {
+    return alpha_block(&block[0..8]).map(|red| [red, 0, 0, 255]);
+}
+
+/// BC5, two channels returned as red and green.
+pub fn decode_bc5(block: &[u8]) -> [[u8; 4]; 16] {
+    let (red, green) = (alpha_block(&block[0..8]), alpha_block(&block[8..16]));
+
+    let mut out = [[0, 0, 0, 255]; 16];
+    for (texel, pixel) in out.iter_mut().enumerate() {
+        pixel[0] = red[texel];
+        pixel[1] = green[texel];
+    }
+    return out;
+}
+
+/// Reads a 128 bit block from the lowest bit up.
+struct Bits {
+    value: u128,
+    position: u32
+}
+
+impl Bits {
+    fn new(block: &[u8]) -> Bits {
+        return Bits { value: u128::from_le_bytes(block[0..16].try_into().unwrap()), position: 0 };
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 { return 0; }
+        let value = (self.value >> self.position) as u32 & ((1u64 << count) - 1) as u32;
+        self.position += count;
+        return value;
+    }
+}
+
+/// Subset of every texel for the 64 two subset partitions, shared by BC6H and BC7.
+const PARTITIONS_2: [[u8; 16]; 64] = [
+    [0,0,1,1,0,0,1,1,0,0,1,1,0,0,1,1], [0,0,0,1,0,0,0,1,0,0,0,1,0,0,0,1], [0,1,1,1,0,1,1,1,0,1,1,1,0,1,1,1], [0,0,0,1,0,0,1,1,0,0,1,1,0,1,1,1],
+    [0,0,0,0,0,0,0,1,0,0,0,1,0,0,1,1], [0,0,1,1,0,1,1,1,0,1,1,1,1,1,1,1], [0,0,0,1,0,0,1,1,0,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,1,0,0,1,1,0,1,1,1],
+    [0,0,0,0,0,0,0,0,0,0,0,1,0,0,1,1], [0,0,1,1,0,1,1,1,1,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,1,0,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,0,0,0,0,1,0,1,1,1],
+    [0,0,0,1,0,1,1,1,1,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,0,1,1,1,1,1,1,1,1], [0,0,0,0,1,1,1,1,1,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,0,0,0,0,0,1,1,1,1],
+    [0,0,0,0,1,0,0,0,1,1,1,0,1,1,1,1], [0,1,1,1,0,0,0,1,0,0,0,0,0,0,0,0], [0,0,0,0,0,0,0,0,1,0,0,0,1,1,1,0], [0,1,1,1,0,0,1,1,0,0,0,1,0,0,0,0],
+    [0,0,1,1,0,0,0,1,0,0,0,0,0,0,0,0], [0,0,0,0,1,0,0,0,1,1,0,0,1,1,1,0], [0,0,0,0,0,0,0,0,1,0,0,0,1,1,0,0], [0,1,1,1,0,0,1,1,0,0,1,1,0,0,0,1],
+    [0,0,1,1,0,0,0,1,0,0,0,1,0,0,0,0], [0,0,0,0,1,0,0,0,1,0,0,0,1,1,0,0], [0,1,1,0,0,1,1,0,0,1,1,0,0,1,1,0], [0,0,1,1,0,1,1,0,0,1,1,0,1,1,0,0],
+    [0,0,0,1,0,1,1,1,1,1,1,0,1,0,0,0], [0,0,0,0,1,1,1,1,1,1,1,1,0,0,0,0], [0,1,1,1,0,0,0,1,1,0,0,0,1,1,1,0], [0,0,1,1,1,0,0,1,1,0,0,1,1,1,0,0],
+    [0,1,0,1,0,1,0,1,0,1,0,1,0,1,0,1], [0,0,0,0,1,1,1,1,0,0,0,0,1,1,1,1], [0,1,0,1,1,0,1,0,0,1,0,1,1,0,1,0], [0,0,1,1,0,0,1,1,1,1,0,0,1,1,0,0],
+    [0,0,1,1,1,1,0,0,0,0,1,1,1,1,0,0], [0,1,0,1,0,1,0,1,1,0,1,0,1,0,1,0], [0,1,1,0,1,0,0,1,0,1,1,0,1,0,0,1], [0,1,0,1,1,0,1,0,1,0,1,0,0,1,0,1],
+    [0,1,1,1,0,0,1,1,1,1,0,0,1,1,1,0], [0,0,0,1,0,0,1,1,1,1,0,0,1,0,0,0], [0,0,1,1,0,0,1,0,0,1,0,0,1,1,0,0], [0,0,1,1,1,0,1,1,1,1,0,1,1,1,0,0],
+    [0,1,1,0,1,0,0,1,1,0,0,1,0,1,1,0], [0,0,1,1,1,1,0,0,1,1,0,0,0,0,1,1], [0,1,1,0,0,1,1,0,1,0,0,1,1,0,0,1], [0,0,0,0,0,1,1,0,0,1,1,0,0,0,0,0],
+    [0,1,0,0,1,1,1,0,0,1,0,0,0,0,0,0], [0,0,1,0,0,1,1,1,0,0,1,0,0,0,0,0], [0,0,0,0,0,0,1,0,0,1,1,1,0,0,1,0], [0,0,0,0,0,1,0,0,1,1,1,0,0,1,0,0],
+    [0,1,1,0,1,1,0,0,1,0,0,1,0,0,1,1], [0,0,1,1,0,1,1,0,1,1,0,0,1,0,0,1], [0,1,1,0,0,0,1,1,1,0,0,1,1,1,0,0], [0,0,1,1,1,0,0,1,1,1,0,0,0,1,1,0],
+    [0,1,1,0,1,1,0,0,1,1,0,0,1,0,0,1], [0,1,1,0,0,0,1,1,0,0,1,1,1,0,0,1], [0,1,1,1,1,1,1,0,1,0,0,0,0,0,0,1], [0,0,0,1,1,0,0,0,1,1,1,0,0,1,1,1],
+    [0,0,0,0,1,1,1,1,0,0,1,1,0,0,1,1], [0,0,1,1,0,0,1,1,1,1,1,1,0,0,0,0], [0,0,1,0,0,0,1,0,1,1,1,0,1,1,1,0], [0,1,0,0,0,1,0,0,0,1,1,1,0,1,1,1]
+];
+
+const PARTITIONS_3: [[u8; 16]; 64] = [
+    [0,0,1,1,0,0,1,1,0,2,2,1,2,2,2,2], [0,0,0,1,0,0,1,1,2,2,1,1,2,2,2,1], [0,0,0,0,2,0,0,1,2,2,1,1,2,2,1,1], [0,2,2,2,0,0,2,2,0,0,1,1,0,1,1,1],
+    [0,0,0,0,0,0,0,0,1,1,2,2,1,1,2,2], [0,0,1,1,0,0,1,1,0,0,2,2,0,0,2,2], [0,0,2,2,0,0,2,2,1,1,1,1,1,1,1,1], [0,0,1,1,0,0,1,1,2,2,1,1,2,2,1,1],
+    [0,0,0,0,0,0,0,0,1,1,1,1,2,2,2,2], [0,0,0,0,1,1,1,1,1,1,1,1,2,2,2,2], [0,0,0,0,1,1,1,1,2,2,2,2,2,2,2,2], [0,0,1,2,0,0,1,2,0,0,1,2,0,0,1,2],
+    [0,1,1,2,0,1,1,2,0,1,1,2,0,1,1,2], [0,1,2,2,0,1,2,2,0,1,2,2,0,1,2,2], [0,0,1,1,0,1,1,2,1,1,2,2,1,2,2,2], [0,0,1,1,2,0,0,1,2,2,0,0,2,2,2,0],
+    [0,0,0,1,0,0,1,1,0,1,1,2,1,1,2,2], [0,1,1,1,0,0,1,1,2,0,0,1,2,2,0,0], [0,0,0,0,1,1,2,2,1,1,2,2,1,1,2,2], [0,0,2,2,0,0,2,2,0,0,2,2,1,1,1,1],
+    [0,1,1,1,0,1,1,1,0,2,2,2,0,2,2,2], [0,0,0,1,0,0,0,1,2,2,2,1,2,2,2,1], [0,0,0,0,0,0,1,1,0,1,2,2,0,1,2,2], [0,0,0,0,1,1,0,0,2,2,1,0,2,2,1,0],
+    [0,1,2,2,0,1,2,2,0,0,1,1,0,0,0,0], [0,0,1,2,0,0,1,2,1,1,2,2,2,2,2,2], [0,1,1,0,1,2,2,1,1,2,2,1,0,1,1,0], [0,0,0,0,0,1,1,0,1,2,2,1,1,2,2,1],
+    [0,0,2,2,1,1,0,2,1,1,0,2,0,0,2,2], [0,1,1,0,0,1,1,0,2,0,0,2,2,2,2,2], [0,0,1,1,0,1,2,2,0,1,2,2,0,0,1,1], [0,0,0,0,2,0,0,0,2,2,1,1,2,2,2,1],
+    [0,0,0,0,0,0,0,2,1,1,2,2,1,2,2,2], [0,2,2,2,0,0,2,2,0,0,1,2,0,0,1,1], [0,0,1,1,0,0,1,2,0,0,2,2,0,2,2,2], [0,1,2,0,0,1,2,0,0,1,2,0,0,1,2,0],
+    [0,0,0,0,1,1,1,1,2,2,2,2,0,0,0,0], [0,1,2,0,1,2,0,1,2,0,1,2,0,1,2,0], [0,1,2,0,2,0,1,2,1,2,0,1,0,1,2,0], [0,0,1,1,2,2,0,0,1,1,2,2,0,0,1,1],
+    [0,0,1,1,1,1,2,2,2,2,0,0,0,0,1,1], [0,1,0,1,0,1,0,1,2,2,2,2,2,2,2,2], [0,0,0,0,0,0,0,0,2,1,2,1,2,1,2,1], [0,0,2,2,1,1,2,2,0,0,2,2,1,1,2,2],
+    [0,0,2,2,0,0,1,1,0,0,2,2,0,0,1,1], [0,2,2,0,1,2,2,1,0,2,2,0,1,2,2,1], [0,1,0,1,2,2,2,2,2,2,2,2,0,1,0,1], [0,0,0,0,2,1,2,1,2,1,2,1,2,1,2,1],
+    [0,1,0,1,0,1,0,1,0,1,0,1,2,2,2,2], [0,2,2,2,0,1,1,1,0,2,2,2,0,1,1,1], [0,0,0,2,1,1,1,2,0,0,0,2,1,1,1,2], [0,0,0,0,2,1,1,2,2,1,1,2,2,1,1,2],
+    [0,2,2,2,0,1,1,1,0,1,1,1,0,2,2,2], [0,0,0,2,1,1,1,2,1,1,1,2,0,0,0,2], [0,1,1,0,0,1,1,0,0,1,1,0,2,2,2,2], [0,0,0,0,0,0,0,0,2,1,1,2,2,1,1,2],
+    [0,1,1,0,0,1,1,0,2,2,2,2,2,2,2,2], [0,0,2,2,0,0,1,1,0,0,1,1,0,0,2,2], [0,0,2,2,1,1,2,2,1,1,2,2,0,0,2,2], [0,0,0,0,0,0,0,0,0,0,0,0,2,1,1,2],
+    [0,0,0,2,0,0,0,1,0,0,0,2,0,0,0,1], [0,2,2,2,1,2,2,2,0,2,2,2,1,2,2,2], [0,1,0,1,2,2,2,2,2,2,2,2,2,2,2,2], [0,1,1,1,2,0,1,1,2,2,0,1,2,2,2,0]
+];
+
+/// Texel whose index is stored one bit shorter, for the second subset of two subset partitions.
+const ANCHORS_2: [u8; 64] = [
+    15,15,15,15,15,15,15,15, 15,15,15,15,15,15,15,15, 15, 2, 8, 2, 2, 8, 8,15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15,15, 6, 8, 2, 8,15,15, 2, 8, 2, 2, 2,15,15, 6, 6, 2, 6, 8,15,15, 2, 2, 15,15,15,15,15, 2, 2,15
+];
+
+/// Anchors of the second and third subset of three subset partitions.
+const ANCHORS_3: [[u8; 64]; 2] = [
+    [3, 3,15,15, 8, 3,15,15, 8, 8, 6, 6, 6, 5, 3, 3, 3, 3, 8,15, 3, 3, 6,10, 5, 8, 8, 6, 8, 5,15,15,
+     8,15, 3, 5, 6,10, 8,15, 15, 3,15, 5,15,15,15,15, 3,15, 5, 5, 5, 8, 5,10, 5,10, 8,13,15,12, 3, 3],
+    [15, 8, 8, 3,15,15, 3, 8, 15,15,15,15,15,15,15, 8, 15, 8,15, 3,15, 8,15, 8, 3,15, 6,10,15,15,10, 8,
+     15, 3,15,10,10, 8, 9,10, 6,15, 8,15, 3, 6, 6, 8, 15, 3,15,15,15,15,15,15, 15,15,15,15, 3,15,15, 8]
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn weight(bits: u32, index: u32) -> u32 {
+    return match bits {
+        2 => WEIGHTS_2[index as usize],
+        3 => WEIGHTS_3[index as usize],
+        _ => WEIGHTS_4[index as usize]
+    };
+}
+
+/// Subset of `texel` and whether it is an anchor, whose index has one bit less.
+fn subset(subsets: usize, partition: usize, texel: usize) -> (usize, bool) {
+    return match subsets {
+        2 => {
+            let subset = PARTITIONS_2[partition][texel] as usize;
+            (subset, texel == 0 || (subset == 1 && texel == ANCHORS_2[partition] as usize))
+        },
+        3 => {
+            let subset = PARTITIONS_3[partition][texel] as usize;
+            (subset, texel == 0 || (subset > 0 && texel == ANCHORS_3[subset - 1][partition] as usize))
+        },
+        _ => (0, texel == 0)
+    };
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_p_bits: bool,
+    shared_p_bits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_p_bits: true, shared_p_bits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: true, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_p_bits: true, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_p_bits: true, shared_p_bits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_p_bits: true, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 }
+];
+
+pub fn decode_bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut bits = Bits::new(block);
+    let Some(mode_index) = (0..8).find(|mode| block[0] & (1 << mode) != 0) else { return [[0; 4]; 16]; };
+    bits.read(mode_index as u32 + 1);
+    let mode = &BC7_MODES[mode_index];
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    // Endpoints are stored channel by channel, two per subset.
+    let mut endpoints = [[[0u32; 4]; 2]; 3];
+    for channel in 0..4 {
+        let channel_bits = if channel < 3 { mode.color_bits } else { mode.alpha_bits };
+        for subset in endpoints.iter_mut().take(mode.subsets) {
+            for endpoint in subset.iter_mut() { endpoint[channel] = bits.read(channel_bits); }
+        }
+    }
+
+    let mut color_bits = mode.color_bits;
+    let mut alpha_bits = mode.alpha_bits;
+    if mode.endpoint_p_bits || mode.shared_p_bits {
+        for subset in endpoints.iter_mut().take(mode.subsets) {
+            let shared = if mode.shared_p_bits { bits.read(1) } else { 0 };
+            for endpoint in subset.iter_mut() {
+                let p_bit = if mode.endpoint_p_bits { bits.read(1) } else { shared };
+                for (channel, value) in endpoint.iter_mut().enumerate() {
+                    if channel < 3 || mode.alpha_bits > 0 { *value = (*value << 1) | p_bit; }
+                }
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 { alpha_bits += 1; }
+    }
+    for subset in endpoints.iter_mut().take(mode.subsets) {
+        for endpoint in subset.iter_mut() {
+            for (channel, value) in endpoint.iter_mut().enumerate() {
+                *value = match (channel, alpha_bits) {
+                    (3, 0) => 255,
+                    (3, _) => expand(*value, alpha_bits) as u32,
+                    _ => expand(*value, color_bits) as u32
+                };
+            }
+        }
+    }
+
+    let mut indices = [0u32; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        let (_, anchor) = subset(mode.subsets, partition, texel);
+        *index = bits.read(mode.index_bits - anchor as u32);
+    }
+    let mut secondary = [0u32; 16];
+    if mode.secondary_index_bits > 0 {
+        for (texel, index) in secondary.iter_mut().enumerate() { *index = bits.read(mode.secondary_index_bits - (texel == 0) as u32); }
+    }
+
+    let mut out = [[0u8; 4]; 16];
+    for (texel, pixel) in out.iter_mut().enumerate() {
+        let (subset, _) = subset(mode.subsets, partition, texel);
+        let [e0, e1] = endpoints[subset];
+
+        let (color_weight, alpha_weight) = match (mode.secondary_index_bits, index_selection) {
+            (0, _) => (weight(mode.index_bits, indices[texel]), weight(mode.index_bits, indices[texel])),
+            (secondary_bits, 0) => (weight(mode.index_bits, indices[texel]), weight(secondary_bits, secondary[texel])),
+            (secondary_bits, _) => (weight(secondary_bits, secondary[texel]), weight(mode.index_bits, indices[texel]))
+        };
+        for channel in 0..4 {
+            let weight = if channel < 3 { color_weight } else { alpha_weight };
+            pixel[channel] = (((64 - weight) * e0[channel] + weight * e1[channel] + 32) >> 6) as u8;
+        }
+
+        match rotation {
+            1 => pixel.swap(0, 3),
+            2 => pixel.swap(1, 3),
+            3 => pixel.swap(2, 3),
+            _ => ()
+        }
+    }
+
+    return out;
+}
+
+/// Where a run of BC6H header bits goes: an endpoint (W, X, Y or Z) and its channel, or the partition.
+#[derive(Copy, Clone)]
+enum Field {
+    Endpoint(usize, usize),
+    Partition
+}
+
+const RW: Field = Field::Endpoint(0, 0);
+const GW: Field = Field::Endpoint(0, 1);
+const BW: Field = Field::Endpoint(0, 2);
+const RX: Field = Field::Endpoint(1, 0);
+const GX: Field = Field::Endpoint(1, 1);
+const BX: Field = Field::Endpoint(1, 2);
+const RY: Field = Field::Endpoint(2, 0);
+const GY: Field = Field::Endpoint(2, 1);
+const BY: Field = Field::Endpoint(2, 2);
+const RZ: Field = Field::Endpoint(3, 0);
+const GZ: Field = Field::Endpoint(3, 1);
+const BZ: Field = Field::Endpoint(3, 2);
+const D: Field = Field::Partition;
+
+/// A run of bits in a BC6H header: the field, its lowest bit and how many bits. A negative count
+/// stores the bits from the highest one down.
+type Run = (Field, u32, i32);
+
+struct Bc6hMode {
+    header: &'static [Run],
+    endpoint_bits: u32,
+    delta_bits: [u32; 3],
+    transformed: bool,
+    subsets: usize
+}
+
+const BC6H_MODES: [Bc6hMode; 14] = [
+    Bc6hMode { header: &[(GY, 4, 1), (BY, 4, 1), (BZ, 4, 1), (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5),
+        (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5)],
+        endpoint_bits: 10, delta_bits: [5, 5, 5], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(GY, 5, 1), (GZ, 4, 1), (GZ, 5, 1), (RW, 0, 7), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 7), (BY, 5, 1), (BZ, 2, 1),
+        (GY, 4, 1), (BW, 0, 7), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4),
+        (RY, 0, 6), (RZ, 0, 6), (D, 0, 5)],
+        endpoint_bits: 7, delta_bits: [6, 6, 6], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (RW, 10, 1), (GY, 0, 4), (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1),
+        (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5)],
+        endpoint_bits: 11, delta_bits: [5, 4, 4], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (GW, 10, 1),
+        (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 0, 1), (BZ, 2, 1), (RZ, 0, 4), (GY, 4, 1), (BZ, 3, 1), (D, 0, 5)],
+        endpoint_bits: 11, delta_bits: [4, 5, 4], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (BY, 4, 1), (GY, 0, 4), (GX, 0, 4), (GW, 10, 1),
+        (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BW, 10, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 1, 1), (BZ, 2, 1), (RZ, 0, 4), (BZ, 4, 1), (BZ, 3, 1), (D, 0, 5)],
+        endpoint_bits: 11, delta_bits: [4, 4, 5], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 9), (BY, 4, 1), (GW, 0, 9), (GY, 4, 1), (BW, 0, 9), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5),
+        (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5)],
+        endpoint_bits: 9, delta_bits: [5, 5, 5], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 8), (GZ, 4, 1), (BY, 4, 1), (GW, 0, 8), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 8), (BZ, 3, 1), (BZ, 4, 1), (RX, 0, 6),
+        (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6), (D, 0, 5)],
+        endpoint_bits: 8, delta_bits: [6, 5, 5], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 8), (BZ, 0, 1), (BY, 4, 1), (GW, 0, 8), (GY, 5, 1), (GY, 4, 1), (BW, 0, 8), (GZ, 5, 1), (BZ, 4, 1), (RX, 0, 5),
+        (GZ, 4, 1), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5)],
+        endpoint_bits: 8, delta_bits: [5, 6, 5], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 8), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 8), (BY, 5, 1), (GY, 4, 1), (BW, 0, 8), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 5),
+        (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5)],
+        endpoint_bits: 8, delta_bits: [5, 5, 6], transformed: true, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 6), (GZ, 4, 1), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 6), (GY, 5, 1), (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1),
+        (BW, 0, 6), (GZ, 5, 1), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4),
+        (RY, 0, 6), (RZ, 0, 6), (D, 0, 5)],
+        endpoint_bits: 6, delta_bits: [6, 6, 6], transformed: false, subsets: 2 },
+    Bc6hMode { header: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 10), (GX, 0, 10), (BX, 0, 10)],
+        endpoint_bits: 10, delta_bits: [10, 10, 10], transformed: false, subsets: 1 },
+    Bc6hMode { header: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 9), (RW, 10, 1), (GX, 0, 9), (GW, 10, 1), (BX, 0, 9), (BW, 10, 1)],
+        endpoint_bits: 11, delta_bits: [9, 9, 9], transformed: true, subsets: 1 },
+    Bc6hMode { header: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 8), (RW, 10, -2), (GX, 0, 8), (GW, 10, -2), (BX, 0, 8), (BW, 10, -2)],
+        endpoint_bits: 12, delta_bits: [8, 8, 8], transformed: true, subsets: 1 },
+    Bc6hMode { header: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, -6), (GX, 0, 4), (GW, 10, -6), (BX, 0, 4), (BW, 10, -6)],
+        endpoint_bits: 16, delta_bits: [4, 4, 4], transformed: true, subsets: 1 }
+];
+
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    return (value << shift) >> shift;
+}
+
+fn bc6h_unquantize(value: i32, bits: u32, signed: bool) -> i32 {
+    if !signed {
+        return if bits >= 15 { value }
+            else if value == 0 { 0 }
+            else if value == (1 << bits) - 1 { 0xffff }
+            else { ((value << 16) + 0x8000) >> bits };
+    }
+
+    if bits >= 16 { return value; }
+    let magnitude = value.abs();
+    let unquantized = if magnitude == 0 { 0 }
+        else if magnitude >= (1 << (bits - 1)) - 1 { 0x7fff }
+        else { ((magnitude << 15) + 0x4000) >> (bits - 1) };
+    return if value < 0 { -unquantized } else { unquantized };
+}
+
+/// Converts the bits of a half float.
+pub fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half >> 15) as u32) << 31;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // Subnormal, shift the mantissa up until it has an implicit leading one.
+            let shift = mantissa.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | ((mantissa << shift) & 0x3ff) << 13
+        },
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13)
+    };
+    return f32::from_bits(bits);
+}
+
+/// BC6H, unsigned or signed half floats converted to `f32` with alpha 1.
+pub fn decode_bc6h(block: &[u8], signed: bool) -> [[f32; 4]; 16] {
+    let mut bits = Bits::new(block);
+    let mode_index = match bits.read(2) {
+        0 => 0,
+        1 => 1,
+        low => match (bits.read(3) << 2) | low {
+            0b00010 => 2, 0b00110 => 3, 0b01010 => 4, 0b01110 => 5, 0b10010 => 6, 0b10110 => 7, 0b11010 => 8, 0b11110 => 9,
+            0b00011 => 10, 0b00111 => 11, 0b01011 => 12, 0b01111 => 13,
+            _ => return [[0.0, 0.0, 0.0, 1.0]; 16]
+        }
+    };
+    let mode = &BC6H_MODES[mode_index];
+
+    let mut endpoints = [[0i32; 3]; 4];
+    let mut partition = 0usize;
+    for &(field, low, count) in mode.header {
+        let value = bits.read(count.unsigned_abs());
+        let value = if count < 0 { value.reverse_bits() >> (32 - count.unsigned_abs()) } else { value };
+        match field {
+            Field::Endpoint(endpoint, channel) => endpoints[endpoint][channel] |= (value << low) as i32,
+            Field::Partition => partition = value as usize
+        }
+    }
+
+    let endpoint_count = mode.subsets * 2;
+    if signed { endpoints[0].iter_mut().take(3).for_each(|value| *value = sign_extend(*value, mode.endpoint_bits)); }
+    for endpoint in endpoints.iter_mut().take(endpoint_count).skip(1) {
+        for (value, delta_bits) in endpoint.iter_mut().zip(mode.delta_bits) {
+            if mode.transformed {
+                *value = sign_extend(*value, delta_bits);
+            } else if signed {
+                *value = sign_extend(*value, mode.endpoint_bits);
+            }
+        }
+    }
+    if mode.transformed {
+        let base = endpoints[0];
+        let mask = (1 << mode.endpoint_bits) - 1;
+        for endpoint in endpoints.iter_mut().take(endpoint_count).skip(1) {
+            for channel in 0..3 {
+                endpoint[channel] = (endpoint[channel] + base[channel]) & mask;
+                if signed { endpoint[channel] = sign_extend(endpoint[channel], mode.endpoint_bits); }
+            }
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        for value in endpoint.iter_mut() { *value = bc6h_unquantize(*value, mode.endpoint_bits, signed); }
+    }
+
+    let index_bits = if mode.subsets == 1 { 4 } else { 3 };
+    let mut out = [[0.0, 0.0, 0.0, 1.0]; 16];
+    for (texel, pixel) in out.iter_mut().enumerate() {
+        let (subset, anchor) = subset(mode.subsets, partition, texel);
+        let weight = weight(index_bits, bits.read(index_bits - anchor as u32)) as i32;
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+
+        for channel in 0..3 {
+            let value = ((64 - weight) * e0[channel] + weight * e1[channel] + 32) >> 6;
+            let half = if !signed { ((value * 31) >> 6) as u16 }
+                else if value < 0 { 0x8000 | (((-value) * 31) >> 5) as u16 }
+                else { ((value * 31) >> 5) as u16 };
+            pixel[channel] = half_to_f32(half);
+        }
+    }
+
+    return out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Red and blue endpoints, texel `i` uses index `i % 4`.
+    const COLORS: [u8; 8] = [0x00, 0xf8, 0x1f, 0x00, 0xe4, 0xe4, 0xe4, 0xe4];
+    /// Texel `i` uses index `i % 8` of an interpolated block.
+    const ALPHA_INDICES: [u8; 6] = [0x88, 0xc6, 0xfa, 0x88, 0xc6, 0xfa];
+
+    #[test]
+    fn bc1_four_colors() {
+        let row = [[255, 0, 0, 255], [0, 0, 255, 255], [170, 0, 85, 255], [85, 0, 170, 255]];
+        assert_eq!(decode_bc1(&COLORS), [row; 4].concat()[..]);
+    }
+
+    #[test]
+    fn bc1_three_colors_and_transparent() {
+        let block = [0x1f, 0x00, 0x00, 0xf8, 0xe4, 0xe4, 0xe4, 0xe4];
+        let row = [[0, 0, 255, 255], [255, 0, 0, 255], [127, 0, 127, 255], [0, 0, 0, 0]];
+        assert_eq!(decode_bc1(&block), [row; 4].concat()[..]);
+    }
+
+    #[test]
+    fn bc2_explicit_alpha() {
+        let mut block = [0x10, 0x32, 0x54, 0x76, 0x98, 0xba, 0xdc, 0xfe, 0, 0, 0, 0, 0, 0, 0, 0];
+        block[8..].copy_from_slice(&[0x00, 0xf8, 0x1f, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+        let decoded = decode_bc2(&block);
+        for (texel, pixel) in decoded.iter().enumerate() { assert_eq!(*pixel, [255, 0, 0, texel as u8 * 17]); }
+    }
+
+    #[test]
+    fn bc3_eight_alpha_values() {
+        let mut block = [0; 16];
+        block[0..2].copy_from_slice(&[255, 0]);
+        block[2..8].copy_from_slice(&ALPHA_INDICES);
+        block[8..].copy_from_slice(&[0xe0, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+        let alphas = [255, 0, 218, 182, 145, 109, 72, 36];
+        for (texel, pixel) in decode_bc3(&block).iter().enumerate() { assert_eq!(*pixel, [0, 255, 0, alphas[texel % 8]]); }
+    }
+
+    #[test]
+    fn bc4_six_values_with_black_and_white() {
+        let mut block = [0; 8];
+        block[0..2].copy_from_slice(&[0, 255]);
+        block[2..8].copy_from_slice(&ALPHA_INDICES);
+
+        let reds = [0, 255, 51, 102, 153, 204, 0, 255];
+        for (texel, pixel) in decode_bc4(&block).iter().enumerate() { assert_eq!(*pixel, [reds[texel % 8], 0, 0, 255]); }
+    }
+
+    #[test]
+    fn bc5_two_channels() {
+        let mut block = [0; 16];
+        block[0..2].copy_from_slice(&[0, 255]);
+        block[2..8].copy_from_slice(&ALPHA_INDICES);
+        block[8..10].copy_from_slice(&[200, 200]);
+
+        let reds = [0, 255, 51, 102, 153, 204, 0, 255];
+        for (texel, pixel) in decode_bc5(&block).iter().enumerate() { assert_eq!(*pixel, [reds[texel % 8], 200, 0, 255]); }
+    }
+
+    #[test]
+    fn bc7_mode_6() {
+        // Red 0 to 127 and blue 127 to 0 at 7 bits, green 64, alpha 127, both p-bits set, texel `i` uses index `i`.
+        let block = [0x40, 0xc0, 0x1f, 0x08, 0xfc, 0x03, 0xfe, 0xff, 0x11, 0x32, 0x54, 0x76, 0x98, 0xba, 0xdc, 0xfe];
+        let reds = [1, 17, 37, 53, 68, 84, 104, 120, 136, 152, 172, 188, 203, 219, 239, 255];
+
+        for (texel, pixel) in decode_bc7(&block).iter().enumerate() {
+            assert_eq!(*pixel, [reds[texel], 129, reds[15 - texel], 255]);
+        }
+    }
+
+    #[test]
+    fn bc7_reserved_mode_is_black() {
+        assert_eq!(decode_bc7(&[0; 16]), [[0; 4]; 16]);
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        assert_eq!(half_to_f32(0x0001), 2.0f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+    }
+
+    #[test]
+    fn bc6h_unsigned() {
+        // Mode 11, one subset with 10 bit endpoints: black and (495, 0, 1023), the top half of the block uses the first.
+        let block = [0x03, 0x00, 0x00, 0x00, 0x78, 0x0f, 0x80, 0xff, 0x01, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff];
+        let decoded = decode_bc6h(&block, false);
+
+        assert!(decoded[..8].iter().all(|pixel| *pixel == [0.0, 0.0, 0.0, 1.0]));
+        assert!(decoded[8..].iter().all(|pixel| *pixel == [1.0, 0.0, 65504.0, 1.0]));
+    }
+
+    #[test]
+    fn bc6h_signed() {
+        // The same mode with (-512, 0, 511) as the second endpoint, the extremes of 10 bit signed values.
+        let block = [0x03, 0x00, 0x00, 0x00, 0x00, 0x10, 0x80, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff];
+        let decoded = decode_bc6h(&block, true);
+
+        assert!(decoded[..8].iter().all(|pixel| *pixel == [0.0, 0.0, 0.0, 1.0]));
+        assert!(decoded[8..].iter().all(|pixel| *pixel == [-65504.0, 0.0, 65504.0, 1.0]));
+    }
+}
@@ -0,0 +1,109 @@
+/// Orthographic camera for 2D scenes measured in pixels, with y pointing up.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Camera2d {
+    /// World position shown at the center of the viewport.
+    pub position: [f32; 2],
+    /// Screen pixels per world unit, 2 shows everything twice as large.
+    pub zoom: f32,
+    /// Counterclockwise rotation of the camera in radians, the world appears to turn the other way.
+    pub rotation: f32
+}
+
+impl Default for Camera2d {
+    fn default() -> Camera2d {
+        return Camera2d { position: [0.0, 0.0], zoom: 1.0, rotation: 0.0 };
+    }
+}
+
+impl Camera2d {
+    /// Camera that maps world units one to one onto the pixels of `viewport`, with the origin in its bottom left corner.
+    pub fn screen(viewport: [u32; 2]) -> Camera2d {
+        return Camera2d { position: [viewport[0] as f32 / 2.0, viewport[1] as f32 / 2.0], .. Camera2d::default() };
+    }
+
+    /// World to clip space for a viewport of `viewport` pixels, column major.
+    pub fn matrix(&self, viewport: [u32; 2]) -> [[f32; 4]; 4] {
+        let scale_x = 2.0 * self.zoom / viewport[0].max(1) as f32;
+        let scale_y = 2.0 * self.zoom / viewport[1].max(1) as f32;
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let [x, y] = self.position;
+
+        return [
+            [scale_x * cos, scale_y * sin, 0.0, 0.0],
+            [-scale_x * sin, scale_y * cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [-scale_x * (cos * x - sin * y), -scale_y * (sin * x + cos * y), 0.0, 1.0]
+        ];
+    }
+
+    /// Converts a position in viewport pixels, measured from the top left like the cursor, into the world.
+    pub fn screen_to_world(&self, screen: [f32; 2], viewport: [u32; 2]) -> [f32; 2] {
+        let offset = [(screen[0] - viewport[0] as f32 / 2.0) / self.zoom, (viewport[1] as f32 / 2.0 - screen[1]) / self.zoom];
+        let (sin, cos) = self.rotation.sin_cos();
+
+        return [
+            self.position[0] + cos * offset[0] - sin * offset[1],
+            self.position[1] + sin * offset[0] + cos * offset[1]
+        ];
+    }
+
+    /// Converts a world position into viewport pixels measured from the top left.
+    pub fn world_to_screen(&self, world: [f32; 2], viewport: [u32; 2]) -> [f32; 2] {
+        let offset = [world[0] - self.position[0], world[1] - self.position[1]];
+        let (sin, cos) = (-self.rotation).sin_cos();
+
+        return [
+            viewport[0] as f32 / 2.0 + (cos * offset[0] - sin * offset[1]) * self.zoom,
+            viewport[1] as f32 / 2.0 - (sin * offset[0] + cos * offset[1]) * self.zoom
+        ];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWPORT: [u32; 2] = [800, 600];
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool { (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3 }
+
+    fn cameras() -> [Camera2d; 3] {
+        return [
+            Camera2d::screen(VIEWPORT),
+            Camera2d { position: [-40.0, 25.0], zoom: 2.5, rotation: 0.0 },
+            Camera2d { position: [120.0, -7.0], zoom: 0.5, rotation: 0.7 }
+        ];
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        for camera in cameras() {
+            for world in [[0.0, 0.0], [13.0, -250.0], [-400.0, 90.5]] {
+                let screen = camera.world_to_screen(world, VIEWPORT);
+                assert!(close(camera.screen_to_world(screen, VIEWPORT), world), "{:?} {:?}", camera, world);
+            }
+        }
+    }
+
+    #[test]
+    fn matrix_matches_world_to_screen() {
+        for camera in cameras() {
+            let matrix = camera.matrix(VIEWPORT);
+            for world in [[0.0, 0.0], [13.0, -250.0], [-400.0, 90.5]] {
+                let clip = [0, 1].map(|row| matrix[0][row] * world[0] + matrix[1][row] * world[1] + matrix[3][row]);
+                let screen = camera.world_to_screen(world, VIEWPORT);
+                let expected = [screen[0] / VIEWPORT[0] as f32 * 2.0 - 1.0, 1.0 - screen[1] / VIEWPORT[1] as f32 * 2.0];
+                assert!(close(clip, expected), "{:?} {:?}", camera, world);
+            }
+        }
+    }
+
+    #[test]
+    fn zoom_and_rotation() {
+        // Turning the camera a quarter counterclockwise makes the world turn clockwise, so up moves right.
+        let camera = Camera2d { position: [10.0, 0.0], zoom: 2.0, rotation: std::f32::consts::FRAC_PI_2 };
+        assert!(close(camera.world_to_screen([10.0, 5.0], VIEWPORT), [410.0, 300.0]));
+        assert!(close(camera.screen_to_world([400.0, 300.0], VIEWPORT), [10.0, 0.0]));
+        assert!(close(Camera2d::screen(VIEWPORT).world_to_screen([0.0, 0.0], VIEWPORT), [0.0, 600.0]));
+    }
+}
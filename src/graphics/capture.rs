@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+use glium::texture::{RawImage2d, Texture2d};
+use glium::uniforms::MagnifySamplerFilter;
+use glium::{BlitTarget, Surface};
+
+use super::context::Display;
+
+/// Image waiting to be written and where it goes.
+type SaveJob = (PathBuf, image::RgbaImage);
+
+/// Images queued for the saver thread at most. Once it falls this far behind the game waits for
+/// it, so a recording keeps every frame without holding an unbounded number of them in memory.
+const MAX_QUEUED_IMAGES: usize = 4;
+
+/// A screenshot or recorded frame that couldn't be written.
+#[derive(Debug)]
+pub struct SaveError {
+    pub path: PathBuf,
+    pub error: image::ImageError
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to save {}: {}", self.path.display(), self.error)
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+struct Saver {
+    sender: SyncSender<SaveJob>,
+    errors: Receiver<SaveError>,
+    thread: JoinHandle<()>
+}
+
+struct Recording {
+    directory: PathBuf,
+    timestep: f32,
+    frame: u32
+}
+
+/// Screenshots and image sequence recording of the main window. Files are written on a
+/// background thread so taking a screenshot doesn't stall the game.
+pub struct Capture {
+    /// Action that saves a screenshot into `screenshot_directory`, `None` turns it off.
+    pub screenshot_action: Option<String>,
+    pub screenshot_directory: PathBuf,
+    screenshots: Vec<PathBuf>,
+    recording: Option<Recording>,
+    texture: Option<Texture2d>,
+    saver: Option<Saver>,
+    errors: Vec<SaveError>
+}
+
+impl Default for Capture {
+    fn default() -> Capture {
+        return Capture {
+            screenshot_action: Some("screenshot".to_string()),
+            screenshot_directory: PathBuf::from("screenshots"),
+            screenshots: Vec::new(),
+            recording: None,
+            texture: None,
+            saver: None,
+            errors: Vec::new()
+        };
+    }
+}
+
+impl Capture {
+    /// Saves the next frame as a PNG at `path`.
+    pub fn screenshot(&mut self, path: impl Into<PathBuf>) { self.screenshots.push(path.into()); }
+
+    /// Saves the next frame into `screenshot_directory` under a name made from the current time.
+    pub fn take_screenshot(&mut self) -> PathBuf {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let path = self.screenshot_directory.join(format!("screenshot_{}_{:03}.png", time.as_secs(), time.subsec_millis()));
+        self.screenshot(path.clone());
+
+        return path;
+    }
+
+    /// Saves every frame to `directory` as `frame_000000.png`, `frame_000001.png` and so on. While recording
+    /// `App::update` gets a fixed delta time of one frame at `frames_per_second`, however long frames really take.
+    pub fn start_recording(&mut self, directory: impl AsRef<Path>, frames_per_second: u32) -> std::io::Result<()> {
+        std::fs::create_dir_all(directory.as_ref())?;
+        self.recording = Some(Recording {
+            directory: directory.as_ref().to_path_buf(),
+            timestep: 1.0 / frames_per_second.max(1) as f32,
+            frame: 0
+        });
+
+        return Ok(());
+    }
+
+    /// Stops recording and returns how many frames were recorded.
+    pub fn stop_recording(&mut self) -> u32 {
+        return self.recording.take().map(|recording| recording.frame).unwrap_or(0);
+    }
+
+    pub fn is_recording(&self) -> bool { self.recording.is_some() }
+
+    /// Simulated time per frame while recording.
+    pub fn timestep(&self) -> Option<f32> { self.recording.as_ref().map(|recording| recording.timestep) }
+
+    /// Images that failed to save since the last call, oldest first.
+    pub fn take_errors(&mut self) -> Vec<SaveError> {
+        if let Some(saver) = &self.saver { self.errors.extend(saver.errors.try_iter()); }
+        return std::mem::take(&mut self.errors);
+    }
+
+    /// Copies what has been drawn to `frame` so far into an image, top row first.
+    pub fn read_frame<S: Surface>(display: &Display, frame: &S) -> image::RgbaImage {
+        let (width, height) = frame.get_dimensions();
+        let texture = Texture2d::empty(display, width, height).unwrap();
+        return Capture::read_into(&texture, frame);
+    }
+
+    fn read_into<S: Surface>(texture: &Texture2d, frame: &S) -> image::RgbaImage {
+        let (width, height) = frame.get_dimensions();
+        let target = BlitTarget { left: 0, bottom: 0, width: width as i32, height: height as i32 };
+        frame.blit_whole_color_to(&texture.as_surface(), &target, MagnifySamplerFilter::Nearest);
+
+        let pixels: RawImage2d<u8> = texture.read();
+        let mut image = image::RgbaImage::from_raw(pixels.width, pixels.height, pixels.data.into_owned()).unwrap();
+        image::imageops::flip_vertical_in_place(&mut image);
+
+        return image;
+    }
+
+    /// Reads back `frame` if a screenshot was asked for or a recording is running, called by the
+    /// engine once the frame is fully drawn.
+    pub(crate) fn capture<S: Surface>(&mut self, display: &Display, frame: &S) {
+        if self.screenshots.is_empty() && self.recording.is_none() { return; }
+
+        let (width, height) = frame.get_dimensions();
+        let texture = match self.texture.take() {
+            Some(texture) if texture.dimensions() == (width, height) => texture,
+            _ => Texture2d::empty(display, width, height).unwrap()
+        };
+        let image = Capture::read_into(&texture, frame);
+        self.texture = Some(texture);
+
+        let mut paths = std::mem::take(&mut self.screenshots);
+        if let Some(recording) = &mut self.recording {
+            paths.push(recording.directory.join(format!("frame_{:06}.png", recording.frame)));
+            recording.frame += 1;
+        }
+
+        let saver = self.saver.get_or_insert_with(|| {
+            let (sender, receiver) = sync_channel::<SaveJob>(MAX_QUEUED_IMAGES);
+            let (error_sender, errors) = channel();
+            let thread = std::thread::spawn(move || {
+                for (path, mut image) in receiver {
+                    // The window's alpha is whatever blending left behind, screenshots are opaque.
+                    image.pixels_mut().for_each(|pixel| pixel[3] = 255);
+                    if let Some(parent) = path.parent() { let _ = std::fs::create_dir_all(parent); }
+                    if let Err(error) = image.save(&path) { let _ = error_sender.send(SaveError { path, error }); }
+                }
+            });
+            Saver { sender, errors, thread }
+        });
+        for path in paths { let _ = saver.sender.send((path, image.clone())); }
+    }
+
+    /// Waits until every queued image is written, called by the engine when it shuts down.
+    pub(crate) fn finish(&mut self) {
+        if let Some(saver) = self.saver.take() {
+            drop(saver.sender);
+            let _ = saver.thread.join();
+            self.errors.extend(saver.errors.try_iter());
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use std::path::Path;
+
+use super::bcn;
+use super::texture::TextureError;
+
+/// Block compression formats, every block covers 4x4 texels.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BlockFormat {
+    /// BC1 without alpha.
+    Bc1,
+    /// BC1 with 1 bit alpha.
+    Bc1Alpha,
+    Bc2,
+    Bc3,
+    /// One channel, read from `.r`.
+    Bc4,
+    /// Two channels, read from `.rg`, mostly normal maps.
+    Bc5,
+    /// HDR color as unsigned half floats.
+    Bc6hUnsigned,
+    /// HDR color as signed half floats.
+    Bc6hSigned,
+    Bc7
+}
+
+impl BlockFormat {
+    pub fn block_size(&self) -> usize {
+        return match self {
+            BlockFormat::Bc1 | BlockFormat::Bc1Alpha | BlockFormat::Bc4 => 8,
+            _ => 16
+        };
+    }
+
+    pub fn is_hdr(&self) -> bool { matches!(self, BlockFormat::Bc6hUnsigned | BlockFormat::Bc6hSigned) }
+}
+
+/// Texels of a decoded level, bottom row first like the images `Texture` uploads.
+pub enum DecodedLevel {
+    Rgba8(Vec<u8>),
+    RgbaF32(Vec<f32>)
+}
+
+/// A block compressed 2D texture with its mip chain as stored in a KTX2 or DDS file. Rows stay
+/// in file order, top row first.
+pub struct CompressedImage {
+    pub format: BlockFormat,
+    pub srgb: bool,
+    pub width: u32,
+    pub height: u32,
+    /// Base level first.
+    pub levels: Vec<Vec<u8>>
+}
+
+fn level_size(format: BlockFormat, width: u32, height: u32) -> Result<usize, TextureError> {
+    return (width.div_ceil(4).max(1) as usize).checked_mul(height.div_ceil(4).max(1) as usize)
+        .and_then(|blocks| blocks.checked_mul(format.block_size()))
+        .ok_or_else(|| TextureError::Invalid(format!("{}x{} texture is too large", width, height)));
+}
+
+/// Rejects empty textures and more levels than halving the larger side down to 1 gives, before
+/// anything is allocated for them.
+fn check_levels(width: u32, height: u32, level_count: u32) -> Result<(), TextureError> {
+    if width == 0 || height == 0 { return Err(TextureError::Invalid(format!("{}x{} texture is empty", width, height))); }
+
+    let max_levels = 32 - width.max(height).leading_zeros();
+    if level_count > max_levels {
+        return Err(TextureError::Invalid(format!("{}x{} texture can't have {} mip levels, at most {}", width, height, level_count, max_levels)));
+    }
+    return Ok(());
+}
+
+fn level_bytes<'a>(data: &'a [u8], offset: usize, length: usize, name: &str, level: usize) -> Result<&'a [u8], TextureError> {
+    return offset.checked_add(length)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| TextureError::Invalid(format!("{} level {} is cut off", name, level)));
+}
+
+fn level_dimensions(width: u32, height: u32, level: usize) -> (u32, u32) {
+    return ((width >> level).max(1), (height >> level).max(1));
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, TextureError> {
+    let bytes = data.get(offset..offset + 4).ok_or_else(|| TextureError::Invalid("file ends inside its header".to_string()))?;
+    return Ok(u32::from_le_bytes(bytes.try_into().unwrap()));
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, TextureError> {
+    let bytes = data.get(offset..offset + 8).ok_or_else(|| TextureError::Invalid("file ends inside its header".to_string()))?;
+    return Ok(u64::from_le_bytes(bytes.try_into().unwrap()));
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a];
+
+impl CompressedImage {
+    /// Loads a `.ktx2` or `.dds` file, told apart by their magic bytes.
+    pub fn load(path: impl AsRef<Path>) -> Result<CompressedImage, TextureError> {
+        let data = std::fs::read(path).map_err(TextureError::Io)?;
+        return CompressedImage::parse(&data);
+    }
+
+    pub fn parse(data: &[u8]) -> Result<CompressedImage, TextureError> {
+        if data.starts_with(&KTX2_IDENTIFIER) { return CompressedImage::parse_ktx2(data); }
+        if data.starts_with(b"DDS ") { return CompressedImage::parse_dds(data); }
+        return Err(TextureError::Invalid("not a KTX2 or DDS file".to_string()));
+    }
+
+    pub fn parse_ktx2(data: &[u8]) -> Result<CompressedImage, TextureError> {
+        let (format, srgb) = match read_u32(data, 12)? {
+            131 => (BlockFormat::Bc1, false),
+            132 => (BlockFormat::Bc1, true),
+            133 => (BlockFormat::Bc1Alpha, false),
+            134 => (BlockFormat::Bc1Alpha, true),
+            135 => (BlockFormat::Bc2, false),
+            136 => (BlockFormat::Bc2, true),
+            137 => (BlockFormat::Bc3, false),
+            138 => (BlockFormat::Bc3, true),
+            139 => (BlockFormat::Bc4, false),
+            141 => (BlockFormat::Bc5, false),
+            143 => (BlockFormat::Bc6hUnsigned, false),
+            144 => (BlockFormat::Bc6hSigned, false),
+            145 => (BlockFormat::Bc7, false),
+            146 => (BlockFormat::Bc7, true),
+            vk_format => return Err(TextureError::Invalid(format!("KTX2 format {} is not a supported BC format", vk_format)))
+        };
+        let (width, height, depth) = (read_u32(data, 20)?, read_u32(data, 24)?, read_u32(data, 28)?);
+        let (layers, faces, level_count) = (read_u32(data, 32)?, read_u32(data, 36)?, read_u32(data, 40)?.max(1));
+        if depth > 1 || layers > 1 || faces != 1 {
+            return Err(TextureError::Invalid("only 2D KTX2 textures are supported, not arrays, cubemaps or 3D textures".to_string()));
+        }
+        if read_u32(data, 44)? != 0 { return Err(TextureError::Invalid("supercompressed KTX2 files are not supported".to_string())); }
+        check_levels(width, height, level_count)?;
+
+        let mut levels = Vec::with_capacity(level_count as usize);
+        for level in 0..level_count as usize {
+            let offset = read_u64(data, 80 + level * 24)? as usize;
+            let length = read_u64(data, 88 + level * 24)? as usize;
+            let (level_width, level_height) = level_dimensions(width, height, level);
+            let expected = level_size(format, level_width, level_height)?;
+            if length != expected {
+                return Err(TextureError::Invalid(format!("KTX2 level {} has {} bytes instead of {}", level, length, expected)));
+            }
+
+            levels.push(level_bytes(data, offset, length, "KTX2", level)?.to_vec());
+        }
+
+        return Ok(CompressedImage { format, srgb, width, height, levels });
+    }
+
+    pub fn parse_dds(data: &[u8]) -> Result<CompressedImage, TextureError> {
+        const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+        const DDSCAPS2_CUBEMAP: u32 = 0x200;
+        const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+        let (flags, height, width) = (read_u32(data, 8)?, read_u32(data, 12)?, read_u32(data, 16)?);
+        let level_count = if flags & DDSD_MIPMAPCOUNT != 0 { read_u32(data, 28)?.max(1) } else { 1 };
+        if read_u32(data, 112)? & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0 {
+            return Err(TextureError::Invalid("only 2D DDS textures are supported, not cubemaps or volumes".to_string()));
+        }
+        check_levels(width, height, level_count)?;
+
+        let four_cc = data.get(84..88).unwrap_or_default();
+        let ((format, srgb), mut offset) = match four_cc {
+            b"DXT1" => ((BlockFormat::Bc1Alpha, false), 128),
+            b"DXT2" | b"DXT3" => ((BlockFormat::Bc2, false), 128),
+            b"DXT4" | b"DXT5" => ((BlockFormat::Bc3, false), 128),
+            b"ATI1" | b"BC4U" => ((BlockFormat::Bc4, false), 128),
+            b"ATI2" | b"BC5U" => ((BlockFormat::Bc5, false), 128),
+            b"DX10" => {
+                if read_u32(data, 140)? > 1 { return Err(TextureError::Invalid("DDS texture arrays are not supported".to_string())); }
+                let format = match read_u32(data, 128)? {
+                    70 | 71 => (BlockFormat::Bc1Alpha, false),
+                    72 => (BlockFormat::Bc1Alpha, true),
+                    73 | 74 => (BlockFormat::Bc2, false),
+                    75 => (BlockFormat::Bc2, true),
+                    76 | 77 => (BlockFormat::Bc3, false),
+                    78 => (BlockFormat::Bc3, true),
+                    79 | 80 => (BlockFormat::Bc4, false),
+                    82 | 83 => (BlockFormat::Bc5, false),
+                    94 | 95 => (BlockFormat::Bc6hUnsigned, false),
+                    96 => (BlockFormat::Bc6hSigned, false),
+                    97 | 98 => (BlockFormat::Bc7, false),
+                    99 => (BlockFormat::Bc7, true),
+                    dxgi_format => return Err(TextureError::Invalid(format!("DXGI format {} is not a supported BC format", dxgi_format)))
+                };
+                (format, 148)
+            },
+            _ => return Err(TextureError::Invalid(format!("DDS FourCC {:?} is not a supported BC format", String::from_utf8_lossy(four_cc))))
+        };
+
+        let mut levels = Vec::with_capacity(level_count as usize);
+        for level in 0..level_count as usize {
+            let (level_width, level_height) = level_dimensions(width, height, level);
+            let length = level_size(format, level_width, level_height)?;
+            levels.push(level_bytes(data, offset, length, "DDS", level)?.to_vec());
+            offset += length;
+        }
+
+        return Ok(CompressedImage { format, srgb, width, height, levels });
+    }
+
+    pub fn level_dimensions(&self, level: usize) -> (u32, u32) { level_dimensions(self.width, self.height, level) }
+
+    /// The first `count` levels with their rows flipped to bottom first, ready to upload as they are. None when
+    /// that can't be done block by block: BC6H and BC7 pack their texels too tightly and levels taller than
+    /// one block row need a height that is a multiple of 4, otherwise flipped rows straddle blocks.
+    pub fn flipped_levels(&self, count: usize) -> Option<Vec<Vec<u8>>> {
+        if matches!(self.format, BlockFormat::Bc6hUnsigned | BlockFormat::Bc6hSigned | BlockFormat::Bc7) { return None; }
+
+        let block_size = self.format.block_size();
+        let mut levels = Vec::with_capacity(count);
+        for (level, data) in self.levels.iter().take(count).enumerate() {
+            let (width, height) = self.level_dimensions(level);
+            if height > 4 && height % 4 != 0 { return None; }
+
+            let row_size = width.div_ceil(4) as usize * block_size;
+            let mut flipped: Vec<u8> = data.chunks_exact(row_size).rev().flatten().copied().collect();
+            for block in flipped.chunks_exact_mut(block_size) { flip_block(self.format, block, height.min(4) as usize); }
+            levels.push(flipped);
+        }
+        return Some(levels);
+    }
+
+    /// Decodes a level on the CPU, BC6H gives floats and everything else 8 bit RGBA.
+    pub fn decode(&self, level: usize) -> DecodedLevel {
+        let (width, height) = self.level_dimensions(level);
+        let blocks_wide = width.div_ceil(4) as usize;
+        let block_size = self.format.block_size();
+        let (width, height) = (width as usize, height as usize);
+
+        // Blocks hang over the edge of levels that aren't a multiple of 4, those texels are dropped. Yields the
+        // texel index in the block and where it goes in the output.
+        let texels = |block_index: usize| {
+            let (block_x, block_y) = (block_index % blocks_wide * 4, block_index / blocks_wide * 4);
+            (0..16).map(move |texel| (block_x + texel % 4, block_y + texel / 4))
+                .enumerate()
+                .filter(move |(_, (x, y))| *x < width && *y < height)
+                .map(move |(texel, (x, y))| (texel, ((height - 1 - y) * width + x) * 4))
+        };
+
+        let blocks = self.levels[level].chunks_exact(block_size).enumerate();
+        if self.format.is_hdr() {
+            let signed = self.format == BlockFormat::Bc6hSigned;
+            let mut out = vec![0.0; width * height * 4];
+            for (index, block) in blocks {
+                let decoded = bcn::decode_bc6h(block, signed);
+                for (texel, offset) in texels(index) { out[offset..][..4].copy_from_slice(&decoded[texel]); }
+            }
+            return DecodedLevel::RgbaF32(out);
+        }
+
+        let decode = match self.format {
+            BlockFormat::Bc1 => |block: &[u8]| bcn::decode_bc1(block).map(|[r, g, b, _]| [r, g, b, 255]),
+            BlockFormat::Bc1Alpha => bcn::decode_bc1,
+            BlockFormat::Bc2 => bcn::decode_bc2,
+            BlockFormat::Bc3 => bcn::decode_bc3,
+            BlockFormat::Bc4 => bcn::decode_bc4,
+            BlockFormat::Bc5 => bcn::decode_bc5,
+            _ => bcn::decode_bc7
+        };
+        let mut out = vec![0; width * height * 4];
+        for (index, block) in blocks {
+            let decoded = decode(block);
+            for (texel, offset) in texels(index) { out[offset..][..4].copy_from_slice(&decoded[texel]); }
+        }
+        return DecodedLevel::Rgba8(out);
+    }
+}
+
+/// Reverses the first `rows` texel rows of a BC1-BC5 block in place.
+fn flip_block(format: BlockFormat, block: &mut [u8], rows: usize) {
+    match format {
+        BlockFormat::Bc1 | BlockFormat::Bc1Alpha => block[4..4 + rows].reverse(),
+        BlockFormat::Bc2 => {
+            // 4 bit alpha, two bytes per row.
+            for row in 0..rows / 2 {
+                let other = rows - 1 - row;
+                block.swap(row * 2, other * 2);
+                block.swap(row * 2 + 1, other * 2 + 1);
+            }
+            block[12..12 + rows].reverse();
+        },
+        BlockFormat::Bc3 => {
+            flip_indices(&mut block[0..8], rows);
+            block[12..12 + rows].reverse();
+        },
+        BlockFormat::Bc4 => flip_indices(block, rows),
+        BlockFormat::Bc5 => {
+            flip_indices(&mut block[0..8], rows);
+            flip_indices(&mut block[8..16], rows);
+        },
+        _ => unreachable!()
+    }
+}
+
+/// Reverses the rows of the 48 bits of 3 bit indices behind the two endpoints of BC3 alpha, BC4 and BC5.
+fn flip_indices(block: &mut [u8], rows: usize) {
+    let mut bits = 0u64;
+    for (i, byte) in block[2..8].iter().enumerate() { bits |= (*byte as u64) << (8 * i); }
+
+    let mut flipped = bits;
+    for row in 0..rows {
+        let source = (bits >> ((rows - 1 - row) * 12)) & 0xfff;
+        flipped = (flipped & !(0xfff << (row * 12))) | (source << (row * 12));
+    }
+    block[2..8].copy_from_slice(&flipped.to_le_bytes()[..6]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put(data: &mut [u8], offset: usize, value: u32) { data[offset..offset + 4].copy_from_slice(&value.to_le_bytes()); }
+
+    /// DXT1 header followed by `data_size` zero bytes.
+    fn dds(width: u32, height: u32, level_count: u32, data_size: usize) -> Vec<u8> {
+        let mut data = vec![0; 128 + data_size];
+        data[0..4].copy_from_slice(b"DDS ");
+        put(&mut data, 4, 124);
+        put(&mut data, 8, 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000);
+        put(&mut data, 12, height);
+        put(&mut data, 16, width);
+        put(&mut data, 28, level_count);
+        put(&mut data, 76, 32);
+        put(&mut data, 80, 0x4);
+        data[84..88].copy_from_slice(b"DXT1");
+        return data;
+    }
+
+    /// BC1 header with one level index entry per `(offset, length)`, followed by `data_size` zero bytes.
+    fn ktx2(width: u32, height: u32, level_count: u32, levels: &[(u64, u64)], data_size: usize) -> Vec<u8> {
+        let mut data = vec![0; 80 + levels.len() * 24];
+        data[0..12].copy_from_slice(&KTX2_IDENTIFIER);
+        put(&mut data, 12, 131);
+        put(&mut data, 20, width);
+        put(&mut data, 24, height);
+        put(&mut data, 36, 1);
+        put(&mut data, 40, level_count);
+        for (level, (offset, length)) in levels.iter().enumerate() {
+            data[80 + level * 24..][..8].copy_from_slice(&offset.to_le_bytes());
+            data[88 + level * 24..][..8].copy_from_slice(&length.to_le_bytes());
+        }
+        data.resize(data.len() + data_size, 0);
+        return data;
+    }
+
+    fn invalid(result: Result<CompressedImage, TextureError>) -> bool { matches!(result, Err(TextureError::Invalid(_))) }
+
+    #[test]
+    fn dds_mip_chain() {
+        let image = CompressedImage::parse(&dds(8, 8, 4, 32 + 8 + 8 + 8)).unwrap();
+        assert_eq!(image.format, BlockFormat::Bc1Alpha);
+        assert_eq!(image.levels.iter().map(Vec::len).collect::<Vec<_>>(), [32, 8, 8, 8]);
+        assert_eq!(image.level_dimensions(3), (1, 1));
+    }
+
+    #[test]
+    fn ktx2_mip_chain() {
+        let data = ktx2(8, 4, 2, &[(128, 16), (144, 8)], 24);
+        let image = CompressedImage::parse(&data).unwrap();
+        assert_eq!((image.format, image.width, image.height), (BlockFormat::Bc1, 8, 4));
+        assert_eq!(image.levels.iter().map(Vec::len).collect::<Vec<_>>(), [16, 8]);
+    }
+
+    #[test]
+    fn truncated_headers() {
+        assert!(invalid(CompressedImage::parse(&dds(8, 8, 1, 32)[..100])));
+        assert!(invalid(CompressedImage::parse(&ktx2(8, 8, 1, &[], 0)[..30])));
+        assert!(invalid(CompressedImage::parse(b"DDS")));
+        assert!(invalid(CompressedImage::parse(b"PNG image")));
+    }
+
+    #[test]
+    fn truncated_levels() {
+        assert!(invalid(CompressedImage::parse(&dds(8, 8, 2, 32))));
+        assert!(invalid(CompressedImage::parse(&ktx2(8, 8, 1, &[(104, 32)], 16))));
+        // The level index itself is cut off.
+        assert!(invalid(CompressedImage::parse(&ktx2(8, 8, 2, &[(128, 32)], 0))));
+    }
+
+    #[test]
+    fn empty_textures() {
+        assert!(invalid(CompressedImage::parse(&dds(0, 8, 1, 32))));
+        assert!(invalid(CompressedImage::parse(&ktx2(8, 0, 1, &[(104, 8)], 8))));
+    }
+
+    #[test]
+    fn oversized_level_counts() {
+        assert!(invalid(CompressedImage::parse(&dds(8, 8, 5, 1024))));
+        assert!(invalid(CompressedImage::parse(&dds(8, 8, 40, 1024))));
+        assert!(invalid(CompressedImage::parse(&dds(8, 8, u32::MAX, 0))));
+        assert!(invalid(CompressedImage::parse(&ktx2(8, 8, u32::MAX, &[], 0))));
+    }
+
+    #[test]
+    fn oversized_offsets_and_dimensions() {
+        assert!(invalid(CompressedImage::parse(&ktx2(4, 4, 1, &[(u64::MAX - 4, 8)], 8))));
+        assert!(invalid(CompressedImage::parse(&ktx2(4, 4, 1, &[(104, u64::MAX)], 8))));
+        assert!(invalid(CompressedImage::parse(&dds(u32::MAX, u32::MAX, 1, 0))));
+    }
+
+    /// Bytes that exercise every index bit, the endpoints don't matter for flipping.
+    fn pattern(length: usize) -> Vec<u8> { (0..length).map(|i| (i * 37 + 11) as u8).collect() }
+
+    #[test]
+    fn flipped_blocks_decode_upside_down() {
+        let formats = [BlockFormat::Bc1, BlockFormat::Bc2, BlockFormat::Bc3, BlockFormat::Bc4, BlockFormat::Bc5];
+        for (format, (width, height)) in formats.into_iter().flat_map(|format| [(format, (8u32, 8u32)), (format, (4, 2)), (format, (4, 1))]) {
+            let blocks = (width.div_ceil(4) * height.div_ceil(4)) as usize;
+            let image = CompressedImage { format, srgb: false, width, height, levels: vec![pattern(blocks * format.block_size())] };
+            let flipped = CompressedImage { levels: image.flipped_levels(1).unwrap(), ..image };
+            let (DecodedLevel::Rgba8(original), DecodedLevel::Rgba8(upside_down)) = (image.decode(0), flipped.decode(0)) else { unreachable!() };
+
+            let rows: Vec<_> = original.chunks(width as usize * 4).rev().flatten().copied().collect();
+            assert_eq!(upside_down, rows, "{:?} {}x{}", format, width, height);
+        }
+    }
+
+    #[test]
+    fn unflippable_levels() {
+        let image = |format, height| CompressedImage { format, srgb: false, width: 4, height, levels: vec![vec![0; 32]] };
+        assert!(image(BlockFormat::Bc7, 4).flipped_levels(1).is_none());
+        assert!(image(BlockFormat::Bc6hUnsigned, 4).flipped_levels(1).is_none());
+        assert!(image(BlockFormat::Bc1, 6).flipped_levels(1).is_none());
+        assert!(image(BlockFormat::Bc1, 8).flipped_levels(1).is_some());
+    }
+}
@@ -0,0 +1,199 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{c_void, CString};
+use std::num::NonZeroU32;
+use std::ops::Deref;
+use std::rc::Rc;
+use raw_window_handle::HasRawWindowHandle;
+use glium::backend::{Backend, Facade};
+use glium::glutin::{self, context::{NotCurrentGlContext, PossiblyCurrentGlContext}, display::{GetGlDisplay, GlDisplay}};
+use glium::SwapBuffersError;
+use glutin::surface::{GlSurface, SwapInterval, WindowSurface};
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::WindowId;
+
+/// Settings for the GL context and the surface of a new window.
+pub struct SurfaceSettings {
+    pub version: [u8; 2],
+    pub vsync: bool,
+    /// MSAA samples, 0 turns multisampling off. Only the first window's value is used,
+    /// every later window shares its context.
+    pub multisampling: u8
+}
+
+/// One GL context with a surface per window, glium draws to whichever surface is current.
+struct SharedBackend {
+    context: glutin::context::PossiblyCurrentContext,
+    config: glutin::config::Config,
+    surfaces: RefCell<HashMap<WindowId, glutin::surface::Surface<WindowSurface>>>,
+    current: Cell<WindowId>
+}
+
+impl SharedBackend {
+    fn make_current_on(&self, window_id: WindowId) {
+        self.current.set(window_id);
+        if let Some(surface) = self.surfaces.borrow().get(&window_id) {
+            self.context.make_current(surface).unwrap();
+        }
+    }
+}
+
+unsafe impl Backend for SharedBackend {
+    fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+        return match self.surfaces.borrow().get(&self.current.get()) {
+            Some(surface) => surface.swap_buffers(&self.context).map_err(|_| SwapBuffersError::ContextLost),
+            None => Ok(())
+        };
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        let symbol = CString::new(symbol).unwrap();
+        return self.context.display().get_proc_address(&symbol) as *const _;
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        return match self.surfaces.borrow().get(&self.current.get()) {
+            Some(surface) => (surface.width().unwrap_or(1), surface.height().unwrap_or(1)),
+            None => (1, 1)
+        };
+    }
+
+    fn resize(&self, new_size: (u32, u32)) {
+        if let Some(surface) = self.surfaces.borrow().get(&self.current.get()) {
+            resize_surface(&self.context, surface, new_size);
+        }
+    }
+
+    fn is_current(&self) -> bool { self.context.is_current() }
+
+    unsafe fn make_current(&self) { self.make_current_on(self.current.get()); }
+}
+
+fn resize_surface(context: &glutin::context::PossiblyCurrentContext, surface: &glutin::surface::Surface<WindowSurface>, size: (u32, u32)) {
+    // Minimized windows report a size of zero on some platforms.
+    let width = NonZeroU32::new(size.0).unwrap_or(NonZeroU32::new(1).unwrap());
+    let height = NonZeroU32::new(size.1).unwrap_or(NonZeroU32::new(1).unwrap());
+    surface.resize(context, width, height);
+}
+
+struct Gpu {
+    backend: Rc<SharedBackend>,
+    context: Rc<glium::backend::Context>
+}
+
+/// A window's view of the GL context shared by all windows. Anything created through one window's
+/// display (textures, buffers, programs) can be used when drawing to any other window.
+#[derive(Clone)]
+pub struct Display {
+    gpu: Rc<Gpu>,
+    window_id: WindowId
+}
+
+impl Display {
+    /// Creates a window together with its surface. Without `share_with` a new GL context is created,
+    /// otherwise the window joins the context of `share_with`.
+    pub fn create<T>(winit_builder: winit::window::WindowBuilder, target: &EventLoopWindowTarget<T>,
+                     settings: &SurfaceSettings, share_with: Option<&Display>) -> (winit::window::Window, Display) {
+        let (window, gl_config) = match share_with {
+            Some(display) => {
+                let gl_config = display.gpu.backend.config.clone();
+                (glutin_winit::finalize_window(target, winit_builder, &gl_config).unwrap(), gl_config)
+            },
+            None => {
+                let mut config_template_builder = glutin::config::ConfigTemplateBuilder::new();
+                if settings.multisampling > 1 { config_template_builder = config_template_builder.with_multisampling(settings.multisampling); }
+
+                let (window, gl_config) = glutin_winit::DisplayBuilder::new().with_window_builder(Some(winit_builder))
+                    .build(target, config_template_builder, |mut configs| {
+                        configs.next().unwrap()
+                    })
+                    .unwrap();
+                (window.unwrap(), gl_config)
+            }
+        };
+
+        let (width, height): (u32, u32) = window.inner_size().into();
+        let attrs = glutin::surface::SurfaceAttributesBuilder::<WindowSurface>::new().build(
+            window.raw_window_handle(),
+            NonZeroU32::new(width.max(1)).unwrap(),
+            NonZeroU32::new(height.max(1)).unwrap(),
+        );
+        let surface = unsafe { gl_config.display().create_window_surface(&gl_config, &attrs).unwrap() };
+
+        let window_id = window.id();
+        let gpu = match share_with {
+            Some(display) => {
+                display.gpu.backend.context.make_current(&surface).unwrap();
+                display.gpu.clone()
+            },
+            None => {
+                let context_attributes = glutin::context::ContextAttributesBuilder::new()
+                    .with_context_api(glutin::context::ContextApi::OpenGl(
+                        Some(glutin::context::Version::new(settings.version[0], settings.version[1]))
+                    ))
+                    .build(Some(window.raw_window_handle()));
+                let current_context = unsafe {
+                    gl_config.display().create_context(&gl_config, &context_attributes).expect("failed to create context")
+                }.make_current(&surface).unwrap();
+
+                let backend = Rc::new(SharedBackend {
+                    context: current_context,
+                    config: gl_config,
+                    surfaces: RefCell::new(HashMap::new()),
+                    current: Cell::new(window_id)
+                });
+                let context = unsafe { glium::backend::Context::new(backend.clone(), true, Default::default()) }.unwrap();
+                Rc::new(Gpu { backend, context })
+            }
+        };
+
+        // The swap interval belongs to the surface that is current while setting it.
+        let swap_interval = if settings.vsync { SwapInterval::Wait(NonZeroU32::new(1).unwrap()) } else { SwapInterval::DontWait };
+        let _ = surface.set_swap_interval(&gpu.backend.context, swap_interval);
+
+        gpu.backend.surfaces.borrow_mut().insert(window_id, surface);
+        gpu.backend.current.set(window_id);
+
+        return (window, Display { gpu, window_id });
+    }
+
+    /// Starts drawing on this window's backbuffer, the buffers are swapped by `Frame::finish`.
+    pub fn draw(&self) -> glium::Frame {
+        self.gpu.backend.make_current_on(self.window_id);
+        return glium::Frame::new(self.gpu.context.clone(), self.dimensions());
+    }
+
+    pub fn resize(&self, new_size: (u32, u32)) {
+        if let Some(surface) = self.gpu.backend.surfaces.borrow().get(&self.window_id) {
+            resize_surface(&self.gpu.backend.context, surface, new_size);
+        }
+    }
+
+    /// Size of this window's surface in physical pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        return match self.gpu.backend.surfaces.borrow().get(&self.window_id) {
+            Some(surface) => (surface.width().unwrap_or(1), surface.height().unwrap_or(1)),
+            None => (1, 1)
+        };
+    }
+
+    /// Destroys the surface, has to happen before the native window is destroyed.
+    pub(crate) fn release(&self) {
+        let backend = &self.gpu.backend;
+        backend.surfaces.borrow_mut().remove(&self.window_id);
+
+        if backend.current.get() == self.window_id {
+            let next = backend.surfaces.borrow().keys().next().copied();
+            if let Some(next) = next { backend.make_current_on(next); }
+        }
+    }
+}
+
+impl Deref for Display {
+    type Target = glium::backend::Context;
+    fn deref(&self) -> &glium::backend::Context { &self.gpu.context }
+}
+
+impl Facade for Display {
+    fn get_context(&self) -> &Rc<glium::backend::Context> { &self.gpu.context }
+}
@@ -0,0 +1,332 @@
+use glium::Surface;
+
+use super::context::Display;
+use super::math::{add, cross, length, normalize, scale, sub};
+
+/// Segments of the circles that make up `circle` and `sphere`.
+const CIRCLE_SEGMENTS: usize = 24;
+
+/// How a primitive is drawn. Converts from a color for primitives drawn for one frame and hidden
+/// behind geometry.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DebugStyle {
+    pub color: [f32; 4],
+    /// Hidden behind what is in the depth buffer, or drawn on top of everything when false.
+    pub depth_test: bool,
+    /// Seconds the primitive stays, 0 draws it in the next `render` only.
+    pub duration: f32
+}
+
+impl From<[f32; 4]> for DebugStyle {
+    fn from(color: [f32; 4]) -> DebugStyle {
+        return DebugStyle { color, depth_test: true, duration: 0.0 };
+    }
+}
+
+impl DebugStyle {
+    /// Drawn on top of everything.
+    pub fn on_top(mut self) -> DebugStyle {
+        self.depth_test = false;
+        return self;
+    }
+
+    /// Stays for `duration` seconds.
+    pub fn lasting(mut self, duration: f32) -> DebugStyle {
+        self.duration = duration;
+        return self;
+    }
+}
+
+#[derive(Copy, Clone)]
+struct DebugVertex {
+    position: [f32; 3],
+    color: [f32; 4]
+}
+
+implement_vertex!(DebugVertex, position, color);
+
+struct DebugLine {
+    from: [f32; 3],
+    to: [f32; 3],
+    style: DebugStyle,
+    /// Seconds left for lines with a duration.
+    remaining: f32
+}
+
+/// Two unit vectors perpendicular to `normal` and each other.
+fn basis(normal: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let normal = normalize(normal);
+    let helper = if normal[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    let u = normalize(cross(normal, helper));
+    return (u, cross(normal, u));
+}
+
+/// Lines, boxes, spheres and the like for seeing bounding volumes, pivots or physics shapes. Primitives
+/// are collected while updating, e.g. `debug.aabb(min, max, [1.0, 0.0, 0.0, 1.0])`, and drawn together by
+/// `render` in world units. Positions are 3D, 2D games pass z = 0 and the `Camera2d` matrix.
+pub struct DebugDraw {
+    /// Nothing is collected or drawn while false.
+    pub enabled: bool,
+    lines: Vec<DebugLine>,
+    /// Created by the first `render` of a headless `DebugDraw`.
+    gpu: Option<DebugGpu>
+}
+
+struct DebugGpu {
+    program: glium::Program,
+    vertices: glium::VertexBuffer<DebugVertex>
+}
+
+impl DebugGpu {
+    fn new(display: &Display) -> DebugGpu {
+        return DebugGpu {
+            program: glium::Program::from_source(display, include_str!("../../shaders/debug.vs"), include_str!("../../shaders/debug.fs"), None).unwrap(),
+            vertices: glium::VertexBuffer::empty_dynamic(display, 2 * 1024).unwrap()
+        };
+    }
+}
+
+impl DebugDraw {
+    pub fn new(display: &Display) -> DebugDraw {
+        return DebugDraw { gpu: Some(DebugGpu::new(display)), .. DebugDraw::headless() };
+    }
+
+    /// Collects primitives without touching the GPU until the first `render`.
+    pub fn headless() -> DebugDraw {
+        return DebugDraw { enabled: true, lines: Vec::new(), gpu: None };
+    }
+
+    pub fn line(&mut self, from: [f32; 3], to: [f32; 3], style: impl Into<DebugStyle>) {
+        if !self.enabled { return; }
+        let style = style.into();
+        self.lines.push(DebugLine { from, to, style, remaining: style.duration });
+    }
+
+    /// Line with a head at `to`, a fifth of its length.
+    pub fn arrow(&mut self, from: [f32; 3], to: [f32; 3], style: impl Into<DebugStyle>) {
+        let style = style.into();
+        let direction = sub(to, from);
+        let head = length(direction) * 0.2;
+        if head <= 0.0 { return; }
+
+        self.line(from, to, style);
+        let (u, v) = basis(direction);
+        let back = sub(to, scale(direction, 0.2));
+        for side in [u, v, scale(u, -1.0), scale(v, -1.0)] {
+            self.line(to, add(back, scale(side, head * 0.4)), style);
+        }
+    }
+
+    /// Axis-aligned box between the corners `min` and `max`.
+    pub fn aabb(&mut self, min: [f32; 3], max: [f32; 3], style: impl Into<DebugStyle>) {
+        let style = style.into();
+        let corner = |index: usize| [
+            if index & 1 == 0 { min[0] } else { max[0] },
+            if index & 2 == 0 { min[1] } else { max[1] },
+            if index & 4 == 0 { min[2] } else { max[2] }
+        ];
+        // Corners whose index differ in one bit share an edge.
+        for index in 0..8 {
+            for bit in [1, 2, 4] {
+                if index & bit == 0 { self.line(corner(index), corner(index | bit), style); }
+            }
+        }
+    }
+
+    pub fn circle(&mut self, center: [f32; 3], normal: [f32; 3], radius: f32, style: impl Into<DebugStyle>) {
+        let style = style.into();
+        let (u, v) = basis(normal);
+        let point = |segment: usize| {
+            let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            return add(center, add(scale(u, angle.cos() * radius), scale(v, angle.sin() * radius)));
+        };
+        for segment in 0..CIRCLE_SEGMENTS { self.line(point(segment), point(segment + 1), style); }
+    }
+
+    /// Three circles around the axes.
+    pub fn sphere(&mut self, center: [f32; 3], radius: f32, style: impl Into<DebugStyle>) {
+        let style = style.into();
+        for normal in [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]] { self.circle(center, normal, radius, style); }
+    }
+
+    /// Three short lines crossing at `position`, e.g. for a pivot.
+    pub fn point(&mut self, position: [f32; 3], size: f32, style: impl Into<DebugStyle>) {
+        let style = style.into();
+        let half = size / 2.0;
+        for axis in [[half, 0.0, 0.0], [0.0, half, 0.0], [0.0, 0.0, half]] {
+            self.line(sub(position, axis), add(position, axis), style);
+        }
+    }
+
+    /// Grid of `cells` by `cells` squares of `cell_size` on the XZ plane, centered on `center`.
+    pub fn grid(&mut self, center: [f32; 3], cells: u32, cell_size: f32, style: impl Into<DebugStyle>) {
+        let style = style.into();
+        let half = cells as f32 * cell_size / 2.0;
+        for line in 0..=cells {
+            let offset = line as f32 * cell_size - half;
+            self.line(add(center, [offset, 0.0, -half]), add(center, [offset, 0.0, half]), style);
+            self.line(add(center, [-half, 0.0, offset]), add(center, [half, 0.0, offset]), style);
+        }
+    }
+
+    /// X, Y and Z arrows of `size` in red, green and blue, drawn on top.
+    pub fn axes(&mut self, origin: [f32; 3], size: f32) {
+        let colors = [[1.0, 0.2, 0.2, 1.0], [0.2, 1.0, 0.2, 1.0], [0.3, 0.5, 1.0, 1.0]];
+        for (axis, color) in colors.into_iter().enumerate() {
+            let mut to = origin;
+            to[axis] += size;
+            self.arrow(origin, to, DebugStyle::from(color).on_top());
+        }
+    }
+
+    /// Ages lasting primitives by `delta_time` and drops the ones that ran out.
+    pub fn update(&mut self, delta_time: f32) {
+        for line in self.lines.iter_mut().filter(|line| line.style.duration > 0.0) { line.remaining -= delta_time; }
+        self.lines.retain(|line| line.style.duration <= 0.0 || line.remaining > 0.0);
+    }
+
+    pub fn clear(&mut self) { self.lines.clear(); }
+
+    /// Number of lines waiting to be drawn.
+    pub fn len(&self) -> usize { self.lines.len() }
+
+    pub fn is_empty(&self) -> bool { self.lines.is_empty() }
+
+    /// Vertices of every line with the depth tested ones first and how many of them are tested. Drops
+    /// what was only meant for this frame.
+    fn take_vertices(&mut self) -> (Vec<DebugVertex>, usize) {
+        if !self.enabled { self.lines.clear(); }
+
+        self.lines.sort_by_key(|line| !line.style.depth_test);
+        let vertices = self.lines.iter()
+            .flat_map(|line| [DebugVertex { position: line.from, color: line.style.color }, DebugVertex { position: line.to, color: line.style.color }])
+            .collect();
+        let tested = self.lines.iter().take_while(|line| line.style.depth_test).count() * 2;
+
+        self.lines.retain(|line| line.style.duration > 0.0);
+        return (vertices, tested);
+    }
+
+    /// Draws everything collected with `view_projection`, depth tested lines first, and drops what was
+    /// only meant for this frame. Returns how many draw calls it took.
+    pub fn render<S: Surface>(&mut self, display: &Display, target: &mut S, view_projection: [[f32; 4]; 4]) -> u32 {
+        let (vertices, tested) = self.take_vertices();
+        if vertices.is_empty() { return 0; }
+
+        let gpu = self.gpu.get_or_insert_with(|| DebugGpu::new(display));
+        if gpu.vertices.len() < vertices.len() {
+            gpu.vertices = glium::VertexBuffer::empty_dynamic(display, vertices.len().next_power_of_two()).unwrap();
+        }
+        gpu.vertices.slice(0..vertices.len()).unwrap().write(&vertices);
+
+        let uniforms = uniform! { view_projection: view_projection };
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::LinesList);
+
+        let mut draw_calls = 0;
+        for (range, test) in [(0..tested, glium::draw_parameters::DepthTest::IfLessOrEqual), (tested..vertices.len(), glium::draw_parameters::DepthTest::Overwrite)] {
+            if range.is_empty() { continue; }
+            let params = glium::DrawParameters {
+                depth: glium::Depth { test, write: false, .. Default::default() },
+                blend: glium::draw_parameters::Blend::alpha_blending(),
+                .. Default::default()
+            };
+            target.draw(gpu.vertices.slice(range).unwrap(), indices, &gpu.program, &uniforms, &params).unwrap();
+            draw_calls += 1;
+        }
+
+        return draw_calls;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::math::dot;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+    fn segments(debug: &DebugDraw) -> Vec<([f32; 3], [f32; 3])> { debug.lines.iter().map(|line| (line.from, line.to)).collect() }
+
+    #[test]
+    fn aabb_has_twelve_axis_aligned_edges() {
+        let mut debug = DebugDraw::headless();
+        debug.aabb([0.0, 0.0, 0.0], [1.0, 2.0, 3.0], RED);
+        let edges = segments(&debug);
+        assert_eq!(edges.len(), 12);
+
+        for axis in 0..3 {
+            let along: Vec<_> = edges.iter().filter(|(from, to)| (0..3).all(|other| (from[other] == to[other]) != (other == axis))).collect();
+            assert_eq!(along.len(), 4, "axis {}", axis);
+        }
+        // No edge is drawn twice.
+        for (index, edge) in edges.iter().enumerate() { assert!(!edges[index + 1..].contains(edge)); }
+    }
+
+    #[test]
+    fn arrow_head() {
+        let mut debug = DebugDraw::headless();
+        debug.arrow([0.0, 0.0, 0.0], [0.0, 0.0, 10.0], RED);
+        let lines = segments(&debug);
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], ([0.0, 0.0, 0.0], [0.0, 0.0, 10.0]));
+
+        // Four barbs from the tip back a fifth of the way, spread out by 40 % of that.
+        for (from, to) in &lines[1..] {
+            assert_eq!(*from, [0.0, 0.0, 10.0]);
+            assert!((to[2] - 8.0).abs() < 1e-5);
+            assert!((length([to[0], to[1], 0.0]) - 0.8).abs() < 1e-5);
+        }
+        let sides: Vec<_> = lines[1..].iter().map(|(_, to)| [to[0], to[1], 0.0]).collect();
+        assert!(dot(sides[0], sides[1]).abs() < 1e-5);
+        assert!((dot(sides[0], sides[2]) + 0.64).abs() < 1e-5);
+
+        debug.clear();
+        debug.arrow([1.0, 1.0, 1.0], [1.0, 1.0, 1.0], RED);
+        assert!(debug.is_empty());
+    }
+
+    #[test]
+    fn grid_line_count() {
+        let mut debug = DebugDraw::headless();
+        debug.grid([0.0, 0.0, 0.0], 4, 1.0, RED);
+        assert_eq!(debug.len(), 10);
+        let lines = segments(&debug);
+        assert_eq!(lines[0], ([-2.0, 0.0, -2.0], [-2.0, 0.0, 2.0]));
+        assert_eq!(lines[9], ([-2.0, 0.0, 2.0], [2.0, 0.0, 2.0]));
+    }
+
+    #[test]
+    fn lasting_lines_run_out() {
+        let mut debug = DebugDraw::headless();
+        debug.line([0.0; 3], [1.0; 3], DebugStyle::from(RED).lasting(1.0));
+        debug.line([0.0; 3], [1.0; 3], RED);
+
+        debug.update(0.6);
+        assert_eq!(debug.len(), 2);
+        debug.update(0.6);
+        assert_eq!(debug.len(), 1);
+        assert_eq!(debug.lines[0].style.duration, 0.0);
+    }
+
+    #[test]
+    fn render_drops_one_frame_lines() {
+        let mut debug = DebugDraw::headless();
+        debug.line([0.0; 3], [1.0; 3], DebugStyle::from(RED).on_top());
+        debug.line([0.0; 3], [2.0; 3], DebugStyle::from(RED).lasting(1.0));
+        debug.line([0.0; 3], [3.0; 3], RED);
+
+        let (vertices, tested) = debug.take_vertices();
+        assert_eq!(vertices.len(), 6);
+        // Depth tested lines come first, the one on top last.
+        assert_eq!(tested, 4);
+        assert_eq!(vertices[5].position, [1.0; 3]);
+        assert_eq!(segments(&debug), [([0.0; 3], [2.0; 3])]);
+
+        let (vertices, tested) = debug.take_vertices();
+        assert_eq!((vertices.len(), tested), (2, 2));
+
+        debug.enabled = false;
+        assert!(debug.take_vertices().0.is_empty());
+        assert!(debug.is_empty());
+    }
+}
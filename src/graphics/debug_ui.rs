@@ -0,0 +1,680 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+use glium::Surface;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+use crate::input::state::Input;
+use super::camera::Camera2d;
+use super::context::Display;
+use super::sprite::{Sprite, SpriteBatch, SpriteRenderer};
+use super::text::{FontId, TextRenderer, TextStyle, DEFAULT_FONT};
+use super::texture::{Texture, TextureFormat};
+
+/// Height of a widget row before `DebugUi::scale`, in pixels.
+const ROW_HEIGHT: f32 = 22.0;
+const PADDING: f32 = 6.0;
+const SPACING: f32 = 4.0;
+const FONT_SIZE: f32 = 14.0;
+const WINDOW_WIDTH: f32 = 280.0;
+const PLOT_HEIGHT: f32 = 60.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DebugTheme {
+    pub window: [f32; 4],
+    pub title: [f32; 4],
+    pub widget: [f32; 4],
+    pub hovered: [f32; 4],
+    pub active: [f32; 4],
+    /// Slider fill, checkbox mark and plot line.
+    pub accent: [f32; 4],
+    pub text: [f32; 4]
+}
+
+impl Default for DebugTheme {
+    fn default() -> DebugTheme {
+        return DebugTheme {
+            window: [0.08, 0.08, 0.1, 0.85],
+            title: [0.2, 0.25, 0.4, 0.95],
+            widget: [0.22, 0.22, 0.26, 1.0],
+            hovered: [0.3, 0.3, 0.36, 1.0],
+            active: [0.38, 0.38, 0.46, 1.0],
+            accent: [0.35, 0.6, 1.0, 1.0],
+            text: [0.92, 0.92, 0.92, 1.0]
+        };
+    }
+}
+
+/// Rectangles are x, y, width and height in pixels from the top left of the screen like the cursor.
+enum Shape {
+    Rect([f32; 4], [f32; 4]),
+    Line([f32; 2], [f32; 2], [f32; 4]),
+    Text([f32; 2], String, [f32; 4])
+}
+
+struct Panel {
+    position: [f32; 2],
+    /// Size at the end of the last frame, used to find the window under the cursor.
+    size: [f32; 2],
+    collapsed: bool,
+    /// Shown during the current frame.
+    open: bool,
+    shapes: Vec<Shape>
+}
+
+/// Where the next widget of the current window goes.
+struct Layout {
+    window: String,
+    x: f32,
+    y: f32,
+    width: f32
+}
+
+/// What the widgets need from `Input` for one frame.
+#[derive(Default)]
+struct FrameInput {
+    cursor: Option<[f32; 2]>,
+    cursor_delta: [f32; 2],
+    pressed: bool,
+    held: bool,
+    released: bool,
+    text: String,
+    backspace: bool,
+    enter: bool,
+    escape: bool
+}
+
+/// Immediate mode UI for tuning values while the game runs. Widgets are declared every frame between
+/// `begin` and `render`, usually in `App::update`, and report interaction through their return values:
+///
+/// ```ignore
+/// ui.begin(&context.input);
+/// ui.window("Tuning", |ui| {
+///     ui.slider("speed", &mut self.speed, 0.0..=5.0);
+///     if ui.button("reset") { self.speed = 1.0; }
+/// });
+/// ```
+///
+/// Windows can be dragged by their title bar and collapsed with the arrow on it. Everything is drawn in
+/// screen pixels on top of whatever `render` is called after.
+pub struct DebugUi {
+    /// Multiplies every size, e.g. the window's scale factor.
+    pub scale: f32,
+    /// Hidden windows skip their contents.
+    pub visible: bool,
+    pub theme: DebugTheme,
+    panels: HashMap<String, Panel>,
+    /// Window titles from back to front.
+    order: Vec<String>,
+    hovered_window: Option<String>,
+    layout: Option<Layout>,
+    input: FrameInput,
+    last_cursor: Option<[f32; 2]>,
+    /// Widget the mouse went down on, it keeps the mouse until the button is released.
+    active: Option<u64>,
+    /// Text field receiving typed text.
+    focused: Option<u64>,
+    /// Created by the first `render` of a headless UI.
+    graphics: Option<DebugGraphics>,
+    text: TextRenderer,
+    font: FontId
+}
+
+struct DebugGraphics {
+    sprites: SpriteRenderer,
+    white: Texture
+}
+
+impl DebugGraphics {
+    fn new(display: &Display) -> DebugGraphics {
+        return DebugGraphics { sprites: SpriteRenderer::new(display), white: Texture::solid(display, [255; 4], TextureFormat::Srgb).unwrap() };
+    }
+}
+
+fn contains(rect: [f32; 4], point: Option<[f32; 2]>) -> bool {
+    let Some([x, y]) = point else { return false; };
+    return x >= rect[0] && x < rect[0] + rect[2] && y >= rect[1] && y < rect[1] + rect[3];
+}
+
+impl DebugUi {
+    pub fn new(display: &Display) -> DebugUi {
+        return DebugUi { graphics: Some(DebugGraphics::new(display)), text: TextRenderer::new(display), .. DebugUi::headless() };
+    }
+
+    /// A UI that doesn't touch the GPU until its first `render`, to declare widgets without a window.
+    pub fn headless() -> DebugUi {
+        let mut text = TextRenderer::headless();
+        let font = text.add_font(DEFAULT_FONT.to_vec()).unwrap();
+
+        return DebugUi {
+            scale: 1.0,
+            visible: true,
+            theme: DebugTheme::default(),
+            panels: HashMap::new(),
+            order: Vec::new(),
+            hovered_window: None,
+            layout: None,
+            input: FrameInput::default(),
+            last_cursor: None,
+            active: None,
+            focused: None,
+            graphics: None,
+            text,
+            font
+        };
+    }
+
+    /// Starts a frame with this frame's input, from the window the UI is drawn to.
+    pub fn begin(&mut self, input: &Input) {
+        let cursor = input.cursor_position();
+        let cursor_delta = match (cursor, self.last_cursor) {
+            (Some(cursor), Some(last)) => [cursor[0] - last[0], cursor[1] - last[1]],
+            _ => [0.0, 0.0]
+        };
+        self.last_cursor = cursor;
+        // The button was up at the end of the last frame, so whatever had it is done.
+        if !self.input.held { self.active = None; }
+        self.input = FrameInput {
+            cursor,
+            cursor_delta,
+            pressed: input.mouse_pressed(MouseButton::Left),
+            held: input.mouse_held(MouseButton::Left),
+            released: input.mouse_released(MouseButton::Left),
+            text: input.text().to_string(),
+            backspace: input.key_pressed(KeyCode::Backspace),
+            enter: input.key_pressed(KeyCode::Enter) || input.key_pressed(KeyCode::NumpadEnter),
+            escape: input.key_pressed(KeyCode::Escape)
+        };
+
+        if self.input.pressed { self.focused = None; }
+
+        self.hovered_window = self.order.iter().rev()
+            .find(|title| {
+                let panel = &self.panels[*title];
+                panel.open && contains([panel.position[0], panel.position[1], panel.size[0], panel.size[1]], cursor)
+            })
+            .cloned();
+        for panel in self.panels.values_mut() {
+            panel.open = false;
+            panel.shapes.clear();
+        }
+        self.layout = None;
+    }
+
+    /// The mouse is over a window or dragging something, the game should ignore it.
+    pub fn wants_mouse(&self) -> bool { self.visible && (self.hovered_window.is_some() || self.active.is_some()) }
+
+    /// A text field has focus, the game should ignore key presses.
+    pub fn wants_keyboard(&self) -> bool { self.visible && self.focused.is_some() }
+
+    fn id(&self, label: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.layout.as_ref().map(|layout| layout.window.as_str()).hash(&mut hasher);
+        label.hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    fn push(&mut self, shape: Shape) {
+        let Some(layout) = &self.layout else { return; };
+        if let Some(panel) = self.panels.get_mut(&layout.window) { panel.shapes.push(shape); }
+    }
+
+    fn text_style(&self, color: [f32; 4]) -> TextStyle {
+        return TextStyle { size: FONT_SIZE * self.scale, color, .. TextStyle::default() };
+    }
+
+    fn text_width(&self, text: &str) -> f32 { self.text.measure(self.font, text, &self.text_style(self.theme.text))[0] }
+
+    /// Queues `text` vertically centered in `rect`, starting at its left edge plus `indent`.
+    fn push_text(&mut self, rect: [f32; 4], indent: f32, text: &str) {
+        let style = self.text_style(self.theme.text);
+        let line_height = self.text.line_height(self.font, &style);
+        self.push(Shape::Text([rect[0] + indent, rect[1] + ((rect[3] - line_height) / 2.0).max(0.0)], text.to_string(), self.theme.text));
+    }
+
+    /// Takes the next `height` pixels of the current window, `None` outside of `window`.
+    fn row(&mut self, height: f32) -> Option<[f32; 4]> {
+        let scale = self.scale;
+        let layout = self.layout.as_mut()?;
+        let rect = [layout.x, layout.y, layout.width, height * scale];
+        layout.y += (height + SPACING) * scale;
+        return Some(rect);
+    }
+
+    fn window_hovered(&self) -> bool {
+        return self.layout.as_ref().is_some_and(|layout| self.hovered_window.as_ref() == Some(&layout.window));
+    }
+
+    /// Returns whether the cursor is over `rect` and whether it was clicked. The widget becomes active
+    /// when the mouse goes down on it and stays so until the button is released.
+    fn interact(&mut self, id: u64, rect: [f32; 4]) -> (bool, bool) {
+        let hovered = self.window_hovered() && self.active.is_none_or(|active| active == id) && contains(rect, self.input.cursor);
+        if hovered && self.input.pressed { self.active = Some(id); }
+        return (hovered, hovered && self.input.released && self.active == Some(id));
+    }
+
+    fn widget_color(&self, id: u64, hovered: bool) -> [f32; 4] {
+        return if self.active == Some(id) { self.theme.active } else if hovered { self.theme.hovered } else { self.theme.widget };
+    }
+
+    /// A window holding the widgets declared in `contents`. Windows are told apart by their title and
+    /// remember where they were dragged to.
+    pub fn window(&mut self, title: &str, contents: impl FnOnce(&mut DebugUi)) {
+        if !self.visible { return; }
+        let scale = self.scale;
+        let count = self.panels.len() as f32;
+        let panel = self.panels.entry(title.to_string()).or_insert_with(|| Panel {
+            position: [20.0 + count * 30.0, 20.0 + count * 30.0],
+            size: [0.0, 0.0],
+            collapsed: false,
+            open: false,
+            shapes: Vec::new()
+        });
+        panel.open = true;
+        if !self.order.iter().any(|other| other == title) { self.order.push(title.to_string()); }
+
+        let hovered = self.hovered_window.as_deref() == Some(title);
+        if hovered && self.input.pressed {
+            self.order.retain(|other| other != title);
+            self.order.push(title.to_string());
+        }
+
+        self.layout = Some(Layout { window: title.to_string(), x: 0.0, y: 0.0, width: 0.0 });
+        let title_id = self.id("#title");
+        let body_id = self.id("#body");
+        let panel = self.panels.get_mut(title).unwrap();
+
+        let width = WINDOW_WIDTH * scale;
+        let title_height = ROW_HEIGHT * scale;
+        let title_rect = [panel.position[0], panel.position[1], width, title_height];
+        if hovered && self.input.pressed && self.active.is_none() && contains(title_rect, self.input.cursor) {
+            // The arrow at the left end of the title bar collapses, the rest of it drags.
+            if self.input.cursor.is_some_and(|[x, _]| x < title_rect[0] + title_height) {
+                panel.collapsed = !panel.collapsed;
+            }
+            self.active = Some(title_id);
+        }
+        // Motion from before the press doesn't count towards the drag.
+        if self.active == Some(title_id) && !self.input.pressed {
+            panel.position[0] += self.input.cursor_delta[0];
+            panel.position[1] += self.input.cursor_delta[1];
+        }
+
+        let [x, y] = panel.position;
+        let collapsed = panel.collapsed;
+        let start = panel.shapes.len();
+        self.push(Shape::Rect([x, y, width, title_height], self.theme.title));
+        self.push_text([x, y, width, title_height], PADDING * scale, &format!("{} {}", if collapsed { ">" } else { "v" }, title));
+
+        let mut height = title_height;
+        if !collapsed {
+            self.layout = Some(Layout { window: title.to_string(), x: x + PADDING * scale, y: y + title_height + PADDING * scale, width: width - 2.0 * PADDING * scale });
+            contents(self);
+            height = self.layout.as_ref().unwrap().y - y + (PADDING - SPACING) * scale;
+            let background = Shape::Rect([x, y, width, height], self.theme.window);
+            self.panels.get_mut(title).unwrap().shapes.insert(start, background);
+        }
+
+        // Clicks on the window's background still keep the mouse from the game until released.
+        if hovered && self.input.pressed && self.active.is_none() { self.active = Some(body_id); }
+
+        self.panels.get_mut(title).unwrap().size = [width, height];
+        self.layout = None;
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let lines = text.lines().count().max(1) as f32;
+        let style = self.text_style(self.theme.text);
+        let line_height = self.text.line_height(self.font, &style) / self.scale;
+        let Some(rect) = self.row((ROW_HEIGHT - FONT_SIZE).max(0.0) + line_height * lines) else { return; };
+        self.push_text(rect, 0.0, text);
+    }
+
+    /// A horizontal line between groups of widgets.
+    pub fn separator(&mut self) {
+        let Some(rect) = self.row(1.0) else { return; };
+        self.push(Shape::Rect(rect, self.theme.widget));
+    }
+
+    /// Returns true when clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        let width = self.text_width(label) + 2.0 * PADDING * self.scale;
+        let Some(row) = self.row(ROW_HEIGHT) else { return false; };
+        let rect = [row[0], row[1], width.min(row[2]), row[3]];
+        let id = self.id(label);
+        let (hovered, clicked) = self.interact(id, rect);
+
+        self.push(Shape::Rect(rect, self.widget_color(id, hovered)));
+        self.push_text(rect, PADDING * self.scale, label);
+        return clicked;
+    }
+
+    /// Toggles `value` when clicked, returns true when it changed.
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let Some(row) = self.row(ROW_HEIGHT) else { return false; };
+        let id = self.id(label);
+        let (hovered, clicked) = self.interact(id, row);
+        if clicked { *value = !*value; }
+
+        let size = row[3];
+        self.push(Shape::Rect([row[0], row[1], size, size], self.widget_color(id, hovered)));
+        if *value {
+            let inset = size / 4.0;
+            self.push(Shape::Rect([row[0] + inset, row[1] + inset, size - 2.0 * inset, size - 2.0 * inset], self.theme.accent));
+        }
+        self.push_text(row, size + PADDING * self.scale, label);
+        return clicked;
+    }
+
+    /// Drags `value` between the ends of `range`, returns true when it changed.
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let Some(rect) = self.row(ROW_HEIGHT) else { return false; };
+        let id = self.id(label);
+        let (hovered, _) = self.interact(id, rect);
+        let (min, max) = (*range.start(), *range.end());
+
+        let mut changed = false;
+        if self.active == Some(id) {
+            if let Some([x, _]) = self.input.cursor {
+                let new = min + ((x - rect[0]) / rect[2]).clamp(0.0, 1.0) * (max - min);
+                changed = new != *value;
+                *value = new;
+            }
+        }
+
+        let fill = if max > min { ((*value - min) / (max - min)).clamp(0.0, 1.0) } else { 0.0 };
+        self.push(Shape::Rect(rect, self.widget_color(id, hovered)));
+        self.push(Shape::Rect([rect[0], rect[1], rect[2] * fill, rect[3]], [self.theme.accent[0], self.theme.accent[1], self.theme.accent[2], 0.5]));
+        self.push_text(rect, PADDING * self.scale, &format!("{}: {:.3}", label, value));
+        return changed;
+    }
+
+    /// Single line of editable text. A click focuses it, Enter, Escape or a click elsewhere lets go.
+    /// Returns true when `value` changed.
+    pub fn text_field(&mut self, label: &str, value: &mut String) -> bool {
+        let Some(row) = self.row(ROW_HEIGHT) else { return false; };
+        let field = [row[0], row[1], (row[2] * 0.6).floor(), row[3]];
+        let id = self.id(label);
+        let (hovered, _) = self.interact(id, field);
+        if hovered && self.input.pressed { self.focused = Some(id); }
+
+        let mut changed = false;
+        if self.focused == Some(id) {
+            if !self.input.text.is_empty() {
+                value.push_str(&self.input.text);
+                changed = true;
+            }
+            if self.input.backspace { changed |= value.pop().is_some(); }
+            if self.input.enter || self.input.escape { self.focused = None; }
+        }
+
+        let focused = self.focused == Some(id);
+        self.push(Shape::Rect(field, if focused { self.theme.active } else { self.widget_color(id, hovered) }));
+        self.push_text(field, PADDING * self.scale, &format!("{}{}", value, if focused { "_" } else { "" }));
+        self.push_text(row, field[2] + PADDING * self.scale, label);
+        return changed;
+    }
+
+    /// Line plot of `values` from left to right, scaled to `range` or to the smallest and largest value.
+    pub fn plot(&mut self, label: &str, values: impl IntoIterator<Item = f32>, range: Option<RangeInclusive<f32>>) {
+        let values: Vec<f32> = values.into_iter().collect();
+        let Some(rect) = self.row(PLOT_HEIGHT) else { return; };
+        let (min, max) = match range {
+            Some(range) => (*range.start(), *range.end()),
+            None => values.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| (min.min(*value), max.max(*value)))
+        };
+
+        self.push(Shape::Rect(rect, self.theme.widget));
+        if values.len() > 1 {
+            let span = if max > min { max - min } else { 1.0 };
+            let step = rect[2] / (values.len() - 1) as f32;
+            let point = |index: usize, value: f32| [rect[0] + index as f32 * step, rect[1] + rect[3] * (1.0 - ((value - min) / span).clamp(0.0, 1.0))];
+            for (index, pair) in values.windows(2).enumerate() {
+                self.push(Shape::Line(point(index, pair[0]), point(index + 1, pair[1]), self.theme.accent));
+            }
+        }
+
+        let text = match values.last() {
+            Some(last) => format!("{}: {:.3}", label, last),
+            None => label.to_string()
+        };
+        let line = [rect[0], rect[1], rect[2], ROW_HEIGHT * self.scale];
+        self.push_text(line, PADDING * self.scale, &text);
+    }
+
+    /// Draws the windows declared since `begin` from back to front, returns how many draw calls it took.
+    pub fn render<S: Surface>(&mut self, display: &Display, target: &mut S) -> u32 {
+        if !self.visible { return 0; }
+        let (width, height) = target.get_dimensions();
+        let camera = Camera2d::screen([width, height]);
+        let screen_height = height as f32;
+        let line_width = self.scale.max(1.0);
+        let graphics = self.graphics.get_or_insert_with(|| DebugGraphics::new(display));
+
+        let mut draw_calls = 0;
+        for title in &self.order {
+            let panel = &self.panels[title];
+            if !panel.open { continue; }
+
+            let mut batch = SpriteBatch::new();
+            for shape in &panel.shapes {
+                match shape {
+                    Shape::Rect([x, y, width, height], color) => batch.draw(&graphics.white, Sprite {
+                        origin: [0.0, 0.0],
+                        color: *color,
+                        .. Sprite::new([*x, screen_height - y - height], [*width, *height])
+                    }),
+                    Shape::Line(from, to, color) => {
+                        let [dx, dy] = [to[0] - from[0], from[1] - to[1]];
+                        batch.draw(&graphics.white, Sprite {
+                            origin: [0.0, 0.5],
+                            rotation: dy.atan2(dx),
+                            color: *color,
+                            .. Sprite::new([from[0], screen_height - from[1]], [(dx * dx + dy * dy).sqrt(), line_width])
+                        });
+                    },
+                    Shape::Text([x, y], text, color) => {
+                        let style = TextStyle { size: FONT_SIZE * self.scale, color: *color, .. TextStyle::default() };
+                        self.text.draw(self.font, text, [*x, screen_height - y], &style);
+                    }
+                }
+            }
+
+            draw_calls += graphics.sprites.render(display, target, &camera, &mut batch);
+            draw_calls += self.text.render(display, target, &camera);
+        }
+
+        return draw_calls;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::event::{DeviceId, Ime, WindowEvent};
+
+    /// Points in the first and second content row of the first window, at its default position.
+    const FIRST_ROW: [f32; 2] = [30.0, 55.0];
+    const SECOND_ROW: [f32; 2] = [30.0, 81.0];
+
+    fn move_cursor(input: &mut Input, position: [f32; 2]) {
+        let device_id = unsafe { DeviceId::dummy() };
+        input.handle_event(&WindowEvent::CursorMoved { device_id, position: [position[0] as f64, position[1] as f64].into() });
+    }
+
+    /// One frame with a single window, returns what `contents` did or None when the window is collapsed.
+    fn frame<R>(ui: &mut DebugUi, input: &mut Input, contents: impl FnOnce(&mut DebugUi) -> R) -> Option<R> {
+        ui.begin(input);
+        let mut result = None;
+        ui.window("Test", |ui| result = Some(contents(ui)));
+        input.end_frame();
+        return result;
+    }
+
+    fn widget_id(ui: &mut DebugUi, label: &str) -> u64 {
+        ui.layout = Some(Layout { window: "Test".to_string(), x: 0.0, y: 0.0, width: 0.0 });
+        let id = ui.id(label);
+        ui.layout = None;
+        return id;
+    }
+
+    fn buttons(ui: &mut DebugUi, input: &mut Input) -> [bool; 2] {
+        return frame(ui, input, |ui| [ui.button("a"), ui.button("b")]).unwrap();
+    }
+
+    fn text_field(ui: &mut DebugUi, input: &mut Input, value: &mut String) -> bool {
+        return frame(ui, input, |ui| ui.text_field("name", value)).unwrap();
+    }
+
+    #[test]
+    fn active_widget_keeps_the_mouse() {
+        let (mut ui, mut input) = (DebugUi::headless(), Input::default());
+        move_cursor(&mut input, FIRST_ROW);
+        buttons(&mut ui, &mut input);
+
+        input.mouse_buttons.press(MouseButton::Left);
+        assert_eq!(buttons(&mut ui, &mut input), [false, false]);
+        let id = widget_id(&mut ui, "a");
+        assert_eq!(ui.active, Some(id));
+
+        // Released over the other button, neither counts as clicked.
+        move_cursor(&mut input, SECOND_ROW);
+        assert_eq!(buttons(&mut ui, &mut input), [false, false]);
+        assert!(ui.wants_mouse());
+        input.mouse_buttons.release(MouseButton::Left);
+        assert_eq!(buttons(&mut ui, &mut input), [false, false]);
+
+        input.mouse_buttons.press(MouseButton::Left);
+        assert_eq!(buttons(&mut ui, &mut input), [false, false]);
+        let id = widget_id(&mut ui, "b");
+        assert_eq!(ui.active, Some(id));
+        input.mouse_buttons.release(MouseButton::Left);
+        assert_eq!(buttons(&mut ui, &mut input), [false, true]);
+        buttons(&mut ui, &mut input);
+        assert_eq!(ui.active, None);
+    }
+
+    #[test]
+    fn slider_maps_the_cursor_onto_its_range() {
+        let (mut ui, mut input) = (DebugUi::headless(), Input::default());
+        let mut value = 5.0;
+        move_cursor(&mut input, FIRST_ROW);
+        frame(&mut ui, &mut input, |ui| ui.slider("s", &mut value, 0.0..=10.0));
+
+        // The row starts at x = 26 and is 268 pixels wide.
+        move_cursor(&mut input, [26.0 + 268.0 * 0.25, 55.0]);
+        input.mouse_buttons.press(MouseButton::Left);
+        assert_eq!(frame(&mut ui, &mut input, |ui| ui.slider("s", &mut value, 0.0..=10.0)), Some(true));
+        assert!((value - 2.5).abs() < 1e-4);
+
+        // Dragging past either end clamps, even outside the window.
+        move_cursor(&mut input, [1000.0, 300.0]);
+        frame(&mut ui, &mut input, |ui| ui.slider("s", &mut value, 0.0..=10.0));
+        assert_eq!(value, 10.0);
+        move_cursor(&mut input, [-50.0, 55.0]);
+        frame(&mut ui, &mut input, |ui| ui.slider("s", &mut value, 0.0..=10.0));
+        assert_eq!(value, 0.0);
+
+        input.mouse_buttons.release(MouseButton::Left);
+        frame(&mut ui, &mut input, |ui| ui.slider("s", &mut value, 0.0..=10.0));
+        move_cursor(&mut input, [200.0, 55.0]);
+        assert_eq!(frame(&mut ui, &mut input, |ui| ui.slider("s", &mut value, 0.0..=10.0)), Some(false));
+        assert_eq!(value, 0.0);
+    }
+
+    #[test]
+    fn text_field_focus() {
+        let (mut ui, mut input) = (DebugUi::headless(), Input::default());
+        let mut value = String::new();
+        let click = |ui: &mut DebugUi, input: &mut Input, value: &mut String, position: [f32; 2]| {
+            move_cursor(input, position);
+            input.mouse_buttons.press(MouseButton::Left);
+            text_field(ui, input, value);
+            input.mouse_buttons.release(MouseButton::Left);
+            text_field(ui, input, value);
+        };
+        let commit = |input: &mut Input, text: &str| input.handle_event(&WindowEvent::Ime(Ime::Commit(text.to_string())));
+        let key = |ui: &mut DebugUi, input: &mut Input, value: &mut String, key: KeyCode| {
+            input.keys.press(key);
+            text_field(ui, input, value);
+            input.keys.release(key);
+        };
+
+        text_field(&mut ui, &mut input, &mut value);
+        commit(&mut input, "ignored");
+        assert!(!text_field(&mut ui, &mut input, &mut value));
+
+        click(&mut ui, &mut input, &mut value, FIRST_ROW);
+        assert!(ui.wants_keyboard());
+        commit(&mut input, "hi");
+        assert!(text_field(&mut ui, &mut input, &mut value));
+        assert_eq!(value, "hi");
+        key(&mut ui, &mut input, &mut value, KeyCode::Enter);
+        assert!(!ui.wants_keyboard());
+
+        click(&mut ui, &mut input, &mut value, FIRST_ROW);
+        key(&mut ui, &mut input, &mut value, KeyCode::Backspace);
+        assert_eq!(value, "h");
+        key(&mut ui, &mut input, &mut value, KeyCode::Escape);
+        assert!(!ui.wants_keyboard());
+
+        // The field covers the left 60 % of the row, a click on the label lets go.
+        click(&mut ui, &mut input, &mut value, FIRST_ROW);
+        assert!(ui.wants_keyboard());
+        click(&mut ui, &mut input, &mut value, [250.0, 55.0]);
+        assert!(!ui.wants_keyboard());
+        commit(&mut input, "!");
+        text_field(&mut ui, &mut input, &mut value);
+        assert_eq!(value, "h");
+    }
+
+    #[test]
+    fn windows_drag_and_collapse() {
+        let (mut ui, mut input) = (DebugUi::headless(), Input::default());
+        move_cursor(&mut input, [100.0, 30.0]);
+        frame(&mut ui, &mut input, |ui| ui.separator());
+
+        input.mouse_buttons.press(MouseButton::Left);
+        frame(&mut ui, &mut input, |ui| ui.separator());
+        move_cursor(&mut input, [150.0, 70.0]);
+        frame(&mut ui, &mut input, |ui| ui.separator());
+        input.mouse_buttons.release(MouseButton::Left);
+        frame(&mut ui, &mut input, |ui| ui.separator());
+        assert_eq!(ui.panels["Test"].position, [70.0, 60.0]);
+
+        // The arrow at the left end of the title bar.
+        move_cursor(&mut input, [75.0, 65.0]);
+        frame(&mut ui, &mut input, |ui| ui.separator());
+        input.mouse_buttons.press(MouseButton::Left);
+        assert_eq!(frame(&mut ui, &mut input, |ui| ui.separator()), None);
+        input.mouse_buttons.release(MouseButton::Left);
+        frame(&mut ui, &mut input, |ui| ui.separator());
+        assert!(ui.panels["Test"].collapsed);
+        assert_eq!(ui.panels["Test"].size, [WINDOW_WIDTH, ROW_HEIGHT]);
+        assert_eq!(ui.panels["Test"].position, [70.0, 60.0]);
+    }
+
+    #[test]
+    fn clicks_raise_the_window_under_the_cursor() {
+        let (mut ui, mut input) = (DebugUi::headless(), Input::default());
+        // "A" is at (20, 20) and "B" at (50, 50), both 280 wide and 80 high.
+        let mut click = |position: [f32; 2]| {
+            move_cursor(&mut input, position);
+            for press in [true, false] {
+                if press { input.mouse_buttons.press(MouseButton::Left); } else { input.mouse_buttons.release(MouseButton::Left); }
+                ui.begin(&input);
+                for title in ["A", "B"] { ui.window(title, |ui| for _ in 0..10 { ui.separator(); }); }
+                input.end_frame();
+            }
+            return ui.order.clone();
+        };
+
+        assert_eq!(click([0.0, 0.0]), ["A", "B"]);
+        assert_eq!(click([100.0, 30.0]), ["B", "A"]);
+        // Both windows are under the cursor, the front one takes the click.
+        assert_eq!(click([100.0, 70.0]), ["B", "A"]);
+        assert_eq!(click([320.0, 110.0]), ["A", "B"]);
+    }
+}
@@ -0,0 +1,288 @@
+use super::context::Display;
+use super::math::{length, normalize, sub};
+use super::shadow::MAX_SPOT_SHADOWS;
+
+/// Size of the light array in `shaders/lights.glsl`, the most `Lighting::max_lights` can be.
+pub const MAX_LIGHTS: usize = 32;
+
+/// Light from far away that reaches everything from the same direction, like the sun.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DirectionalLight {
+    /// Direction the light travels in.
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Casts cascaded shadows, only the first directional light that does gets them.
+    pub cast_shadows: bool
+}
+
+/// Light shining in every direction from a point, fading out until `range`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32
+}
+
+/// Point light limited to a cone around `direction`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SpotLight {
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    /// Half angles of the cone in radians, full brightness inside `inner_angle` and fading to nothing at `outer_angle`.
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    /// Casts shadows when it is one of the first `MAX_SPOT_SHADOWS` shaded spot lights that do.
+    pub cast_shadows: bool
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Light {
+    Directional(DirectionalLight),
+    Point(PointLight),
+    Spot(SpotLight)
+}
+
+impl From<DirectionalLight> for Light { fn from(light: DirectionalLight) -> Light { Light::Directional(light) } }
+impl From<PointLight> for Light { fn from(light: PointLight) -> Light { Light::Point(light) } }
+impl From<SpotLight> for Light { fn from(light: SpotLight) -> Light { Light::Spot(light) } }
+
+impl Light {
+    /// How much the light matters around `focus`, used to pick which lights to shade with.
+    fn importance(&self, focus: [f32; 3]) -> f32 {
+        let falloff = |position: [f32; 3], range: f32| {
+            return range / (length(sub(position, focus)) + 1.0);
+        };
+        return match self {
+            Light::Directional(_) => f32::INFINITY,
+            Light::Point(light) => light.intensity * falloff(light.position, light.range),
+            Light::Spot(light) => light.intensity * falloff(light.position, light.range)
+        };
+    }
+
+    fn data(&self) -> LightData {
+        let radiance = |color: [f32; 3], intensity: f32| [color[0] * intensity, color[1] * intensity, color[2] * intensity, 0.0];
+
+        return match self {
+            Light::Directional(light) => {
+                let direction = normalize(light.direction);
+                LightData {
+                    position: [0.0, 0.0, 0.0, 0.0],
+                    direction: [direction[0], direction[1], direction[2], 0.0],
+                    color: radiance(light.color, light.intensity),
+                    cone: [0.0, 0.0, -1.0, 0.0]
+                }
+            },
+            Light::Point(light) => LightData {
+                position: [light.position[0], light.position[1], light.position[2], 1.0],
+                direction: [0.0, 0.0, 0.0, light.range],
+                color: radiance(light.color, light.intensity),
+                cone: [0.0, 0.0, -1.0, 0.0]
+            },
+            Light::Spot(light) => {
+                let direction = normalize(light.direction);
+                LightData {
+                    position: [light.position[0], light.position[1], light.position[2], 2.0],
+                    direction: [direction[0], direction[1], direction[2], light.range],
+                    color: radiance(light.color, light.intensity),
+                    cone: [light.inner_angle.cos(), light.outer_angle.cos(), -1.0, 0.0]
+                }
+            }
+        };
+    }
+}
+
+/// One light as laid out in the shader's `Lights` block.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct LightData {
+    /// xyz is the position, w the kind: 0 directional, 1 point and 2 spot.
+    position: [f32; 4],
+    /// xyz is the direction, w the range.
+    direction: [f32; 4],
+    /// Color times intensity.
+    color: [f32; 4],
+    /// Cosines of the spot light's inner and outer angle, then its shadow map or -1 without shadows.
+    cone: [f32; 4]
+}
+
+implement_uniform_block!(LightData, position, direction, color, cone);
+
+/// The `Lights` uniform block of `shaders/lights.glsl`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct LightBlock {
+    lights: [LightData; MAX_LIGHTS],
+    ambient: [f32; 4],
+    light_count: i32,
+    /// std140 rounds the block up to a multiple of 16 bytes, and the buffer can't be smaller.
+    padding: [i32; 3]
+}
+
+implement_uniform_block!(LightBlock, lights, ambient, light_count);
+
+/// What `Lighting::upload` writes and the kept lights that cast shadows.
+struct Selection {
+    block: LightBlock,
+    shadowed_directional: Option<DirectionalLight>,
+    shadowed_spots: Vec<SpotLight>
+}
+
+/// Keeps the `max_lights` lights that matter most around `focus` and hands out the shadow maps.
+fn select_lights(lights: &[Light], ambient: [f32; 3], max_lights: usize, focus: [f32; 3]) -> Selection {
+    let mut lights: Vec<(f32, &Light)> = lights.iter().map(|light| (light.importance(focus), light)).collect();
+    lights.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    lights.truncate(max_lights.min(MAX_LIGHTS));
+
+    let empty = LightData { position: [0.0; 4], direction: [0.0; 4], color: [0.0; 4], cone: [0.0; 4] };
+    let mut selection = Selection {
+        block: LightBlock {
+            lights: [empty; MAX_LIGHTS],
+            ambient: [ambient[0], ambient[1], ambient[2], 0.0],
+            light_count: lights.len() as i32,
+            padding: [0; 3]
+        },
+        shadowed_directional: None,
+        shadowed_spots: Vec::new()
+    };
+    for (data, (_, light)) in selection.block.lights.iter_mut().zip(&lights) {
+        *data = light.data();
+        match light {
+            Light::Directional(light) if light.cast_shadows && selection.shadowed_directional.is_none() => {
+                selection.shadowed_directional = Some(*light);
+                data.cone[2] = 0.0;
+            },
+            Light::Spot(light) if light.cast_shadows && selection.shadowed_spots.len() < MAX_SPOT_SHADOWS => {
+                data.cone[2] = selection.shadowed_spots.len() as f32;
+                selection.shadowed_spots.push(*light);
+            },
+            _ => ()
+        }
+    }
+    return selection;
+}
+
+/// Lights of a scene for forward Blinn-Phong shading. `upload` picks the lights that matter most and
+/// writes them to a uniform buffer, bound as `Lights` when drawing:
+///
+/// ```ignore
+/// lighting.upload(camera_position);
+/// let uniforms = uniform! { Lights: lighting.buffer(), camera_position: camera_position, .. };
+/// ```
+pub struct Lighting {
+    pub lights: Vec<Light>,
+    /// Added to every surface regardless of lights.
+    pub ambient: [f32; 3],
+    /// Lights shaded per frame, clamped to `MAX_LIGHTS`. Directional lights go first, then the point
+    /// and spot lights that are brightest near the focus given to `upload`.
+    pub max_lights: usize,
+    buffer: glium::uniforms::UniformBuffer<LightBlock>,
+    /// Lights of the last `upload` that cast shadows.
+    shadowed_directional: Option<DirectionalLight>,
+    shadowed_spots: Vec<SpotLight>
+}
+
+impl Lighting {
+    pub fn new(display: &Display) -> Lighting {
+        return Lighting {
+            lights: Vec::new(),
+            ambient: [0.1, 0.1, 0.1],
+            max_lights: 8,
+            buffer: glium::uniforms::UniformBuffer::empty_dynamic(display).unwrap(),
+            shadowed_directional: None,
+            shadowed_spots: Vec::new()
+        };
+    }
+
+    /// Writes this frame's lights to the buffer, returns how many were kept. Also picks the kept lights
+    /// that cast shadows, `Shadows::render` draws their maps.
+    pub fn upload(&mut self, focus: [f32; 3]) -> usize {
+        let selection = select_lights(&self.lights, self.ambient, self.max_lights, focus);
+        self.buffer.write(&selection.block);
+        self.shadowed_directional = selection.shadowed_directional;
+        self.shadowed_spots = selection.shadowed_spots;
+        return selection.block.light_count as usize;
+    }
+
+    pub fn buffer(&self) -> &glium::uniforms::UniformBuffer<LightBlock> { &self.buffer }
+
+    pub fn shadowed_directional(&self) -> Option<&DirectionalLight> { self.shadowed_directional.as_ref() }
+
+    pub fn shadowed_spots(&self) -> &[SpotLight] { &self.shadowed_spots }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sun(cast_shadows: bool) -> Light {
+        return Light::Directional(DirectionalLight { direction: [0.0, -2.0, 0.0], color: [1.0; 3], intensity: 1.0, cast_shadows });
+    }
+
+    fn point(x: f32, range: f32) -> Light {
+        return Light::Point(PointLight { position: [x, 0.0, 0.0], color: [1.0; 3], intensity: 1.0, range });
+    }
+
+    fn spot(x: f32, cast_shadows: bool) -> Light {
+        return Light::Spot(SpotLight {
+            position: [x, 0.0, 0.0],
+            direction: [0.0, -1.0, 0.0],
+            color: [1.0; 3],
+            intensity: 1.0,
+            range: 10.0,
+            inner_angle: 0.3,
+            outer_angle: 0.5,
+            cast_shadows
+        });
+    }
+
+    /// Kind and x position of every kept light.
+    fn kept(selection: &Selection) -> Vec<(f32, f32)> {
+        return selection.block.lights[..selection.block.light_count as usize].iter().map(|light| (light.position[3], light.position[0])).collect();
+    }
+
+    #[test]
+    fn directional_lights_first_then_by_importance() {
+        let lights = [point(20.0, 10.0), point(1.0, 10.0), sun(false), point(5.0, 10.0), point(1.0, 50.0)];
+        let selection = select_lights(&lights, [0.1; 3], 8, [0.0; 3]);
+        assert_eq!(kept(&selection), [(0.0, 0.0), (1.0, 1.0), (1.0, 1.0), (1.0, 5.0), (1.0, 20.0)]);
+        // The wider of the two lights at x = 1 comes first.
+        assert_eq!(selection.block.lights[1].direction[3], 50.0);
+        assert_eq!(selection.block.ambient, [0.1, 0.1, 0.1, 0.0]);
+        // Directions are normalized.
+        assert_eq!(selection.block.lights[0].direction, [0.0, -1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn keeps_at_most_max_lights() {
+        let lights: Vec<_> = (0..40).map(|index| point(index as f32, 10.0)).collect();
+        let selection = select_lights(&lights, [0.0; 3], 3, [0.0; 3]);
+        assert_eq!(kept(&selection), [(1.0, 0.0), (1.0, 1.0), (1.0, 2.0)]);
+        assert_eq!(select_lights(&lights, [0.0; 3], 100, [0.0; 3]).block.light_count, MAX_LIGHTS as i32);
+        assert_eq!(select_lights(&lights, [0.0; 3], 0, [0.0; 3]).block.light_count, 0);
+    }
+
+    #[test]
+    fn only_the_first_shadowed_directional_light_gets_cascades() {
+        let lights = [sun(false), sun(true), sun(true)];
+        let selection = select_lights(&lights, [0.0; 3], 8, [0.0; 3]);
+        let maps: Vec<_> = selection.block.lights[..3].iter().map(|light| light.cone[2]).collect();
+        assert_eq!(maps, [-1.0, 0.0, -1.0]);
+        assert!(selection.shadowed_directional.unwrap().cast_shadows);
+    }
+
+    #[test]
+    fn spot_shadow_slots_run_out() {
+        let lights: Vec<_> = (0..MAX_SPOT_SHADOWS + 2).map(|index| spot(index as f32, index != 1)).collect();
+        let selection = select_lights(&lights, [0.0; 3], 8, [0.0; 3]);
+        let maps: Vec<_> = selection.block.lights[..lights.len()].iter().map(|light| light.cone[2]).collect();
+        assert_eq!(maps, [0.0, -1.0, 1.0, 2.0, 3.0, -1.0]);
+        assert_eq!(selection.shadowed_spots.len(), MAX_SPOT_SHADOWS);
+        assert_eq!(selection.shadowed_spots[1].position[0], 2.0);
+        assert!(selection.shadowed_directional.is_none());
+    }
+}
@@ -1,6 +1,14 @@
 use ndarray::prelude::*;
 use super::types::Vec3;
 
+/// Column-major identity matrix, e.g. the view projection for geometry given in clip space.
+pub const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0]
+];
+
 pub fn rotate(position: Vec3, rotation: Vec3) -> Vec3 {
     let vector_position = arr1(&[position.x, position.y, position.z, 0.0]);
 
@@ -33,4 +41,126 @@ pub fn rotate(position: Vec3, rotation: Vec3) -> Vec3 {
     ]);
 
     return vector_position.dot(&x_mat.dot(&y_mat.dot(&z_mat))).to_vec().into();
-}
\ No newline at end of file
+}
+pub fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] { [a[0] + b[0], a[1] + b[1], a[2] + b[2]] }
+
+pub fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] { [a[0] - b[0], a[1] - b[1], a[2] - b[2]] }
+
+pub fn scale(a: [f32; 3], factor: f32) -> [f32; 3] { [a[0] * factor, a[1] * factor, a[2] * factor] }
+
+pub fn dot(a: [f32; 3], b: [f32; 3]) -> f32 { a[0] * b[0] + a[1] * b[1] + a[2] * b[2] }
+
+pub fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] { [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]] }
+
+pub fn length(a: [f32; 3]) -> f32 { dot(a, a).sqrt() }
+
+/// `a` scaled to length 1, zero vectors stay zero.
+pub fn normalize(a: [f32; 3]) -> [f32; 3] { scale(a, 1.0 / length(a).max(f32::EPSILON)) }
+
+/// Product of two column-major matrices, `a` applied last.
+pub fn multiply(a: [[f32; 4]; 4], b: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    return std::array::from_fn(|column| std::array::from_fn(|row| (0..4).map(|index| a[index][row] * b[column][index]).sum()));
+}
+
+/// View matrix of a right-handed camera at `eye` looking along `forward`.
+pub fn look_at(eye: [f32; 3], forward: [f32; 3], up: [f32; 3]) -> [[f32; 4]; 4] {
+    let f = normalize(forward);
+    let s = normalize(cross(f, up));
+    let u = cross(s, f);
+    return [
+        [s[0], u[0], -f[0], 0.0],
+        [s[1], u[1], -f[1], 0.0],
+        [s[2], u[2], -f[2], 0.0],
+        [-dot(s, eye), -dot(u, eye), dot(f, eye), 1.0]
+    ];
+}
+
+/// Maps the box between the planes to clip space, `near` and `far` are distances in front of the camera.
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    return [
+        [2.0 / (right - left), 0.0, 0.0, 0.0],
+        [0.0, 2.0 / (top - bottom), 0.0, 0.0],
+        [0.0, 0.0, -2.0 / (far - near), 0.0],
+        [-(right + left) / (right - left), -(top + bottom) / (top - bottom), -(far + near) / (far - near), 1.0]
+    ];
+}
+
+/// Perspective projection with a vertical field of view in radians.
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    return [
+        [f / aspect, 0.0, 0.0, 0.0],
+        [0.0, f, 0.0, 0.0],
+        [0.0, 0.0, (far + near) / (near - far), -1.0],
+        [0.0, 0.0, 2.0 * far * near / (near - far), 0.0]
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(matrix: [[f32; 4]; 4], point: [f32; 3]) -> [f32; 4] {
+        return std::array::from_fn(|row| (0..3).map(|column| matrix[column][row] * point[column]).sum::<f32>() + matrix[3][row]);
+    }
+
+    fn assert_near(actual: &[f32], expected: &[f32]) {
+        assert!(actual.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-4), "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn vectors() {
+        assert_eq!(add([1.0, 2.0, 3.0], [4.0, 5.0, 6.0]), [5.0, 7.0, 9.0]);
+        assert_eq!(sub([1.0, 2.0, 3.0], [4.0, 5.0, 6.0]), [-3.0, -3.0, -3.0]);
+        assert_eq!(scale([1.0, -2.0, 3.0], 2.0), [2.0, -4.0, 6.0]);
+        assert_eq!(dot([1.0, 2.0, 3.0], [4.0, 5.0, 6.0]), 32.0);
+        assert_eq!(cross([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]);
+        assert_eq!(length([2.0, 3.0, 6.0]), 7.0);
+        assert_near(&normalize([0.0, 3.0, 4.0]), &[0.0, 0.6, 0.8]);
+        assert_eq!(normalize([0.0, 0.0, 0.0]), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn multiply_applies_b_first() {
+        let translate = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [1.0, 2.0, 3.0, 1.0]];
+        let scale = [[2.0, 0.0, 0.0, 0.0], [0.0, 2.0, 0.0, 0.0], [0.0, 0.0, 2.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+        assert_eq!(multiply(IDENTITY, translate), translate);
+        assert_eq!(multiply(translate, IDENTITY), translate);
+        assert_near(&transform(multiply(scale, translate), [1.0, 1.0, 1.0]), &[4.0, 6.0, 8.0, 1.0]);
+        assert_near(&transform(multiply(translate, scale), [1.0, 1.0, 1.0]), &[3.0, 4.0, 5.0, 1.0]);
+    }
+
+    #[test]
+    fn look_at_puts_the_eye_at_the_origin() {
+        let view = look_at([1.0, 2.0, 3.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_near(&transform(view, [1.0, 2.0, 3.0]), &[0.0, 0.0, 0.0, 1.0]);
+        // Forward is -z, up stays y and the right hand side is x.
+        assert_near(&transform(view, [3.0, 2.0, 3.0]), &[0.0, 0.0, -2.0, 1.0]);
+        assert_near(&transform(view, [1.0, 3.0, 3.0]), &[0.0, 1.0, 0.0, 1.0]);
+        assert_near(&transform(view, [1.0, 2.0, 4.0]), &[1.0, 0.0, 0.0, 1.0]);
+        // Up only has to point roughly up.
+        let tilted = look_at([0.0, 0.0, 0.0], [0.0, 0.0, -2.0], [0.0, 1.0, 1.0]);
+        assert_near(&transform(tilted, [0.0, 1.0, 0.0]), &[0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn orthographic_maps_the_box_to_clip_space() {
+        let projection = orthographic(-2.0, 6.0, -1.0, 3.0, 1.0, 11.0);
+        assert_near(&transform(projection, [-2.0, -1.0, -1.0]), &[-1.0, -1.0, -1.0, 1.0]);
+        assert_near(&transform(projection, [6.0, 3.0, -11.0]), &[1.0, 1.0, 1.0, 1.0]);
+        assert_near(&transform(projection, [2.0, 1.0, -6.0]), &[0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn perspective_divides_by_distance() {
+        let projection = perspective(std::f32::consts::FRAC_PI_2, 2.0, 1.0, 10.0);
+        let near = transform(projection, [0.0, 0.0, -1.0]);
+        let far = transform(projection, [0.0, 0.0, -10.0]);
+        assert_near(&[near[2] / near[3], far[2] / far[3]], &[-1.0, 1.0]);
+        assert_eq!((near[3], far[3]), (1.0, 10.0));
+
+        // A 90 degree field of view shows as far up as ahead, and twice that to the sides.
+        let corner = transform(projection, [8.0, 4.0, -4.0]);
+        assert_near(&[corner[0] / corner[3], corner[1] / corner[3]], &[1.0, 1.0]);
+    }
+}
@@ -1,8 +1,30 @@
+pub mod context;
 pub mod opengl;
 pub mod vulkan;
 pub mod shaders {
     pub mod shader;
 }
 pub mod window;
+pub mod window_manager;
+pub mod scaling;
+pub mod capture;
+pub mod texture;
+pub mod compressed;
+pub mod bcn;
+pub mod atlas;
+pub mod camera;
+pub mod sprite;
+pub mod animation;
+pub mod tiled;
+pub mod tilemap;
+pub mod text;
+pub mod debug_ui;
+pub mod ui;
+pub mod debug_draw;
+pub mod lighting;
+pub mod pbr;
+pub mod shadow;
+pub mod post;
+pub mod settings;
 pub mod math;
 pub mod types;
\ No newline at end of file
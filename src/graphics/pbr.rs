@@ -0,0 +1,270 @@
+use std::rc::Rc;
+
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::IndicesSource;
+use glium::texture::{CubeLayer, Cubemap, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::Surface;
+
+use super::context::Display;
+use super::lighting::Lighting;
+use super::shaders::shader::{prepend, GGX, LIGHTS};
+use super::shadow::{ShadowFlags, ShadowSettings, Shadows};
+use super::texture::{SamplerSettings, Texture, TextureData, TextureError, TextureFormat, Wrap};
+use super::types::RenderVertex;
+
+/// Face size of the irradiance map, it only holds low frequencies.
+const IRRADIANCE_SIZE: u32 = 32;
+/// Face size of the prefiltered map's base level.
+const PREFILTERED_SIZE: u32 = 128;
+/// Mip levels of the prefiltered map, spread evenly from roughness 0 to 1.
+const PREFILTERED_LEVELS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 256;
+
+/// Directions a cubemap face covers, matching how GL looks up directions in a cubemap.
+struct Face {
+    layer: CubeLayer,
+    forward: [f32; 3],
+    right: [f32; 3],
+    up: [f32; 3]
+}
+
+const FACES: [Face; 6] = [
+    Face { layer: CubeLayer::PositiveX, forward: [1.0, 0.0, 0.0], right: [0.0, 0.0, -1.0], up: [0.0, -1.0, 0.0] },
+    Face { layer: CubeLayer::NegativeX, forward: [-1.0, 0.0, 0.0], right: [0.0, 0.0, 1.0], up: [0.0, -1.0, 0.0] },
+    Face { layer: CubeLayer::PositiveY, forward: [0.0, 1.0, 0.0], right: [1.0, 0.0, 0.0], up: [0.0, 0.0, 1.0] },
+    Face { layer: CubeLayer::NegativeY, forward: [0.0, -1.0, 0.0], right: [1.0, 0.0, 0.0], up: [0.0, 0.0, -1.0] },
+    Face { layer: CubeLayer::PositiveZ, forward: [0.0, 0.0, 1.0], right: [1.0, 0.0, 0.0], up: [0.0, -1.0, 0.0] },
+    Face { layer: CubeLayer::NegativeZ, forward: [0.0, 0.0, -1.0], right: [-1.0, 0.0, 0.0], up: [0.0, -1.0, 0.0] }
+];
+
+/// Metallic-roughness material with the factors and maps of a glTF material. Every map is optional,
+/// missing ones read as white, or as a flat normal for `normal_texture`, so only the factors count.
+#[derive(Clone)]
+pub struct PbrMaterial {
+    /// Multiplies the base color map, alpha included.
+    pub base_color_factor: [f32; 4],
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub emissive_factor: [f32; 3],
+    /// Scales the X and Y of the normal map.
+    pub normal_scale: f32,
+    /// 0 ignores the occlusion map, 1 applies it fully.
+    pub occlusion_strength: f32,
+    /// Albedo for dielectrics and reflectance for metals, loaded as `TextureFormat::Srgb`.
+    pub base_color_texture: Option<Rc<Texture>>,
+    /// Roughness in green and metallic in blue, loaded as `TextureFormat::Linear`.
+    pub metallic_roughness_texture: Option<Rc<Texture>>,
+    /// Tangent space normals with +Y up, loaded as `TextureFormat::Linear`.
+    pub normal_texture: Option<Rc<Texture>>,
+    /// Ambient occlusion in red, loaded as `TextureFormat::Linear`. Only darkens light from the environment.
+    pub occlusion_texture: Option<Rc<Texture>>,
+    /// Light the surface gives off, loaded as `TextureFormat::Srgb`.
+    pub emissive_texture: Option<Rc<Texture>>,
+    /// Whether `PbrRenderer::draw` darkens it with the scene's shadows, pass it to `ShadowPass::draw` for casting.
+    pub shadows: ShadowFlags
+}
+
+impl Default for PbrMaterial {
+    /// The glTF defaults, a white rough metal.
+    fn default() -> PbrMaterial {
+        return PbrMaterial {
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            emissive_factor: [0.0, 0.0, 0.0],
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None,
+            shadows: ShadowFlags::default()
+        };
+    }
+}
+
+/// Image based lighting made from an environment cubemap by `PbrRenderer::environment`.
+pub struct Environment {
+    /// Multiplies the light from the environment.
+    pub intensity: f32,
+    /// Cosine weighted light from every direction, for the diffuse part.
+    irradiance: Texture,
+    /// The environment blurred for rougher surfaces at each mip level, for the specular part.
+    prefiltered: Texture,
+    levels: u32
+}
+
+impl Environment {
+    pub fn irradiance(&self) -> &Texture { &self.irradiance }
+
+    pub fn prefiltered(&self) -> &Texture { &self.prefiltered }
+}
+
+/// What a frame's PBR draws share.
+pub struct PbrScene<'a> {
+    pub view_projection: [[f32; 4]; 4],
+    pub camera_position: [f32; 3],
+    /// Lights after `Lighting::upload` for this frame.
+    pub lighting: &'a Lighting,
+    /// No environment only lights with `lighting`, its ambient included.
+    pub environment: Option<&'a Environment>,
+    /// Shadow maps rendered for this frame's `lighting`.
+    pub shadows: Option<&'a Shadows>
+}
+
+#[derive(Copy, Clone)]
+struct FaceVertex {
+    position: [f32; 2]
+}
+
+implement_vertex!(FaceVertex, position);
+
+/// Shades `RenderVertex` meshes with `PbrMaterial`s, Cook-Torrance GGX for the lights of a `Lighting`
+/// plus image based lighting from an `Environment`. Generates the BRDF lookup table the environment
+/// lighting needs when created, and makes environments out of cubemaps:
+///
+/// ```ignore
+/// let sky = Texture::load_cubemap(display, &faces, &TextureSettings { format: TextureFormat::Hdr, ..Default::default() })?;
+/// let environment = pbr.environment(display, &sky)?;
+/// lighting.upload(camera_position);
+/// pbr.draw(target, &vertices, &indices, &material, &PbrScene { environment: Some(&environment), .. }, &params)?;
+/// ```
+pub struct PbrRenderer {
+    program: glium::Program,
+    irradiance_program: glium::Program,
+    prefilter_program: glium::Program,
+    quad: glium::VertexBuffer<FaceVertex>,
+    brdf_lut: Texture,
+    /// Stand-ins for the maps a material leaves out.
+    white_srgb: Texture,
+    white_linear: Texture,
+    flat_normal: Texture,
+    /// Black maps for draws without an environment.
+    no_environment: Environment,
+    /// Empty maps for draws without shadows.
+    no_shadows: Shadows
+}
+
+fn environment_sampler() -> SamplerSettings {
+    return SamplerSettings { wrap: Wrap::Clamp, ..Default::default() };
+}
+
+/// Calls `draw` with a framebuffer for each face of `level` of `cubemap`.
+fn render_faces(display: &Display, cubemap: &Cubemap, level: u32, mut draw: impl FnMut(&mut SimpleFrameBuffer, &Face)) -> Result<(), TextureError> {
+    let mipmap = cubemap.mipmap(level).ok_or_else(|| TextureError::Invalid(format!("cubemap has no mip level {}", level)))?;
+    for face in &FACES {
+        let mut framebuffer = SimpleFrameBuffer::new(display, mipmap.image(face.layer))
+            .map_err(|error| TextureError::Invalid(format!("cubemap face can't be drawn to: {:?}", error)))?;
+        draw(&mut framebuffer, face);
+    }
+    return Ok(());
+}
+
+impl PbrRenderer {
+    pub fn new(display: &Display) -> PbrRenderer {
+        let program = |fragment_shader: &str| glium::Program::from_source(display, include_str!("../../shaders/cubemap_face.vs"), fragment_shader, None).unwrap();
+        let quad = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]].map(|position| FaceVertex { position });
+        let quad = glium::VertexBuffer::new(display, &quad).unwrap();
+        let strip = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
+
+        let lut = Texture2d::empty_with_format(display, UncompressedFloatFormat::F16F16, MipmapsOption::NoMipmap, BRDF_LUT_SIZE, BRDF_LUT_SIZE).unwrap();
+        SimpleFrameBuffer::new(display, &lut).unwrap()
+            .draw(&quad, strip, &program(&prepend(include_str!("../../shaders/brdf_lut.fs"), &[GGX])), &glium::uniforms::EmptyUniforms, &Default::default())
+            .unwrap();
+
+        let black_cubemap = || {
+            let cubemap = Cubemap::empty_with_format(display, UncompressedFloatFormat::F16F16F16F16, MipmapsOption::NoMipmap, 1).unwrap();
+            render_faces(display, &cubemap, 0, |framebuffer, _| framebuffer.clear_color(0.0, 0.0, 0.0, 1.0)).unwrap();
+            return Texture { data: TextureData::Cubemap(cubemap), format: TextureFormat::Hdr, sampler: environment_sampler() };
+        };
+
+        return PbrRenderer {
+            program: glium::Program::from_source(display, include_str!("../../shaders/simple.vs"), prepend(include_str!("../../shaders/pbr.fs"), &[LIGHTS, GGX]).as_str(), None).unwrap(),
+            irradiance_program: program(include_str!("../../shaders/irradiance.fs")),
+            prefilter_program: program(&prepend(include_str!("../../shaders/prefilter.fs"), &[GGX])),
+            quad,
+            brdf_lut: Texture { data: TextureData::Color(lut), format: TextureFormat::Hdr, sampler: environment_sampler() },
+            white_srgb: Texture::solid(display, [255, 255, 255, 255], TextureFormat::Srgb).unwrap(),
+            white_linear: Texture::solid(display, [255, 255, 255, 255], TextureFormat::Linear).unwrap(),
+            flat_normal: Texture::solid(display, [128, 128, 255, 255], TextureFormat::Linear).unwrap(),
+            no_environment: Environment { intensity: 0.0, irradiance: black_cubemap(), prefiltered: black_cubemap(), levels: 1 },
+            no_shadows: Shadows::new(display, ShadowSettings { resolution: 1, ..Default::default() })
+        };
+    }
+
+    /// The generated table of scale and bias to the Fresnel reflectance, by angle and roughness.
+    pub fn brdf_lut(&self) -> &Texture { &self.brdf_lut }
+
+    /// Convolves an environment cubemap into irradiance and prefiltered specular maps. HDR sources with
+    /// mipmaps give the best results, the mipmaps keep small bright spots from turning into noise.
+    pub fn environment(&self, display: &Display, source: &Texture) -> Result<Environment, TextureError> {
+        if !matches!(source.data, TextureData::Cubemap(_) | TextureData::SrgbCubemap(_)) {
+            return Err(TextureError::Invalid("environment lighting needs a cubemap".to_string()));
+        }
+        let source_size = source.dimensions().0 as f32;
+        let strip = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
+
+        let irradiance = Cubemap::empty_with_format(display, UncompressedFloatFormat::F16F16F16F16, MipmapsOption::NoMipmap, IRRADIANCE_SIZE)
+            .map_err(TextureError::Create)?;
+        // Reading from a level about as coarse as the convolution's steps avoids missing bright details between them.
+        let source_level = (source_size / IRRADIANCE_SIZE as f32).log2().max(0.0);
+        render_faces(display, &irradiance, 0, |framebuffer, face| {
+            let uniforms = uniform! { environment: source, face_forward: face.forward, face_right: face.right, face_up: face.up, source_level: source_level };
+            framebuffer.draw(&self.quad, strip, &self.irradiance_program, &uniforms, &Default::default()).unwrap();
+        })?;
+
+        let prefiltered = Cubemap::empty_with_format(display, UncompressedFloatFormat::F16F16F16F16,
+                                                     MipmapsOption::EmptyMipmapsMax(PREFILTERED_LEVELS - 1), PREFILTERED_SIZE)
+            .map_err(TextureError::Create)?;
+        for level in 0..PREFILTERED_LEVELS {
+            let roughness = level as f32 / (PREFILTERED_LEVELS - 1) as f32;
+            render_faces(display, &prefiltered, level, |framebuffer, face| {
+                let uniforms = uniform! {
+                    environment: source, face_forward: face.forward, face_right: face.right, face_up: face.up, roughness: roughness, source_size: source_size
+                };
+                framebuffer.draw(&self.quad, strip, &self.prefilter_program, &uniforms, &Default::default()).unwrap();
+            })?;
+        }
+
+        return Ok(Environment {
+            intensity: 1.0,
+            irradiance: Texture { data: TextureData::Cubemap(irradiance), format: TextureFormat::Hdr, sampler: environment_sampler() },
+            prefiltered: Texture { data: TextureData::Cubemap(prefiltered), format: TextureFormat::Hdr, sampler: environment_sampler() },
+            levels: PREFILTERED_LEVELS
+        });
+    }
+
+    /// Draws a mesh given in world space. Colors come out linear and unclamped.
+    pub fn draw<'a, S: Surface>(&self, target: &mut S, vertices: &glium::VertexBuffer<RenderVertex>, indices: impl Into<IndicesSource<'a>>,
+                                material: &PbrMaterial, scene: &PbrScene, params: &glium::DrawParameters) -> Result<(), glium::DrawError> {
+        let environment = scene.environment.unwrap_or(&self.no_environment);
+        let shadows = scene.shadows.unwrap_or(&self.no_shadows);
+        let uniforms = uniform! {
+            view_projection: scene.view_projection,
+            camera_position: scene.camera_position,
+            Lights: scene.lighting.buffer(),
+            base_color_texture: material.base_color_texture.as_deref().unwrap_or(&self.white_srgb),
+            metallic_roughness_texture: material.metallic_roughness_texture.as_deref().unwrap_or(&self.white_linear),
+            normal_texture: material.normal_texture.as_deref().unwrap_or(&self.flat_normal),
+            occlusion_texture: material.occlusion_texture.as_deref().unwrap_or(&self.white_linear),
+            emissive_texture: material.emissive_texture.as_deref().unwrap_or(&self.white_srgb),
+            base_color_factor: material.base_color_factor,
+            metallic_factor: material.metallic_factor,
+            roughness_factor: material.roughness_factor,
+            emissive_factor: material.emissive_factor,
+            normal_scale: material.normal_scale,
+            occlusion_strength: material.occlusion_strength,
+            irradiance_map: &environment.irradiance,
+            prefiltered_map: &environment.prefiltered,
+            brdf_lut: &self.brdf_lut,
+            prefiltered_max_level: (environment.levels - 1) as f32,
+            environment_intensity: environment.intensity,
+            Shadows: shadows.buffer(),
+            cascade_maps: shadows.cascade_maps(),
+            spot_maps: shadows.spot_maps(),
+            receive_shadows: material.shadows.receive
+        };
+        return target.draw(vertices, indices, &self.program, &uniforms, params);
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::texture::{DepthFormat, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction, Uniforms};
+use glium::Surface;
+use serde::{Deserialize, Serialize};
+
+use super::context::Display;
+use super::texture::{SamplerSettings, Texture, TextureFormat, TextureSettings, Wrap};
+
+#[derive(Debug)]
+pub enum PostStackError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    /// A color grading LUT that can't be read or isn't made of square slices.
+    Invalid(String)
+}
+
+impl std::fmt::Display for PostStackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostStackError::Io(error) => write!(f, "failed to read post-process stack: {}", error),
+            PostStackError::Parse(error) => write!(f, "failed to parse post-process stack: {}", error),
+            PostStackError::Invalid(message) => write!(f, "invalid post-process stack: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for PostStackError {}
+
+/// Curve that maps HDR colors into the range the window shows.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Tonemapper {
+    /// `color / (1 + color)`, keeps hues but looks flat.
+    Reinhard,
+    /// Filmic curve with more contrast and desaturated highlights.
+    Aces
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Effect {
+    /// Scales colors by 2 to the power of `stops`.
+    Exposure { stops: f32 },
+    /// Glow around what is brighter than `threshold`, blurred over `levels` halvings of the resolution.
+    Bloom { threshold: f32, intensity: f32, levels: u32 },
+    Tonemap(Tonemapper),
+    /// Fast approximate anti-aliasing, meant for after tonemapping.
+    Fxaa,
+    /// Darkens toward the corners, `smoothness` is the fraction of the way out the darkening fades in over.
+    Vignette { intensity: f32, smoothness: f32 },
+    /// Remaps colors through a LUT image of square slices side by side, one per blue value, with red along
+    /// each slice and green rising from the bottom row. A 16 slice LUT is 256x16. Relative paths start at
+    /// the stack's file when loaded with `PostStack::load`.
+    ColorGrading { lut: PathBuf, strength: f32 }
+}
+
+impl Effect {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Effect::Exposure { .. } => "exposure",
+            Effect::Bloom { .. } => "bloom",
+            Effect::Tonemap(_) => "tonemap",
+            Effect::Fxaa => "fxaa",
+            Effect::Vignette { .. } => "vignette",
+            Effect::ColorGrading { .. } => "color grading"
+        };
+    }
+}
+
+fn enabled_by_default() -> bool { true }
+
+/// Whether an image of `width` by `height` pixels holds `height` square slices side by side.
+fn is_lut_size(width: u32, height: u32) -> bool { height > 1 && height.checked_mul(height) == Some(width) }
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PostPass {
+    pub effect: Effect,
+    /// Disabled passes are skipped without losing their place in the stack.
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool
+}
+
+/// Effects applied in order to the frame after `App::render`, e.g. in TOML:
+///
+/// ```toml
+/// [[passes]]
+/// effect = { Bloom = { threshold = 1.0, intensity = 0.3, levels = 5 } }
+///
+/// [[passes]]
+/// effect = { Tonemap = "Aces" }
+/// ```
+///
+/// While there are passes, enabled or not, the scene is rendered into an HDR target instead of the window,
+/// so colors above 1 survive until tonemapping. HDR passes like exposure and bloom go before tonemapping,
+/// FXAA, vignette and color grading after.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct PostStack {
+    pub passes: Vec<PostPass>
+}
+
+impl PostStack {
+    /// Reads a stack, resolves relative LUT paths against the file's directory and checks the LUTs.
+    pub fn load(path: impl AsRef<Path>) -> Result<PostStack, PostStackError> {
+        let mut stack = PostStack::parse(&std::fs::read_to_string(&path).map_err(PostStackError::Io)?)?;
+        let directory = path.as_ref().parent().unwrap_or(Path::new(""));
+        for pass in &mut stack.passes {
+            if let Effect::ColorGrading { lut, .. } = &mut pass.effect {
+                if lut.is_relative() { *lut = directory.join(&*lut); }
+            }
+        }
+        stack.check_luts()?;
+        return Ok(stack);
+    }
+
+    /// Checks that the LUT of every color grading pass, enabled or not, can be read and is `size` square
+    /// slices of `size` pixels side by side. A LUT that fails later is skipped by `PostProcessor`.
+    pub fn check_luts(&self) -> Result<(), PostStackError> {
+        for pass in &self.passes {
+            let Effect::ColorGrading { lut, .. } = &pass.effect else { continue; };
+            let (width, height) = image::image_dimensions(lut)
+                .map_err(|error| PostStackError::Invalid(format!("failed to read color grading LUT {}: {}", lut.display(), error)))?;
+            if !is_lut_size(width, height) {
+                return Err(PostStackError::Invalid(format!("color grading LUT {} is {}x{}, a LUT of {} slices has to be {}x{}",
+                                                           lut.display(), width, height, height, height.saturating_mul(height), height)));
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn parse(source: &str) -> Result<PostStack, PostStackError> {
+        return toml::from_str(source).map_err(PostStackError::Parse);
+    }
+
+    pub fn push(&mut self, effect: Effect) {
+        self.passes.push(PostPass { effect, enabled: true });
+    }
+
+    /// First pass whose effect has `name`, e.g. `stack.find_mut("bloom")`.
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut PostPass> {
+        return self.passes.iter_mut().find(|pass| pass.effect.name() == name);
+    }
+}
+
+#[derive(Copy, Clone)]
+struct PostVertex {
+    position: [f32; 2]
+}
+
+implement_vertex!(PostVertex, position);
+
+fn clamped(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    return texture.sampled()
+        .wrap_function(SamplerWrapFunction::Clamp)
+        .minify_filter(MinifySamplerFilter::Linear)
+        .magnify_filter(MagnifySamplerFilter::Linear);
+}
+
+fn hdr_texture(display: &Display, size: [u32; 2]) -> Texture2d {
+    return Texture2d::empty_with_format(display, UncompressedFloatFormat::F16F16F16F16, MipmapsOption::NoMipmap, size[0].max(1), size[1].max(1)).unwrap();
+}
+
+/// Renders the scene into an HDR target and runs a `PostStack` over it. Driven by the engine with
+/// `Context::post`, games only change the stack.
+pub struct PostProcessor {
+    target: Option<(Texture2d, DepthRenderBuffer)>,
+    /// Passes alternate between these, the last one draws to the output.
+    swap: Vec<Texture2d>,
+    /// Halving sizes of the bloom blur.
+    bloom: Vec<Texture2d>,
+    luts: HashMap<PathBuf, Option<Texture>>,
+    quad: glium::VertexBuffer<PostVertex>,
+    copy: glium::Program,
+    exposure: glium::Program,
+    tonemap: glium::Program,
+    bloom_downsample: glium::Program,
+    bloom_upsample: glium::Program,
+    bloom_composite: glium::Program,
+    fxaa: glium::Program,
+    vignette: glium::Program,
+    color_grading: glium::Program
+}
+
+impl PostProcessor {
+    pub fn new(display: &Display) -> PostProcessor {
+        let program = |fragment_shader: &str| glium::Program::from_source(display, include_str!("../../shaders/upscale.vs"), fragment_shader, None).unwrap();
+        let quad = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]].map(|position| PostVertex { position });
+
+        return PostProcessor {
+            target: None,
+            swap: Vec::new(),
+            bloom: Vec::new(),
+            luts: HashMap::new(),
+            quad: glium::VertexBuffer::new(display, &quad).unwrap(),
+            copy: program(include_str!("../../shaders/upscale.fs")),
+            exposure: program(include_str!("../../shaders/exposure.fs")),
+            tonemap: program(include_str!("../../shaders/tonemap.fs")),
+            bloom_downsample: program(include_str!("../../shaders/bloom_downsample.fs")),
+            bloom_upsample: program(include_str!("../../shaders/bloom_upsample.fs")),
+            bloom_composite: program(include_str!("../../shaders/bloom_composite.fs")),
+            fxaa: program(include_str!("../../shaders/fxaa.fs")),
+            vignette: program(include_str!("../../shaders/vignette.fs")),
+            color_grading: program(include_str!("../../shaders/color_grading.fs"))
+        };
+    }
+
+    /// Makes sure the HDR target has `size`, the size the scene is rendered at.
+    pub fn prepare(&mut self, display: &Display, size: [u32; 2]) {
+        if let Some((texture, _)) = &self.target {
+            if [texture.width(), texture.height()] == size { return; }
+        }
+
+        let depth = DepthRenderBuffer::new(display, DepthFormat::I24, size[0].max(1), size[1].max(1)).unwrap();
+        self.target = Some((hdr_texture(display, size), depth));
+        self.swap = vec![hdr_texture(display, size), hdr_texture(display, size)];
+        self.bloom.clear();
+    }
+
+    /// The HDR framebuffer the scene is rendered into, after `prepare`.
+    pub fn framebuffer(&self, display: &Display) -> SimpleFrameBuffer<'_> {
+        let (texture, depth) = self.target.as_ref().expect("PostProcessor::prepare wasn't called");
+        return SimpleFrameBuffer::with_depth_buffer(display, texture, depth).unwrap();
+    }
+
+    /// Runs the enabled passes of `stack` over the rendered scene and draws the result to `output`, which
+    /// has the size given to `prepare`. Without enabled passes the scene is copied as it is.
+    pub fn apply<S: Surface>(&mut self, display: &Display, stack: &PostStack, output: &mut S) {
+        let passes: Vec<&Effect> = stack.passes.iter().filter(|pass| pass.enabled).map(|pass| &pass.effect).collect();
+        for effect in &passes {
+            match effect {
+                Effect::Bloom { levels, .. } => self.prepare_bloom(display, *levels),
+                Effect::ColorGrading { lut, .. } => self.load_lut(display, lut),
+                _ => ()
+            }
+        }
+
+        let Some((scene, _)) = &self.target else { return; };
+        if passes.is_empty() {
+            self.draw(output, &self.copy, &uniform! { scene: clamped(scene), sharpness: 0.0f32 }, false);
+            return;
+        }
+
+        let mut source = scene;
+        for (index, effect) in passes.iter().enumerate() {
+            if index + 1 == passes.len() {
+                self.run(display, effect, source, output);
+            } else {
+                let destination = &self.swap[index % 2];
+                self.run(display, effect, source, &mut SimpleFrameBuffer::new(display, destination).unwrap());
+                source = destination;
+            }
+        }
+    }
+
+    fn prepare_bloom(&mut self, display: &Display, levels: u32) {
+        let Some((scene, _)) = &self.target else { return; };
+        let mut size = match self.bloom.last() {
+            Some(level) => [level.width(), level.height()],
+            None => [scene.width(), scene.height()]
+        };
+        while self.bloom.len() < levels as usize && size[0] > 1 && size[1] > 1 {
+            size = [size[0] / 2, size[1] / 2];
+            self.bloom.push(hdr_texture(display, size));
+        }
+    }
+
+    fn load_lut(&mut self, display: &Display, path: &Path) {
+        if self.luts.contains_key(path) { return; }
+
+        let settings = TextureSettings { format: TextureFormat::Linear, mipmaps: false, sampler: SamplerSettings { wrap: Wrap::Clamp, ..Default::default() } };
+        // `PostStack::check_luts` reports why, the pass just copies the scene without a usable LUT.
+        let lut = Texture::load(display, path, &settings).ok()
+            .filter(|lut| is_lut_size(lut.dimensions().0, lut.dimensions().1));
+        self.luts.insert(path.to_path_buf(), lut);
+    }
+
+    fn draw<S: Surface, U: Uniforms>(&self, target: &mut S, program: &glium::Program, uniforms: &U, additive: bool) {
+        let params = glium::DrawParameters {
+            blend: if additive {
+                let add = glium::BlendingFunction::Addition { source: glium::LinearBlendingFactor::One, destination: glium::LinearBlendingFactor::One };
+                glium::Blend { color: add, alpha: add, constant_value: (0.0, 0.0, 0.0, 0.0) }
+            } else {
+                Default::default()
+            },
+            .. Default::default()
+        };
+        target.draw(&self.quad, glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip), program, uniforms, &params).unwrap();
+    }
+
+    fn run<S: Surface>(&self, display: &Display, effect: &Effect, source: &Texture2d, target: &mut S) {
+        let scene = clamped(source);
+        match effect {
+            Effect::Exposure { stops } => self.draw(target, &self.exposure, &uniform! { scene: scene, exposure: stops.exp2() }, false),
+            Effect::Bloom { threshold, intensity, levels } => self.bloom(display, source, target, *threshold, *intensity, *levels as usize),
+            Effect::Tonemap(tonemapper) => {
+                let operator = match tonemapper { Tonemapper::Reinhard => 0, Tonemapper::Aces => 1 };
+                self.draw(target, &self.tonemap, &uniform! { scene: scene, operator: operator }, false);
+            },
+            Effect::Fxaa => self.draw(target, &self.fxaa, &uniform! { scene: scene }, false),
+            Effect::Vignette { intensity, smoothness } => {
+                self.draw(target, &self.vignette, &uniform! { scene: scene, intensity: *intensity, smoothness: *smoothness }, false);
+            },
+            Effect::ColorGrading { lut, strength } => match self.luts.get(lut) {
+                Some(Some(lut)) => self.draw(target, &self.color_grading, &uniform! { scene: scene, lut: lut, strength: *strength }, false),
+                _ => self.draw(target, &self.copy, &uniform! { scene: scene, sharpness: 0.0f32 }, false)
+            }
+        }
+    }
+
+    /// Blurs the bright parts by downsampling through the bloom levels and adding each level back onto
+    /// the next bigger one, then adds the result to the scene.
+    fn bloom<S: Surface>(&self, display: &Display, source: &Texture2d, target: &mut S, threshold: f32, intensity: f32, levels: usize) {
+        let levels = &self.bloom[..levels.min(self.bloom.len())];
+        let Some(first) = levels.first() else {
+            self.draw(target, &self.copy, &uniform! { scene: clamped(source), sharpness: 0.0f32 }, false);
+            return;
+        };
+
+        let mut input = source;
+        for (index, level) in levels.iter().enumerate() {
+            let uniforms = uniform! { source: clamped(input), prefilter: index == 0, threshold: threshold };
+            self.draw(&mut SimpleFrameBuffer::new(display, level).unwrap(), &self.bloom_downsample, &uniforms, false);
+            input = level;
+        }
+        for pair in levels.windows(2).rev() {
+            self.draw(&mut SimpleFrameBuffer::new(display, &pair[0]).unwrap(), &self.bloom_upsample, &uniform! { source: clamped(&pair[1]) }, true);
+        }
+
+        self.draw(target, &self.bloom_composite, &uniform! { scene: clamped(source), bloom: clamped(first), intensity: intensity }, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for a test's files, removed when it is dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = std::env::temp_dir().join(format!("post-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(path.join("luts")).unwrap();
+            return TempDir(path);
+        }
+
+        fn write_lut(&self, name: &str, width: u32, height: u32) {
+            image::RgbImage::new(width, height).save(self.0.join(name)).unwrap();
+        }
+
+        fn write_stack(&self, lut: &str) -> PathBuf {
+            let path = self.0.join("post.toml");
+            std::fs::write(&path, format!("[[passes]]\neffect = {{ ColorGrading = {{ lut = \"{}\", strength = 0.5 }} }}\nenabled = false\n", lut)).unwrap();
+            return path;
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) { let _ = std::fs::remove_dir_all(&self.0); }
+    }
+
+    #[test]
+    fn demo_stack() {
+        let stack = PostStack::load(crate::inner_path!("config/post.toml")).unwrap();
+        let names: Vec<_> = stack.passes.iter().map(|pass| pass.effect.name()).collect();
+        assert_eq!(names, ["exposure", "bloom", "tonemap", "color grading", "fxaa", "vignette"]);
+        assert!(stack.passes.iter().all(|pass| pass.enabled));
+        assert_eq!(stack.passes[1].effect, Effect::Bloom { threshold: 1.0, intensity: 0.3, levels: 5 });
+        assert_eq!(stack.passes[2].effect, Effect::Tonemap(Tonemapper::Aces));
+
+        let Effect::ColorGrading { lut, strength } = &stack.passes[3].effect else { panic!("expected color grading") };
+        assert_eq!(*strength, 1.0);
+        assert_eq!(lut, &crate::inner_path!("config").join("../img/grading.png"));
+    }
+
+    #[test]
+    fn passes_keep_their_order() {
+        let mut stack = PostStack::parse("[[passes]]\neffect = \"Fxaa\"\n\n[[passes]]\neffect = { Tonemap = \"Reinhard\" }\nenabled = false").unwrap();
+        stack.push(Effect::Vignette { intensity: 0.5, smoothness: 0.5 });
+        stack.push(Effect::Fxaa);
+        let names: Vec<_> = stack.passes.iter().map(|pass| (pass.effect.name(), pass.enabled)).collect();
+        assert_eq!(names, [("fxaa", true), ("tonemap", false), ("vignette", true), ("fxaa", true)]);
+
+        stack.find_mut("fxaa").unwrap().enabled = false;
+        assert!(!stack.passes[0].enabled && stack.passes[3].enabled);
+        assert!(stack.find_mut("bloom").is_none());
+
+        let saved = toml::to_string(&stack).unwrap();
+        assert_eq!(PostStack::parse(&saved).unwrap(), stack);
+        assert_eq!(PostStack::parse("").unwrap(), PostStack::default());
+        assert!(matches!(PostStack::parse("[[passes]]\neffect = \"Sharpen\""), Err(PostStackError::Parse(_))));
+    }
+
+    #[test]
+    fn relative_luts_start_at_the_stack() {
+        let directory = TempDir::new("relative");
+        directory.write_lut("luts/grading.png", 16, 4);
+        let stack = PostStack::load(directory.write_stack("luts/grading.png")).unwrap();
+        assert_eq!(stack.passes[0].effect, Effect::ColorGrading { lut: directory.0.join("luts/grading.png"), strength: 0.5 });
+
+        let absolute = directory.0.join("luts/grading.png");
+        let stack = PostStack::load(directory.write_stack(&absolute.display().to_string().replace('\\', "/"))).unwrap();
+        let Effect::ColorGrading { lut, .. } = &stack.passes[0].effect else { panic!("expected color grading") };
+        assert_eq!(std::fs::canonicalize(lut).unwrap(), std::fs::canonicalize(absolute).unwrap());
+    }
+
+    #[test]
+    fn bad_luts_fail_to_load() {
+        let directory = TempDir::new("bad");
+        directory.write_lut("luts/wide.png", 20, 4);
+        directory.write_lut("luts/line.png", 1, 1);
+        assert!(matches!(PostStack::load(directory.write_stack("luts/wide.png")), Err(PostStackError::Invalid(_))));
+        assert!(matches!(PostStack::load(directory.write_stack("luts/line.png")), Err(PostStackError::Invalid(_))));
+        assert!(matches!(PostStack::load(directory.write_stack("luts/missing.png")), Err(PostStackError::Invalid(_))));
+        assert!(matches!(PostStack::load(directory.0.join("missing.toml")), Err(PostStackError::Io(_))));
+
+        assert!(is_lut_size(256, 16) && is_lut_size(1024, 32) && is_lut_size(4, 2));
+        assert!(!is_lut_size(256, 17) && !is_lut_size(0, 0) && !is_lut_size(u32::MAX, 65536));
+    }
+}
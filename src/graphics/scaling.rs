@@ -0,0 +1,258 @@
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::texture::{DepthFormat, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::Surface;
+use serde::{Deserialize, Serialize};
+
+use super::context::Display;
+
+/// Resolution the scene is rendered at before it is scaled to the window.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum RenderResolution {
+    /// The window's own size, nothing gets scaled.
+    Native,
+    /// Fraction of the window size, 0.5 renders a quarter of the pixels.
+    Scale(f32),
+    /// The same size whatever the window size, e.g. 320x180 for pixel art.
+    Fixed([u32; 2])
+}
+
+impl RenderResolution {
+    pub fn size(&self, window_size: [u32; 2]) -> [u32; 2] {
+        return match *self {
+            RenderResolution::Native => window_size,
+            RenderResolution::Scale(scale) => scale_size(window_size, scale),
+            RenderResolution::Fixed(size) => size
+        };
+    }
+}
+
+/// How the internal render target is stretched over the window.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum UpscaleFilter {
+    Nearest,
+    Bilinear,
+    /// Bilinear followed by sharpening with a strength between 0 and 1.
+    Sharpened(f32)
+}
+
+/// Lowers the render resolution while frames take longer than `target_frame_time` and raises
+/// it again once there is time to spare.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct DynamicResolution {
+    /// Milliseconds spent on a frame, not counting the wait for vsync.
+    pub target_frame_time: f32,
+    pub min_scale: f32,
+    pub max_scale: f32
+}
+
+impl Default for DynamicResolution {
+    fn default() -> DynamicResolution {
+        return DynamicResolution { target_frame_time: 16.6, min_scale: 0.5, max_scale: 1.0 };
+    }
+}
+
+/// Amount the dynamic scale changes by at once.
+const SCALE_STEP: f32 = 0.05;
+/// Seconds of frames averaged before the dynamic scale may change again, so the render target is
+/// reallocated a few times a second at most while adapting.
+const SCALE_INTERVAL: f32 = 0.25;
+
+fn scale_size(size: [u32; 2], scale: f32) -> [u32; 2] {
+    return [(size[0] as f32 * scale).round().max(1.0) as u32, (size[1] as f32 * scale).round().max(1.0) as u32];
+}
+
+/// The factor dynamic resolution applies and the frame times it is adjusted from.
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct DynamicScale {
+    scale: f32,
+    /// Frame times summed and counted since the scale last changed.
+    frame_times: (f32, u32)
+}
+
+impl Default for DynamicScale {
+    fn default() -> DynamicScale {
+        return DynamicScale { scale: 1.0, frame_times: (0.0, 0) };
+    }
+}
+
+impl DynamicScale {
+    fn update(&mut self, dynamic: Option<&DynamicResolution>, frame_time: f32) {
+        let Some(dynamic) = dynamic else {
+            *self = DynamicScale::default();
+            return;
+        };
+
+        self.frame_times = (self.frame_times.0 + frame_time, self.frame_times.1 + 1);
+        if self.frame_times.0 < SCALE_INTERVAL { return; }
+        let frame_time = self.frame_times.0 / self.frame_times.1 as f32;
+        self.frame_times = (0.0, 0);
+
+        // The gap between the two thresholds keeps the scale from flipping back and forth.
+        let target_frame_time = dynamic.target_frame_time / 1000.0;
+        if frame_time > target_frame_time * 1.05 {
+            self.scale -= SCALE_STEP;
+        } else if frame_time < target_frame_time * 0.85 {
+            self.scale += SCALE_STEP;
+        }
+        // Swapped bounds from a config file shouldn't panic like `f32::clamp` does.
+        let (min, max) = if dynamic.min_scale <= dynamic.max_scale { (dynamic.min_scale, dynamic.max_scale) } else { (dynamic.max_scale, dynamic.min_scale) };
+        self.scale = self.scale.max(min).min(max);
+    }
+}
+
+#[derive(Copy, Clone)]
+struct BlitVertex {
+    position: [f32; 2]
+}
+
+implement_vertex!(BlitVertex, position);
+
+/// Renders the scene into an offscreen target at the render resolution and upscales it to the window.
+pub struct Scaler {
+    dynamic: DynamicScale,
+    target: Option<(Texture2d, DepthRenderBuffer)>,
+    program: glium::Program,
+    vertex_buffer: glium::VertexBuffer<BlitVertex>
+}
+
+impl Scaler {
+    pub fn new(display: &Display) -> Scaler {
+        let quad = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]].map(|position| BlitVertex { position });
+
+        return Scaler {
+            dynamic: DynamicScale::default(),
+            target: None,
+            program: glium::Program::from_source(display, include_str!("../../shaders/upscale.vs"), include_str!("../../shaders/upscale.fs"), None).unwrap(),
+            vertex_buffer: glium::VertexBuffer::new(display, &quad).unwrap()
+        };
+    }
+
+    /// Factor dynamic resolution currently applies on top of the `RenderResolution`.
+    pub fn dynamic_scale(&self) -> f32 { self.dynamic.scale }
+
+    pub fn render_size(&self, resolution: RenderResolution, window_size: [u32; 2]) -> [u32; 2] {
+        return scale_size(resolution.size(window_size), self.dynamic.scale);
+    }
+
+    /// Feeds the time the last frame took in seconds, `None` turns dynamic resolution off.
+    pub fn update(&mut self, dynamic: Option<&DynamicResolution>, frame_time: f32) {
+        self.dynamic.update(dynamic, frame_time);
+    }
+
+    /// Makes sure the offscreen target has `size`. When that is the window size the scene is drawn
+    /// to the window directly and no target is kept.
+    pub fn prepare(&mut self, display: &Display, size: [u32; 2], window_size: [u32; 2]) {
+        if size == window_size {
+            self.target = None;
+            return;
+        }
+        if let Some((texture, _)) = &self.target {
+            if [texture.width(), texture.height()] == size { return; }
+        }
+
+        let texture = Texture2d::empty_with_format(display, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap, size[0], size[1]).unwrap();
+        let depth = DepthRenderBuffer::new(display, DepthFormat::I24, size[0], size[1]).unwrap();
+        self.target = Some((texture, depth));
+    }
+
+    /// Framebuffer the scene is rendered into, `None` when it goes straight to the window.
+    pub fn framebuffer(&self, display: &Display) -> Option<SimpleFrameBuffer<'_>> {
+        let (texture, depth) = self.target.as_ref()?;
+        return Some(SimpleFrameBuffer::with_depth_buffer(display, texture, depth).unwrap());
+    }
+
+    /// Stretches the rendered scene over `frame`, does nothing if it was drawn there directly.
+    pub fn present<S: Surface>(&self, frame: &mut S, filter: UpscaleFilter) {
+        let Some((texture, _)) = &self.target else { return; };
+
+        let (magnify, minify, sharpness) = match filter {
+            UpscaleFilter::Nearest => (MagnifySamplerFilter::Nearest, MinifySamplerFilter::Nearest, 0.0f32),
+            UpscaleFilter::Bilinear => (MagnifySamplerFilter::Linear, MinifySamplerFilter::Linear, 0.0),
+            UpscaleFilter::Sharpened(sharpness) => (MagnifySamplerFilter::Linear, MinifySamplerFilter::Linear, sharpness)
+        };
+        let uniforms = uniform! {
+            scene: texture.sampled().magnify_filter(magnify).minify_filter(minify).wrap_function(SamplerWrapFunction::Clamp),
+            sharpness: sharpness
+        };
+
+        frame.draw(&self.vertex_buffer, glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip), &self.program, &uniforms, &Default::default()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DYNAMIC: DynamicResolution = DynamicResolution { target_frame_time: 20.0, min_scale: 0.5, max_scale: 1.0 };
+
+    /// Feeds frames of `frame_time` seconds until the scale is reconsidered, returns how many it took.
+    fn interval(scale: &mut DynamicScale, dynamic: &DynamicResolution, frame_time: f32) -> u32 {
+        for frames in 1..1000 {
+            scale.update(Some(dynamic), frame_time);
+            if scale.frame_times.1 == 0 { return frames; }
+        }
+        panic!("the scale was never reconsidered");
+    }
+
+    fn near(a: f32, b: f32) -> bool { (a - b).abs() < 1e-5 }
+
+    #[test]
+    fn averages_over_the_interval() {
+        let mut scale = DynamicScale::default();
+        scale.update(Some(&DYNAMIC), 0.1);
+        scale.update(Some(&DYNAMIC), 0.1);
+        assert_eq!(scale.scale, 1.0);
+        scale.update(Some(&DYNAMIC), 0.1);
+        assert!(near(scale.scale, 0.95));
+        assert_eq!(scale.frame_times, (0.0, 0));
+
+        // One long frame among fast ones doesn't lower the scale when the average is fine.
+        assert_eq!(interval(&mut scale, &DYNAMIC, 0.016), 16);
+        assert!(near(scale.scale, 1.0));
+        for _ in 0..13 { scale.update(Some(&DYNAMIC), 0.012); }
+        scale.update(Some(&DYNAMIC), 0.1);
+        assert_eq!(scale.frame_times, (0.0, 0));
+        assert!(near(scale.scale, 1.0));
+    }
+
+    #[test]
+    fn thresholds_leave_a_gap() {
+        let mut scale = DynamicScale { scale: 0.75, frame_times: (0.0, 0) };
+        // Within 85 % to 105 % of the 20 ms target nothing changes.
+        for frame_time in [0.0205, 0.020, 0.0175] {
+            interval(&mut scale, &DYNAMIC, frame_time);
+            assert!(near(scale.scale, 0.75), "{}", frame_time);
+        }
+        interval(&mut scale, &DYNAMIC, 0.0215);
+        assert!(near(scale.scale, 0.7));
+        interval(&mut scale, &DYNAMIC, 0.0165);
+        assert!(near(scale.scale, 0.75));
+    }
+
+    #[test]
+    fn steps_stay_within_the_bounds() {
+        let mut scale = DynamicScale::default();
+        for _ in 0..20 { interval(&mut scale, &DYNAMIC, 0.05); }
+        assert_eq!(scale.scale, 0.5);
+        for _ in 0..20 { interval(&mut scale, &DYNAMIC, 0.001); }
+        assert_eq!(scale.scale, 1.0);
+
+        // Swapped bounds are used the right way around instead of panicking.
+        let swapped = DynamicResolution { min_scale: 0.9, max_scale: 0.6, ..DYNAMIC };
+        interval(&mut scale, &swapped, 0.001);
+        assert_eq!(scale.scale, 0.9);
+        for _ in 0..20 { interval(&mut scale, &swapped, 0.05); }
+        assert_eq!(scale.scale, 0.6);
+    }
+
+    #[test]
+    fn turning_it_off_resets() {
+        let mut scale = DynamicScale::default();
+        interval(&mut scale, &DYNAMIC, 0.05);
+        scale.update(Some(&DYNAMIC), 0.05);
+        scale.update(None, 0.05);
+        assert_eq!(scale, DynamicScale::default());
+    }
+}
@@ -0,0 +1,248 @@
+use std::path::Path;
+
+use super::scaling::{DynamicResolution, RenderResolution, UpscaleFilter};
+use super::window::{Version, WindowConfig, WindowMode};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    Invalid(String)
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "failed to access window settings: {}", error),
+            ConfigError::Parse(error) => write!(f, "failed to parse window settings: {}", error),
+            ConfigError::Serialize(error) => write!(f, "failed to write window settings: {}", error),
+            ConfigError::Invalid(reason) => write!(f, "invalid window settings: {}", reason)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn parse_size(value: &str) -> Option<[u32; 2]> {
+    let (width, height) = value.split_once('x')?;
+    return Some([width.trim().parse().ok()?, height.trim().parse().ok()?]);
+}
+
+impl WindowConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<WindowConfig, ConfigError> {
+        let source = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        return WindowConfig::parse(&source);
+    }
+
+    /// Like `load`, but a missing file gives the default settings so the first start works.
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<WindowConfig, ConfigError> {
+        return match WindowConfig::load(path) {
+            Err(ConfigError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => Ok(WindowConfig::default()),
+            result => result
+        };
+    }
+
+    pub fn parse(source: &str) -> Result<WindowConfig, ConfigError> {
+        let config: WindowConfig = toml::from_str(source).map_err(ConfigError::Parse)?;
+        config.validate()?;
+
+        return Ok(config);
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        self.validate()?;
+        let source = toml::to_string(self).map_err(ConfigError::Serialize)?;
+
+        if let Some(parent) = path.as_ref().parent() { std::fs::create_dir_all(parent).map_err(ConfigError::Io)?; }
+        return std::fs::write(path, source).map_err(ConfigError::Io);
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let WindowMode::Normal(size) | WindowMode::Borderless(size) = self.window_mode {
+            if size[0] == 0 || size[1] == 0 { return Err(ConfigError::Invalid(format!("window size {}x{} is empty", size[0], size[1]))); }
+        }
+        if self.resolution[0] == 0 || self.resolution[1] == 0 {
+            return Err(ConfigError::Invalid(format!("resolution {}x{} is empty", self.resolution[0], self.resolution[1])));
+        }
+        if self.refresh_rate == Some(0) { return Err(ConfigError::Invalid("refresh rate is 0".to_string())); }
+        if self.msaa > 16 || (self.msaa > 1 && !self.msaa.is_power_of_two()) {
+            return Err(ConfigError::Invalid(format!("msaa has to be 0, 2, 4, 8 or 16, not {}", self.msaa)));
+        }
+        match self.render_resolution {
+            RenderResolution::Scale(scale) if !(scale > 0.0 && scale <= 4.0) => {
+                return Err(ConfigError::Invalid(format!("render scale {} is outside 0 to 4", scale)));
+            },
+            RenderResolution::Fixed(size) if size[0] == 0 || size[1] == 0 => {
+                return Err(ConfigError::Invalid(format!("render resolution {}x{} is empty", size[0], size[1])));
+            },
+            _ => ()
+        }
+        if let UpscaleFilter::Sharpened(sharpness) = self.upscale_filter {
+            if !(0.0..=1.0).contains(&sharpness) { return Err(ConfigError::Invalid(format!("sharpness {} is outside 0 to 1", sharpness))); }
+        }
+        if let Some(dynamic) = self.dynamic_resolution {
+            if dynamic.target_frame_time.is_nan() || dynamic.target_frame_time <= 0.0 {
+                return Err(ConfigError::Invalid(format!("target frame time {} is not positive", dynamic.target_frame_time)));
+            }
+            if !(dynamic.min_scale > 0.0 && dynamic.min_scale <= dynamic.max_scale && dynamic.max_scale <= 4.0) {
+                return Err(ConfigError::Invalid(format!("dynamic resolution scale {} to {} is not valid", dynamic.min_scale, dynamic.max_scale)));
+            }
+        }
+
+        return match self.version {
+            // The lit and shadowed shaders need `#version 430`.
+            Version::OpenGL(4, minor) if (3..=6).contains(&minor) => Ok(()),
+            Version::Vulkan(1, _, _) => Ok(()),
+            Version::OpenGL(major, minor) => Err(ConfigError::Invalid(format!("OpenGL {}.{} is not supported", major, minor))),
+            Version::Vulkan(major, minor, patch) => Err(ConfigError::Invalid(format!("Vulkan {}.{}.{} is not supported", major, minor, patch)))
+        };
+    }
+
+    /// Applies command line overrides on top of the loaded settings:
+    /// `--fullscreen`, `--windowed-fullscreen`, `--windowed WxH`, `--borderless WxH`, `--resolution WxH`,
+    /// `--refresh-rate HZ`, `--monitor INDEX`, `--vsync`, `--no-vsync`, `--msaa SAMPLES`, `--opengl MAJOR.MINOR`,
+    /// `--render-scale SCALE`, `--render-resolution WxH`, `--upscale-filter nearest|bilinear|sharpened`,
+    /// `--target-frame-time MS` and `--title TITLE`. Unknown arguments are left for the game.
+    pub fn apply_args(&mut self, args: impl IntoIterator<Item = String>) -> Result<Vec<String>, ConfigError> {
+        let mut args = args.into_iter();
+        let mut unused = Vec::new();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| ConfigError::Invalid(format!("{} needs a value", name)));
+            let invalid = |name: &str, value: &str| ConfigError::Invalid(format!("{} {} is not valid", name, value));
+
+            match arg.as_str() {
+                "--fullscreen" => self.window_mode = WindowMode::Fullscreen,
+                "--windowed-fullscreen" => self.window_mode = WindowMode::WindowedFullscreen,
+                "--windowed" | "--borderless" | "--resolution" | "--render-resolution" => {
+                    let text = value(&arg)?;
+                    let size = parse_size(&text).ok_or_else(|| invalid(&arg, &text))?;
+                    match arg.as_str() {
+                        "--windowed" => self.window_mode = WindowMode::Normal(size),
+                        "--borderless" => self.window_mode = WindowMode::Borderless(size),
+                        "--render-resolution" => self.render_resolution = RenderResolution::Fixed(size),
+                        _ => self.resolution = size
+                    }
+                },
+                "--refresh-rate" => {
+                    let text = value(&arg)?;
+                    self.refresh_rate = Some(text.parse().map_err(|_| invalid(&arg, &text))?);
+                },
+                "--monitor" => {
+                    let text = value(&arg)?;
+                    self.monitor = Some(text.parse().map_err(|_| invalid(&arg, &text))?);
+                },
+                "--vsync" => self.vsync = true,
+                "--no-vsync" => self.vsync = false,
+                "--msaa" => {
+                    let text = value(&arg)?;
+                    self.msaa = text.parse().map_err(|_| invalid(&arg, &text))?;
+                },
+                "--opengl" => {
+                    let text = value(&arg)?;
+                    let (major, minor) = text.split_once('.').ok_or_else(|| invalid(&arg, &text))?;
+                    self.version = Version::OpenGL(
+                        major.parse().map_err(|_| invalid(&arg, &text))?,
+                        minor.parse().map_err(|_| invalid(&arg, &text))?);
+                },
+                "--render-scale" => {
+                    let text = value(&arg)?;
+                    self.render_resolution = RenderResolution::Scale(text.parse().map_err(|_| invalid(&arg, &text))?);
+                },
+                "--upscale-filter" => {
+                    let text = value(&arg)?;
+                    self.upscale_filter = match text.as_str() {
+                        "nearest" => UpscaleFilter::Nearest,
+                        "bilinear" => UpscaleFilter::Bilinear,
+                        "sharpened" => UpscaleFilter::Sharpened(0.5),
+                        _ => return Err(invalid(&arg, &text))
+                    };
+                },
+                "--target-frame-time" => {
+                    let text = value(&arg)?;
+                    self.dynamic_resolution = Some(DynamicResolution {
+                        target_frame_time: text.parse().map_err(|_| invalid(&arg, &text))?,
+                        ..self.dynamic_resolution.unwrap_or_default()
+                    });
+                },
+                "--title" => self.title = value(&arg)?,
+                _ => unused.push(arg)
+            }
+        }
+
+        self.validate()?;
+        return Ok(unused);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() }
+
+    #[test]
+    fn parse_keeps_defaults_for_missing_keys() {
+        let config = WindowConfig::parse("title = \"Game\"\nmsaa = 4\nwindow_mode = { Normal = [800, 600] }").unwrap();
+        assert_eq!(config.title, "Game");
+        assert_eq!(config.msaa, 4);
+        assert_eq!(config.window_mode, WindowMode::Normal([800, 600]));
+        assert_eq!(config.resolution, WindowConfig::default().resolution);
+
+        assert!(matches!(WindowConfig::parse("msaa = \"many\""), Err(ConfigError::Parse(_))));
+        assert!(matches!(WindowConfig::parse("msaa = 3"), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn saved_settings_load_back() {
+        let config = WindowConfig { title: "Saved".to_string(), refresh_rate: Some(144), ..WindowConfig::default() };
+        let source = toml::to_string(&config).unwrap();
+        assert_eq!(WindowConfig::parse(&source).unwrap(), config);
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_values() {
+        assert!(WindowConfig::default().validate().is_ok());
+
+        let invalid = [
+            WindowConfig { window_mode: WindowMode::Borderless([0, 600]), ..WindowConfig::default() },
+            WindowConfig { resolution: [1920, 0], ..WindowConfig::default() },
+            WindowConfig { refresh_rate: Some(0), ..WindowConfig::default() },
+            WindowConfig { msaa: 6, ..WindowConfig::default() },
+            WindowConfig { render_resolution: RenderResolution::Scale(0.0), ..WindowConfig::default() },
+            WindowConfig { upscale_filter: UpscaleFilter::Sharpened(2.0), ..WindowConfig::default() },
+            WindowConfig { dynamic_resolution: Some(DynamicResolution { target_frame_time: -1.0, ..DynamicResolution::default() }), ..WindowConfig::default() },
+            WindowConfig { version: Version::Vulkan(2, 0, 0), ..WindowConfig::default() }
+        ];
+        for config in invalid { assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))), "{:?}", config); }
+    }
+
+    #[test]
+    fn validate_needs_opengl_4_3() {
+        let opengl = |major, minor| WindowConfig { version: Version::OpenGL(major, minor), ..WindowConfig::default() }.validate().is_ok();
+        assert!(opengl(4, 3) && opengl(4, 6));
+        assert!(!opengl(3, 3) && !opengl(4, 2) && !opengl(4, 7));
+    }
+
+    #[test]
+    fn apply_args_overrides_and_keeps_unknown_arguments() {
+        let mut config = WindowConfig::default();
+        let unused = config.apply_args(args(&["--windowed", "1280x720", "--level", "2", "--no-vsync", "--opengl", "4.5", "--render-scale", "0.5"])).unwrap();
+
+        assert_eq!(unused, args(&["--level", "2"]));
+        assert_eq!(config.window_mode, WindowMode::Normal([1280, 720]));
+        assert!(!config.vsync);
+        assert_eq!(config.version, Version::OpenGL(4, 5));
+        assert_eq!(config.render_resolution, RenderResolution::Scale(0.5));
+    }
+
+    #[test]
+    fn apply_args_rejects_bad_values() {
+        let fails = |list: &[&str]| matches!(WindowConfig::default().apply_args(args(list)), Err(ConfigError::Invalid(_)));
+        assert!(fails(&["--windowed", "wide"]));
+        assert!(fails(&["--msaa"]));
+        assert!(fails(&["--opengl", "3.3"]));
+        assert!(fails(&["--upscale-filter", "cubic"]));
+    }
+}
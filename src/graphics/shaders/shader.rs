@@ -8,4 +8,76 @@ pub enum ShaderType {
 pub struct Shader {
     pub shader_type: ShaderType,
     pub shader_binary: Vec<u32>
-}
\ No newline at end of file
+}
+/// Lights, the `Lights` and `Shadows` uniform blocks, attenuation and shadow lookups, for `simple.fs`
+/// and `pbr.fs`.
+pub const LIGHTS: &str = include_str!("../../../shaders/lights.glsl");
+
+/// Hammersley points, GGX importance sampling and the GGX distribution, for `prefilter.fs`,
+/// `brdf_lut.fs` and `pbr.fs`.
+pub const GGX: &str = include_str!("../../../shaders/ggx.glsl");
+
+/// Inserts `snippets` after the `#version` line of `source`, or before everything without one. A
+/// `#line` directive after them keeps compile errors pointing at the lines of `source`.
+pub fn prepend(source: &str, snippets: &[&str]) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let after = lines.iter().position(|line| line.trim_start().starts_with("#version")).map_or(0, |index| index + 1);
+
+    let mut combined = String::with_capacity(source.len() + snippets.iter().map(|snippet| snippet.len() + 1).sum::<usize>() + 16);
+    for line in &lines[..after] {
+        combined.push_str(line);
+        combined.push('\n');
+    }
+    for snippet in snippets {
+        combined.push_str(snippet);
+        if !snippet.ends_with('\n') { combined.push('\n'); }
+    }
+    combined.push_str(&format!("#line {}\n", after + 1));
+    for line in &lines[after..] {
+        combined.push_str(line);
+        combined.push('\n');
+    }
+    return combined;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_go_after_the_version() {
+        let combined = prepend("#version 330\n\nvoid main() {}\n", &["float a;", "float b;\n"]);
+        assert_eq!(combined, "#version 330\nfloat a;\nfloat b;\n#line 2\n\nvoid main() {}\n");
+
+        let commented = prepend("// A shader.\n  #version 430\nvoid main() {}", &["float a;"]);
+        assert_eq!(commented, "// A shader.\n  #version 430\nfloat a;\n#line 3\nvoid main() {}\n");
+
+        assert_eq!(prepend("void main() {}", &["float a;"]), "float a;\n#line 1\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn lights_are_defined_once() {
+        for shader in [include_str!("../../../shaders/simple.fs"), include_str!("../../../shaders/pbr.fs")] {
+            let combined = prepend(shader, &[LIGHTS]);
+            for definition in ["#define MAX_LIGHTS ", "struct Light ", "uniform Lights ", "uniform Shadows ", "uniform vec3 camera_position;", "vec3 facing_normal(", "float attenuation(", "float shadow("] {
+                assert_eq!(combined.matches(definition).count(), 1, "{}", definition);
+            }
+        }
+    }
+
+    #[test]
+    fn ggx_is_defined_once() {
+        let shaders = [
+            include_str!("../../../shaders/prefilter.fs"),
+            include_str!("../../../shaders/brdf_lut.fs"),
+            include_str!("../../../shaders/pbr.fs")
+        ];
+        for shader in shaders {
+            let combined = prepend(shader, &[GGX]);
+            assert!(combined.starts_with("#version"));
+            for definition in ["#define PI ", "float radical_inverse(", "vec3 importance_sample_ggx(", "float distribution_ggx("] {
+                assert_eq!(combined.matches(definition).count(), 1, "{}", definition);
+            }
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::IndicesSource;
+use glium::texture::{DepthFormat, DepthTexture2dArray, MipmapsOption};
+use glium::uniforms::{DepthTextureComparison, MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction};
+use glium::Surface;
+use serde::{Deserialize, Serialize};
+
+use super::context::Display;
+use super::lighting::Lighting;
+use super::math::{self, cross, dot, length, normalize, sub};
+use super::types::RenderVertex;
+
+/// Size of the cascade array in the shaders' `Shadows` block, the most `ShadowSettings::cascades` can be.
+pub const MAX_CASCADES: usize = 4;
+/// Spot lights that can cast shadows at once, the rest light without them.
+pub const MAX_SPOT_SHADOWS: usize = 4;
+
+/// Whether an object shows up in shadow maps and whether shadows darken it.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ShadowFlags {
+    pub cast: bool,
+    pub receive: bool
+}
+
+impl Default for ShadowFlags {
+    fn default() -> ShadowFlags {
+        return ShadowFlags { cast: true, receive: true };
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct ShadowSettings {
+    /// Width and height of every shadow map, fixed when `Shadows` is created.
+    pub resolution: u32,
+    /// Cascades the directional light's shadows are split into, clamped to `MAX_CASCADES`.
+    pub cascades: usize,
+    /// Distance from the camera where directional shadows end.
+    pub max_distance: f32,
+    /// 0 splits the cascades evenly, 1 logarithmically so near cascades get more detail.
+    pub split_lambda: f32,
+    /// How far toward the light from a cascade casters are still drawn into it.
+    pub caster_distance: f32,
+    /// Subtracted from the depth compared against the map, against shadow acne.
+    pub depth_bias: f32,
+    /// Offset of the compared position along the normal in world units, grows on surfaces facing away from the light.
+    pub normal_bias: f32,
+    /// Texels around the center averaged by percentage closer filtering, 0 gives hard edges.
+    pub pcf_radius: u32
+}
+
+impl Default for ShadowSettings {
+    fn default() -> ShadowSettings {
+        return ShadowSettings {
+            resolution: 2048,
+            cascades: 4,
+            max_distance: 50.0,
+            split_lambda: 0.75,
+            caster_distance: 20.0,
+            depth_bias: 0.0015,
+            normal_bias: 0.02,
+            pcf_radius: 1
+        };
+    }
+}
+
+/// The camera the directional light's cascades follow.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ShadowView {
+    pub position: [f32; 3],
+    pub forward: [f32; 3],
+    pub up: [f32; 3],
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32
+}
+
+/// The `Shadows` uniform block of `shaders/lights.glsl`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ShadowBlock {
+    /// View projection of the cascades, followed by the spot lights'. One array because glium can't
+    /// match arrays of four matrices.
+    light_matrices: [[[f32; 4]; 4]; MAX_CASCADES + MAX_SPOT_SHADOWS],
+    /// Distance along the view direction where each cascade ends.
+    cascade_splits: [f32; 4],
+    camera_forward: [f32; 4],
+    /// Depth bias, normal bias and PCF radius.
+    filter: [f32; 4],
+    /// Cascades and spot light maps rendered this frame.
+    counts: [i32; 4]
+}
+
+implement_uniform_block!(ShadowBlock, light_matrices, cascade_splits, camera_forward, filter, counts);
+
+impl ShadowBlock {
+    /// No shadow maps, receivers stay lit.
+    fn empty() -> ShadowBlock {
+        return ShadowBlock {
+            light_matrices: [math::IDENTITY; MAX_CASCADES + MAX_SPOT_SHADOWS],
+            cascade_splits: [0.0; 4],
+            camera_forward: [0.0; 4],
+            filter: [0.0; 4],
+            counts: [0; 4]
+        };
+    }
+}
+
+/// One shadow map being rendered, draws casters with the light's matrix.
+pub struct ShadowPass<'a> {
+    framebuffer: SimpleFrameBuffer<'a>,
+    program: &'a glium::Program,
+    light_view_projection: [[f32; 4]; 4]
+}
+
+impl ShadowPass<'_> {
+    /// Maps world space to the light's clip space, e.g. for culling casters.
+    pub fn light_view_projection(&self) -> [[f32; 4]; 4] { self.light_view_projection }
+
+    /// Draws a mesh given in world space into the map, skipped when it doesn't cast shadows.
+    pub fn draw<'b>(&mut self, vertices: &glium::VertexBuffer<RenderVertex>, indices: impl Into<IndicesSource<'b>>,
+                    flags: ShadowFlags) -> Result<(), glium::DrawError> {
+        if !flags.cast { return Ok(()); }
+        let params = glium::DrawParameters {
+            depth: glium::Depth { test: glium::draw_parameters::DepthTest::IfLess, write: true, .. Default::default() },
+            .. Default::default()
+        };
+        let uniforms = uniform! { light_view_projection: self.light_view_projection };
+        return self.framebuffer.draw(vertices, indices, self.program, &uniforms, &params);
+    }
+}
+
+/// Any direction that isn't parallel to `direction`, for the up of a light's view.
+fn up_for(direction: [f32; 3]) -> [f32; 3] {
+    return if normalize(direction)[1].abs() < 0.99 { [0.0, 1.0, 0.0] } else { [1.0, 0.0, 0.0] };
+}
+
+/// Depth maps for the directional light's cascades and the spot lights that cast shadows. Rendered each
+/// frame after `Lighting::upload`, which decides what casts shadows, and bound when drawing receivers:
+///
+/// ```ignore
+/// lighting.upload(camera_position);
+/// shadows.render(display, &lighting, &view, |pass| { pass.draw(&vertices, &indices, flags).unwrap(); });
+/// let uniforms = uniform! { Shadows: shadows.buffer(), cascade_maps: shadows.cascade_maps(), spot_maps: shadows.spot_maps(), .. };
+/// ```
+pub struct Shadows {
+    pub settings: ShadowSettings,
+    cascade_maps: DepthTexture2dArray,
+    spot_maps: DepthTexture2dArray,
+    program: glium::Program,
+    buffer: glium::uniforms::UniformBuffer<ShadowBlock>
+}
+
+impl Shadows {
+    pub fn new(display: &Display, settings: ShadowSettings) -> Shadows {
+        let size = settings.resolution.max(1);
+        let maps = |layers: usize| DepthTexture2dArray::empty_with_format(display, DepthFormat::F32, MipmapsOption::NoMipmap, size, size, layers as u32).unwrap();
+
+        return Shadows {
+            settings,
+            cascade_maps: maps(MAX_CASCADES),
+            spot_maps: maps(MAX_SPOT_SHADOWS),
+            program: glium::Program::from_source(display, include_str!("../../shaders/shadow.vs"), include_str!("../../shaders/shadow.fs"), None).unwrap(),
+            buffer: glium::uniforms::UniformBuffer::dynamic(display, ShadowBlock::empty()).unwrap()
+        };
+    }
+
+    /// Distances along the view direction where each cascade ends.
+    fn splits(settings: &ShadowSettings, view: &ShadowView, count: usize) -> Vec<f32> {
+        let near = view.near.max(0.001);
+        let far = view.far.min(settings.max_distance).max(near);
+        let lambda = settings.split_lambda.clamp(0.0, 1.0);
+        return (1..=count).map(|index| {
+            let fraction = index as f32 / count as f32;
+            let logarithmic = near * (far / near).powf(fraction);
+            let uniform = near + (far - near) * fraction;
+            return lambda * logarithmic + (1.0 - lambda) * uniform;
+        }).collect();
+    }
+
+    /// Matrix of the cascade covering the view between `start` and `end`. The cascade is a box around the
+    /// slice's bounding sphere, snapped to texels, so its shadows don't swim as the camera moves and turns.
+    fn cascade_matrix(settings: &ShadowSettings, view: &ShadowView, direction: [f32; 3], start: f32, end: f32) -> [[f32; 4]; 4] {
+        let forward = normalize(view.forward);
+        let right = normalize(cross(forward, view.up));
+        let up = cross(right, forward);
+        let half_height = (view.fov_y / 2.0).tan();
+
+        let mut corners = Vec::with_capacity(8);
+        for distance in [start, end] {
+            let (height, width) = (half_height * distance, half_height * distance * view.aspect);
+            for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+                corners.push(std::array::from_fn::<f32, 3, _>(|axis| {
+                    view.position[axis] + forward[axis] * distance + right[axis] * width * x + up[axis] * height * y
+                }));
+            }
+        }
+        let center: [f32; 3] = std::array::from_fn(|axis| corners.iter().map(|corner| corner[axis]).sum::<f32>() / 8.0);
+        let radius = corners.iter().map(|corner| length(sub(*corner, center))).fold(0.0, f32::max).ceil();
+
+        let light_view = math::look_at([0.0, 0.0, 0.0], direction, up_for(direction));
+        let texel = 2.0 * radius / settings.resolution.max(1) as f32;
+        let snap = |value: f32| (value / texel).floor() * texel;
+        let x = snap(light_view[0][0] * center[0] + light_view[1][0] * center[1] + light_view[2][0] * center[2]);
+        let y = snap(light_view[0][1] * center[0] + light_view[1][1] * center[1] + light_view[2][1] * center[2]);
+        let depth = dot(normalize(direction), center);
+
+        let projection = math::orthographic(x - radius, x + radius, y - radius, y + radius,
+                                            depth - radius - settings.caster_distance, depth + radius);
+        return math::multiply(projection, light_view);
+    }
+
+    /// Renders the shadow maps of the lights `lighting` picked in its last `upload`. `draw_casters` is
+    /// called once per map and draws everything that could cast into it.
+    pub fn render(&mut self, display: &Display, lighting: &Lighting, view: &ShadowView, mut draw_casters: impl FnMut(&mut ShadowPass)) {
+        let cascade_count = self.settings.cascades.clamp(1, MAX_CASCADES);
+        let forward = normalize(view.forward);
+        let mut block = ShadowBlock {
+            camera_forward: [forward[0], forward[1], forward[2], 0.0],
+            filter: [self.settings.depth_bias, self.settings.normal_bias, self.settings.pcf_radius as f32, 0.0],
+            .. ShadowBlock::empty()
+        };
+
+        if let Some(light) = lighting.shadowed_directional() {
+            let splits = Shadows::splits(&self.settings, view, cascade_count);
+            let mut start = view.near;
+            for (cascade, &end) in splits.iter().enumerate() {
+                block.light_matrices[cascade] = Shadows::cascade_matrix(&self.settings, view, light.direction, start, end);
+                block.cascade_splits[cascade] = end;
+                start = end;
+            }
+            block.counts[0] = cascade_count as i32;
+        }
+        for (slot, light) in lighting.shadowed_spots().iter().take(MAX_SPOT_SHADOWS).enumerate() {
+            let projection = math::perspective(2.0 * light.outer_angle.min(1.5), 1.0, 0.05, light.range.max(0.1));
+            block.light_matrices[MAX_CASCADES + slot] = math::multiply(projection, math::look_at(light.position, light.direction, up_for(light.direction)));
+            block.counts[1] = slot as i32 + 1;
+        }
+
+        let passes = (0..block.counts[0] as usize).map(|layer| (&self.cascade_maps, layer, block.light_matrices[layer]))
+            .chain((0..block.counts[1] as usize).map(|layer| (&self.spot_maps, layer, block.light_matrices[MAX_CASCADES + layer])));
+        for (maps, layer, light_view_projection) in passes {
+            let mut framebuffer = SimpleFrameBuffer::depth_only(display, maps.main_level().layer(layer as u32).unwrap()).unwrap();
+            framebuffer.clear_depth(1.0);
+            draw_casters(&mut ShadowPass { framebuffer, program: &self.program, light_view_projection });
+        }
+
+        self.buffer.write(&block);
+    }
+
+    /// The buffer bound as `Shadows` when drawing receivers.
+    pub fn buffer(&self) -> &glium::uniforms::UniformBuffer<ShadowBlock> { &self.buffer }
+
+    /// The cascades, bound as `cascade_maps`.
+    pub fn cascade_maps(&self) -> Sampler<'_, DepthTexture2dArray> { Shadows::sampler(&self.cascade_maps) }
+
+    /// The spot light maps, bound as `spot_maps`.
+    pub fn spot_maps(&self) -> Sampler<'_, DepthTexture2dArray> { Shadows::sampler(&self.spot_maps) }
+
+    /// Compares depths while sampling, with linear filtering that blends the results of neighboring texels.
+    fn sampler(maps: &DepthTexture2dArray) -> Sampler<'_, DepthTexture2dArray> {
+        return maps.sampled()
+            .wrap_function(SamplerWrapFunction::Clamp)
+            .minify_filter(MinifySamplerFilter::Linear)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .depth_texture_comparison(Some(DepthTextureComparison::LessOrEqual));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view() -> ShadowView {
+        return ShadowView { position: [1.0, 2.0, 3.0], forward: [0.0, 0.0, -1.0], up: [0.0, 1.0, 0.0], fov_y: 1.0, aspect: 1.5, near: 0.1, far: 100.0 };
+    }
+
+    fn transform(matrix: [[f32; 4]; 4], point: [f32; 3]) -> [f32; 3] {
+        let [x, y, z, w]: [f32; 4] = std::array::from_fn(|row| (0..3).map(|column| matrix[column][row] * point[column]).sum::<f32>() + matrix[3][row]);
+        return [x / w, y / w, z / w];
+    }
+
+    #[test]
+    fn splits_end_at_the_max_distance() {
+        let uniform = ShadowSettings { split_lambda: 0.0, max_distance: 50.0, ..ShadowSettings::default() };
+        let splits = Shadows::splits(&uniform, &view(), 4);
+        for (split, expected) in splits.iter().zip([12.575, 25.05, 37.525, 50.0]) { assert!((split - expected).abs() < 1e-3, "{:?}", splits); }
+
+        let logarithmic = ShadowSettings { split_lambda: 1.0, ..uniform };
+        let splits = Shadows::splits(&logarithmic, &view(), 2);
+        assert!((splits[0] - 0.1 * 500f32.sqrt()).abs() < 1e-3 && (splits[1] - 50.0).abs() < 1e-3);
+
+        // The view's far plane wins when it is closer, and lambdas outside 0 to 1 are clamped.
+        let close = ShadowView { far: 10.0, ..view() };
+        let splits = Shadows::splits(&ShadowSettings { split_lambda: 3.0, ..uniform }, &close, 3);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((splits[2] - 10.0).abs() < 1e-3);
+        assert!((splits[0] - 0.1 * 100f32.powf(1.0 / 3.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cascades_contain_their_slice() {
+        let settings = ShadowSettings::default();
+        let view = view();
+        let direction = [0.3, -1.0, 0.2];
+        let matrix = Shadows::cascade_matrix(&settings, &view, direction, 5.0, 20.0);
+
+        let half_height = (view.fov_y / 2.0).tan();
+        for distance in [5.0, 20.0] {
+            for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+                let corner = [view.position[0] + x * half_height * distance * view.aspect, view.position[1] + y * half_height * distance, view.position[2] - distance];
+                assert!(transform(matrix, corner).iter().all(|value| value.abs() <= 1.0), "{:?}", corner);
+            }
+        }
+
+        // Casters between the light and the slice still land in the map.
+        let center = [view.position[0], view.position[1], view.position[2] - 12.5];
+        let towards_light = normalize(direction).map(|axis| -axis * (settings.caster_distance + 10.0));
+        let caster = transform(matrix, std::array::from_fn(|axis| center[axis] + towards_light[axis]));
+        assert!(caster.iter().all(|value| value.abs() <= 1.0), "{:?}", caster);
+    }
+
+    #[test]
+    fn cascades_move_in_whole_texels() {
+        let settings = ShadowSettings { resolution: 1024, ..ShadowSettings::default() };
+        for offset in [0.0, 0.013, 0.31, 2.7] {
+            let view = ShadowView { position: [1.0 + offset, 2.0, 3.0 - offset], ..view() };
+            let matrix = Shadows::cascade_matrix(&settings, &view, [0.0, -1.0, 0.5], 1.0, 10.0);
+            for translation in &matrix[3][..2] {
+                let texels = translation * settings.resolution as f32 / 2.0;
+                assert!((texels - texels.round()).abs() < 0.01, "{} {}", offset, texels);
+            }
+        }
+    }
+
+    #[test]
+    fn up_is_never_parallel() {
+        assert_eq!(up_for([0.0, -1.0, 0.0]), [1.0, 0.0, 0.0]);
+        assert_eq!(up_for([0.0, -1.0, 0.5]), [0.0, 1.0, 0.0]);
+        assert!(dot(normalize(cross([0.0, 5.0, 0.0], up_for([0.0, 5.0, 0.0]))), [0.0, 0.0, 1.0]).abs() > 0.99);
+    }
+}
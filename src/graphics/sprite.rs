@@ -0,0 +1,207 @@
+use std::ops::Range;
+
+use glium::Surface;
+
+use super::atlas::AtlasRegion;
+use super::camera::Camera2d;
+use super::context::Display;
+use super::texture::Texture;
+
+/// A textured quad in world units, see `Camera2d`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Sprite {
+    pub position: [f32; 2],
+    /// Counterclockwise, in radians around `origin`.
+    pub rotation: f32,
+    /// Multiplies `size`, negative values mirror the sprite.
+    pub scale: [f32; 2],
+    pub size: [f32; 2],
+    /// Point that sits at `position` and that the sprite rotates around, (0, 0) is the bottom left
+    /// corner and (1, 1) the top right.
+    pub origin: [f32; 2],
+    /// Part of the texture that is drawn, bottom left and top right.
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    /// Multiplies the texture color.
+    pub color: [f32; 4],
+    /// Higher layers are drawn on top of lower ones.
+    pub layer: i32
+}
+
+impl Default for Sprite {
+    fn default() -> Sprite {
+        return Sprite {
+            position: [0.0, 0.0],
+            rotation: 0.0,
+            scale: [1.0, 1.0],
+            size: [1.0, 1.0],
+            origin: [0.5, 0.5],
+            uv_min: [0.0, 0.0],
+            uv_max: [1.0, 1.0],
+            color: [1.0, 1.0, 1.0, 1.0],
+            layer: 0
+        };
+    }
+}
+
+impl Sprite {
+    /// Sprite showing the whole texture at `size`.
+    pub fn new(position: [f32; 2], size: [f32; 2]) -> Sprite {
+        return Sprite { position, size, .. Sprite::default() };
+    }
+
+    /// Sprite showing an atlas region at its size in pixels.
+    pub fn from_region(position: [f32; 2], region: &AtlasRegion) -> Sprite {
+        return Sprite {
+            position,
+            size: [region.width as f32, region.height as f32],
+            uv_min: region.uv_min,
+            uv_max: region.uv_max,
+            .. Sprite::default()
+        };
+    }
+}
+
+/// Sprites to draw this frame together with their textures.
+#[derive(Default)]
+pub struct SpriteBatch<'a> {
+    sprites: Vec<(&'a Texture, Sprite)>
+}
+
+impl<'a> SpriteBatch<'a> {
+    pub fn new() -> SpriteBatch<'a> { SpriteBatch { sprites: Vec::new() } }
+
+    pub fn draw(&mut self, texture: &'a Texture, sprite: Sprite) { self.sprites.push((texture, sprite)); }
+
+    pub fn len(&self) -> usize { self.sprites.len() }
+
+    pub fn is_empty(&self) -> bool { self.sprites.is_empty() }
+
+    pub fn clear(&mut self) { self.sprites.clear(); }
+}
+
+/// Sorts sprites by layer, keeping the order they were added in within a layer, and splits them into
+/// runs of consecutive sprites whose textures are `same`.
+fn sort_into_runs<T>(sprites: &mut [(T, Sprite)], same: impl Fn(&T, &T) -> bool) -> Vec<Range<usize>> {
+    sprites.sort_by_key(|(_, sprite)| sprite.layer);
+
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    for index in 0..sprites.len() {
+        match runs.last_mut() {
+            Some(run) if same(&sprites[run.start].0, &sprites[index].0) => run.end = index + 1,
+            _ => runs.push(index..index + 1)
+        }
+    }
+    return runs;
+}
+
+#[derive(Copy, Clone)]
+struct Corner {
+    corner: [f32; 2]
+}
+
+implement_vertex!(Corner, corner);
+
+#[derive(Copy, Clone)]
+struct SpriteInstance {
+    sprite_position: [f32; 2],
+    sprite_size: [f32; 2],
+    sprite_origin: [f32; 2],
+    sprite_rotation: f32,
+    sprite_uv_min: [f32; 2],
+    sprite_uv_max: [f32; 2],
+    sprite_color: [f32; 4]
+}
+
+implement_vertex!(SpriteInstance, sprite_position, sprite_size, sprite_origin, sprite_rotation, sprite_uv_min, sprite_uv_max, sprite_color);
+
+/// Draws `SpriteBatch`es with one instanced draw call per run of sprites sharing a texture.
+pub struct SpriteRenderer {
+    program: glium::Program,
+    quad: glium::VertexBuffer<Corner>,
+    instances: glium::VertexBuffer<SpriteInstance>
+}
+
+impl SpriteRenderer {
+    pub fn new(display: &Display) -> SpriteRenderer {
+        let quad = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]].map(|corner| Corner { corner });
+
+        return SpriteRenderer {
+            program: glium::Program::from_source(display, include_str!("../../shaders/sprite.vs"), include_str!("../../shaders/sprite.fs"), None).unwrap(),
+            quad: glium::VertexBuffer::new(display, &quad).unwrap(),
+            instances: glium::VertexBuffer::empty_dynamic(display, 1024).unwrap()
+        };
+    }
+
+    /// Draws and empties `batch`, returns how many draw calls it took. Sprites are drawn by layer and in
+    /// the order they were added within a layer, consecutive sprites with the same texture share a draw call.
+    pub fn render<S: Surface>(&mut self, display: &Display, target: &mut S, camera: &Camera2d, batch: &mut SpriteBatch) -> u32 {
+        if batch.is_empty() { return 0; }
+
+        let runs = sort_into_runs(&mut batch.sprites, |a, b| std::ptr::eq(*a, *b));
+
+        let instances: Vec<_> = batch.sprites.iter().map(|(_, sprite)| SpriteInstance {
+            sprite_position: sprite.position,
+            sprite_size: [sprite.size[0] * sprite.scale[0], sprite.size[1] * sprite.scale[1]],
+            sprite_origin: sprite.origin,
+            sprite_rotation: sprite.rotation,
+            sprite_uv_min: sprite.uv_min,
+            sprite_uv_max: sprite.uv_max,
+            sprite_color: sprite.color
+        }).collect();
+        if self.instances.len() < instances.len() {
+            self.instances = glium::VertexBuffer::empty_dynamic(display, instances.len().next_power_of_two()).unwrap();
+        }
+        self.instances.slice(0..instances.len()).unwrap().write(&instances);
+
+        let (width, height) = target.get_dimensions();
+        let view_projection = camera.matrix([width, height]);
+        let params = glium::DrawParameters {
+            blend: glium::draw_parameters::Blend::alpha_blending(),
+            .. Default::default()
+        };
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
+
+        for run in &runs {
+            let uniforms = uniform! {
+                view_projection: view_projection,
+                sprite_texture: batch.sprites[run.start].0
+            };
+            let instances = self.instances.slice(run.clone()).unwrap();
+            target.draw((&self.quad, instances.per_instance().unwrap()), indices, &self.program, &uniforms, &params).unwrap();
+        }
+
+        batch.clear();
+        return runs.len() as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprite(layer: i32, x: f32) -> Sprite { Sprite { layer, ..Sprite::new([x, 0.0], [1.0, 1.0]) } }
+
+    #[test]
+    fn runs_follow_layers_and_insertion_order() {
+        let mut sprites = vec![('b', sprite(1, 0.0)), ('a', sprite(0, 1.0)), ('b', sprite(0, 2.0)), ('a', sprite(1, 3.0)), ('a', sprite(1, 4.0)), ('b', sprite(0, 5.0))];
+        let runs = sort_into_runs(&mut sprites, |a, b| a == b);
+
+        let order: Vec<_> = sprites.iter().map(|(texture, sprite)| (*texture, sprite.position[0] as i32)).collect();
+        assert_eq!(order, [('a', 1), ('b', 2), ('b', 5), ('b', 0), ('a', 3), ('a', 4)]);
+        // The two 'b' runs across the layer boundary touch, so they merge.
+        assert_eq!(runs, [0..1, 1..4, 4..6]);
+    }
+
+    #[test]
+    fn alternating_textures_keep_their_order() {
+        let mut sprites = vec![('a', sprite(0, 0.0)), ('b', sprite(0, 1.0)), ('a', sprite(0, 2.0))];
+        assert_eq!(sort_into_runs(&mut sprites, |a, b| a == b), [0..1, 1..2, 2..3]);
+        assert_eq!(sprites.iter().map(|(_, sprite)| sprite.position[0]).collect::<Vec<_>>(), [0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn empty_batches_have_no_runs() {
+        assert!(sort_into_runs::<char>(&mut [], |a, b| a == b).is_empty());
+    }
+}
@@ -0,0 +1,701 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+use ab_glyph::{Font as _, FontArc, GlyphId, PxScale, ScaleFont};
+use glium::texture::{ClientFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::{Rect, Surface};
+use serde::{Deserialize, Serialize};
+
+use super::camera::Camera2d;
+use super::context::Display;
+use super::texture::{SamplerSettings, Texture, TextureError, TextureFormat, TextureSettings, Wrap};
+
+#[derive(Debug)]
+pub enum TextError {
+    Io(std::io::Error),
+    Font(ab_glyph::InvalidFont),
+    /// A BMFont file that couldn't be read.
+    Invalid(String),
+    Texture(TextureError)
+}
+
+impl std::fmt::Display for TextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextError::Io(error) => write!(f, "failed to read font: {}", error),
+            TextError::Font(error) => write!(f, "invalid font: {}", error),
+            TextError::Invalid(message) => write!(f, "invalid bitmap font: {}", message),
+            TextError::Texture(error) => write!(f, "failed to load bitmap font page: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+/// DejaVu Sans Mono, for the engine's UIs to have something to draw with before fonts are loaded.
+pub(crate) static DEFAULT_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSansMono.ttf");
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TextStyle {
+    /// Height from the lowest descender to the highest ascender, in world units.
+    pub size: f32,
+    pub color: [f32; 4],
+    /// Lines are aligned around the x of the text's position: left starts there, center is centered on it.
+    pub align: TextAlign,
+    /// Lines are wrapped at spaces to stay narrower than this, words that are too long on their own are split.
+    pub max_width: Option<f32>,
+    /// Multiplies the font's line height.
+    pub line_spacing: f32
+}
+
+impl Default for TextStyle {
+    fn default() -> TextStyle {
+        return TextStyle { size: 16.0, color: [1.0, 1.0, 1.0, 1.0], align: TextAlign::Left, max_width: None, line_spacing: 1.0 };
+    }
+}
+
+/// A font loaded into a `TextRenderer`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct FontId(usize);
+
+/// A glyph placed by `TextRenderer::layout`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PositionedGlyph {
+    pub font: FontId,
+    pub glyph: u32,
+    pub size: f32,
+    /// Pen position on the baseline.
+    pub position: [f32; 2]
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextLayout {
+    /// Whitespace is left out.
+    pub glyphs: Vec<PositionedGlyph>,
+    /// Width of the widest line and height of all lines.
+    pub size: [f32; 2],
+    pub lines: usize
+}
+
+struct BitmapChar {
+    /// Pixel rectangle in the page image, from its top left.
+    rect: [f32; 4],
+    /// From the pen position to the rectangle's top left, y down from the top of the line.
+    offset: [f32; 2],
+    advance: f32,
+    page: usize
+}
+
+/// A font in the text format of AngelCode BMFont, with glyphs drawn into page images.
+struct BitmapFont {
+    size: f32,
+    line_height: f32,
+    /// Distance from the top of a line to the baseline.
+    base: f32,
+    chars: HashMap<u32, BitmapChar>,
+    kerning: HashMap<(u32, u32), f32>,
+    pages: Vec<Texture>
+}
+
+/// Reads `key=value` pairs of a BMFont line, values may be quoted.
+fn bmfont_pairs(line: &str) -> HashMap<&str, &str> {
+    let mut pairs = HashMap::new();
+    let mut rest = line;
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].split_whitespace().last().unwrap_or_default();
+        rest = &rest[equals + 1..];
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            rest = &quoted[(end + 1).min(quoted.len())..];
+            &quoted[..end]
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+        pairs.insert(key, value);
+    }
+
+    return pairs;
+}
+
+impl BitmapFont {
+    fn load(display: &Display, path: &Path) -> Result<BitmapFont, TextError> {
+        let source = std::fs::read_to_string(path).map_err(TextError::Io)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let number = |pairs: &HashMap<&str, &str>, key: &str| -> Result<f32, TextError> {
+            return pairs.get(key).and_then(|value| value.parse::<f32>().ok()).ok_or_else(|| TextError::Invalid(format!("missing or bad {}", key)));
+        };
+
+        let mut font = BitmapFont { size: 0.0, line_height: 0.0, base: 0.0, chars: HashMap::new(), kerning: HashMap::new(), pages: Vec::new() };
+        let mut page_files = Vec::new();
+        for line in source.lines() {
+            let pairs = bmfont_pairs(line);
+            match line.split_whitespace().next() {
+                Some("info") => font.size = number(&pairs, "size")?.abs(),
+                Some("common") => {
+                    font.line_height = number(&pairs, "lineHeight")?;
+                    font.base = number(&pairs, "base")?;
+                },
+                Some("page") => page_files.push((number(&pairs, "id")? as usize, pairs.get("file").copied().unwrap_or_default().to_string())),
+                Some("char") => {
+                    font.chars.insert(number(&pairs, "id")? as u32, BitmapChar {
+                        rect: [number(&pairs, "x")?, number(&pairs, "y")?, number(&pairs, "width")?, number(&pairs, "height")?],
+                        offset: [number(&pairs, "xoffset")?, number(&pairs, "yoffset")?],
+                        advance: number(&pairs, "xadvance")?,
+                        page: number(&pairs, "page")? as usize
+                    });
+                },
+                Some("kerning") => {
+                    font.kerning.insert((number(&pairs, "first")? as u32, number(&pairs, "second")? as u32), number(&pairs, "amount")?);
+                },
+                _ => {}
+            }
+        }
+        if font.size <= 0.0 || font.line_height <= 0.0 { return Err(TextError::Invalid("no info or common line".to_string())); }
+
+        page_files.sort();
+        let settings = TextureSettings { format: TextureFormat::Srgb, mipmaps: false, sampler: SamplerSettings { wrap: Wrap::Clamp, .. SamplerSettings::default() } };
+        for (_, file) in page_files {
+            font.pages.push(Texture::load(display, directory.join(file), &settings).map_err(TextError::Texture)?);
+        }
+        if font.chars.values().any(|char| char.page >= font.pages.len()) { return Err(TextError::Invalid("character on a missing page".to_string())); }
+
+        return Ok(font);
+    }
+}
+
+enum FontKind {
+    Outline(FontArc),
+    Bitmap(BitmapFont)
+}
+
+impl FontKind {
+    fn glyph(&self, char: char) -> u32 {
+        return match self {
+            FontKind::Outline(font) => font.glyph_id(char).0 as u32,
+            FontKind::Bitmap(font) if font.chars.contains_key(&(char as u32)) => char as u32,
+            FontKind::Bitmap(_) => '?' as u32
+        };
+    }
+
+    fn ascent(&self, size: f32) -> f32 {
+        return match self {
+            FontKind::Outline(font) => font.as_scaled(PxScale::from(size)).ascent(),
+            FontKind::Bitmap(font) => font.base * size / font.size
+        };
+    }
+
+    fn line_height(&self, size: f32) -> f32 {
+        return match self {
+            FontKind::Outline(font) => {
+                let font = font.as_scaled(PxScale::from(size));
+                font.height() + font.line_gap()
+            },
+            FontKind::Bitmap(font) => font.line_height * size / font.size
+        };
+    }
+
+    fn advance(&self, glyph: u32, size: f32) -> f32 {
+        return match self {
+            FontKind::Outline(font) => font.as_scaled(PxScale::from(size)).h_advance(GlyphId(glyph as u16)),
+            FontKind::Bitmap(font) => font.chars.get(&glyph).map_or(0.0, |char| char.advance * size / font.size)
+        };
+    }
+
+    fn kern(&self, first: u32, second: u32, size: f32) -> f32 {
+        return match self {
+            FontKind::Outline(font) => font.as_scaled(PxScale::from(size)).kern(GlyphId(first as u16), GlyphId(second as u16)),
+            FontKind::Bitmap(font) => font.kerning.get(&(first, second)).map_or(0.0, |amount| amount * size / font.size)
+        };
+    }
+}
+
+/// A rasterized glyph in the atlas.
+#[derive(Copy, Clone)]
+struct CachedGlyph {
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+    /// Top left of the bitmap relative to the pen position, y down, and its size in pixels of the
+    /// size it was rasterized at.
+    offset: [f32; 2],
+    size: [f32; 2]
+}
+
+/// Size in pixels outline glyphs are rasterized at to be shown `size` pixels high. Whole pixels keep
+/// the usual text sizes exact, above 32 pixels the steps grow to 8 per octave. A smoothly zooming
+/// camera reuses the cached glyphs and stretches them a little instead of rasterizing every frame.
+fn raster_size(size: f32) -> f32 {
+    if size <= 32.0 { return size.round().max(1.0); }
+    return ((size.log2() * 8.0).round() / 8.0).exp2();
+}
+
+/// Largest the glyph atlas grows to before it is emptied instead.
+const MAX_ATLAS_SIZE: u32 = 4096;
+
+/// Coverage of rasterized outline glyphs, filled row by row as glyphs are first drawn.
+struct GlyphAtlas {
+    texture: Texture2d,
+    size: u32,
+    /// `None` for glyphs without an outline, like spaces, and for glyphs too large for even the
+    /// largest atlas, which are left out instead of growing the atlas every frame.
+    glyphs: HashMap<(usize, u32, u32), Option<CachedGlyph>>,
+    cursor: [u32; 2],
+    row_height: u32
+}
+
+impl GlyphAtlas {
+    fn new(display: &Display, size: u32) -> GlyphAtlas {
+        let texture = Texture2d::empty_with_format(display, UncompressedFloatFormat::U8, MipmapsOption::NoMipmap, size, size).unwrap();
+        // Empty textures hold garbage, the gaps between glyphs must be transparent.
+        texture.write(Rect { left: 0, bottom: 0, width: size, height: size }, RawImage2d {
+            data: Cow::Owned(vec![0u8; (size * size) as usize]),
+            width: size,
+            height: size,
+            format: ClientFormat::U8
+        });
+        return GlyphAtlas { texture, size, glyphs: HashMap::new(), cursor: [1, 1], row_height: 0 };
+    }
+
+    /// Rasterizes the glyph if it isn't cached yet, `Err` when the atlas is full.
+    fn cache(&mut self, font_index: usize, font: &FontArc, glyph: u32, size: f32) -> Result<(), ()> {
+        let key = (font_index, glyph, size.to_bits());
+        if self.glyphs.contains_key(&key) { return Ok(()); }
+
+        let Some(outline) = font.outline_glyph(GlyphId(glyph as u16).with_scale(PxScale::from(size))) else {
+            self.glyphs.insert(key, None);
+            return Ok(());
+        };
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        if width + 2 > MAX_ATLAS_SIZE || height + 2 > MAX_ATLAS_SIZE {
+            self.glyphs.insert(key, None);
+            return Ok(());
+        }
+
+        // One pixel between glyphs so linear filtering doesn't pick up the neighbours.
+        if self.cursor[0] + width + 1 > self.size {
+            self.cursor = [1, self.cursor[1] + self.row_height + 1];
+            self.row_height = 0;
+        }
+        if self.cursor[1] + height + 1 > self.size || width + 2 > self.size { return Err(()); }
+
+        let mut coverage = vec![0u8; (width * height) as usize];
+        outline.draw(|x, y, value| {
+            if x < width && y < height { coverage[(y * width + x) as usize] = (value.clamp(0.0, 1.0) * 255.0) as u8; }
+        });
+        let [left, bottom] = self.cursor;
+        if width > 0 && height > 0 {
+            self.texture.write(Rect { left, bottom, width, height }, RawImage2d { data: Cow::Owned(coverage), width, height, format: ClientFormat::U8 });
+        }
+        self.cursor[0] += width + 1;
+        self.row_height = self.row_height.max(height);
+
+        // Rows are uploaded top row first, so the glyph's top sits at the lower v.
+        let size = self.size as f32;
+        self.glyphs.insert(key, Some(CachedGlyph {
+            uv_min: [left as f32 / size, bottom as f32 / size],
+            uv_max: [(left + width) as f32 / size, (bottom + height) as f32 / size],
+            offset: [bounds.min.x, bounds.min.y],
+            size: [width as f32, height as f32]
+        }));
+        return Ok(());
+    }
+}
+
+#[derive(Copy, Clone)]
+struct TextVertex {
+    position: [f32; 2],
+    texture_coords: [f32; 2],
+    color: [f32; 4]
+}
+
+implement_vertex!(TextVertex, position, texture_coords, color);
+
+/// Moves a pen position onto the pixel grid if `camera` shows world units one to one on pixels, like
+/// `Camera2d::screen`. Text drawn through a zoomed or rotated camera stays where it is, snapping it
+/// would make it jitter as the camera moves.
+fn snap(position: [f32; 2], camera: &Camera2d, viewport: [u32; 2]) -> [f32; 2] {
+    if camera.zoom != 1.0 || camera.rotation != 0.0 { return position; }
+    let [x, y] = camera.world_to_screen(position, viewport);
+    return camera.screen_to_world([x.round(), y.round()], viewport);
+}
+
+/// Lines broken at `\n` and wrapped to `style.max_width`, see `TextRenderer::layout`.
+fn layout(kind: &FontKind, font: FontId, text: &str, position: [f32; 2], style: &TextStyle) -> TextLayout {
+    let size = style.size;
+
+    // Each line as glyphs with their x and advance, plus the line's width without trailing spaces.
+    let mut lines: Vec<Vec<(char, u32, f32, f32)>> = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line: Vec<(char, u32, f32, f32)> = Vec::new();
+        let mut pen = 0.0;
+        // Index of the first glyph after the last space, where the line may be broken.
+        let mut break_at = None;
+
+        for char in paragraph.chars() {
+            let glyph = kind.glyph(char);
+            if let Some(&(_, previous, _, _)) = line.last() { pen += kind.kern(previous, glyph, size); }
+            let advance = kind.advance(glyph, size);
+
+            let too_wide = style.max_width.is_some_and(|max_width| pen + advance > max_width);
+            if too_wide && !char.is_whitespace() && !line.is_empty() {
+                let rest = match break_at {
+                    Some(index) if index < line.len() => line.split_off(index),
+                    _ => Vec::new()
+                };
+                lines.push(std::mem::replace(&mut line, rest));
+
+                let shift = line.first().map_or(0.0, |&(_, _, x, _)| x);
+                line.iter_mut().for_each(|(_, _, x, _)| *x -= shift);
+                pen = line.last().map_or(0.0, |&(_, _, x, advance)| x + advance);
+                break_at = None;
+            }
+
+            line.push((char, glyph, pen, advance));
+            pen += advance;
+            if char.is_whitespace() { break_at = Some(line.len()); }
+        }
+        lines.push(line);
+    }
+
+    let ascent = kind.ascent(size);
+    let line_height = kind.line_height(size) * style.line_spacing;
+    let mut glyphs = Vec::new();
+    let mut width: f32 = 0.0;
+    for (index, line) in lines.iter().enumerate() {
+        let line_width = line.iter().rev().find(|(char, ..)| !char.is_whitespace()).map_or(0.0, |&(_, _, x, advance)| x + advance);
+        width = width.max(line_width);
+
+        let start = match style.align {
+            TextAlign::Left => position[0],
+            TextAlign::Center => position[0] - line_width / 2.0,
+            TextAlign::Right => position[0] - line_width
+        };
+        let baseline = position[1] - ascent - index as f32 * line_height;
+        for &(char, glyph, x, _) in line {
+            if char.is_whitespace() { continue; }
+            glyphs.push(PositionedGlyph { font, glyph, size, position: [start + x, baseline] });
+        }
+    }
+
+    return TextLayout { glyphs, size: [width, lines.len() as f32 * line_height], lines: lines.len() };
+}
+
+/// Texture a glyph quad samples from.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum GlyphSource {
+    Atlas,
+    Page(usize, usize)
+}
+
+/// Loads fonts and draws text with them. Text is queued with `draw` and drawn by `render` with a
+/// `Camera2d`, `Camera2d::screen` for screen space or the game's camera for text in the world.
+pub struct TextRenderer {
+    /// Pixels of the final image per pixel of the target text is rendered into, e.g. the window's
+    /// scale factor for a target that is shown scaled up. Outline glyphs are rasterized at their
+    /// size times the camera's zoom times this, so they stay sharp.
+    pub scale_factor: f32,
+    fonts: Vec<FontKind>,
+    queued: Vec<(PositionedGlyph, [f32; 4])>,
+    /// Created by `new`, or by the first `render` of a renderer from `headless`.
+    gpu: Option<TextGpu>
+}
+
+/// What a `TextRenderer` draws with.
+struct TextGpu {
+    atlas: GlyphAtlas,
+    program: glium::Program,
+    vertices: glium::VertexBuffer<TextVertex>
+}
+
+impl TextGpu {
+    fn new(display: &Display) -> TextGpu {
+        return TextGpu {
+            atlas: GlyphAtlas::new(display, 512),
+            program: glium::Program::from_source(display, include_str!("../../shaders/text.vs"), include_str!("../../shaders/text.fs"), None).unwrap(),
+            vertices: glium::VertexBuffer::empty_dynamic(display, 6 * 1024).unwrap()
+        };
+    }
+}
+
+impl TextRenderer {
+    pub fn new(display: &Display) -> TextRenderer {
+        return TextRenderer { gpu: Some(TextGpu::new(display)), .. TextRenderer::headless() };
+    }
+
+    /// A renderer that doesn't touch the GPU until its first `render`, for laying out and measuring
+    /// text without a window. Only outline fonts can be added without a display.
+    pub fn headless() -> TextRenderer {
+        return TextRenderer { scale_factor: 1.0, fonts: Vec::new(), queued: Vec::new(), gpu: None };
+    }
+
+    /// Loads a TTF or OTF font, or a BMFont `.fnt` file in the text format together with its pages.
+    pub fn load_font(&mut self, display: &Display, path: impl AsRef<Path>) -> Result<FontId, TextError> {
+        let path = path.as_ref();
+        if path.extension().and_then(|extension| extension.to_str()) == Some("fnt") {
+            self.fonts.push(FontKind::Bitmap(BitmapFont::load(display, path)?));
+            return Ok(FontId(self.fonts.len() - 1));
+        }
+
+        return self.add_font(std::fs::read(path).map_err(TextError::Io)?);
+    }
+
+    /// Adds a TTF or OTF font from memory, e.g. from `include_bytes!`.
+    pub fn add_font(&mut self, data: Vec<u8>) -> Result<FontId, TextError> {
+        self.fonts.push(FontKind::Outline(FontArc::try_from_vec(data).map_err(TextError::Font)?));
+        return Ok(FontId(self.fonts.len() - 1));
+    }
+
+    /// Distance between the baselines of two lines.
+    pub fn line_height(&self, font: FontId, style: &TextStyle) -> f32 { self.fonts[font.0].line_height(style.size) * style.line_spacing }
+
+    /// Places the glyphs of `text` with `position` at the top of the first line, y up like `Camera2d`.
+    pub fn layout(&self, font: FontId, text: &str, position: [f32; 2], style: &TextStyle) -> TextLayout {
+        return layout(&self.fonts[font.0], font, text, position, style);
+    }
+
+    /// Width and height `text` takes up.
+    pub fn measure(&self, font: FontId, text: &str, style: &TextStyle) -> [f32; 2] { self.layout(font, text, [0.0, 0.0], style).size }
+
+    /// Queues `text` for the next `render`, see `layout` for where it goes.
+    pub fn draw(&mut self, font: FontId, text: &str, position: [f32; 2], style: &TextStyle) {
+        let layout = self.layout(font, text, position, style);
+        self.draw_layout(&layout, style.color);
+    }
+
+    pub fn draw_layout(&mut self, layout: &TextLayout, color: [f32; 4]) {
+        self.queued.extend(layout.glyphs.iter().map(|glyph| (*glyph, color)));
+    }
+
+    /// Rasterizes every queued outline glyph that isn't in the atlas yet at `pixels_per_unit`, rounded by
+    /// `raster_size`. A full
+    /// atlas is replaced by one twice the size, or emptied once it is as large as it gets, and
+    /// everything is cached again.
+    fn cache_queued(&mut self, display: &Display, pixels_per_unit: f32) {
+        let gpu = self.gpu.get_or_insert_with(|| TextGpu::new(display));
+        for _ in 0..2 {
+            let full = self.queued.iter().any(|(glyph, _)| match &self.fonts[glyph.font.0] {
+                FontKind::Outline(font) => gpu.atlas.cache(glyph.font.0, font, glyph.glyph, raster_size(glyph.size * pixels_per_unit)).is_err(),
+                FontKind::Bitmap(_) => false
+            });
+            if !full { return; }
+            gpu.atlas = GlyphAtlas::new(display, (gpu.atlas.size * 2).min(MAX_ATLAS_SIZE));
+        }
+    }
+
+    /// Draws and clears the queued text, returns how many draw calls it took.
+    pub fn render<S: Surface>(&mut self, display: &Display, target: &mut S, camera: &Camera2d) -> u32 {
+        if self.queued.is_empty() { return 0; }
+        let (width, height) = target.get_dimensions();
+        let pixels_per_unit = camera.zoom.abs() * self.scale_factor;
+        if pixels_per_unit <= 0.0 || !pixels_per_unit.is_finite() {
+            self.queued.clear();
+            return 0;
+        }
+        self.cache_queued(display, pixels_per_unit);
+        let Some(gpu) = self.gpu.as_mut() else { return 0; };
+
+        let mut batches: Vec<(GlyphSource, Vec<TextVertex>)> = Vec::new();
+        for (glyph, color) in self.queued.drain(..) {
+            let [x, y] = snap(glyph.position, camera, [width, height]);
+            // Corners as left, top, right, bottom in world units and the texture coordinates of the top left and bottom right.
+            let (source, [left, top, right, bottom], uv_top_left, uv_bottom_right) = match &self.fonts[glyph.font.0] {
+                FontKind::Outline(_) => {
+                    let raster = raster_size(glyph.size * pixels_per_unit);
+                    let Some(Some(cached)) = gpu.atlas.glyphs.get(&(glyph.font.0, glyph.glyph, raster.to_bits())) else { continue; };
+                    // World units per rasterized pixel, stretches the glyph to its actual size.
+                    let scale = glyph.size / raster;
+                    let left = x + cached.offset[0] * scale;
+                    let top = y - cached.offset[1] * scale;
+                    (GlyphSource::Atlas, [left, top, left + cached.size[0] * scale, top - cached.size[1] * scale], cached.uv_min, cached.uv_max)
+                },
+                FontKind::Bitmap(font) => {
+                    let Some(char) = font.chars.get(&glyph.glyph) else { continue; };
+                    let scale = glyph.size / font.size;
+                    let (page_width, page_height) = font.pages[char.page].dimensions();
+                    let [u, v, width, height] = char.rect;
+                    let left = x + char.offset[0] * scale;
+                    let top = y + (font.base - char.offset[1]) * scale;
+                    (GlyphSource::Page(glyph.font.0, char.page), [left, top, left + width * scale, top - height * scale],
+                     [u / page_width as f32, 1.0 - v / page_height as f32], [(u + width) / page_width as f32, 1.0 - (v + height) / page_height as f32])
+                }
+            };
+
+            let corner = |position: [f32; 2], texture_coords: [f32; 2]| TextVertex { position, texture_coords, color };
+            let quad = [
+                corner([left, top], uv_top_left), corner([left, bottom], [uv_top_left[0], uv_bottom_right[1]]), corner([right, bottom], uv_bottom_right),
+                corner([left, top], uv_top_left), corner([right, bottom], uv_bottom_right), corner([right, top], [uv_bottom_right[0], uv_top_left[1]])
+            ];
+            match batches.iter_mut().find(|(batch_source, _)| *batch_source == source) {
+                Some((_, vertices)) => vertices.extend(quad),
+                None => batches.push((source, quad.to_vec()))
+            }
+        }
+
+        let vertices: Vec<_> = batches.iter().flat_map(|(_, vertices)| vertices.iter().copied()).collect();
+        if vertices.is_empty() { return 0; }
+        if gpu.vertices.len() < vertices.len() {
+            gpu.vertices = glium::VertexBuffer::empty_dynamic(display, vertices.len().next_power_of_two()).unwrap();
+        }
+        gpu.vertices.slice(0..vertices.len()).unwrap().write(&vertices);
+
+        let view_projection = camera.matrix([width, height]);
+        let params = glium::DrawParameters {
+            blend: glium::draw_parameters::Blend::alpha_blending(),
+            .. Default::default()
+        };
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+
+        let mut start = 0;
+        for (source, batch) in &batches {
+            let range = gpu.vertices.slice(start..start + batch.len()).unwrap();
+            start += batch.len();
+            match *source {
+                GlyphSource::Atlas => {
+                    let uniforms = uniform! {
+                        view_projection: view_projection,
+                        glyphs: gpu.atlas.texture.sampled().magnify_filter(MagnifySamplerFilter::Linear).minify_filter(MinifySamplerFilter::Linear),
+                        coverage: true
+                    };
+                    target.draw(range, indices, &gpu.program, &uniforms, &params).unwrap();
+                },
+                GlyphSource::Page(font, page) => {
+                    let FontKind::Bitmap(font) = &self.fonts[font] else { continue; };
+                    let uniforms = uniform! {
+                        view_projection: view_projection,
+                        glyphs: &font.pages[page],
+                        coverage: false
+                    };
+                    target.draw(range, indices, &gpu.program, &uniforms, &params).unwrap();
+                }
+            }
+        }
+
+        return batches.len() as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> FontKind { FontKind::Outline(FontArc::try_from_slice(DEFAULT_FONT).unwrap()) }
+
+    fn style(max_width: Option<f32>) -> TextStyle { TextStyle { max_width, .. TextStyle::default() } }
+
+    /// The x of every glyph, relative to the first one, in units of the monospace advance.
+    fn columns(layout: &TextLayout, advance: f32) -> Vec<f32> {
+        return layout.glyphs.iter().map(|glyph| ((glyph.position[0] - layout.glyphs[0].position[0]) / advance).round()).collect();
+    }
+
+    #[test]
+    fn single_line() {
+        let font = font();
+        let advance = font.advance(font.glyph('a'), 16.0);
+        let layout = layout(&font, FontId(0), "ab cd", [10.0, 100.0], &style(None));
+
+        assert_eq!(layout.lines, 1);
+        assert_eq!(layout.glyphs.len(), 4);
+        assert_eq!(columns(&layout, advance), [0.0, 1.0, 3.0, 4.0]);
+        assert_eq!(layout.glyphs[0].position, [10.0, 100.0 - font.ascent(16.0)]);
+        assert!((layout.size[0] - 5.0 * advance).abs() < 1e-3);
+        assert_eq!(layout.size[1], font.line_height(16.0));
+    }
+
+    #[test]
+    fn newlines_and_line_spacing() {
+        let font = font();
+        let spaced = TextStyle { line_spacing: 2.0, .. TextStyle::default() };
+        let layout = layout(&font, FontId(0), "a\n\nbb  ", [0.0, 0.0], &spaced);
+
+        assert_eq!(layout.lines, 3);
+        let line_height = font.line_height(16.0) * 2.0;
+        assert_eq!(layout.size[1], 3.0 * line_height);
+        assert!((layout.glyphs[1].position[1] - (layout.glyphs[0].position[1] - 2.0 * line_height)).abs() < 1e-3);
+        // Trailing spaces don't count towards the width.
+        assert!((layout.size[0] - 2.0 * font.advance(font.glyph('b'), 16.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let font = font();
+        let advance = font.advance(font.glyph('a'), 16.0);
+        let layout = layout(&font, FontId(0), "aaa bbb cc", [0.0, 0.0], &style(Some(7.5 * advance)));
+
+        assert_eq!(layout.lines, 2);
+        assert_eq!(columns(&layout, advance), [0.0, 1.0, 2.0, 4.0, 5.0, 6.0, 0.0, 1.0]);
+        assert!(layout.glyphs[6].position[1] < layout.glyphs[0].position[1]);
+        assert!(layout.size[0] <= 7.5 * advance);
+    }
+
+    #[test]
+    fn splits_words_that_are_too_long() {
+        let font = font();
+        let advance = font.advance(font.glyph('a'), 16.0);
+        let split = layout(&font, FontId(0), "aaaaaaa", [0.0, 0.0], &style(Some(3.5 * advance)));
+
+        assert_eq!(split.lines, 3);
+        assert_eq!(columns(&split, advance), [0.0, 1.0, 2.0, 0.0, 1.0, 2.0, 0.0]);
+
+        // Even a single glyph wider than the limit gets a line of its own instead of vanishing.
+        let narrow = layout(&font, FontId(0), "ab", [0.0, 0.0], &style(Some(1.0)));
+        assert_eq!((narrow.lines, narrow.glyphs.len()), (2, 2));
+    }
+
+    #[test]
+    fn alignment() {
+        let font = font();
+        let advance = font.advance(font.glyph('a'), 16.0);
+        let aligned = |align: TextAlign| layout(&font, FontId(0), "aaaa\naa", [100.0, 0.0], &TextStyle { align, .. TextStyle::default() });
+
+        let center = aligned(TextAlign::Center);
+        assert!((center.glyphs[0].position[0] - (100.0 - 2.0 * advance)).abs() < 1e-3);
+        assert!((center.glyphs[4].position[0] - (100.0 - advance)).abs() < 1e-3);
+
+        let right = aligned(TextAlign::Right);
+        assert!((right.glyphs[3].position[0] + advance - 100.0).abs() < 1e-3);
+        assert!((right.glyphs[5].position[0] + advance - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn only_screen_space_text_is_snapped() {
+        let viewport = [800, 600];
+        assert_eq!(snap([10.4, 20.6], &Camera2d::screen(viewport), viewport), [10.0, 21.0]);
+
+        let odd = Camera2d { position: [0.25, 0.0], .. Camera2d::default() };
+        assert_eq!(snap([10.4, 20.6], &odd, viewport), [10.25, 21.0]);
+
+        let zoomed = Camera2d { zoom: 2.5, .. Camera2d::screen(viewport) };
+        assert_eq!(snap([10.4, 20.6], &zoomed, viewport), [10.4, 20.6]);
+        let rotated = Camera2d { rotation: 0.5, .. Camera2d::screen(viewport) };
+        assert_eq!(snap([10.4, 20.6], &rotated, viewport), [10.4, 20.6]);
+    }
+
+    #[test]
+    fn raster_sizes_are_quantized() {
+        assert_eq!([14.0, 14.3, 13.6, 0.2, 32.0].map(raster_size), [14.0, 14.0, 14.0, 1.0, 32.0]);
+        assert_eq!(raster_size(64.0), 64.0);
+        assert_eq!(raster_size(66.0), 64.0);
+
+        // 16 pixel text zoomed from 1 to 8 smoothly passes through a bounded number of sizes.
+        let mut sizes: Vec<u32> = (0..=1000).map(|step| raster_size(16.0 * (1.0 + step as f32 * 0.007)) as u32).collect();
+        sizes.dedup();
+        assert!(sizes.len() < 45, "{}", sizes.len());
+        assert!(sizes.windows(2).all(|pair| pair[0] < pair[1]));
+        // Never more than a sixteenth of an octave away from the size it is shown at.
+        for step in 0..=1000 {
+            let size = 16.0 * (1.0 + step as f32 * 0.007);
+            assert!((raster_size(size) / size).log2().abs() <= 1.0 / 16.0 + 0.01, "{}", size);
+        }
+    }
+}
@@ -0,0 +1,521 @@
+use std::borrow::Cow;
+use std::path::Path;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{ClientFormat, CompressedFormat, CompressedMipmapsOption, CompressedSrgbFormat, CompressedSrgbTexture2d, CompressedTexture2d,
+                     CubeLayer, Cubemap, MipmapsOption, RawImage2d, SrgbCubemap, SrgbFormat, SrgbTexture2d, SrgbTexture2dArray, Texture2d,
+                     Texture2dArray, TextureCreationError, UncompressedFloatFormat};
+use glium::uniforms::{AsUniformValue, MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior, SamplerWrapFunction, UniformValue};
+use glium::{BlitTarget, Rect, Surface};
+use serde::{Deserialize, Serialize};
+
+use super::compressed::{BlockFormat, CompressedImage, DecodedLevel};
+use super::context::Display;
+
+/// How texels are stored on the GPU.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TextureFormat {
+    /// 8 bit color in sRGB, for anything painted like albedo and UI. Shaders read it as linear.
+    Srgb,
+    /// 8 bit color read as it is stored, for normal maps, masks and other data.
+    Linear,
+    /// A single 8 bit channel, read from `.r` in shaders.
+    SingleChannel,
+    /// 16 bit float color for HDR images and environment maps.
+    Hdr
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Wrap {
+    Repeat,
+    Mirror,
+    Clamp,
+    /// Mirrors once, then clamps.
+    MirrorClamp
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Filter {
+    Nearest,
+    Linear
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct SamplerSettings {
+    pub wrap: Wrap,
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// Blending between mipmap levels, ignored by textures without mipmaps.
+    pub mipmap_filter: Filter,
+    /// 1 turns anisotropic filtering off, the hardware clamps values it doesn't support.
+    pub anisotropy: u16
+}
+
+impl Default for SamplerSettings {
+    fn default() -> SamplerSettings {
+        return SamplerSettings { wrap: Wrap::Repeat, min_filter: Filter::Linear, mag_filter: Filter::Linear, mipmap_filter: Filter::Linear, anisotropy: 1 };
+    }
+}
+
+impl SamplerSettings {
+    /// Sharp texels and no bleeding over the edges, for pixel art.
+    pub fn pixelated() -> SamplerSettings {
+        return SamplerSettings { wrap: Wrap::Clamp, min_filter: Filter::Nearest, mag_filter: Filter::Nearest, mipmap_filter: Filter::Nearest, anisotropy: 1 };
+    }
+
+    pub fn behavior(&self, mipmapped: bool) -> SamplerBehavior {
+        let wrap = match self.wrap {
+            Wrap::Repeat => SamplerWrapFunction::Repeat,
+            Wrap::Mirror => SamplerWrapFunction::Mirror,
+            Wrap::Clamp => SamplerWrapFunction::Clamp,
+            Wrap::MirrorClamp => SamplerWrapFunction::MirrorClamp
+        };
+        let minify_filter = match (self.min_filter, mipmapped.then_some(self.mipmap_filter)) {
+            (Filter::Nearest, None) => MinifySamplerFilter::Nearest,
+            (Filter::Linear, None) => MinifySamplerFilter::Linear,
+            (Filter::Nearest, Some(Filter::Nearest)) => MinifySamplerFilter::NearestMipmapNearest,
+            (Filter::Linear, Some(Filter::Nearest)) => MinifySamplerFilter::LinearMipmapNearest,
+            (Filter::Nearest, Some(Filter::Linear)) => MinifySamplerFilter::NearestMipmapLinear,
+            (Filter::Linear, Some(Filter::Linear)) => MinifySamplerFilter::LinearMipmapLinear
+        };
+        let magnify_filter = match self.mag_filter {
+            Filter::Nearest => MagnifySamplerFilter::Nearest,
+            Filter::Linear => MagnifySamplerFilter::Linear
+        };
+
+        return SamplerBehavior {
+            wrap_function: (wrap, wrap, wrap),
+            minify_filter,
+            magnify_filter,
+            max_anisotropy: self.anisotropy.max(1),
+            ..Default::default()
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct TextureSettings {
+    pub format: TextureFormat,
+    /// Generates the full mipmap chain when the texture is created.
+    pub mipmaps: bool,
+    pub sampler: SamplerSettings
+}
+
+impl Default for TextureSettings {
+    fn default() -> TextureSettings {
+        return TextureSettings { format: TextureFormat::Srgb, mipmaps: true, sampler: SamplerSettings::default() };
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(std::io::Error),
+    Decode(image::ImageError),
+    Create(TextureCreationError),
+    Invalid(String)
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io(error) => write!(f, "failed to read texture: {}", error),
+            TextureError::Decode(error) => write!(f, "failed to decode texture: {}", error),
+            TextureError::Create(error) => write!(f, "failed to create texture: {:?}", error),
+            TextureError::Invalid(reason) => write!(f, "invalid texture: {}", reason)
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+/// The GPU texture behind a `Texture`, sRGB textures are separate types in glium.
+pub enum TextureData {
+    Color(Texture2d),
+    Srgb(SrgbTexture2d),
+    ColorArray(Texture2dArray),
+    SrgbArray(SrgbTexture2dArray),
+    Cubemap(Cubemap),
+    SrgbCubemap(SrgbCubemap),
+    Compressed(CompressedTexture2d),
+    CompressedSrgb(CompressedSrgbTexture2d)
+}
+
+/// A 2D texture, texture array or cubemap together with how it is sampled. Passed to `uniform!` as `&texture`.
+pub struct Texture {
+    pub data: TextureData,
+    pub format: TextureFormat,
+    pub sampler: SamplerSettings
+}
+
+/// Texels of an image in the layout the format uploads, rows bottom to top unless `flip` is off.
+enum Pixels {
+    U8(RawImage2d<'static, u8>),
+    F32(RawImage2d<'static, f32>)
+}
+
+fn pixels(image: &image::DynamicImage, format: TextureFormat, flip: bool) -> Pixels {
+    let image = if flip { image.flipv() } else { image.clone() };
+    let (width, height) = (image.width(), image.height());
+
+    return match format {
+        TextureFormat::Srgb | TextureFormat::Linear => Pixels::U8(RawImage2d::from_raw_rgba(image.into_rgba8().into_raw(), (width, height))),
+        TextureFormat::SingleChannel => Pixels::U8(RawImage2d {
+            data: Cow::Owned(image.into_luma8().into_raw()),
+            width,
+            height,
+            format: ClientFormat::U8
+        }),
+        TextureFormat::Hdr => Pixels::F32(RawImage2d::from_raw_rgba(image.into_rgba32f().into_raw(), (width, height)))
+    };
+}
+
+fn decoded_pixels(image: &CompressedImage, level: usize) -> Pixels {
+    let dimensions = image.level_dimensions(level);
+    return match image.decode(level) {
+        DecodedLevel::Rgba8(texels) => Pixels::U8(RawImage2d::from_raw_rgba(texels, dimensions)),
+        DecodedLevel::RgbaF32(texels) => Pixels::F32(RawImage2d::from_raw_rgba(texels, dimensions))
+    };
+}
+
+fn float_format(format: TextureFormat) -> UncompressedFloatFormat {
+    return match format {
+        TextureFormat::SingleChannel => UncompressedFloatFormat::U8,
+        TextureFormat::Hdr => UncompressedFloatFormat::F16F16F16F16,
+        _ => UncompressedFloatFormat::U8U8U8U8
+    };
+}
+
+/// The GL format for a block format and its sRGB version if there is one.
+fn compressed_formats(format: BlockFormat) -> (CompressedFormat, Option<CompressedSrgbFormat>) {
+    return match format {
+        BlockFormat::Bc1 => (CompressedFormat::S3tcDxt1NoAlpha, Some(CompressedSrgbFormat::S3tcDxt1NoAlpha)),
+        BlockFormat::Bc1Alpha => (CompressedFormat::S3tcDxt1Alpha, Some(CompressedSrgbFormat::S3tcDxt1Alpha)),
+        BlockFormat::Bc2 => (CompressedFormat::S3tcDxt3Alpha, Some(CompressedSrgbFormat::S3tcDxt3Alpha)),
+        BlockFormat::Bc3 => (CompressedFormat::S3tcDxt5Alpha, Some(CompressedSrgbFormat::S3tcDxt5Alpha)),
+        BlockFormat::Bc4 => (CompressedFormat::RgtcFormatU, None),
+        BlockFormat::Bc5 => (CompressedFormat::RgtcFormatUU, None),
+        BlockFormat::Bc6hUnsigned => (CompressedFormat::BptcUnsignedFloat3, None),
+        BlockFormat::Bc6hSigned => (CompressedFormat::BptcSignedFloat3, None),
+        BlockFormat::Bc7 => (CompressedFormat::BptcUnorm4, Some(CompressedSrgbFormat::Bptc))
+    };
+}
+
+/// Whether blocks can be sampled as `format` and if so through the sRGB version of their GL format.
+fn compressed_srgb(blocks: BlockFormat, format: TextureFormat) -> Result<bool, TextureError> {
+    let srgb = match (format, blocks) {
+        (TextureFormat::Hdr, BlockFormat::Bc6hUnsigned | BlockFormat::Bc6hSigned) => Some(false),
+        (TextureFormat::SingleChannel, BlockFormat::Bc4) => Some(false),
+        (TextureFormat::Srgb, _) if compressed_formats(blocks).1.is_some() => Some(true),
+        (TextureFormat::Linear, _) if !blocks.is_hdr() => Some(false),
+        _ => None
+    };
+    return srgb.ok_or_else(|| TextureError::Invalid(format!("{:?} blocks can't be loaded as {:?}", blocks, format)));
+}
+
+fn mipmaps_option(mipmaps: bool) -> MipmapsOption {
+    return if mipmaps { MipmapsOption::AutoGeneratedMipmaps } else { MipmapsOption::NoMipmap };
+}
+
+impl Texture {
+    /// Loads PNG, JPEG, HDR and the other formats of the `image` crate, `.ktx2` and `.dds` files go through
+    /// `from_compressed`. Every image ends up with its bottom row at v = 0.
+    pub fn load(display: &Display, path: impl AsRef<Path>, settings: &TextureSettings) -> Result<Texture, TextureError> {
+        let extension = path.as_ref().extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_ascii_lowercase();
+        if extension == "ktx2" || extension == "dds" {
+            return Texture::from_compressed(display, &CompressedImage::load(path)?, settings);
+        }
+
+        let image = image::open(path).map_err(TextureError::Decode)?;
+        return Texture::from_image(display, &image, settings);
+    }
+
+    pub fn from_image(display: &Display, image: &image::DynamicImage, settings: &TextureSettings) -> Result<Texture, TextureError> {
+        return Texture::from_pixels(display, pixels(image, settings.format, true), settings);
+    }
+
+    /// A single texel of `color` without mipmaps, like white for flat colored sprites or the defaults of missing material maps.
+    pub fn solid(display: &Display, color: [u8; 4], format: TextureFormat) -> Result<Texture, TextureError> {
+        let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(color)));
+        return Texture::from_image(display, &image, &TextureSettings { format, mipmaps: false, sampler: SamplerSettings::default() });
+    }
+
+    /// Loads every path as one layer of a texture array, all images need the same size.
+    pub fn load_array(display: &Display, paths: &[impl AsRef<Path>], settings: &TextureSettings) -> Result<Texture, TextureError> {
+        let images = paths.iter().map(image::open).collect::<Result<Vec<_>, _>>().map_err(TextureError::Decode)?;
+        return Texture::array_from_images(display, &images, settings);
+    }
+
+    pub fn array_from_images(display: &Display, images: &[image::DynamicImage], settings: &TextureSettings) -> Result<Texture, TextureError> {
+        let Some(first) = images.first() else { return Err(TextureError::Invalid("texture array without layers".to_string())); };
+        if images.iter().any(|image| (image.width(), image.height()) != (first.width(), first.height())) {
+            return Err(TextureError::Invalid("texture array layers differ in size".to_string()));
+        }
+
+        let mipmaps = mipmaps_option(settings.mipmaps);
+        let layers = images.iter().map(|image| pixels(image, settings.format, true));
+
+        let data = if settings.format == TextureFormat::Hdr {
+            let layers: Vec<_> = layers.filter_map(|layer| match layer { Pixels::F32(pixels) => Some(pixels), _ => None }).collect();
+            TextureData::ColorArray(Texture2dArray::with_format(display, layers, float_format(settings.format), mipmaps).map_err(TextureError::Create)?)
+        } else {
+            let layers: Vec<_> = layers.filter_map(|layer| match layer { Pixels::U8(pixels) => Some(pixels), _ => None }).collect();
+            match settings.format {
+                TextureFormat::Srgb => TextureData::SrgbArray(
+                    SrgbTexture2dArray::with_format(display, layers, SrgbFormat::U8U8U8U8, mipmaps).map_err(TextureError::Create)?),
+                format => TextureData::ColorArray(
+                    Texture2dArray::with_format(display, layers, float_format(format), mipmaps).map_err(TextureError::Create)?)
+            }
+        };
+
+        return Ok(Texture { data, format: settings.format, sampler: settings.sampler });
+    }
+
+    /// Loads a cubemap from its faces in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn load_cubemap(display: &Display, paths: &[impl AsRef<Path>; 6], settings: &TextureSettings) -> Result<Texture, TextureError> {
+        let mut faces = Vec::with_capacity(6);
+        for path in paths { faces.push(image::open(path).map_err(TextureError::Decode)?); }
+
+        return Texture::cubemap_from_images(display, &faces, settings);
+    }
+
+    /// Builds a cubemap from six square faces in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cubemap_from_images(display: &Display, faces: &[image::DynamicImage], settings: &TextureSettings) -> Result<Texture, TextureError> {
+        if faces.len() != 6 { return Err(TextureError::Invalid(format!("a cubemap needs 6 faces, not {}", faces.len()))); }
+        let size = faces[0].width();
+        if faces.iter().any(|face| face.width() != size || face.height() != size) {
+            return Err(TextureError::Invalid("cubemap faces have to be squares of the same size".to_string()));
+        }
+
+        let mipmaps = if settings.mipmaps { MipmapsOption::EmptyMipmaps } else { MipmapsOption::NoMipmap };
+        let data = match settings.format {
+            TextureFormat::Srgb => TextureData::SrgbCubemap(
+                SrgbCubemap::empty_with_format(display, SrgbFormat::U8U8U8U8, mipmaps, size).map_err(TextureError::Create)?),
+            format => TextureData::Cubemap(
+                Cubemap::empty_with_format(display, float_format(format), mipmaps, size).map_err(TextureError::Create)?)
+        };
+
+        // Faces are drawn into the cubemap through framebuffers, cubemap rows already run top to bottom.
+        let layers = [CubeLayer::PositiveX, CubeLayer::NegativeX, CubeLayer::PositiveY, CubeLayer::NegativeY, CubeLayer::PositiveZ, CubeLayer::NegativeZ];
+        let target = BlitTarget { left: 0, bottom: 0, width: size as i32, height: size as i32 };
+        let face_settings = TextureSettings { mipmaps: false, ..*settings };
+
+        for (face, layer) in faces.iter().zip(layers) {
+            let source = Texture::from_pixels(display, pixels(face, settings.format, false), &face_settings)?;
+            let framebuffer = match &data {
+                TextureData::SrgbCubemap(cubemap) => SimpleFrameBuffer::new(display, cubemap.main_level().image(layer)),
+                TextureData::Cubemap(cubemap) => SimpleFrameBuffer::new(display, cubemap.main_level().image(layer)),
+                _ => unreachable!()
+            }.map_err(|error| TextureError::Invalid(format!("cubemap face can't be drawn to: {:?}", error)))?;
+
+            let source = match &source.data {
+                TextureData::Srgb(texture) => SimpleFrameBuffer::new(display, texture),
+                TextureData::Color(texture) => SimpleFrameBuffer::new(display, texture),
+                _ => unreachable!()
+            }.map_err(|error| TextureError::Invalid(format!("cubemap face can't be read: {:?}", error)))?;
+            source.blit_whole_color_to(&framebuffer, &target, MagnifySamplerFilter::Nearest);
+        }
+
+        if settings.mipmaps {
+            match &data {
+                TextureData::SrgbCubemap(cubemap) => unsafe { cubemap.generate_mipmaps() },
+                TextureData::Cubemap(cubemap) => unsafe { cubemap.generate_mipmaps() },
+                _ => unreachable!()
+            }
+        }
+
+        return Ok(Texture { data, format: settings.format, sampler: settings.sampler });
+    }
+
+    fn from_pixels(display: &Display, pixels: Pixels, settings: &TextureSettings) -> Result<Texture, TextureError> {
+        let mipmaps = mipmaps_option(settings.mipmaps);
+        let data = match (settings.format, pixels) {
+            (TextureFormat::Srgb, Pixels::U8(pixels)) => TextureData::Srgb(
+                SrgbTexture2d::with_format(display, pixels, SrgbFormat::U8U8U8U8, mipmaps).map_err(TextureError::Create)?),
+            (format, Pixels::U8(pixels)) => TextureData::Color(
+                Texture2d::with_format(display, pixels, float_format(format), mipmaps).map_err(TextureError::Create)?),
+            (format, Pixels::F32(pixels)) => TextureData::Color(
+                Texture2d::with_format(display, pixels, float_format(format), mipmaps).map_err(TextureError::Create)?)
+        };
+
+        return Ok(Texture { data, format: settings.format, sampler: settings.sampler });
+    }
+
+    /// Uploads the blocks when the context supports the format and they can be flipped to bottom row first,
+    /// otherwise decodes them on the CPU. `settings.format` picks between the linear and sRGB versions of the
+    /// format and has to match the kind of data in the blocks. Without `settings.mipmaps` only the base level
+    /// is used, with it a file without a mip chain is decoded to generate one.
+    pub fn from_compressed(display: &Display, image: &CompressedImage, settings: &TextureSettings) -> Result<Texture, TextureError> {
+        let srgb = compressed_srgb(image.format, settings.format)?;
+        if settings.mipmaps && image.levels.len() == 1 && (image.width > 1 || image.height > 1) {
+            return Texture::decode_compressed(display, image, settings);
+        }
+        let level_count = if settings.mipmaps { image.levels.len() } else { 1 };
+        let Some(levels) = image.flipped_levels(level_count) else { return Texture::decode_compressed(display, image, settings); };
+
+        let mipmaps = match level_count {
+            1 => CompressedMipmapsOption::NoMipmap,
+            levels => CompressedMipmapsOption::EmptyMipmapsMax(levels as u32 - 1)
+        };
+        let upload_failed = |level| TextureError::Invalid(format!("level {} could not be uploaded", level));
+
+        let data = match (srgb, compressed_formats(image.format)) {
+            (true, (_, Some(format))) if format.is_supported(display) => {
+                let texture = CompressedSrgbTexture2d::empty_with_format(display, format, mipmaps, image.width, image.height).map_err(TextureError::Create)?;
+                for (level, blocks) in levels.iter().enumerate() {
+                    let (width, height) = image.level_dimensions(level);
+                    texture.mipmap(level as u32).ok_or_else(|| upload_failed(level))?
+                        .write_compressed_data(Rect { left: 0, bottom: 0, width, height }, blocks, width, height, format)
+                        .map_err(|_| upload_failed(level))?;
+                }
+                TextureData::CompressedSrgb(texture)
+            },
+            (false, (format, _)) if format.is_supported(display) => {
+                let texture = CompressedTexture2d::empty_with_format(display, format, mipmaps, image.width, image.height).map_err(TextureError::Create)?;
+                for (level, blocks) in levels.iter().enumerate() {
+                    let (width, height) = image.level_dimensions(level);
+                    texture.mipmap(level as u32).ok_or_else(|| upload_failed(level))?
+                        .write_compressed_data(Rect { left: 0, bottom: 0, width, height }, blocks, width, height, format)
+                        .map_err(|_| upload_failed(level))?;
+                }
+                TextureData::Compressed(texture)
+            },
+            _ => return Texture::decode_compressed(display, image, settings)
+        };
+
+        return Ok(Texture { data, format: settings.format, sampler: settings.sampler });
+    }
+
+    /// Decodes the levels on the CPU and uploads the texels uncompressed, what `from_compressed` falls back to.
+    /// Follows `settings` the same way.
+    pub fn decode_compressed(display: &Display, image: &CompressedImage, settings: &TextureSettings) -> Result<Texture, TextureError> {
+        let srgb = compressed_srgb(image.format, settings.format)?;
+        let (mipmaps, level_count) = match (settings.mipmaps, image.levels.len()) {
+            (false, _) => (MipmapsOption::NoMipmap, 1),
+            (true, 1) => (MipmapsOption::AutoGeneratedMipmaps, 1),
+            (true, levels) => (MipmapsOption::EmptyMipmapsMax(levels as u32 - 1), levels)
+        };
+
+        let data = match decoded_pixels(image, 0) {
+            Pixels::U8(pixels) if srgb => TextureData::Srgb(
+                SrgbTexture2d::with_format(display, pixels, SrgbFormat::U8U8U8U8, mipmaps).map_err(TextureError::Create)?),
+            Pixels::U8(pixels) => TextureData::Color(
+                Texture2d::with_format(display, pixels, float_format(settings.format), mipmaps).map_err(TextureError::Create)?),
+            Pixels::F32(pixels) => TextureData::Color(
+                Texture2d::with_format(display, pixels, float_format(settings.format), mipmaps).map_err(TextureError::Create)?)
+        };
+
+        for level in 1..level_count {
+            let (width, height) = image.level_dimensions(level);
+            let rect = Rect { left: 0, bottom: 0, width, height };
+            match (&data, decoded_pixels(image, level)) {
+                (TextureData::Srgb(texture), Pixels::U8(pixels)) => texture.mipmap(level as u32).unwrap().write(rect, pixels),
+                (TextureData::Color(texture), Pixels::U8(pixels)) => texture.mipmap(level as u32).unwrap().write(rect, pixels),
+                (TextureData::Color(texture), Pixels::F32(pixels)) => texture.mipmap(level as u32).unwrap().write(rect, pixels),
+                _ => unreachable!()
+            }
+        }
+
+        return Ok(Texture { data, format: settings.format, sampler: settings.sampler });
+    }
+
+    /// Width and height of the base level, for cubemaps the size of one face.
+    pub fn dimensions(&self) -> (u32, u32) {
+        return match &self.data {
+            TextureData::Color(texture) => texture.dimensions(),
+            TextureData::Srgb(texture) => texture.dimensions(),
+            TextureData::ColorArray(texture) => (texture.width(), texture.height()),
+            TextureData::SrgbArray(texture) => (texture.width(), texture.height()),
+            TextureData::Cubemap(texture) => (texture.dimensions(), texture.dimensions()),
+            TextureData::SrgbCubemap(texture) => (texture.dimensions(), texture.dimensions()),
+            TextureData::Compressed(texture) => texture.dimensions(),
+            TextureData::CompressedSrgb(texture) => texture.dimensions()
+        };
+    }
+
+    /// Layers of a texture array, 1 for everything else.
+    pub fn layers(&self) -> u32 {
+        return match &self.data {
+            TextureData::ColorArray(texture) => texture.array_size(),
+            TextureData::SrgbArray(texture) => texture.array_size(),
+            _ => 1
+        };
+    }
+
+    pub fn mipmapped(&self) -> bool {
+        return match &self.data {
+            TextureData::Color(texture) => texture.get_mipmap_levels() > 1,
+            TextureData::Srgb(texture) => texture.get_mipmap_levels() > 1,
+            TextureData::ColorArray(texture) => texture.get_mipmap_levels() > 1,
+            TextureData::SrgbArray(texture) => texture.get_mipmap_levels() > 1,
+            TextureData::Cubemap(texture) => texture.get_mipmap_levels() > 1,
+            TextureData::SrgbCubemap(texture) => texture.get_mipmap_levels() > 1,
+            TextureData::Compressed(texture) => texture.get_mipmap_levels() > 1,
+            TextureData::CompressedSrgb(texture) => texture.get_mipmap_levels() > 1
+        };
+    }
+}
+
+impl AsUniformValue for &Texture {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        let behavior = Some(self.sampler.behavior(self.mipmapped()));
+        return match &self.data {
+            TextureData::Color(texture) => UniformValue::Texture2d(texture, behavior),
+            TextureData::Srgb(texture) => UniformValue::SrgbTexture2d(texture, behavior),
+            TextureData::ColorArray(texture) => UniformValue::Texture2dArray(texture, behavior),
+            TextureData::SrgbArray(texture) => UniformValue::SrgbTexture2dArray(texture, behavior),
+            TextureData::Cubemap(texture) => UniformValue::Cubemap(texture, behavior),
+            TextureData::SrgbCubemap(texture) => UniformValue::SrgbCubemap(texture, behavior),
+            TextureData::Compressed(texture) => UniformValue::CompressedTexture2d(texture, behavior),
+            TextureData::CompressedSrgb(texture) => UniformValue::CompressedSrgbTexture2d(texture, behavior)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A 4x4 DXT1 file whose top row is red and the rest blue.
+    fn red_top_dds() -> Vec<u8> {
+        let mut data = vec![0; 128];
+        data[0..4].copy_from_slice(b"DDS ");
+        for (offset, value) in [(4, 124), (8, 0x1 | 0x2 | 0x4 | 0x1000), (12, 4), (16, 4), (28, 1), (76, 32), (80, 0x4)] {
+            data[offset..offset + 4].copy_from_slice(&(value as u32).to_le_bytes());
+        }
+        data[84..88].copy_from_slice(b"DXT1");
+        data.extend_from_slice(&0xf800u16.to_le_bytes());
+        data.extend_from_slice(&0x001fu16.to_le_bytes());
+        data.extend_from_slice(&[0x00, 0x55, 0x55, 0x55]);
+        return data;
+    }
+
+    #[test]
+    fn compressed_and_png_agree_on_orientation() {
+        let image = CompressedImage::parse(&red_top_dds()).unwrap();
+        let DecodedLevel::Rgba8(decoded) = image.decode(0) else { panic!("BC1 decodes to 8 bit") };
+
+        let png = image::RgbaImage::from_fn(4, 4, |_, y| if y == 0 { image::Rgba([255, 0, 0, 255]) } else { image::Rgba([0, 0, 255, 255]) });
+        let mut encoded = Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(png).write_to(&mut encoded, image::ImageOutputFormat::Png).unwrap();
+        let png = image::load_from_memory(encoded.get_ref()).unwrap();
+        let Pixels::U8(uploaded) = pixels(&png, TextureFormat::Srgb, true) else { panic!("sRGB uploads 8 bit") };
+
+        assert_eq!(decoded, uploaded.data.into_owned());
+        // Bottom row first, so v = 0 is blue and the red top row comes last.
+        assert_eq!(decoded[..4], [0, 0, 255, 255]);
+        assert_eq!(decoded[48..52], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn compressed_formats_follow_settings() {
+        assert!(compressed_srgb(BlockFormat::Bc7, TextureFormat::Srgb).unwrap());
+        assert!(!compressed_srgb(BlockFormat::Bc7, TextureFormat::Linear).unwrap());
+        assert!(!compressed_srgb(BlockFormat::Bc4, TextureFormat::SingleChannel).unwrap());
+        assert!(!compressed_srgb(BlockFormat::Bc6hSigned, TextureFormat::Hdr).unwrap());
+        assert!(compressed_srgb(BlockFormat::Bc5, TextureFormat::Srgb).is_err());
+        assert!(compressed_srgb(BlockFormat::Bc6hUnsigned, TextureFormat::Linear).is_err());
+        assert!(compressed_srgb(BlockFormat::Bc1, TextureFormat::Hdr).is_err());
+    }
+}
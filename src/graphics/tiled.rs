@@ -0,0 +1,867 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use base64::Engine as _;
+use roxmltree::Node;
+use serde_json::Value;
+
+#[derive(Debug)]
+pub enum TiledError {
+    Io(std::io::Error),
+    Xml(roxmltree::Error),
+    Json(serde_json::Error),
+    Invalid(String)
+}
+
+impl std::fmt::Display for TiledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TiledError::Io(error) => write!(f, "failed to read Tiled file: {}", error),
+            TiledError::Xml(error) => write!(f, "invalid TMX: {}", error),
+            TiledError::Json(error) => write!(f, "invalid TMJ: {}", error),
+            TiledError::Invalid(message) => write!(f, "unsupported Tiled map: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for TiledError {}
+
+fn invalid(message: impl Into<String>) -> TiledError { TiledError::Invalid(message.into()) }
+
+/// Tile flipped left to right, set in the top bit of a gid.
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+/// Tile mirrored along its top left to bottom right diagonal, combined with the other flips for rotations.
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Bits of a gid that aren't flags, 0 is an empty cell.
+pub const GID_MASK: u32 = 0x0fff_ffff;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PropertyValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// `#AARRGGBB` as Tiled writes it.
+    Color(String),
+    /// Path as written in the map.
+    File(String),
+    /// Id of an object in the map.
+    Object(u32)
+}
+
+/// Custom properties set in Tiled.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Properties(pub HashMap<String, PropertyValue>);
+
+impl Properties {
+    pub fn get(&self, name: &str) -> Option<&PropertyValue> { self.0.get(name) }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        return match self.0.get(name)? { PropertyValue::Bool(value) => Some(*value), _ => None };
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        return match self.0.get(name)? { PropertyValue::Int(value) => Some(*value), _ => None };
+    }
+
+    /// Also reads int properties.
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        return match self.0.get(name)? { PropertyValue::Float(value) => Some(*value), PropertyValue::Int(value) => Some(*value as f64), _ => None };
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        return match self.0.get(name)? {
+            PropertyValue::String(value) | PropertyValue::Color(value) | PropertyValue::File(value) => Some(value),
+            _ => None
+        };
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to `MapObject::position`, y up.
+    Polygon(Vec<[f32; 2]>),
+    Polyline(Vec<[f32; 2]>)
+}
+
+/// An object from an object layer or a tile's collision shapes. Positions are in pixels with y up, see `TiledMap`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    /// Tiled's anchor point: the top left corner of shapes, the bottom left corner of tile objects.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    /// Counterclockwise in radians around `position`.
+    pub rotation: f32,
+    pub shape: ObjectShape,
+    /// Set for tile objects, may carry flip flags.
+    pub gid: Option<u32>,
+    pub visible: bool,
+    pub properties: Properties
+}
+
+impl MapObject {
+    /// Bottom left and top right corners, ignoring rotation.
+    pub fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        let [x, y] = self.position;
+        return match &self.shape {
+            ObjectShape::Polygon(points) | ObjectShape::Polyline(points) => {
+                let min = points.iter().fold([f32::MAX; 2], |min, point| [min[0].min(point[0]), min[1].min(point[1])]);
+                let max = points.iter().fold([f32::MIN; 2], |max, point| [max[0].max(point[0]), max[1].max(point[1])]);
+                ([x + min[0], y + min[1]], [x + max[0], y + max[1]])
+            },
+            _ if self.gid.is_some() => ([x, y], [x + self.size[0], y + self.size[1]]),
+            _ => ([x, y - self.size[1]], [x + self.size[0], y])
+        };
+    }
+}
+
+/// Data Tiled stores for single tiles of a tileset.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct TileInfo {
+    pub class: String,
+    pub properties: Properties,
+    /// Collision shapes drawn in Tiled's tile collision editor, relative to the tile's bottom left corner.
+    pub objects: Vec<MapObject>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tileset {
+    /// Gid of the tileset's first tile, local tile ids are added to it.
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub spacing: u32,
+    pub margin: u32,
+    /// Resolved against the file the tileset came from.
+    pub image: PathBuf,
+    pub properties: Properties,
+    /// Only tiles that have something set in Tiled, by local id.
+    pub tiles: HashMap<u32, TileInfo>
+}
+
+impl Tileset {
+    pub fn contains(&self, gid: u32) -> bool { (gid & GID_MASK).checked_sub(self.first_gid).is_some_and(|local| local < self.tile_count) }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Gids row by row from the top row, flags included.
+    pub tiles: Vec<u32>,
+    /// In pixels with y up, including the offsets of enclosing groups.
+    pub offset: [f32; 2],
+    pub opacity: f32,
+    pub visible: bool,
+    pub properties: Properties
+}
+
+impl TileLayer {
+    /// Gid at a column and row counted from the top left, 0 for empty cells and outside the layer.
+    pub fn gid(&self, column: u32, row: u32) -> u32 {
+        if column >= self.width || row >= self.height { return 0; }
+        return self.tiles.get(row as usize * self.width as usize + column as usize).copied().unwrap_or(0);
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub objects: Vec<MapObject>,
+    pub offset: [f32; 2],
+    pub opacity: f32,
+    pub visible: bool,
+    pub properties: Properties
+}
+
+/// Group layers are flattened into their children, image layers are skipped.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Layer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer)
+}
+
+impl Layer {
+    pub fn name(&self) -> &str {
+        return match self { Layer::Tiles(layer) => &layer.name, Layer::Objects(layer) => &layer.name };
+    }
+
+    pub fn properties(&self) -> &Properties {
+        return match self { Layer::Tiles(layer) => &layer.properties, Layer::Objects(layer) => &layer.properties };
+    }
+}
+
+/// An orthogonal, finite Tiled map. Tiled measures y downwards from the top of the map, here every
+/// position is converted to pixels with y up and the origin at the map's bottom left corner so it
+/// lines up with `Camera2d`. Tile columns and rows keep Tiled's numbering, row 0 is the top row.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TiledMap {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tilesets: Vec<Tileset>,
+    /// Bottom to top in drawing order.
+    pub layers: Vec<Layer>,
+    pub properties: Properties
+}
+
+/// Offset, opacity and visibility passed down from group layers.
+#[derive(Copy, Clone)]
+struct Inherited {
+    offset: [f32; 2],
+    opacity: f32,
+    visible: bool
+}
+
+impl Inherited {
+    fn combine(&self, offset: [f32; 2], opacity: f32, visible: bool) -> Inherited {
+        return Inherited { offset: [self.offset[0] + offset[0], self.offset[1] - offset[1]], opacity: self.opacity * opacity, visible: self.visible && visible };
+    }
+}
+
+const ROOT: Inherited = Inherited { offset: [0.0, 0.0], opacity: 1.0, visible: true };
+
+fn read_to_string(path: &Path) -> Result<String, TiledError> { std::fs::read_to_string(path).map_err(TiledError::Io) }
+
+/// Number of tiles a layer has to have, an error if it doesn't fit into memory.
+fn tile_count(name: &str, width: u32, height: u32) -> Result<usize, TiledError> {
+    return (width as usize).checked_mul(height as usize)
+        .filter(|count| count.checked_mul(4).is_some())
+        .ok_or_else(|| invalid(format!("layer {:?} is too large at {}x{} tiles", name, width, height)));
+}
+
+/// Turns `data` of a tile layer into gids, `encoding` is `csv`, `base64` or `None` for a JSON array
+/// that was already read. Decompression stops one gid past `count`, so a stream that inflates to
+/// more than the layer holds fails the tile count check instead of filling memory.
+fn decode_tiles(data: &str, encoding: Option<&str>, compression: Option<&str>, count: usize) -> Result<Vec<u32>, TiledError> {
+    if encoding == Some("csv") {
+        return data.split(',').map(|gid| gid.trim().parse::<u32>().map_err(|_| invalid(format!("bad tile {:?}", gid.trim())))).collect();
+    }
+    if encoding != Some("base64") { return Err(invalid(format!("tile data encoding {:?}", encoding))); }
+
+    let compressed = base64::engine::general_purpose::STANDARD.decode(data.trim()).map_err(|error| invalid(format!("bad base64 tile data: {}", error)))?;
+    let limit = (count as u64 + 1) * 4;
+    let mut bytes = Vec::new();
+    match compression {
+        None | Some("") => bytes = compressed,
+        Some("zlib") => { flate2::read::ZlibDecoder::new(&compressed[..]).take(limit).read_to_end(&mut bytes).map_err(TiledError::Io)?; },
+        Some("gzip") => { flate2::read::GzDecoder::new(&compressed[..]).take(limit).read_to_end(&mut bytes).map_err(TiledError::Io)?; },
+        Some(compression) => return Err(invalid(format!("{} compressed tile data", compression)))
+    }
+    if bytes.len() % 4 != 0 { return Err(invalid(format!("tile data of {} bytes isn't made of 4 byte gids", bytes.len()))); }
+
+    return Ok(bytes.chunks_exact(4).map(|gid| u32::from_le_bytes(gid.try_into().unwrap())).collect());
+}
+
+fn check_tile_count(name: &str, tiles: &[u32], count: usize) -> Result<(), TiledError> {
+    if tiles.len() != count { return Err(invalid(format!("layer {:?} has {} tiles instead of {}", name, tiles.len(), count))); }
+    return Ok(());
+}
+
+/// Gids of the tileset have to stay below the flip flags.
+fn check_gids(tileset: &Tileset) -> Result<(), TiledError> {
+    if tileset.first_gid == 0 || tileset.first_gid.checked_add(tileset.tile_count).is_none_or(|end| end > GID_MASK + 1) {
+        return Err(invalid(format!("tileset {:?} has gids {} to {} + {}", tileset.name, tileset.first_gid, tileset.first_gid, tileset.tile_count)));
+    }
+    return Ok(());
+}
+
+impl TiledMap {
+    /// Loads a `.tmx` or `.tmj` map, external tilesets are loaded relative to it.
+    pub fn load(path: impl AsRef<Path>) -> Result<TiledMap, TiledError> {
+        let path = path.as_ref();
+        let source = read_to_string(path)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        return match path.extension().and_then(|extension| extension.to_str()) {
+            Some("tmj") | Some("json") => TiledMap::parse_tmj(&source, directory),
+            _ => TiledMap::parse_tmx(&source, directory)
+        };
+    }
+
+    pub fn pixel_size(&self) -> [f32; 2] { [self.width as f32 * self.tile_width as f32, self.height as f32 * self.tile_height as f32] }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> { self.layers.iter().find(|layer| layer.name() == name) }
+
+    pub fn tile_layer(&self, name: &str) -> Option<&TileLayer> {
+        return self.layers.iter().find_map(|layer| match layer { Layer::Tiles(layer) if layer.name == name => Some(layer), _ => None });
+    }
+
+    pub fn object_layer(&self, name: &str) -> Option<&ObjectLayer> {
+        return self.layers.iter().find_map(|layer| match layer { Layer::Objects(layer) if layer.name == name => Some(layer), _ => None });
+    }
+
+    /// Tileset the gid belongs to.
+    pub fn tileset(&self, gid: u32) -> Option<&Tileset> { self.tilesets.iter().find(|tileset| tileset.contains(gid)) }
+
+    /// What Tiled stores for the tile, `None` for empty cells and tiles without any data.
+    pub fn tile_info(&self, gid: u32) -> Option<&TileInfo> {
+        let tileset = self.tileset(gid)?;
+        return tileset.tiles.get(&((gid & GID_MASK) - tileset.first_gid));
+    }
+
+    /// World position of a tile's bottom left corner.
+    pub fn tile_to_world(&self, column: u32, row: u32) -> [f32; 2] {
+        return [column as f32 * self.tile_width as f32, (self.height - 1).saturating_sub(row) as f32 * self.tile_height as f32];
+    }
+
+    /// Column and row of the tile under a world position, `None` outside the map.
+    pub fn world_to_tile(&self, position: [f32; 2]) -> Option<[u32; 2]> {
+        let column = (position[0] / self.tile_width as f32).floor();
+        let row = self.height as f32 - 1.0 - (position[1] / self.tile_height as f32).floor();
+        if column < 0.0 || row < 0.0 || column >= self.width as f32 || row >= self.height as f32 { return None; }
+        return Some([column as u32, row as u32]);
+    }
+
+    fn check(width: u32, height: u32, tile_width: u32, tile_height: u32, orientation: &str, infinite: bool) -> Result<(), TiledError> {
+        if orientation != "orthogonal" { return Err(invalid(format!("{} maps, only orthogonal ones are supported", orientation))); }
+        if infinite { return Err(invalid("infinite maps")); }
+        if width == 0 || height == 0 || tile_width == 0 || tile_height == 0 { return Err(invalid("map without tiles")); }
+        // Cells and pixels are counted in `u32`.
+        if width.checked_mul(height).is_none() || width.checked_mul(tile_width).is_none() || height.checked_mul(tile_height).is_none() {
+            return Err(invalid(format!("{}x{} map of {}x{} tiles is too large", width, height, tile_width, tile_height)));
+        }
+        return Ok(());
+    }
+
+    pub fn parse_tmx(source: &str, directory: &Path) -> Result<TiledMap, TiledError> {
+        let document = roxmltree::Document::parse(source).map_err(TiledError::Xml)?;
+        let root = document.root_element();
+        if !root.has_tag_name("map") { return Err(invalid("TMX without a map element")); }
+
+        let (width, height) = (required(root, "width")?, required(root, "height")?);
+        let (tile_width, tile_height): (u32, u32) = (required(root, "tilewidth")?, required(root, "tileheight")?);
+        TiledMap::check(width, height, tile_width, tile_height, root.attribute("orientation").unwrap_or("orthogonal"), attribute(root, "infinite", 0u32)? != 0)?;
+
+        let mut tilesets = Vec::new();
+        for node in root.children().filter(|node| node.has_tag_name("tileset")) {
+            let first_gid = required(node, "firstgid")?;
+            let tileset = match node.attribute("source") {
+                Some(source) => load_external_tileset(&directory.join(source), first_gid)?,
+                None => tmx_tileset(node, first_gid, directory)?
+            };
+            check_gids(&tileset)?;
+            tilesets.push(tileset);
+        }
+
+        let map_height = height as f32 * tile_height as f32;
+        let mut layers = Vec::new();
+        tmx_layers(root, ROOT, map_height, &mut layers)?;
+
+        return Ok(TiledMap { width, height, tile_width, tile_height, tilesets, layers, properties: tmx_properties(root)? });
+    }
+
+    pub fn parse_tmj(source: &str, directory: &Path) -> Result<TiledMap, TiledError> {
+        let root: Value = serde_json::from_str(source).map_err(TiledError::Json)?;
+
+        let (width, height) = (json_u32(&root, "width"), json_u32(&root, "height"));
+        let (tile_width, tile_height) = (json_u32(&root, "tilewidth"), json_u32(&root, "tileheight"));
+        TiledMap::check(width, height, tile_width, tile_height, json_str(&root, "orientation").unwrap_or("orthogonal"), json_bool(&root, "infinite", false))?;
+
+        let mut tilesets = Vec::new();
+        for value in json_array(&root, "tilesets") {
+            let first_gid = json_u32(value, "firstgid");
+            let tileset = match json_str(value, "source") {
+                Some(source) => load_external_tileset(&directory.join(source), first_gid)?,
+                None => tmj_tileset(value, first_gid, directory)?
+            };
+            check_gids(&tileset)?;
+            tilesets.push(tileset);
+        }
+
+        let map_height = height as f32 * tile_height as f32;
+        let mut layers = Vec::new();
+        tmj_layers(json_array(&root, "layers"), ROOT, map_height, &mut layers)?;
+
+        return Ok(TiledMap { width, height, tile_width, tile_height, tilesets, layers, properties: tmj_properties(&root) });
+    }
+}
+
+/// Loads a `.tsx` or `.tsj` tileset.
+fn load_external_tileset(path: &Path, first_gid: u32) -> Result<Tileset, TiledError> {
+    let source = read_to_string(path)?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    if matches!(path.extension().and_then(|extension| extension.to_str()), Some("tsj") | Some("json")) {
+        let value: Value = serde_json::from_str(&source).map_err(TiledError::Json)?;
+        return tmj_tileset(&value, first_gid, directory);
+    }
+
+    let document = roxmltree::Document::parse(&source).map_err(TiledError::Xml)?;
+    return tmx_tileset(document.root_element(), first_gid, directory);
+}
+
+fn check_tileset(name: &str, image: Option<&str>) -> Result<PathBuf, TiledError> {
+    return image.map(PathBuf::from).ok_or_else(|| invalid(format!("tileset {:?} is an image collection, only single image tilesets are supported", name)));
+}
+
+// TMX
+
+fn attribute<T: std::str::FromStr>(node: Node, name: &str, default: T) -> Result<T, TiledError> {
+    return match node.attribute(name) {
+        Some(value) => value.parse().map_err(|_| invalid(format!("bad {} {:?} on <{}>", name, value, node.tag_name().name()))),
+        None => Ok(default)
+    };
+}
+
+fn required<T: std::str::FromStr>(node: Node, name: &str) -> Result<T, TiledError> {
+    let value = node.attribute(name).ok_or_else(|| invalid(format!("<{}> without {}", node.tag_name().name(), name)))?;
+    return value.parse().map_err(|_| invalid(format!("bad {} {:?} on <{}>", name, value, node.tag_name().name())));
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> { node.children().find(|child| child.has_tag_name(name)) }
+
+fn tmx_properties(node: Node) -> Result<Properties, TiledError> {
+    let mut properties = HashMap::new();
+    let Some(list) = child(node, "properties") else { return Ok(Properties(properties)); };
+
+    for property in list.children().filter(|child| child.has_tag_name("property")) {
+        let name = property.attribute("name").unwrap_or_default().to_string();
+        // Multiline strings are stored as text instead of in the value attribute.
+        let text = property.attribute("value").or_else(|| property.text()).unwrap_or_default();
+        let value = match property.attribute("type").unwrap_or("string") {
+            "int" => PropertyValue::Int(required(property, "value")?),
+            "float" => PropertyValue::Float(required(property, "value")?),
+            "bool" => PropertyValue::Bool(text == "true"),
+            "color" => PropertyValue::Color(text.to_string()),
+            "file" => PropertyValue::File(text.to_string()),
+            "object" => PropertyValue::Object(attribute(property, "value", 0)?),
+            "class" => continue,
+            _ => PropertyValue::String(text.to_string())
+        };
+        properties.insert(name, value);
+    }
+
+    return Ok(Properties(properties));
+}
+
+fn tmx_points(node: Node) -> Result<Vec<[f32; 2]>, TiledError> {
+    let points = node.attribute("points").unwrap_or_default();
+    return points.split_whitespace().map(|point| {
+        let (x, y) = point.split_once(',').ok_or_else(|| invalid(format!("bad point {:?}", point)))?;
+        let (x, y) = (x.parse::<f32>(), y.parse::<f32>());
+        return match (x, y) { (Ok(x), Ok(y)) => Ok([x, -y]), _ => Err(invalid(format!("bad point {:?}", point))) };
+    }).collect();
+}
+
+/// `flip_height` is the height y is measured down from, the map's or the tile's.
+fn tmx_object(node: Node, flip_height: f32) -> Result<MapObject, TiledError> {
+    let shape = if child(node, "ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if child(node, "point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = child(node, "polygon") {
+        ObjectShape::Polygon(tmx_points(polygon)?)
+    } else if let Some(polyline) = child(node, "polyline") {
+        ObjectShape::Polyline(tmx_points(polyline)?)
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    return Ok(MapObject {
+        id: attribute(node, "id", 0)?,
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        class: node.attribute("class").or_else(|| node.attribute("type")).unwrap_or_default().to_string(),
+        position: [attribute(node, "x", 0.0)?, flip_height - attribute(node, "y", 0.0)?],
+        size: [attribute(node, "width", 0.0)?, attribute(node, "height", 0.0)?],
+        rotation: -attribute(node, "rotation", 0.0f32)?.to_radians(),
+        shape,
+        gid: node.attribute("gid").map(|gid| gid.parse()).transpose().map_err(|_| invalid("bad object gid"))?,
+        visible: attribute(node, "visible", 1u32)? != 0,
+        properties: tmx_properties(node)?
+    });
+}
+
+fn tmx_tileset(node: Node, first_gid: u32, directory: &Path) -> Result<Tileset, TiledError> {
+    let name = node.attribute("name").unwrap_or_default().to_string();
+    let image = check_tileset(&name, child(node, "image").and_then(|image| image.attribute("source")))?;
+    let tile_height: u32 = required(node, "tileheight")?;
+
+    let mut tiles = HashMap::new();
+    for tile in node.children().filter(|child| child.has_tag_name("tile")) {
+        let objects = match child(tile, "objectgroup") {
+            Some(group) => group.children().filter(|child| child.has_tag_name("object")).map(|object| tmx_object(object, tile_height as f32)).collect::<Result<_, _>>()?,
+            None => Vec::new()
+        };
+        tiles.insert(required(tile, "id")?, TileInfo {
+            class: tile.attribute("class").or_else(|| tile.attribute("type")).unwrap_or_default().to_string(),
+            properties: tmx_properties(tile)?,
+            objects
+        });
+    }
+
+    return Ok(Tileset {
+        first_gid,
+        tile_width: required(node, "tilewidth")?,
+        tile_height,
+        columns: required(node, "columns")?,
+        tile_count: required(node, "tilecount")?,
+        spacing: attribute(node, "spacing", 0)?,
+        margin: attribute(node, "margin", 0)?,
+        image: directory.join(image),
+        properties: tmx_properties(node)?,
+        tiles,
+        name
+    });
+}
+
+fn tmx_layers(parent: Node, inherited: Inherited, map_height: f32, layers: &mut Vec<Layer>) -> Result<(), TiledError> {
+    for node in parent.children().filter(|node| node.is_element()) {
+        let inherited = inherited.combine([attribute(node, "offsetx", 0.0)?, attribute(node, "offsety", 0.0)?],
+            attribute(node, "opacity", 1.0)?, attribute(node, "visible", 1u32)? != 0);
+        let name = node.attribute("name").unwrap_or_default().to_string();
+
+        match node.tag_name().name() {
+            "layer" => {
+                let (width, height) = (required(node, "width")?, required(node, "height")?);
+                let count = tile_count(&name, width, height)?;
+                let data = child(node, "data").ok_or_else(|| invalid(format!("layer {:?} without data", name)))?;
+                let tiles = match data.attribute("encoding") {
+                    None => data.children().filter(|child| child.has_tag_name("tile")).map(|tile| attribute(tile, "gid", 0)).collect::<Result<_, _>>()?,
+                    encoding => decode_tiles(data.text().unwrap_or_default(), encoding, data.attribute("compression"), count)?
+                };
+                check_tile_count(&name, &tiles, count)?;
+
+                layers.push(Layer::Tiles(TileLayer {
+                    width, height, tiles,
+                    offset: inherited.offset, opacity: inherited.opacity, visible: inherited.visible,
+                    properties: tmx_properties(node)?,
+                    name
+                }));
+            },
+            "objectgroup" => {
+                let objects = node.children().filter(|child| child.has_tag_name("object")).map(|object| tmx_object(object, map_height)).collect::<Result<_, _>>()?;
+                layers.push(Layer::Objects(ObjectLayer {
+                    objects,
+                    offset: inherited.offset, opacity: inherited.opacity, visible: inherited.visible,
+                    properties: tmx_properties(node)?,
+                    name
+                }));
+            },
+            "group" => tmx_layers(node, inherited, map_height, layers)?,
+            _ => {}
+        }
+    }
+
+    return Ok(());
+}
+
+// TMJ
+
+fn json_u32(value: &Value, name: &str) -> u32 { value.get(name).and_then(Value::as_u64).unwrap_or(0) as u32 }
+
+fn json_f32(value: &Value, name: &str, default: f32) -> f32 { value.get(name).and_then(Value::as_f64).map_or(default, |value| value as f32) }
+
+fn json_bool(value: &Value, name: &str, default: bool) -> bool { value.get(name).and_then(Value::as_bool).unwrap_or(default) }
+
+fn json_str<'a>(value: &'a Value, name: &str) -> Option<&'a str> { value.get(name).and_then(Value::as_str) }
+
+fn json_array<'a>(value: &'a Value, name: &str) -> &'a [Value] { value.get(name).and_then(Value::as_array).map_or(&[], |array| array.as_slice()) }
+
+fn tmj_properties(value: &Value) -> Properties {
+    let mut properties = HashMap::new();
+    for property in json_array(value, "properties") {
+        let name = json_str(property, "name").unwrap_or_default().to_string();
+        let raw = property.get("value").unwrap_or(&Value::Null);
+        let text = raw.as_str().unwrap_or_default().to_string();
+        let value = match json_str(property, "type").unwrap_or("string") {
+            "int" => PropertyValue::Int(raw.as_i64().unwrap_or(0)),
+            "float" => PropertyValue::Float(raw.as_f64().unwrap_or(0.0)),
+            "bool" => PropertyValue::Bool(raw.as_bool().unwrap_or(false)),
+            "color" => PropertyValue::Color(text),
+            "file" => PropertyValue::File(text),
+            "object" => PropertyValue::Object(raw.as_u64().unwrap_or(0) as u32),
+            "class" => continue,
+            _ => PropertyValue::String(text)
+        };
+        properties.insert(name, value);
+    }
+
+    return Properties(properties);
+}
+
+fn tmj_points(value: &Value, name: &str) -> Vec<[f32; 2]> {
+    return json_array(value, name).iter().map(|point| [json_f32(point, "x", 0.0), -json_f32(point, "y", 0.0)]).collect();
+}
+
+fn tmj_object(value: &Value, flip_height: f32) -> MapObject {
+    let shape = if json_bool(value, "ellipse", false) {
+        ObjectShape::Ellipse
+    } else if json_bool(value, "point", false) {
+        ObjectShape::Point
+    } else if value.get("polygon").is_some() {
+        ObjectShape::Polygon(tmj_points(value, "polygon"))
+    } else if value.get("polyline").is_some() {
+        ObjectShape::Polyline(tmj_points(value, "polyline"))
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    return MapObject {
+        id: json_u32(value, "id"),
+        name: json_str(value, "name").unwrap_or_default().to_string(),
+        class: json_str(value, "class").or_else(|| json_str(value, "type")).unwrap_or_default().to_string(),
+        position: [json_f32(value, "x", 0.0), flip_height - json_f32(value, "y", 0.0)],
+        size: [json_f32(value, "width", 0.0), json_f32(value, "height", 0.0)],
+        rotation: -json_f32(value, "rotation", 0.0).to_radians(),
+        shape,
+        gid: value.get("gid").and_then(Value::as_u64).map(|gid| gid as u32),
+        visible: json_bool(value, "visible", true),
+        properties: tmj_properties(value)
+    };
+}
+
+fn tmj_tileset(value: &Value, first_gid: u32, directory: &Path) -> Result<Tileset, TiledError> {
+    let name = json_str(value, "name").unwrap_or_default().to_string();
+    let image = check_tileset(&name, json_str(value, "image"))?;
+    let tile_height = json_u32(value, "tileheight");
+
+    let tiles = json_array(value, "tiles").iter().map(|tile| {
+        let objects = tile.get("objectgroup").map_or(Vec::new(), |group| json_array(group, "objects").iter().map(|object| tmj_object(object, tile_height as f32)).collect());
+        let info = TileInfo {
+            class: json_str(tile, "class").or_else(|| json_str(tile, "type")).unwrap_or_default().to_string(),
+            properties: tmj_properties(tile),
+            objects
+        };
+        return (json_u32(tile, "id"), info);
+    }).collect();
+
+    return Ok(Tileset {
+        first_gid,
+        tile_width: json_u32(value, "tilewidth"),
+        tile_height,
+        columns: json_u32(value, "columns"),
+        tile_count: json_u32(value, "tilecount"),
+        spacing: json_u32(value, "spacing"),
+        margin: json_u32(value, "margin"),
+        image: directory.join(image),
+        properties: tmj_properties(value),
+        tiles,
+        name
+    });
+}
+
+fn tmj_layers(values: &[Value], inherited: Inherited, map_height: f32, layers: &mut Vec<Layer>) -> Result<(), TiledError> {
+    for value in values {
+        let inherited = inherited.combine([json_f32(value, "offsetx", 0.0), json_f32(value, "offsety", 0.0)],
+            json_f32(value, "opacity", 1.0), json_bool(value, "visible", true));
+        let name = json_str(value, "name").unwrap_or_default().to_string();
+
+        match json_str(value, "type").unwrap_or_default() {
+            "tilelayer" => {
+                let (width, height) = (json_u32(value, "width"), json_u32(value, "height"));
+                let count = tile_count(&name, width, height)?;
+                let tiles = match value.get("data") {
+                    Some(Value::Array(gids)) => gids.iter().map(|gid| gid.as_u64().unwrap_or(0) as u32).collect(),
+                    Some(Value::String(data)) => decode_tiles(data, json_str(value, "encoding"), json_str(value, "compression"), count)?,
+                    _ => return Err(invalid(format!("layer {:?} without data", name)))
+                };
+                check_tile_count(&name, &tiles, count)?;
+
+                layers.push(Layer::Tiles(TileLayer {
+                    width, height, tiles,
+                    offset: inherited.offset, opacity: inherited.opacity, visible: inherited.visible,
+                    properties: tmj_properties(value),
+                    name
+                }));
+            },
+            "objectgroup" => {
+                layers.push(Layer::Objects(ObjectLayer {
+                    objects: json_array(value, "objects").iter().map(|object| tmj_object(object, map_height)).collect(),
+                    offset: inherited.offset, opacity: inherited.opacity, visible: inherited.visible,
+                    properties: tmj_properties(value),
+                    name
+                }));
+            },
+            "group" => tmj_layers(json_array(value, "layers"), inherited, map_height, layers)?,
+            _ => {}
+        }
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn demo(extension: &str) -> TiledMap {
+        return TiledMap::load(crate::inner_path!(format!("maps/demo.{}", extension))).unwrap();
+    }
+
+    /// A 2x1 map with one tile layer whose `<data>` element is `data`.
+    fn tmx_with_data(layer_size: [u32; 2], data: &str) -> Result<TiledMap, TiledError> {
+        let source = format!(r#"<map orientation="orthogonal" width="2" height="1" tilewidth="16" tileheight="16">
+            <layer name="tiles" width="{}" height="{}">{}</layer></map>"#, layer_size[0], layer_size[1], data);
+        return TiledMap::parse_tmx(&source, Path::new(""));
+    }
+
+    fn zlib(bytes: &[u8]) -> String {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, bytes).unwrap();
+        return base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap());
+    }
+
+    #[test]
+    fn tmx_csv_layer_and_external_tileset() {
+        let map = demo("tmx");
+        assert_eq!((map.width, map.height, map.tile_width, map.tile_height), (10, 6, 16, 16));
+        assert_eq!(map.pixel_size(), [160.0, 96.0]);
+        assert_eq!(map.properties.get_str("music"), Some("theme.ogg"));
+
+        let tileset = &map.tilesets[0];
+        assert_eq!((tileset.first_gid, tileset.tile_count, tileset.columns), (1, 8, 4));
+        assert_eq!(tileset.image, crate::inner_path!("maps/tiles.png"));
+        assert_eq!(map.tile_info(3).unwrap().properties.get_bool("collision"), Some(true));
+        assert_eq!(map.tile_info(8).unwrap().objects[0].position, [2.0, 14.0]);
+        assert!(map.tile_info(1).is_none());
+
+        let ground = map.tile_layer("ground").unwrap();
+        assert_eq!(ground.gid(0, 4), 1);
+        assert_eq!(ground.gid(4, 4), 4);
+        assert_eq!(ground.gid(9, 5), 2);
+        assert_eq!(ground.gid(0, 0), 0);
+        assert_eq!(ground.gid(10, 0), 0);
+    }
+
+    #[test]
+    fn tmx_zlib_and_gzip_layers() {
+        let map = demo("tmx");
+        let walls = map.tile_layer("walls").unwrap();
+        assert_eq!(walls.tiles.iter().filter(|gid| **gid != 0).count(), 3);
+        assert_eq!((walls.gid(0, 3), walls.gid(7, 3), walls.gid(9, 3)), (5, 3, 5));
+
+        let decor = map.tile_layer("decor").unwrap();
+        assert_eq!(decor.gid(2, 3), 6);
+        assert_eq!(decor.tiles.iter().filter(|gid| **gid != 0).count(), 3);
+    }
+
+    #[test]
+    fn flip_flags_are_kept_on_gids() {
+        let map = demo("tmx");
+        let decor = map.tile_layer("decor").unwrap();
+
+        let horizontal = decor.gid(4, 3);
+        assert_eq!(horizontal & GID_MASK, 7);
+        assert!(horizontal & FLIPPED_HORIZONTALLY != 0 && horizontal & (FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY) == 0);
+
+        let rotated = decor.gid(5, 3);
+        assert_eq!(rotated & GID_MASK, 7);
+        assert_eq!(rotated & !GID_MASK, FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY);
+        assert_eq!(map.tileset(rotated).unwrap().name, "tiles");
+    }
+
+    #[test]
+    fn group_layers_pass_down_offset_and_opacity() {
+        let map = demo("tmx");
+        let names: Vec<_> = map.layers.iter().map(Layer::name).collect();
+        assert_eq!(names, ["ground", "walls", "decor", "spawns"]);
+
+        let walls = map.tile_layer("walls").unwrap();
+        assert_eq!(walls.offset, [0.0, 4.0]);
+        assert_eq!(walls.opacity, 0.5);
+        assert_eq!(walls.properties.get_bool("collision"), Some(true));
+    }
+
+    #[test]
+    fn object_layers_are_converted_to_y_up() {
+        let map = demo("tmx");
+        let objects = &map.object_layer("spawns").unwrap().objects;
+
+        let player = &objects[0];
+        assert_eq!((player.name.as_str(), player.class.as_str(), player.shape.clone()), ("player", "spawn", ObjectShape::Rectangle));
+        assert_eq!(player.bounds(), ([32.0, 32.0], [48.0, 48.0]));
+
+        assert_eq!((objects[1].shape.clone(), objects[1].position), (ObjectShape::Point, [120.0, 40.0]));
+        assert_eq!(objects[2].shape, ObjectShape::Polygon(vec![[0.0, 0.0], [32.0, 0.0], [32.0, -16.0]]));
+        assert_eq!(objects[2].bounds(), ([64.0, 64.0], [96.0, 80.0]));
+
+        let crate_object = &objects[3];
+        assert_eq!(crate_object.gid, Some(FLIPPED_HORIZONTALLY | 8));
+        assert_eq!(crate_object.bounds(), ([144.0, 32.0], [160.0, 48.0]));
+        assert_eq!(crate_object.properties.get_int("health"), Some(3));
+    }
+
+    #[test]
+    fn tmj_matches_tmx() {
+        let (tmx, tmj) = (demo("tmx"), demo("tmj"));
+        assert_eq!(tmj.layers, tmx.layers);
+        assert_eq!(tmj.tilesets, tmx.tilesets);
+        assert_eq!(tmj.properties, tmx.properties);
+    }
+
+    #[test]
+    fn tile_and_world_positions() {
+        let map = demo("tmx");
+        assert_eq!(map.tile_to_world(0, 5), [0.0, 0.0]);
+        assert_eq!(map.tile_to_world(2, 0), [32.0, 80.0]);
+        assert_eq!(map.world_to_tile([33.0, 81.0]), Some([2, 0]));
+        assert_eq!(map.world_to_tile([-1.0, 0.0]), None);
+        assert_eq!(map.world_to_tile([0.0, 96.0]), None);
+    }
+
+    #[test]
+    fn tileset_gid_ranges() {
+        let map = demo("tmx");
+        let tileset = &map.tilesets[0];
+        assert!(!tileset.contains(0) && tileset.contains(1) && tileset.contains(8) && !tileset.contains(9));
+        assert!(tileset.contains(FLIPPED_DIAGONALLY | 8));
+
+        let last = Tileset { first_gid: GID_MASK, tile_count: 1, ..tileset.clone() };
+        assert!(last.contains(GID_MASK) && !last.contains(1));
+        assert!(check_gids(&Tileset { first_gid: u32::MAX, tile_count: 2, ..tileset.clone() }).is_err());
+        assert!(check_gids(&Tileset { first_gid: 0, ..tileset.clone() }).is_err());
+    }
+
+    #[test]
+    fn tile_data_has_to_match_the_layer() {
+        assert!(tmx_with_data([2, 1], r#"<data encoding="csv">1,2</data>"#).is_ok());
+        assert!(tmx_with_data([2, 1], r#"<data encoding="csv">1,2,3</data>"#).is_err());
+        assert!(tmx_with_data([2, 1], r#"<data encoding="csv">1,x</data>"#).is_err());
+        assert!(tmx_with_data([2, 1], r#"<data encoding="base64">AQAAAAIAAAAD</data>"#).is_err());
+        assert!(tmx_with_data([2, 1], r#"<data encoding="base64" compression="zstd">AQAAAAIAAAA=</data>"#).is_err());
+    }
+
+    #[test]
+    fn huge_layers_are_rejected_without_overflow() {
+        assert!(matches!(tmx_with_data([70000, 70000], r#"<data encoding="csv">1,2</data>"#), Err(TiledError::Invalid(_))));
+        assert!(matches!(tmx_with_data([u32::MAX, u32::MAX], r#"<data encoding="csv">1</data>"#), Err(TiledError::Invalid(_))));
+
+        let source = r#"<map orientation="orthogonal" width="70000" height="70000" tilewidth="16" tileheight="16"></map>"#;
+        assert!(matches!(TiledMap::parse_tmx(source, Path::new("")), Err(TiledError::Invalid(_))));
+        let source = r#"{"orientation": "orthogonal", "width": 4000000000, "height": 1, "tilewidth": 16, "tileheight": 16}"#;
+        assert!(matches!(TiledMap::parse_tmj(source, Path::new("")), Err(TiledError::Invalid(_))));
+    }
+
+    #[test]
+    fn decompression_stops_at_the_layer_size() {
+        let layer = zlib(&[1, 0, 0, 0, 2, 0, 0, 0]);
+        assert!(tmx_with_data([2, 1], &format!(r#"<data encoding="base64" compression="zlib">{}</data>"#, layer)).is_ok());
+
+        // A megabyte of zeros only gets inflated as far as one gid past the layer.
+        let bomb = zlib(&vec![0; 1 << 20]);
+        assert!(decode_tiles(&bomb, Some("base64"), Some("zlib"), 2).unwrap().len() == 3);
+        assert!(tmx_with_data([2, 1], &format!(r#"<data encoding="base64" compression="zlib">{}</data>"#, bomb)).is_err());
+    }
+
+    #[test]
+    fn unsupported_maps() {
+        let parse = |attributes: &str| TiledMap::parse_tmx(&format!("<map {}></map>", attributes), Path::new(""));
+        assert!(parse(r#"orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16""#).is_ok());
+        assert!(parse(r#"orientation="isometric" width="2" height="2" tilewidth="16" tileheight="16""#).is_err());
+        assert!(parse(r#"orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16" infinite="1""#).is_err());
+        assert!(parse(r#"orientation="orthogonal" width="0" height="2" tilewidth="16" tileheight="16""#).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use std::path::Path;
+use glium::Surface;
+
+use super::camera::Camera2d;
+use super::context::Display;
+use super::texture::{SamplerSettings, Texture, TextureError, TextureFormat, TextureSettings};
+use super::tiled::{Layer, TileLayer, TiledError, TiledMap, FLIPPED_DIAGONALLY, FLIPPED_HORIZONTALLY, FLIPPED_VERTICALLY, GID_MASK};
+
+#[derive(Debug)]
+pub enum TilemapError {
+    Tiled(TiledError),
+    Texture(TextureError)
+}
+
+impl std::fmt::Display for TilemapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TilemapError::Tiled(error) => write!(f, "{}", error),
+            TilemapError::Texture(error) => write!(f, "failed to load tileset: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for TilemapError {}
+
+/// Tiles per side of a chunk, chunks are what gets culled.
+pub const CHUNK_TILES: u32 = 16;
+
+/// Which tiles block movement, by column and row with row 0 at the top like `TiledMap`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CollisionGrid {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: [f32; 2],
+    solid: Vec<bool>
+}
+
+impl CollisionGrid {
+    /// Panics if `width * height` tiles don't fit into memory, `TiledMap` checks that when it is parsed.
+    pub fn new(width: u32, height: u32, tile_size: [f32; 2]) -> CollisionGrid {
+        let count = (width as usize).checked_mul(height as usize).expect("collision grid is too large");
+        return CollisionGrid { width, height, tile_size, solid: vec![false; count] };
+    }
+
+    fn index(&self, column: u32, row: u32) -> usize { row as usize * self.width as usize + column as usize }
+
+    /// Every tile of `layer` that isn't empty is solid.
+    pub fn from_layer(map: &TiledMap, layer: &TileLayer) -> CollisionGrid {
+        let mut grid = CollisionGrid::new(map.width, map.height, [map.tile_width as f32, map.tile_height as f32]);
+        grid.add_layer(layer, |gid| gid & GID_MASK != 0);
+        return grid;
+    }
+
+    /// Tiles whose tileset sets the bool property `property` to true are solid, on every tile layer.
+    pub fn from_property(map: &TiledMap, property: &str) -> CollisionGrid {
+        let mut grid = CollisionGrid::new(map.width, map.height, [map.tile_width as f32, map.tile_height as f32]);
+        for layer in &map.layers {
+            let Layer::Tiles(layer) = layer else { continue; };
+            grid.add_layer(layer, |gid| map.tile_info(gid).and_then(|info| info.properties.get_bool(property)) == Some(true));
+        }
+        return grid;
+    }
+
+    fn add_layer(&mut self, layer: &TileLayer, solid: impl Fn(u32) -> bool) {
+        for row in 0..layer.height.min(self.height) {
+            for column in 0..layer.width.min(self.width) {
+                if solid(layer.gid(column, row)) { self.set_solid(column, row, true); }
+            }
+        }
+    }
+
+    /// Outside the grid counts as not solid.
+    pub fn is_solid(&self, column: u32, row: u32) -> bool {
+        return column < self.width && row < self.height && self.solid[self.index(column, row)];
+    }
+
+    pub fn set_solid(&mut self, column: u32, row: u32, solid: bool) {
+        if column < self.width && row < self.height {
+            let index = self.index(column, row);
+            self.solid[index] = solid;
+        }
+    }
+
+    /// Whether the tile under a world position is solid.
+    pub fn solid_at(&self, position: [f32; 2]) -> bool {
+        let column = (position[0] / self.tile_size[0]).floor();
+        let row = self.height as f32 - 1.0 - (position[1] / self.tile_size[1]).floor();
+        return column >= 0.0 && row >= 0.0 && self.is_solid(column as u32, row as u32);
+    }
+
+    /// Whether any solid tile overlaps the world rectangle between `min` and `max`.
+    pub fn overlaps(&self, min: [f32; 2], max: [f32; 2]) -> bool {
+        let first_column = (min[0] / self.tile_size[0]).floor().max(0.0) as u32;
+        let last_column = ((max[0] / self.tile_size[0]).ceil().max(0.0) as u32).min(self.width);
+        let bottom = (min[1] / self.tile_size[1]).floor().max(0.0) as u32;
+        let top = ((max[1] / self.tile_size[1]).ceil().max(0.0) as u32).min(self.height);
+
+        // Rows counted from the bottom here, flipped to Tiled's rows for the lookup.
+        return (bottom..top).any(|from_bottom| (first_column..last_column).any(|column| self.is_solid(column, self.height - 1 - from_bottom)));
+    }
+}
+
+#[derive(Copy, Clone)]
+struct TileVertex {
+    position: [f32; 2],
+    texture_coords: [f32; 2]
+}
+
+implement_vertex!(TileVertex, position, texture_coords);
+
+/// Static geometry of one chunk of one layer for one tileset.
+struct Chunk {
+    layer: usize,
+    tileset: usize,
+    min: [f32; 2],
+    max: [f32; 2],
+    vertices: glium::VertexBuffer<TileVertex>,
+    indices: glium::IndexBuffer<u32>
+}
+
+/// A Tiled map ready to draw, with one texture per tileset and the tile layers baked into static chunks.
+pub struct Tilemap {
+    pub map: TiledMap,
+    /// Built from tile layers with the bool property `collision` and from tiles with the bool property `collision`.
+    pub collision: CollisionGrid,
+    textures: Vec<Texture>,
+    chunks: Vec<Chunk>,
+    program: glium::Program
+}
+
+/// Texture coordinates of a tile's bottom left, bottom right, top right and top left corners.
+fn tile_corners(gid: u32, min: [f32; 2], max: [f32; 2]) -> [[f32; 2]; 4] {
+    // Corners as (right, down) fractions of the tile the way Tiled lays out images. Sampling undoes
+    // the flips in reverse order of how Tiled applies them: diagonal, horizontal, then vertical.
+    return [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]].map(|[mut s, mut t]| {
+        if gid & FLIPPED_VERTICALLY != 0 { t = 1.0 - t; }
+        if gid & FLIPPED_HORIZONTALLY != 0 { s = 1.0 - s; }
+        if gid & FLIPPED_DIAGONALLY != 0 { std::mem::swap(&mut s, &mut t); }
+        return [min[0] + (max[0] - min[0]) * s, max[1] - (max[1] - min[1]) * t];
+    });
+}
+
+impl Tilemap {
+    /// Loads a `.tmx` or `.tmj` map and its tileset images.
+    pub fn load(display: &Display, path: impl AsRef<Path>) -> Result<Tilemap, TilemapError> {
+        return Tilemap::new(display, TiledMap::load(path).map_err(TilemapError::Tiled)?);
+    }
+
+    /// Tileset images are loaded without mipmaps and with nearest filtering so tiles don't bleed into each other.
+    pub fn new(display: &Display, map: TiledMap) -> Result<Tilemap, TilemapError> {
+        let settings = TextureSettings { format: TextureFormat::Srgb, mipmaps: false, sampler: SamplerSettings::pixelated() };
+        let textures = map.tilesets.iter().map(|tileset| Texture::load(display, &tileset.image, &settings)).collect::<Result<Vec<_>, _>>().map_err(TilemapError::Texture)?;
+
+        let mut collision = CollisionGrid::from_property(&map, "collision");
+        for layer in &map.layers {
+            if let Layer::Tiles(layer) = layer {
+                if layer.properties.get_bool("collision") == Some(true) { collision.add_layer(layer, |gid| gid & GID_MASK != 0); }
+            }
+        }
+
+        let mut tilemap = Tilemap {
+            collision,
+            textures,
+            chunks: Vec::new(),
+            program: glium::Program::from_source(display, include_str!("../../shaders/tile.vs"), include_str!("../../shaders/tile.fs"), None).unwrap(),
+            map
+        };
+        tilemap.build_chunks(display);
+
+        return Ok(tilemap);
+    }
+
+    fn build_chunks(&mut self, display: &Display) {
+        let map = &self.map;
+        let mut chunks = Vec::new();
+
+        for (layer_index, layer) in map.layers.iter().enumerate() {
+            let Layer::Tiles(layer) = layer else { continue; };
+
+            for chunk_row in (0..layer.height).step_by(CHUNK_TILES as usize) {
+                for chunk_column in (0..layer.width).step_by(CHUNK_TILES as usize) {
+                    // Vertices and indices per tileset, most chunks only use one.
+                    let mut geometry: Vec<(Vec<TileVertex>, Vec<u32>)> = map.tilesets.iter().map(|_| (Vec::new(), Vec::new())).collect();
+
+                    for row in chunk_row..(chunk_row + CHUNK_TILES).min(layer.height) {
+                        for column in chunk_column..(chunk_column + CHUNK_TILES).min(layer.width) {
+                            let gid = layer.gid(column, row);
+                            let Some(tileset_index) = map.tilesets.iter().position(|tileset| tileset.contains(gid)) else { continue; };
+                            let tileset = &map.tilesets[tileset_index];
+                            let (image_width, image_height) = self.textures[tileset_index].dimensions();
+
+                            let local = (gid & GID_MASK) - tileset.first_gid;
+                            let x = tileset.margin + local % tileset.columns.max(1) * (tileset.tile_width + tileset.spacing);
+                            let y = tileset.margin + local / tileset.columns.max(1) * (tileset.tile_height + tileset.spacing);
+                            let uv_min = [x as f32 / image_width as f32, 1.0 - (y + tileset.tile_height) as f32 / image_height as f32];
+                            let uv_max = [(x + tileset.tile_width) as f32 / image_width as f32, 1.0 - y as f32 / image_height as f32];
+
+                            // Tiles larger than the map's grid stick out of the top of their cell like in Tiled.
+                            let [left, bottom] = map.tile_to_world(column, row);
+                            let (left, bottom) = (left + layer.offset[0], bottom + layer.offset[1]);
+                            let (right, top) = (left + tileset.tile_width as f32, bottom + tileset.tile_height as f32);
+
+                            let (vertices, indices) = &mut geometry[tileset_index];
+                            let first = vertices.len() as u32;
+                            let positions = [[left, bottom], [right, bottom], [right, top], [left, top]];
+                            for (position, texture_coords) in positions.into_iter().zip(tile_corners(gid, uv_min, uv_max)) {
+                                vertices.push(TileVertex { position, texture_coords });
+                            }
+                            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+                        }
+                    }
+
+                    for (tileset, (vertices, indices)) in geometry.into_iter().enumerate() {
+                        if vertices.is_empty() { continue; }
+                        let min = vertices.iter().fold([f32::MAX; 2], |min, vertex| [min[0].min(vertex.position[0]), min[1].min(vertex.position[1])]);
+                        let max = vertices.iter().fold([f32::MIN; 2], |max, vertex| [max[0].max(vertex.position[0]), max[1].max(vertex.position[1])]);
+
+                        chunks.push(Chunk {
+                            layer: layer_index,
+                            tileset,
+                            min,
+                            max,
+                            vertices: glium::VertexBuffer::immutable(display, &vertices).unwrap(),
+                            indices: glium::IndexBuffer::immutable(display, glium::index::PrimitiveType::TrianglesList, &indices).unwrap()
+                        });
+                    }
+                }
+            }
+        }
+
+        self.chunks = chunks;
+    }
+
+    /// Draws the visible tile layers in order, skipping chunks outside the camera's view. Returns how
+    /// many chunks were drawn. Object layers aren't drawn, they are for gameplay code.
+    pub fn render<S: Surface>(&self, target: &mut S, camera: &Camera2d) -> u32 {
+        let (width, height) = target.get_dimensions();
+        let viewport = [width, height];
+        let view_projection = camera.matrix(viewport);
+
+        // The camera may be rotated, so the view is bounded by all four screen corners.
+        let corners = [[0.0, 0.0], [width as f32, 0.0], [0.0, height as f32], [width as f32, height as f32]].map(|corner| camera.screen_to_world(corner, viewport));
+        let view_min = corners.iter().fold([f32::MAX; 2], |min, corner| [min[0].min(corner[0]), min[1].min(corner[1])]);
+        let view_max = corners.iter().fold([f32::MIN; 2], |max, corner| [max[0].max(corner[0]), max[1].max(corner[1])]);
+
+        let params = glium::DrawParameters {
+            blend: glium::draw_parameters::Blend::alpha_blending(),
+            .. Default::default()
+        };
+
+        let mut drawn = 0;
+        for chunk in &self.chunks {
+            let Layer::Tiles(layer) = &self.map.layers[chunk.layer] else { continue; };
+            if !layer.visible || layer.opacity <= 0.0 { continue; }
+            if chunk.max[0] < view_min[0] || chunk.min[0] > view_max[0] || chunk.max[1] < view_min[1] || chunk.min[1] > view_max[1] { continue; }
+
+            let uniforms = uniform! {
+                view_projection: view_projection,
+                tileset: &self.textures[chunk.tileset],
+                opacity: layer.opacity
+            };
+            target.draw(&chunk.vertices, &chunk.indices, &self.program, &uniforms, &params).unwrap();
+            drawn += 1;
+        }
+
+        return drawn;
+    }
+
+    /// Texture of a tileset, in the order of `map.tilesets`.
+    pub fn texture(&self, tileset: usize) -> Option<&Texture> { self.textures.get(tileset) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn demo() -> TiledMap {
+        return TiledMap::load(crate::inner_path!("maps/demo.tmx")).unwrap();
+    }
+
+    #[test]
+    fn tile_corners_follow_flip_flags() {
+        let corners = |flags: u32| tile_corners(flags | 1, [0.0, 0.0], [1.0, 1.0]);
+        assert_eq!(corners(0), [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+        assert_eq!(corners(FLIPPED_HORIZONTALLY), [[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]]);
+        assert_eq!(corners(FLIPPED_VERTICALLY), [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+        assert_eq!(corners(FLIPPED_DIAGONALLY), [[1.0, 1.0], [1.0, 0.0], [0.0, 0.0], [0.0, 1.0]]);
+        // Tiled's 90 degree clockwise rotation.
+        assert_eq!(corners(FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY), [[1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]]);
+        assert_eq!(tile_corners(1, [0.25, 0.5], [0.5, 1.0]), [[0.25, 0.5], [0.5, 0.5], [0.5, 1.0], [0.25, 1.0]]);
+    }
+
+    #[test]
+    fn collision_from_layer_and_property() {
+        let map = demo();
+        let walls = CollisionGrid::from_layer(&map, map.tile_layer("walls").unwrap());
+        let tiles = CollisionGrid::from_property(&map, "collision");
+        assert_eq!(walls, tiles);
+        assert_eq!((walls.width, walls.height, walls.tile_size), (10, 6, [16.0, 16.0]));
+
+        let solid: Vec<_> = (0..6).flat_map(|row| (0..10).map(move |column| (column, row))).filter(|(column, row)| walls.is_solid(*column, *row)).collect();
+        assert_eq!(solid, [(0, 3), (7, 3), (9, 3)]);
+        assert!(!walls.is_solid(10, 3) && !walls.is_solid(0, 6));
+    }
+
+    #[test]
+    fn collision_in_world_space() {
+        let mut grid = CollisionGrid::from_property(&demo(), "collision");
+        assert!(grid.solid_at([8.0, 40.0]) && grid.solid_at([127.9, 32.0]));
+        assert!(!grid.solid_at([8.0, 48.0]) && !grid.solid_at([-8.0, 40.0]) && !grid.solid_at([8.0, -8.0]));
+
+        assert!(grid.overlaps([10.0, 30.0], [20.0, 35.0]));
+        assert!(!grid.overlaps([16.0, 0.0], [112.0, 96.0]));
+        assert!(grid.overlaps([16.0, 0.0], [112.1, 96.0]));
+        assert!(grid.overlaps([-100.0, -100.0], [1000.0, 1000.0]));
+
+        grid.set_solid(7, 3, false);
+        grid.set_solid(100, 100, true);
+        assert!(!grid.solid_at([120.0, 40.0]));
+    }
+}
@@ -61,11 +61,11 @@ impl DivAssign for Vec3 {
 #[derive(Default)]
 
 pub struct Shape {
-    verticles: Vec<Vertex>,
-    indices: Vec<u32>,
-    texture: Option<glium::texture::Texture2d>,
-    vertex_shader: Option<String>,
-    fragment_shader: Option<String>
+    pub verticles: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub texture: Option<glium::texture::Texture2d>,
+    pub vertex_shader: Option<String>,
+    pub fragment_shader: Option<String>
 }
 
 #[derive(Default, Copy, Clone)]
@@ -75,7 +75,7 @@ pub struct Vertex {
     texture_coordinates: [f32; 2]
 }
 
-trait VertexMath {
+pub trait VertexMath {
     fn rotate_around(&mut self, rotation: Vec3, rotation_point: Vec3);
     fn move_to(&mut self, position: Vec3);
     fn get_position(&self) -> Vec3;
@@ -89,11 +89,11 @@ impl VertexMath for Vertex {
     }
 
     fn move_to(&mut self, position: Vec3) {
-        self.position = Some(position.into());
+        self.position = Some(position);
     }
 
     fn get_position(&self) -> Vec3 {
-        if self.position.is_some() { self.position.unwrap().into() } else { self.default_position.into() }
+        self.position.unwrap_or(self.default_position)
     }
 
     fn reset(&mut self) {
@@ -104,14 +104,18 @@ impl VertexMath for Vertex {
 #[derive(Default, Copy, Clone)]
 pub struct RenderVertex {
     pub position: [f32; 3],
-    pub texture_coords: [f32; 2]
+    pub texture_coords: [f32; 2],
+    /// Unit length, facing away from the front of the surface.
+    pub normal: [f32; 3]
 }
 
 impl From<Vertex> for RenderVertex {
     fn from(mut src: Vertex) -> RenderVertex {
         RenderVertex {
             position: (*src.position.get_or_insert(src.default_position)).into(),
-            texture_coords: src.texture_coordinates
+            texture_coords: src.texture_coordinates,
+            // `Vertex` has no normal, so it faces the viewer of a scene drawn in clip space.
+            normal: [0.0, 0.0, -1.0]
         }
     }
 }
@@ -124,6 +128,7 @@ impl Rotate for Vec<RenderVertex> {
     fn rotate(&mut self, rotation: Vec3, rotation_point: Vec3) {
         for vertex in self {
             vertex.position = <[f32; 3]>::from(math::rotate(Vec3::from(vertex.position) - rotation_point, rotation) + rotation_point);
+            vertex.normal = math::rotate(vertex.normal.into(), rotation).into();
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,1138 @@
+use std::collections::HashMap;
+use std::path::Path;
+use glium::Surface;
+use serde::{Deserialize, Serialize};
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+use crate::input::gamepad::GamepadButton;
+use crate::input::state::Input;
+use super::atlas::{Atlas, AtlasRegion};
+use super::camera::Camera2d;
+use super::context::Display;
+use super::sprite::{Sprite, SpriteBatch, SpriteRenderer};
+use super::text::{FontId, TextAlign, TextError, TextRenderer, TextStyle, DEFAULT_FONT};
+use super::texture::{Texture, TextureFormat};
+
+#[derive(Debug)]
+pub enum StylesheetError {
+    Io(std::io::Error),
+    Parse(toml::de::Error)
+}
+
+impl std::fmt::Display for StylesheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StylesheetError::Io(error) => write!(f, "failed to read stylesheet: {}", error),
+            StylesheetError::Parse(error) => write!(f, "failed to parse stylesheet: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for StylesheetError {}
+
+/// Width or height of a node. Written as `"Auto"`, a number of pixels or a percentage like `"50%"` of the
+/// parent's content in stylesheets.
+#[derive(Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(try_from = "SizeValue")]
+pub enum Size {
+    /// Fits the content.
+    Auto,
+    Pixels(f32),
+    Percent(f32)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeValue {
+    Pixels(f32),
+    Text(String)
+}
+
+impl TryFrom<SizeValue> for Size {
+    type Error = String;
+
+    fn try_from(value: SizeValue) -> Result<Size, String> {
+        return match value {
+            SizeValue::Pixels(pixels) => Ok(Size::Pixels(pixels)),
+            SizeValue::Text(text) if text == "Auto" => Ok(Size::Auto),
+            SizeValue::Text(text) => text.strip_suffix('%').and_then(|percent| percent.trim().parse().ok())
+                .map(Size::Percent)
+                .ok_or_else(|| format!("expected \"Auto\", a number or a percentage, found \"{}\"", text))
+        };
+    }
+}
+
+impl Size {
+    fn resolve(self, available: f32) -> Option<f32> {
+        return match self {
+            Size::Auto => None,
+            Size::Pixels(pixels) => Some(pixels),
+            Size::Percent(percent) => Some(available * percent / 100.0)
+        };
+    }
+}
+
+/// Axis children are placed along.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Row,
+    Column
+}
+
+/// Where children go along the direction when there is room left.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    /// The first child at the start, the last at the end and the room between them split evenly.
+    SpaceBetween
+}
+
+/// Where children go across the direction.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    /// Children with an `Auto` size fill the parent.
+    Stretch
+}
+
+/// Takes a node out of its parent's layout and pins it to a point of the parent instead.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Anchor {
+    /// Point of the parent, (0, 0) is its top left and (1, 1) its bottom right.
+    pub point: [f32; 2],
+    /// Point of the node that sits on `point`, in the same units.
+    pub pivot: [f32; 2],
+    /// Pixels added after anchoring, y down.
+    pub offset: [f32; 2]
+}
+
+impl Default for Anchor {
+    fn default() -> Anchor {
+        return Anchor { point: [0.0, 0.0], pivot: [0.0, 0.0], offset: [0.0, 0.0] };
+    }
+}
+
+impl Anchor {
+    /// Pins the same point of the node and its parent, e.g. (1, 0) keeps it in the top right corner.
+    pub fn new(point: [f32; 2], offset: [f32; 2]) -> Anchor {
+        return Anchor { point, pivot: point, offset };
+    }
+}
+
+/// Everything about how a node is laid out and drawn, after the stylesheet is applied.
+/// Sides are given as left, top, right and bottom.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Style {
+    pub direction: Direction,
+    pub justify: Justify,
+    pub align: Align,
+    pub width: Size,
+    pub height: Size,
+    /// Share of the room left along the parent's direction that this node takes.
+    pub grow: f32,
+    pub padding: [f32; 4],
+    pub margin: [f32; 4],
+    /// Space between children.
+    pub gap: f32,
+    pub anchor: Option<Anchor>,
+    /// Fill color, or the tint of `image`.
+    pub background: [f32; 4],
+    /// Atlas region drawn as a nine-slice behind the node.
+    pub image: Option<String>,
+    /// Pixels of `image` at each side that keep their size while the middle stretches.
+    pub slice: [f32; 4],
+    /// Text color and the tint of image widgets, inherited by children like the other text settings.
+    pub color: [f32; 4],
+    /// Name given to `Ui::load_font`, the built in font when unset.
+    pub font: Option<String>,
+    pub font_size: f32,
+    pub text_align: TextAlign
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        return Style {
+            direction: Direction::Column,
+            justify: Justify::Start,
+            align: Align::Stretch,
+            width: Size::Auto,
+            height: Size::Auto,
+            grow: 0.0,
+            padding: [0.0; 4],
+            margin: [0.0; 4],
+            gap: 0.0,
+            anchor: None,
+            background: [0.0; 4],
+            image: None,
+            slice: [0.0; 4],
+            color: [1.0, 1.0, 1.0, 1.0],
+            font: None,
+            font_size: 16.0,
+            text_align: TextAlign::Center
+        };
+    }
+}
+
+impl Style {
+    /// Default style with the text settings of `parent`.
+    fn inherit(parent: &Style) -> Style {
+        return Style {
+            color: parent.color,
+            font: parent.font.clone(),
+            font_size: parent.font_size,
+            text_align: parent.text_align,
+            .. Style::default()
+        };
+    }
+
+    /// Overrides everything `rule` sets.
+    pub fn apply(&mut self, rule: &StyleRule) {
+        if let Some(direction) = rule.direction { self.direction = direction; }
+        if let Some(justify) = rule.justify { self.justify = justify; }
+        if let Some(align) = rule.align { self.align = align; }
+        if let Some(width) = rule.width { self.width = width; }
+        if let Some(height) = rule.height { self.height = height; }
+        if let Some(grow) = rule.grow { self.grow = grow; }
+        if let Some(padding) = rule.padding { self.padding = padding; }
+        if let Some(margin) = rule.margin { self.margin = margin; }
+        if let Some(gap) = rule.gap { self.gap = gap; }
+        if let Some(anchor) = rule.anchor { self.anchor = Some(anchor); }
+        if let Some(background) = rule.background { self.background = background; }
+        if let Some(image) = &rule.image { self.image = Some(image.clone()); }
+        if let Some(slice) = rule.slice { self.slice = slice; }
+        if let Some(color) = rule.color { self.color = color; }
+        if let Some(font) = &rule.font { self.font = Some(font.clone()); }
+        if let Some(font_size) = rule.font_size { self.font_size = font_size; }
+        if let Some(text_align) = rule.text_align { self.text_align = text_align; }
+    }
+}
+
+/// Part of a `Style`, as written in a stylesheet or set on a single node with `Ui::style_mut`.
+#[derive(Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleRule {
+    pub direction: Option<Direction>,
+    pub justify: Option<Justify>,
+    pub align: Option<Align>,
+    pub width: Option<Size>,
+    pub height: Option<Size>,
+    pub grow: Option<f32>,
+    pub padding: Option<[f32; 4]>,
+    pub margin: Option<[f32; 4]>,
+    pub gap: Option<f32>,
+    pub anchor: Option<Anchor>,
+    pub background: Option<[f32; 4]>,
+    pub image: Option<String>,
+    pub slice: Option<[f32; 4]>,
+    pub color: Option<[f32; 4]>,
+    pub font: Option<String>,
+    pub font_size: Option<f32>,
+    pub text_align: Option<TextAlign>
+}
+
+/// Style rules by selector, loaded from a TOML file with one table per selector:
+///
+/// ```toml
+/// [button]
+/// background = [0.2, 0.2, 0.3, 1.0]
+/// padding = [12, 6, 12, 6]
+///
+/// ["button:focus"]
+/// background = [0.3, 0.4, 0.7, 1.0]
+///
+/// [".title"]
+/// font_size = 32
+/// ```
+///
+/// Selectors are a widget kind (`panel`, `label`, `button`, `image`), a class (`.name`) or a node name
+/// (`#name`), optionally followed by a state: `:hover`, `:focus`, `:pressed` or `:disabled`. Rules for the
+/// kind are applied first, then those for classes in the order they were added and then the name, each
+/// followed by their state rules.
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(transparent)]
+pub struct Stylesheet {
+    pub rules: HashMap<String, StyleRule>
+}
+
+impl Stylesheet {
+    pub fn load(path: impl AsRef<Path>) -> Result<Stylesheet, StylesheetError> {
+        return Stylesheet::parse(&std::fs::read_to_string(path).map_err(StylesheetError::Io)?);
+    }
+
+    pub fn parse(source: &str) -> Result<Stylesheet, StylesheetError> {
+        return toml::from_str(source).map_err(StylesheetError::Parse);
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Widget {
+    /// Holds other nodes.
+    Panel,
+    Label(String),
+    /// Can be focused and clicked.
+    Button(String),
+    /// Atlas region stretched over the node.
+    Image(String)
+}
+
+impl Widget {
+    fn kind(&self) -> &'static str {
+        return match self {
+            Widget::Panel => "panel",
+            Widget::Label(_) => "label",
+            Widget::Button(_) => "button",
+            Widget::Image(_) => "image"
+        };
+    }
+}
+
+/// A node of a `Ui`, stays valid until the node is removed.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId(usize);
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum UiEventKind {
+    /// Released the mouse over the button it went down on, or activated the focused button with Enter,
+    /// Space or the gamepad's south button.
+    Click,
+    Focus,
+    Blur,
+    /// The cursor moved onto the button.
+    Enter,
+    Leave
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct UiEvent {
+    pub node: NodeId,
+    pub kind: UiEventKind
+}
+
+type Callback = Box<dyn FnMut(&mut Ui, &UiEvent)>;
+
+struct Node {
+    widget: Widget,
+    name: Option<String>,
+    classes: Vec<String>,
+    /// Applied after the stylesheet.
+    inline: StyleRule,
+    style: Style,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// x, y, width and height in pixels before `scale` from the top left of the screen, from the last layout.
+    rect: [f32; 4],
+    visible: bool,
+    enabled: bool,
+    callbacks: Vec<(UiEventKind, Callback)>
+}
+
+fn contains(rect: [f32; 4], [x, y]: [f32; 2]) -> bool {
+    return x >= rect[0] && x < rect[0] + rect[2] && y >= rect[1] && y < rect[1] + rect[3];
+}
+
+/// Margins or padding before and after the content along `axis`, 0 for x and 1 for y.
+fn sides(sides: [f32; 4], axis: usize) -> (f32, f32) { (sides[axis], sides[axis + 2]) }
+
+/// Sprites drawing `region` over `rect`, a screen rectangle with y up, with the `slice` borders kept at
+/// their size and the rest stretched.
+fn nine_slice(rect: [f32; 4], region: &AtlasRegion, slice: [f32; 4], color: [f32; 4], layer: i32) -> Vec<Sprite> {
+    let [x, bottom, width, height] = rect;
+    let [mut left, mut top, mut right, mut bottom_border] = slice;
+    // Borders of a node smaller than them shrink together.
+    let horizontal = (width / (left + right).max(f32::EPSILON)).min(1.0);
+    let vertical = (height / (top + bottom_border).max(f32::EPSILON)).min(1.0);
+    let (du, dv) = ((region.uv_max[0] - region.uv_min[0]) / region.width.max(1) as f32, (region.uv_max[1] - region.uv_min[1]) / region.height.max(1) as f32);
+
+    let columns = [x, x + left * horizontal, x + width - right * horizontal, x + width];
+    let rows = [bottom, bottom + bottom_border * vertical, bottom + height - top * vertical, bottom + height];
+    left *= du;
+    right *= du;
+    top *= dv;
+    bottom_border *= dv;
+    let us = [region.uv_min[0], region.uv_min[0] + left, region.uv_max[0] - right, region.uv_max[0]];
+    let vs = [region.uv_min[1], region.uv_min[1] + bottom_border, region.uv_max[1] - top, region.uv_max[1]];
+
+    let mut sprites = Vec::with_capacity(9);
+    for row in 0..3 {
+        for column in 0..3 {
+            let size = [columns[column + 1] - columns[column], rows[row + 1] - rows[row]];
+            if size[0] <= 0.0 || size[1] <= 0.0 { continue; }
+            sprites.push(Sprite {
+                origin: [0.0, 0.0],
+                uv_min: [us[column], vs[row]],
+                uv_max: [us[column + 1], vs[row + 1]],
+                color,
+                layer,
+                .. Sprite::new([columns[column], rows[row]], size)
+            });
+        }
+    }
+    return sprites;
+}
+
+/// Retained widget tree for menus and HUDs. Nodes are added once, laid out in rows and columns like
+/// flexbox, styled by a `Stylesheet` and drawn in screen pixels times `scale` by `render`. `update` feeds it the frame's
+/// input: buttons are clicked with the mouse, and focus moves between them with the arrow keys, Tab
+/// or the gamepad's d-pad.
+pub struct Ui {
+    /// Multiplies every size, e.g. the window's scale factor. Layouts and `rect`s are in pixels before
+    /// it is applied.
+    pub scale: f32,
+    nodes: Vec<Option<Node>>,
+    root: NodeId,
+    pub stylesheet: Stylesheet,
+    atlas: Option<Atlas>,
+    /// Deepest node under the cursor that has something drawn.
+    hovered: Option<NodeId>,
+    /// Button under the cursor.
+    hovered_button: Option<NodeId>,
+    /// Button the mouse went down on.
+    pressed: Option<NodeId>,
+    focused: Option<NodeId>,
+    events: Vec<UiEvent>,
+    /// Created by `new`, or by the first `render` of a UI from `headless`.
+    graphics: Option<UiGraphics>,
+    text: TextRenderer,
+    fonts: HashMap<String, FontId>,
+    default_font: FontId
+}
+
+/// What a `Ui` draws with.
+struct UiGraphics {
+    sprites: SpriteRenderer,
+    white: Texture
+}
+
+impl UiGraphics {
+    fn new(display: &Display) -> UiGraphics {
+        return UiGraphics { sprites: SpriteRenderer::new(display), white: Texture::solid(display, [255; 4], TextureFormat::Srgb).unwrap() };
+    }
+}
+
+impl Ui {
+    pub fn new(display: &Display) -> Ui {
+        return Ui { graphics: Some(UiGraphics::new(display)), text: TextRenderer::new(display), .. Ui::headless() };
+    }
+
+    /// A UI that doesn't touch the GPU until its first `render`, to build and lay out without a window.
+    pub fn headless() -> Ui {
+        let mut text = TextRenderer::headless();
+        let default_font = text.add_font(DEFAULT_FONT.to_vec()).unwrap();
+
+        let mut ui = Ui {
+            scale: 1.0,
+            nodes: Vec::new(),
+            root: NodeId(0),
+            stylesheet: Stylesheet::default(),
+            atlas: None,
+            hovered: None,
+            hovered_button: None,
+            pressed: None,
+            focused: None,
+            events: Vec::new(),
+            graphics: None,
+            text,
+            fonts: HashMap::new(),
+            default_font
+        };
+        ui.root = ui.insert(None, Widget::Panel);
+        return ui;
+    }
+
+    pub fn load_stylesheet(&mut self, path: impl AsRef<Path>) -> Result<(), StylesheetError> {
+        self.stylesheet = Stylesheet::load(path)?;
+        return Ok(());
+    }
+
+    /// Atlas that `image` styles and image widgets take their regions from.
+    pub fn set_atlas(&mut self, atlas: Atlas) { self.atlas = Some(atlas); }
+
+    /// Loads a font that styles refer to by `name`, see `TextRenderer::load_font`.
+    pub fn load_font(&mut self, display: &Display, name: &str, path: impl AsRef<Path>) -> Result<(), TextError> {
+        let font = self.text.load_font(display, path)?;
+        self.fonts.insert(name.to_string(), font);
+        return Ok(());
+    }
+
+    /// Panel covering the whole screen that everything else is added to.
+    pub fn root(&self) -> NodeId { self.root }
+
+    fn insert(&mut self, parent: Option<NodeId>, widget: Widget) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Some(Node {
+            widget,
+            name: None,
+            classes: Vec::new(),
+            inline: StyleRule::default(),
+            style: Style::default(),
+            parent,
+            children: Vec::new(),
+            rect: [0.0; 4],
+            visible: true,
+            enabled: true,
+            callbacks: Vec::new()
+        }));
+        if let Some(parent) = parent { self.node_mut(parent).children.push(id); }
+        return id;
+    }
+
+    fn node(&self, id: NodeId) -> &Node { self.nodes[id.0].as_ref().expect("node was removed") }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node { self.nodes[id.0].as_mut().expect("node was removed") }
+
+    /// Adds `widget` as the last child of `parent`.
+    pub fn add(&mut self, parent: NodeId, widget: Widget) -> NodeId { self.insert(Some(parent), widget) }
+
+    /// Removes `node` and everything below it.
+    pub fn remove(&mut self, node: NodeId) {
+        if node == self.root { return; }
+        if let Some(parent) = self.node(node).parent { self.node_mut(parent).children.retain(|child| *child != node); }
+
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            if let Some(removed) = self.nodes[id.0].take() { stack.extend(removed.children); }
+            for reference in [&mut self.hovered, &mut self.hovered_button, &mut self.pressed, &mut self.focused] {
+                if *reference == Some(id) { *reference = None; }
+            }
+        }
+    }
+
+    pub fn contains(&self, node: NodeId) -> bool { self.nodes.get(node.0).is_some_and(Option::is_some) }
+
+    pub fn widget(&self, node: NodeId) -> &Widget { &self.node(node).widget }
+
+    pub fn children(&self, node: NodeId) -> &[NodeId] { &self.node(node).children }
+
+    /// Changes the text of a label or button.
+    pub fn set_text(&mut self, node: NodeId, text: &str) {
+        match &mut self.node_mut(node).widget {
+            Widget::Label(current) | Widget::Button(current) => { current.clear(); current.push_str(text); },
+            _ => ()
+        }
+    }
+
+    /// Name for `#name` selectors.
+    pub fn set_name(&mut self, node: NodeId, name: &str) { self.node_mut(node).name = Some(name.to_string()); }
+
+    pub fn add_class(&mut self, node: NodeId, class: &str) {
+        let classes = &mut self.node_mut(node).classes;
+        if !classes.iter().any(|other| other == class) { classes.push(class.to_string()); }
+    }
+
+    pub fn remove_class(&mut self, node: NodeId, class: &str) { self.node_mut(node).classes.retain(|other| other != class); }
+
+    /// Style applied to this node on top of the stylesheet.
+    pub fn style_mut(&mut self, node: NodeId) -> &mut StyleRule { &mut self.node_mut(node).inline }
+
+    /// Style from the last layout.
+    pub fn style(&self, node: NodeId) -> &Style { &self.node(node).style }
+
+    /// Hidden nodes and their children take no room, aren't drawn and can't be focused.
+    pub fn set_visible(&mut self, node: NodeId, visible: bool) {
+        self.node_mut(node).visible = visible;
+        if !visible && self.focused.is_some_and(|focused| self.is_inside(focused, node)) { self.focus(None); }
+    }
+
+    pub fn is_visible(&self, node: NodeId) -> bool { self.node(node).visible }
+
+    /// Disabled buttons can't be focused or clicked and are styled with `:disabled`.
+    pub fn set_enabled(&mut self, node: NodeId, enabled: bool) {
+        self.node_mut(node).enabled = enabled;
+        if !enabled && self.focused == Some(node) { self.focus(None); }
+    }
+
+    /// x, y, width and height in pixels before `scale` from the top left of the screen, as of the last layout.
+    pub fn rect(&self, node: NodeId) -> [f32; 4] { self.node(node).rect }
+
+    /// Calls `callback` for every event of `kind` on `node`. The callback gets the `Ui` to change it,
+    /// e.g. to hide a menu when one of its buttons is clicked.
+    pub fn on(&mut self, node: NodeId, kind: UiEventKind, callback: impl FnMut(&mut Ui, &UiEvent) + 'static) {
+        self.node_mut(node).callbacks.push((kind, Box::new(callback)));
+    }
+
+    pub fn on_click(&mut self, node: NodeId, callback: impl FnMut(&mut Ui, &UiEvent) + 'static) { self.on(node, UiEventKind::Click, callback); }
+
+    pub fn focused(&self) -> Option<NodeId> { self.focused }
+
+    /// Moves the focus, e.g. to the first button when a menu opens.
+    pub fn focus(&mut self, node: Option<NodeId>) {
+        if node == self.focused { return; }
+        if let Some(old) = self.focused { self.events.push(UiEvent { node: old, kind: UiEventKind::Blur }); }
+        if let Some(new) = node { self.events.push(UiEvent { node: new, kind: UiEventKind::Focus }); }
+        self.focused = node;
+    }
+
+    /// Events of the last `update`, after their callbacks ran.
+    pub fn events(&self) -> &[UiEvent] { &self.events }
+
+    /// The cursor is over something the UI draws, the game should ignore the mouse.
+    pub fn wants_mouse(&self) -> bool { self.hovered.is_some() || self.pressed.is_some() }
+
+    fn is_inside(&self, node: NodeId, ancestor: NodeId) -> bool {
+        let mut current = Some(node);
+        while let Some(id) = current {
+            if id == ancestor { return true; }
+            current = self.node(id).parent;
+        }
+        return false;
+    }
+
+    fn is_button(&self, node: NodeId) -> bool {
+        let node = self.node(node);
+        return matches!(node.widget, Widget::Button(_)) && node.enabled;
+    }
+
+    /// Deepest visible node at `point` that draws something, later siblings first since they are on top.
+    fn hit(&self, id: NodeId, point: [f32; 2]) -> Option<NodeId> {
+        let node = self.node(id);
+        if !node.visible { return None; }
+        if let Some(hit) = node.children.iter().rev().find_map(|child| self.hit(*child, point)) { return Some(hit); }
+
+        let draws = matches!(node.widget, Widget::Button(_) | Widget::Image(_)) || node.style.background[3] > 0.0 || node.style.image.is_some();
+        return (id != self.root && draws && contains(node.rect, point)).then_some(id);
+    }
+
+    /// Visible, enabled buttons in tree order.
+    fn focusable(&self) -> Vec<NodeId> {
+        let mut buttons = Vec::new();
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            if !node.visible { continue; }
+            if self.is_button(id) { buttons.push(id); }
+            stack.extend(node.children.iter().rev());
+        }
+        return buttons;
+    }
+
+    /// Button nearest to the focused one in `direction`, y down, favoring ones straight ahead.
+    fn neighbor(&self, direction: [f32; 2]) -> Option<NodeId> {
+        let buttons = self.focusable();
+        let Some(current) = self.focused.filter(|focused| buttons.contains(focused)) else { return buttons.first().copied(); };
+
+        let center = |rect: [f32; 4]| [rect[0] + rect[2] / 2.0, rect[1] + rect[3] / 2.0];
+        let from = center(self.node(current).rect);
+        return buttons.into_iter()
+            .filter(|button| *button != current)
+            .filter_map(|button| {
+                let to = center(self.node(button).rect);
+                let offset = [to[0] - from[0], to[1] - from[1]];
+                let along = offset[0] * direction[0] + offset[1] * direction[1];
+                let across = (offset[0] * direction[1] - offset[1] * direction[0]).abs();
+                (along > 0.0).then_some((along + 2.0 * across, button))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, button)| button);
+    }
+
+    /// Handles this frame's input and runs the callbacks of what happened. Hit testing uses the layout
+    /// of the last `render`.
+    pub fn update(&mut self, input: &Input) {
+        self.events.clear();
+
+        let cursor = input.cursor_position().map(|[x, y]| [x / self.scale, y / self.scale]);
+        self.hovered = cursor.and_then(|cursor| self.hit(self.root, cursor));
+        let mut hovered_button = self.hovered;
+        while let Some(id) = hovered_button.filter(|id| !self.is_button(*id)) { hovered_button = self.node(id).parent; }
+        if hovered_button != self.hovered_button {
+            if let Some(old) = self.hovered_button { self.events.push(UiEvent { node: old, kind: UiEventKind::Leave }); }
+            if let Some(new) = hovered_button { self.events.push(UiEvent { node: new, kind: UiEventKind::Enter }); }
+            self.hovered_button = hovered_button;
+        }
+
+        if input.mouse_pressed(MouseButton::Left) {
+            self.pressed = hovered_button;
+            if hovered_button.is_some() { self.focus(hovered_button); }
+        }
+        if input.mouse_released(MouseButton::Left) {
+            if let Some(pressed) = self.pressed.take() {
+                if hovered_button == Some(pressed) { self.events.push(UiEvent { node: pressed, kind: UiEventKind::Click }); }
+            }
+        }
+
+        let key = |keys: &[KeyCode], button: GamepadButton| keys.iter().any(|key| input.key_pressed(*key)) || input.gamepads.pressed(button);
+        let direction = if key(&[KeyCode::ArrowUp], GamepadButton::DPadUp) { Some([0.0, -1.0]) }
+            else if key(&[KeyCode::ArrowDown], GamepadButton::DPadDown) { Some([0.0, 1.0]) }
+            else if key(&[KeyCode::ArrowLeft], GamepadButton::DPadLeft) { Some([-1.0, 0.0]) }
+            else if key(&[KeyCode::ArrowRight], GamepadButton::DPadRight) { Some([1.0, 0.0]) }
+            else { None };
+        if let Some(direction) = direction {
+            if let Some(next) = self.neighbor(direction) { self.focus(Some(next)); }
+        }
+        if input.key_pressed(KeyCode::Tab) {
+            let buttons = self.focusable();
+            if !buttons.is_empty() {
+                let backwards = input.key_held(KeyCode::ShiftLeft) || input.key_held(KeyCode::ShiftRight);
+                let next = match self.focused.and_then(|focused| buttons.iter().position(|button| *button == focused)) {
+                    Some(index) if backwards => (index + buttons.len() - 1) % buttons.len(),
+                    Some(index) => (index + 1) % buttons.len(),
+                    None if backwards => buttons.len() - 1,
+                    None => 0
+                };
+                self.focus(Some(buttons[next]));
+            }
+        }
+        if key(&[KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Space], GamepadButton::South) {
+            if let Some(focused) = self.focused.filter(|focused| self.is_button(*focused)) {
+                self.events.push(UiEvent { node: focused, kind: UiEventKind::Click });
+            }
+        }
+
+        self.dispatch();
+    }
+
+    fn dispatch(&mut self) {
+        let mut index = 0;
+        // Callbacks can cause more events, e.g. by moving the focus, which are handled in the same pass.
+        while index < self.events.len() {
+            let event = self.events[index];
+            index += 1;
+            let Some(node) = self.nodes.get_mut(event.node.0).and_then(Option::as_mut) else { continue; };
+
+            let mut callbacks = std::mem::take(&mut node.callbacks);
+            for (kind, callback) in callbacks.iter_mut() {
+                if *kind == event.kind { callback(self, &event); }
+            }
+            if let Some(node) = self.nodes.get_mut(event.node.0).and_then(Option::as_mut) {
+                callbacks.append(&mut node.callbacks);
+                node.callbacks = callbacks;
+            }
+        }
+    }
+
+    fn compute_style(&mut self, id: NodeId, parent: &Style) {
+        let node = self.node(id);
+        let mut selectors = vec![node.widget.kind().to_string()];
+        selectors.extend(node.classes.iter().map(|class| format!(".{}", class)));
+        selectors.extend(node.name.iter().map(|name| format!("#{}", name)));
+        let states = [
+            ("hover", self.hovered_button == Some(id)),
+            ("focus", self.focused == Some(id)),
+            ("pressed", self.pressed == Some(id) && self.hovered_button == Some(id)),
+            ("disabled", !node.enabled)
+        ];
+
+        let mut style = Style::inherit(parent);
+        for selector in &selectors {
+            if let Some(rule) = self.stylesheet.rules.get(selector) { style.apply(rule); }
+        }
+        for (state, _) in states.iter().filter(|(_, active)| *active) {
+            for selector in &selectors {
+                if let Some(rule) = self.stylesheet.rules.get(&format!("{}:{}", selector, state)) { style.apply(rule); }
+            }
+        }
+        style.apply(&node.inline);
+
+        let children = node.children.clone();
+        for child in children { self.compute_style(child, &style); }
+        self.node_mut(id).style = style;
+    }
+
+    fn font(&self, style: &Style) -> FontId {
+        return style.font.as_ref().and_then(|name| self.fonts.get(name)).copied().unwrap_or(self.default_font);
+    }
+
+    fn text_style(style: &Style) -> TextStyle {
+        return TextStyle { size: style.font_size, color: style.color, align: style.text_align, .. TextStyle::default() };
+    }
+
+    /// Size `id` asks for with `available` pixels of room in its parent, margins not included.
+    fn preferred_size(&self, id: NodeId, available: [f32; 2]) -> [f32; 2] {
+        let node = self.node(id);
+        let style = &node.style;
+        let fixed = [style.width.resolve(available[0]), style.height.resolve(available[1])];
+        if let [Some(width), Some(height)] = fixed { return [width, height]; }
+
+        let padding = [style.padding[0] + style.padding[2], style.padding[1] + style.padding[3]];
+        let content = match &node.widget {
+            Widget::Label(text) | Widget::Button(text) => self.text.measure(self.font(style), text, &Ui::text_style(style)),
+            Widget::Image(region) => self.atlas.as_ref().and_then(|atlas| atlas.get(region)).map_or([0.0, 0.0], |region| [region.width as f32, region.height as f32]),
+            Widget::Panel => {
+                let inner = [fixed[0].unwrap_or(available[0]) - padding[0], fixed[1].unwrap_or(available[1]) - padding[1]];
+                let main = if style.direction == Direction::Row { 0 } else { 1 };
+                let mut size = [0.0, 0.0];
+                let mut count = 0;
+                for child in node.children.iter().filter(|child| self.node(**child).visible && self.node(**child).style.anchor.is_none()) {
+                    let child_size = self.preferred_size(*child, inner);
+                    let margin = self.node(*child).style.margin;
+                    let outer = [child_size[0] + margin[0] + margin[2], child_size[1] + margin[1] + margin[3]];
+                    size[main] += outer[main];
+                    size[1 - main] = f32::max(size[1 - main], outer[1 - main]);
+                    count += 1;
+                }
+                if count > 1 { size[main] += style.gap * (count - 1) as f32; }
+                size
+            }
+        };
+
+        return [fixed[0].unwrap_or(content[0] + padding[0]), fixed[1].unwrap_or(content[1] + padding[1])];
+    }
+
+    fn layout_node(&mut self, id: NodeId, rect: [f32; 4]) {
+        self.node_mut(id).rect = rect;
+        let node = self.node(id);
+        let style = node.style.clone();
+        let children: Vec<NodeId> = node.children.iter().copied().filter(|child| self.node(*child).visible).collect();
+        let (flow, anchored): (Vec<NodeId>, Vec<NodeId>) = children.into_iter().partition(|child| self.node(*child).style.anchor.is_none());
+
+        let content = [
+            rect[0] + style.padding[0],
+            rect[1] + style.padding[1],
+            (rect[2] - style.padding[0] - style.padding[2]).max(0.0),
+            (rect[3] - style.padding[1] - style.padding[3]).max(0.0)
+        ];
+        let main = if style.direction == Direction::Row { 0 } else { 1 };
+        let cross = 1 - main;
+
+        let mut sizes: Vec<[f32; 2]> = flow.iter().map(|child| self.preferred_size(*child, [content[2], content[3]])).collect();
+        let used: f32 = flow.iter().zip(&sizes).map(|(child, size)| {
+            let (before, after) = sides(self.node(*child).style.margin, main);
+            size[main] + before + after
+        }).sum::<f32>() + style.gap * flow.len().saturating_sub(1) as f32;
+        let mut free = content[2 + main] - used;
+
+        let grow: f32 = flow.iter().map(|child| self.node(*child).style.grow.max(0.0)).sum();
+        if free > 0.0 && grow > 0.0 {
+            for (child, size) in flow.iter().zip(&mut sizes) { size[main] += free * self.node(*child).style.grow.max(0.0) / grow; }
+            free = 0.0;
+        }
+        let free = free.max(0.0);
+
+        let (offset, spacing) = match style.justify {
+            Justify::Start => (0.0, style.gap),
+            Justify::Center => (free / 2.0, style.gap),
+            Justify::End => (free, style.gap),
+            Justify::SpaceBetween if flow.len() > 1 => (0.0, style.gap + free / (flow.len() - 1) as f32),
+            Justify::SpaceBetween => (0.0, style.gap)
+        };
+
+        let mut position = content[main] + offset;
+        let mut rects = Vec::with_capacity(flow.len() + anchored.len());
+        for (child, mut size) in flow.into_iter().zip(sizes) {
+            let child_style = &self.node(child).style;
+            let (main_before, main_after) = sides(child_style.margin, main);
+            let (cross_before, cross_after) = sides(child_style.margin, cross);
+            let cross_room = content[2 + cross] - cross_before - cross_after;
+            let cross_auto = if cross == 0 { child_style.width == Size::Auto } else { child_style.height == Size::Auto };
+            if style.align == Align::Stretch && cross_auto { size[cross] = cross_room.max(0.0); }
+
+            let mut child_rect = [0.0; 4];
+            child_rect[main] = position + main_before;
+            child_rect[cross] = content[cross] + cross_before + match style.align {
+                Align::Start | Align::Stretch => 0.0,
+                Align::Center => (cross_room - size[cross]) / 2.0,
+                Align::End => cross_room - size[cross]
+            };
+            child_rect[2] = size[0];
+            child_rect[3] = size[1];
+            rects.push((child, child_rect));
+            position = child_rect[main] + size[main] + main_after + spacing;
+        }
+
+        for child in anchored {
+            let anchor = self.node(child).style.anchor.unwrap_or_default();
+            let size = self.preferred_size(child, [rect[2], rect[3]]);
+            rects.push((child, [
+                rect[0] + anchor.point[0] * rect[2] - anchor.pivot[0] * size[0] + anchor.offset[0],
+                rect[1] + anchor.point[1] * rect[3] - anchor.pivot[1] * size[1] + anchor.offset[1],
+                size[0],
+                size[1]
+            ]));
+        }
+
+        for (child, child_rect) in rects { self.layout_node(child, child_rect); }
+    }
+
+    /// Applies the stylesheet and places every node on a screen of `viewport` pixels, which is `scale`
+    /// times larger than the layout. `render` does this on its own, call it to read `rect`s before the
+    /// first frame is drawn.
+    pub fn layout(&mut self, viewport: [u32; 2]) {
+        self.compute_style(self.root, &Style::default());
+        self.layout_node(self.root, [0.0, 0.0, viewport[0] as f32 / self.scale, viewport[1] as f32 / self.scale]);
+    }
+
+    /// Lays out and draws the tree on top of `target`, returns how many draw calls it took.
+    pub fn render<S: Surface>(&mut self, display: &Display, target: &mut S) -> u32 {
+        let (width, height) = target.get_dimensions();
+        self.layout([width, height]);
+        // The layout's pixels are `scale` screen pixels wide, so text is rasterized at the larger size.
+        let screen_height = height as f32 / self.scale;
+        let camera = Camera2d { position: [width as f32 / self.scale / 2.0, screen_height / 2.0], zoom: self.scale, rotation: 0.0 };
+        let mut graphics = self.graphics.take().unwrap_or_else(|| UiGraphics::new(display));
+        let UiGraphics { sprites: sprite_renderer, white } = &mut graphics;
+
+        let mut order = Vec::new();
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            if !node.visible { continue; }
+            order.push(id);
+            stack.extend(node.children.iter().rev());
+        }
+
+        let mut batch = SpriteBatch::new();
+        let mut text_queued = false;
+        let mut draw_calls = 0;
+        for (index, id) in order.into_iter().enumerate() {
+            let node = self.nodes[id.0].as_ref().unwrap();
+            let style = &node.style;
+            let [x, y, node_width, node_height] = node.rect;
+            let rect = [x, screen_height - y - node_height, node_width, node_height];
+            // Every node gets two layers, so its content stays above its own background.
+            let (background_layer, content_layer) = (2 * index as i32, 2 * index as i32 + 1);
+
+            let mut sprites = Vec::new();
+            let background = style.image.as_ref().and_then(|image| Some((self.atlas.as_ref()?, self.atlas.as_ref()?.get(image)?)));
+            match background {
+                Some((atlas, region)) => sprites.extend(nine_slice(rect, region, style.slice, style.background, background_layer).into_iter().map(|sprite| (&atlas.texture, sprite))),
+                None if style.background[3] > 0.0 => sprites.push((&*white, Sprite {
+                    origin: [0.0, 0.0],
+                    color: style.background,
+                    layer: background_layer,
+                    .. Sprite::new([rect[0], rect[1]], [rect[2], rect[3]])
+                })),
+                None => ()
+            }
+
+            let content = [
+                x + style.padding[0],
+                y + style.padding[1],
+                node_width - style.padding[0] - style.padding[2],
+                node_height - style.padding[1] - style.padding[3]
+            ];
+            if let Widget::Image(name) = &node.widget {
+                if let Some((atlas, region)) = self.atlas.as_ref().and_then(|atlas| Some((atlas, atlas.get(name)?))) {
+                    sprites.push((&atlas.texture, Sprite {
+                        origin: [0.0, 0.0],
+                        uv_min: region.uv_min,
+                        uv_max: region.uv_max,
+                        color: style.color,
+                        layer: content_layer,
+                        .. Sprite::new([content[0], screen_height - content[1] - content[3]], [content[2], content[3]])
+                    }));
+                }
+            }
+
+            // Text is drawn after sprites, so sprites on top of text need what came before drawn first.
+            if text_queued && !sprites.is_empty() {
+                draw_calls += sprite_renderer.render(display, target, &camera, &mut batch);
+                draw_calls += self.text.render(display, target, &camera);
+                text_queued = false;
+            }
+            for (texture, sprite) in sprites { batch.draw(texture, sprite); }
+
+            if let Widget::Label(text) | Widget::Button(text) = &node.widget {
+                let font = self.font(style);
+                let text_style = Ui::text_style(style);
+                let text_height = self.text.measure(font, text, &text_style)[1];
+                let left = match style.text_align {
+                    TextAlign::Left => content[0],
+                    TextAlign::Center => content[0] + content[2] / 2.0,
+                    TextAlign::Right => content[0] + content[2]
+                };
+                let top = content[1] + (content[3] - text_height) / 2.0;
+                self.text.draw(font, text, [left, screen_height - top], &text_style);
+                text_queued = true;
+            }
+        }
+
+        draw_calls += sprite_renderer.render(display, target, &camera, &mut batch);
+        draw_calls += self.text.render(display, target, &camera);
+        self.graphics = Some(graphics);
+        return draw_calls;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(ui: &mut Ui, parent: NodeId, widget: Widget, size: [f32; 2]) -> NodeId {
+        let node = ui.add(parent, widget);
+        let style = ui.style_mut(node);
+        style.width = Some(Size::Pixels(size[0]));
+        style.height = Some(Size::Pixels(size[1]));
+        return node;
+    }
+
+    fn move_cursor(input: &mut Input, position: [f64; 2]) {
+        let device_id = unsafe { winit::event::DeviceId::dummy() };
+        input.handle_event(&winit::event::WindowEvent::CursorMoved { device_id, position: position.into() });
+    }
+
+    /// Presses `keys` for one frame and returns the focused node afterwards.
+    fn press(ui: &mut Ui, input: &mut Input, keys: &[KeyCode]) -> Option<NodeId> {
+        for key in keys { input.keys.press(*key); }
+        ui.update(input);
+        for key in keys { input.keys.release(*key); }
+        input.end_frame();
+        return ui.focused();
+    }
+
+    #[test]
+    fn sizes_in_stylesheets() {
+        let stylesheet = Stylesheet::parse("[panel]\nwidth = 120\nheight = \"50 %\"\n[label]\nwidth = \"Auto\"\nheight = 7.5").unwrap();
+        assert_eq!((stylesheet.rules["panel"].width, stylesheet.rules["panel"].height), (Some(Size::Pixels(120.0)), Some(Size::Percent(50.0))));
+        assert_eq!((stylesheet.rules["label"].width, stylesheet.rules["label"].height), (Some(Size::Auto), Some(Size::Pixels(7.5))));
+
+        assert!(Stylesheet::parse("[panel]\nwidth = \"wide\"").is_err());
+        assert!(Stylesheet::parse("[panel]\nwidth = \"%\"").is_err());
+        assert!(Stylesheet::parse("[panel]\nwidth = \"auto\"").is_err());
+        assert!(Stylesheet::parse("[panel]\nwidth = true").is_err());
+        assert!(Stylesheet::parse("[panel]\nsize = 10").is_err());
+
+        assert_eq!(Size::Percent(25.0).resolve(200.0), Some(50.0));
+        assert_eq!(Size::Auto.resolve(200.0), None);
+        Stylesheet::load(crate::inner_path!("config/ui.toml")).unwrap();
+    }
+
+    #[test]
+    fn row_layout() {
+        let mut ui = Ui::headless();
+        let root = ui.root();
+        let row = fixed(&mut ui, root, Widget::Panel, [300.0, 100.0]);
+        let style = ui.style_mut(row);
+        style.direction = Some(Direction::Row);
+        style.padding = Some([10.0; 4]);
+        style.gap = Some(5.0);
+
+        let first = fixed(&mut ui, row, Widget::Panel, [50.0, 20.0]);
+        let second = ui.add(row, Widget::Panel);
+        ui.style_mut(second).width = Some(Size::Percent(10.0));
+        let grown = ui.add(row, Widget::Panel);
+        ui.style_mut(grown).grow = Some(1.0);
+        ui.style_mut(grown).margin = Some([0.0, 5.0, 0.0, 5.0]);
+        ui.layout([800, 600]);
+
+        assert_eq!(ui.rect(row), [0.0, 0.0, 300.0, 100.0]);
+        assert_eq!(ui.rect(first), [10.0, 10.0, 50.0, 20.0]);
+        assert_eq!(ui.rect(second), [65.0, 10.0, 28.0, 80.0]);
+        assert_eq!(ui.rect(grown), [98.0, 15.0, 192.0, 70.0]);
+    }
+
+    #[test]
+    fn column_layout_justify_and_align() {
+        let mut ui = Ui::headless();
+        let root = ui.root();
+        let column = fixed(&mut ui, root, Widget::Panel, [100.0, 200.0]);
+        ui.style_mut(column).justify = Some(Justify::End);
+        ui.style_mut(column).align = Some(Align::Center);
+        let top = fixed(&mut ui, column, Widget::Panel, [20.0, 30.0]);
+        let bottom = fixed(&mut ui, column, Widget::Panel, [40.0, 50.0]);
+        ui.layout([800, 600]);
+        assert_eq!(ui.rect(top), [40.0, 120.0, 20.0, 30.0]);
+        assert_eq!(ui.rect(bottom), [30.0, 150.0, 40.0, 50.0]);
+
+        ui.style_mut(column).justify = Some(Justify::SpaceBetween);
+        ui.set_visible(bottom, false);
+        let last = fixed(&mut ui, column, Widget::Panel, [10.0, 10.0]);
+        ui.layout([800, 600]);
+        assert_eq!(ui.rect(top)[1], 0.0);
+        assert_eq!(ui.rect(last)[1], 190.0);
+    }
+
+    #[test]
+    fn anchors_and_text() {
+        let mut ui = Ui::headless();
+        let root = ui.root();
+        let corner = fixed(&mut ui, root, Widget::Panel, [20.0, 10.0]);
+        ui.style_mut(corner).anchor = Some(Anchor::new([1.0, 1.0], [-4.0, -4.0]));
+        let label = ui.add(root, Widget::Label("Hello".to_string()));
+        ui.style_mut(label).padding = Some([2.0; 4]);
+        ui.layout([800, 600]);
+
+        assert_eq!(ui.rect(corner), [776.0, 586.0, 20.0, 10.0]);
+        let text = ui.text.measure(ui.default_font, "Hello", &TextStyle::default());
+        assert_eq!(ui.rect(label), [0.0, 0.0, 800.0, text[1] + 4.0]);
+    }
+
+    #[test]
+    fn scale_applies_to_layout_and_hit_testing() {
+        let mut ui = Ui::headless();
+        ui.scale = 2.0;
+        let root = ui.root();
+        let corner = fixed(&mut ui, root, Widget::Button("x".to_string()), [20.0, 10.0]);
+        ui.style_mut(corner).anchor = Some(Anchor::new([1.0, 1.0], [0.0, 0.0]));
+        ui.layout([800, 600]);
+        assert_eq!(ui.rect(root), [0.0, 0.0, 400.0, 300.0]);
+        assert_eq!(ui.rect(corner), [380.0, 290.0, 20.0, 10.0]);
+
+        let mut input = Input::default();
+        move_cursor(&mut input, [770.0, 590.0]);
+        ui.update(&input);
+        assert!(ui.wants_mouse());
+        assert_eq!(ui.events(), [UiEvent { node: corner, kind: UiEventKind::Enter }]);
+
+        move_cursor(&mut input, [390.0, 295.0]);
+        ui.update(&input);
+        assert!(!ui.wants_mouse());
+    }
+
+    #[test]
+    fn clicks_need_press_and_release_on_the_button() {
+        let mut ui = Ui::headless();
+        let root = ui.root();
+        let button = fixed(&mut ui, root, Widget::Button("Ok".to_string()), [100.0, 40.0]);
+        ui.layout([800, 600]);
+        let mut input = Input::default();
+        move_cursor(&mut input, [50.0, 20.0]);
+
+        input.mouse_buttons.press(MouseButton::Left);
+        ui.update(&input);
+        assert_eq!(ui.focused(), Some(button));
+        input.end_frame();
+        input.mouse_buttons.release(MouseButton::Left);
+        ui.update(&input);
+        assert!(ui.events().contains(&UiEvent { node: button, kind: UiEventKind::Click }));
+        input.end_frame();
+
+        input.mouse_buttons.press(MouseButton::Left);
+        ui.update(&input);
+        input.end_frame();
+        move_cursor(&mut input, [150.0, 20.0]);
+        input.mouse_buttons.release(MouseButton::Left);
+        ui.update(&input);
+        assert!(!ui.events().iter().any(|event| event.kind == UiEventKind::Click));
+    }
+
+    #[test]
+    fn focus_navigation() {
+        let mut ui = Ui::headless();
+        let root = ui.root();
+        let grid = ui.add(root, Widget::Panel);
+        ui.style_mut(grid).direction = Some(Direction::Row);
+        let left = ui.add(grid, Widget::Panel);
+        let right = ui.add(grid, Widget::Panel);
+        let buttons: Vec<NodeId> = [left, left, right, right].into_iter().enumerate()
+            .map(|(index, column)| fixed(&mut ui, column, Widget::Button(index.to_string()), [50.0, 20.0]))
+            .collect();
+        let [top_left, bottom_left, top_right, bottom_right] = buttons[..] else { unreachable!() };
+        ui.layout([800, 600]);
+        let mut input = Input::default();
+
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::ArrowDown]), Some(top_left));
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::ArrowDown]), Some(bottom_left));
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::ArrowRight]), Some(bottom_right));
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::ArrowUp]), Some(top_right));
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::ArrowUp]), Some(top_right));
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::ArrowLeft]), Some(top_left));
+
+        // Tab goes through the buttons in tree order and wraps, skipping disabled ones.
+        ui.set_enabled(bottom_left, false);
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::Tab]), Some(top_right));
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::Tab]), Some(bottom_right));
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::Tab]), Some(top_left));
+        input.keys.press(KeyCode::ShiftLeft);
+        assert_eq!(press(&mut ui, &mut input, &[KeyCode::Tab]), Some(bottom_right));
+        input.keys.release(KeyCode::ShiftLeft);
+
+        press(&mut ui, &mut input, &[KeyCode::Enter]);
+        assert!(ui.events().contains(&UiEvent { node: bottom_right, kind: UiEventKind::Click }));
+    }
+}
@@ -1,11 +1,18 @@
 use std::rc::Rc;
-use glium::glutin;
-use winit::{event_loop::EventLoop, monitor::VideoMode, window::Fullscreen};
+use serde::{Deserialize, Serialize};
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::monitor::MonitorHandle;
+use winit::window::{CursorIcon, ResizeDirection};
+use winit::{event_loop::EventLoopWindowTarget, monitor::VideoMode, window::Fullscreen};
 
 use crate::graphics::vulkan::VulkanWindowBuilder;
 
-use super::{opengl::{OpenglWindow, OpenglWindowBuilder}, vulkan::VulkanWindow};
+use super::context::Display;
+use super::opengl::OpenglWindowBuilder;
+use super::scaling::{DynamicResolution, RenderResolution, UpscaleFilter};
 
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
 pub enum WindowMode {
     WindowedFullscreen,
     Fullscreen,
@@ -13,12 +20,18 @@ pub enum WindowMode {
     Borderless([u32; 2])
 }
 
-#[derive(Copy, Clone)]
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Version {
     OpenGL(u8, u8),
     Vulkan(u32, u32, u32)
 }
 
+/// Thickness in logical pixels of the edges that resize a borderless window.
+const RESIZE_BORDER: f64 = 6.0;
+/// Upper size limit for an axis that is allowed to resize.
+const MAX_WINDOW_SIZE: u32 = 16384;
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
 pub enum ResizeSide {
     Left,
     Right,
@@ -26,6 +39,7 @@ pub enum ResizeSide {
     Bottom
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub enum ResizeType {
     Specific(Vec<ResizeSide>),
     Vertical,
@@ -34,21 +48,83 @@ pub enum ResizeType {
     None
 }
 
+impl ResizeType {
+    pub fn allows(&self, side: ResizeSide) -> bool {
+        return match self {
+            ResizeType::Specific(sides) => sides.contains(&side),
+            ResizeType::Vertical => matches!(side, ResizeSide::Top | ResizeSide::Bottom),
+            ResizeType::Horizontal => matches!(side, ResizeSide::Left | ResizeSide::Right),
+            ResizeType::All => true,
+            ResizeType::None => false
+        };
+    }
+
+    pub fn horizontal(&self) -> bool { self.allows(ResizeSide::Left) || self.allows(ResizeSide::Right) }
+
+    pub fn vertical(&self) -> bool { self.allows(ResizeSide::Top) || self.allows(ResizeSide::Bottom) }
+}
+
+/// Area of the current monitor the window has to stay inside while it is moved.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub enum WindowMove {
-    CenterBox([u32; 2]), /// width and height
-    Box([u32; 2], [u32; 2]), /// position x and y, width and height
-    SideMargin([u32; 4]), ///  left, right, top, bottom
+    /// Width and height of an area centered on the monitor.
+    CenterBox([u32; 2]),
+    /// Position x and y from the monitor's top left, width and height.
+    Box([u32; 2], [u32; 2]),
+    /// Distance kept from the left, right, top and bottom monitor edges.
+    SideMargin([u32; 4]),
     Full,
+    /// The window stays where it was created.
     None
 }
 
-struct WindowConfig {
-    title: String,
-    window_mode: WindowMode,
-    resizable: ResizeType,
-    movable: WindowMove,
-    resolution: [u32; 2],
-    version: Version
+impl WindowMove {
+    /// Allowed area as position and size in desktop coordinates, `None` when the window can't move.
+    pub fn bounds(&self, monitor_position: PhysicalPosition<i32>, monitor_size: PhysicalSize<u32>) -> Option<([i32; 2], [u32; 2])> {
+        let (x, y) = (monitor_position.x, monitor_position.y);
+        let (width, height) = (monitor_size.width, monitor_size.height);
+
+        // Half the difference of two u32 always fits an i32, even when the box is larger than the monitor.
+        let centered = |origin: i32, available: u32, size: u32| origin.saturating_add(((available as i64 - size as i64) / 2) as i32);
+
+        return match *self {
+            WindowMove::CenterBox(size) => Some(([centered(x, width, size[0]), centered(y, height, size[1])], size)),
+            WindowMove::Box(position, size) => Some(([x.saturating_add_unsigned(position[0]), y.saturating_add_unsigned(position[1])], size)),
+            WindowMove::SideMargin([left, right, top, bottom]) => Some((
+                [x.saturating_add_unsigned(left), y.saturating_add_unsigned(top)],
+                [width.saturating_sub(left.saturating_add(right)), height.saturating_sub(top.saturating_add(bottom))])),
+            WindowMove::Full => Some(([x, y], [width, height])),
+            WindowMove::None => None
+        };
+    }
+}
+
+/// Everything needed to open a window, loaded from and saved to a settings file by `WindowConfig::load`
+/// and `WindowConfig::save`. Missing keys keep their default.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub title: String,
+    pub window_mode: WindowMode,
+    pub resizable: ResizeType,
+    pub movable: WindowMove,
+    /// Resolution used for exclusive fullscreen.
+    pub resolution: [u32; 2],
+    /// Preferred fullscreen refresh rate in Hz, `None` picks the fastest.
+    pub refresh_rate: Option<u32>,
+    pub version: Version,
+    /// Index into the available monitors, `None` uses the one the window opens on.
+    pub monitor: Option<usize>,
+    /// Applied when the window is created, changing it takes effect on the next start.
+    pub vsync: bool,
+    /// MSAA samples, 0 turns multisampling off. Applied when the window is created, and only
+    /// while the scene is rendered at the window's own size.
+    pub msaa: u8,
+    /// Resolution the scene is rendered at, separate from the window size and `resolution`.
+    pub render_resolution: RenderResolution,
+    pub upscale_filter: UpscaleFilter,
+    /// `None` keeps the render resolution fixed.
+    pub dynamic_resolution: Option<DynamicResolution>
 }
 
 impl Default for WindowConfig {
@@ -59,26 +135,46 @@ impl Default for WindowConfig {
             resizable: ResizeType::All,
             movable: WindowMove::SideMargin([0, 0, 0, 50]),
             resolution: [1920, 1080],
-            version: Version::OpenGL(4, 6)
+            refresh_rate: None,
+            version: Version::OpenGL(4, 6),
+            monitor: None,
+            vsync: true,
+            msaa: 0,
+            render_resolution: RenderResolution::Native,
+            upscale_filter: UpscaleFilter::Bilinear,
+            dynamic_resolution: None
         };
     }
 }
 
 pub trait WindowBuilder {
-    fn build<T>(&mut self, event_loop: &winit::event_loop::EventLoop<T>) -> (winit::window::Window, glium::Display<glutin::surface::WindowSurface>);
+    fn build<T>(&mut self, target: &EventLoopWindowTarget<T>, share_with: Option<&Display>) -> (winit::window::Window, Display);
     fn get_winit(&self) -> winit::window::WindowBuilder;
     fn set_winit(&mut self, winit_builder: winit::window::WindowBuilder);
 }
 
-pub trait AnyWindow {
-    fn start(&self);
-    fn render(&self);
-}
-
 pub struct Window {
     pub window_mode: WindowMode,
+    pub resizable: ResizeType,
+    pub movable: WindowMove,
+    /// Exclusive fullscreen resolution and refresh rate, applied by `set_mode`.
+    pub resolution: [u32; 2],
+    pub refresh_rate: Option<u32>,
+    /// Scene resolution and upscaling, applied by the engine every frame.
+    pub render_resolution: RenderResolution,
+    pub upscale_filter: UpscaleFilter,
+    pub dynamic_resolution: Option<DynamicResolution>,
     pub winit_window: Rc<winit::window::Window>,
-    pub display: glium::backend::glutin::Display<glutin::surface::WindowSurface>
+    pub display: Display,
+    /// Settings the window was created with, `config` merges the current state back in.
+    created_with: WindowConfig,
+    /// Part of a borderless window that drags it, as position and size in logical pixels. `None` is the whole window.
+    pub drag_region: Option<([u32; 2], [u32; 2])>,
+    scale_factor: f64,
+    monitor: Option<MonitorHandle>,
+    anchor: Option<PhysicalPosition<i32>>,
+    cursor_position: Option<PhysicalPosition<f64>>,
+    hovered_edge: Option<ResizeDirection>
 }
 
 enum AnyWindowBuilder {
@@ -87,10 +183,10 @@ enum AnyWindowBuilder {
 }
 
 impl AnyWindowBuilder {
-    fn build<T>(&mut self, event_loop: &winit::event_loop::EventLoop<T>) -> (winit::window::Window, glium::Display<glutin::surface::WindowSurface>) {
+    fn build<T>(&mut self, target: &EventLoopWindowTarget<T>, share_with: Option<&Display>) -> (winit::window::Window, Display) {
         match self {
-            AnyWindowBuilder::OpenGL(window_builder) => window_builder.build(event_loop),
-            AnyWindowBuilder::Vulkan(window_builder) => window_builder.build(event_loop)
+            AnyWindowBuilder::OpenGL(window_builder) => window_builder.build(target, share_with),
+            AnyWindowBuilder::Vulkan(window_builder) => window_builder.build(target, share_with)
         }
     }
 
@@ -110,113 +206,357 @@ impl AnyWindowBuilder {
 }
 
 
-fn create_window_builder(version: Version) -> AnyWindowBuilder {
-    match version {
+fn create_window_builder(config: &WindowConfig) -> AnyWindowBuilder {
+    match config.version {
         Version::OpenGL(major, minor) => AnyWindowBuilder::OpenGL(OpenglWindowBuilder {
             winit_builder: winit::window::WindowBuilder::new(),
-            version: [major, minor]
+            version: [major, minor],
+            vsync: config.vsync,
+            multisampling: config.msaa
         }),
         Version::Vulkan(major, minor, patch) => AnyWindowBuilder::Vulkan(VulkanWindowBuilder {
             winit_builder: winit::window::WindowBuilder::new(),
-            version: [major, minor, patch]
+            version: [major, minor, patch],
+            vsync: config.vsync,
+            multisampling: config.msaa
         })
     }
 }
 
 impl Window {
-    pub fn new(config: Option<WindowConfig>) -> Box<dyn AnyWindow> {
-        let event_loop = winit::event_loop::EventLoopBuilder::new().build().unwrap();
-        let config = config.unwrap_or_default();
-
-        let mut builder: AnyWindowBuilder = create_window_builder(config.version);
+    /// Opens a window described by `config`. With `share_with` the window joins that display's GL context,
+    /// so resources created for one window can be drawn in the other.
+    pub fn new<T>(config: WindowConfig, target: &EventLoopWindowTarget<T>, share_with: Option<&Display>) -> Window {
+        let mut builder: AnyWindowBuilder = create_window_builder(&config);
 
         let mut winit_builder = builder.get_winit();
-        winit_builder = winit_builder.with_title(config.title);
-        let (winit_window, display);
+        winit_builder = winit_builder.with_title(config.title.as_str());
 
-        match config.window_mode {
-            WindowMode::Fullscreen => {
-                (winit_window, display) = builder.build(&event_loop);
-                let size = [winit_window.current_monitor().unwrap().size().width,
-                winit_window.current_monitor().unwrap().size().height];
-                winit_window.set_fullscreen(
-                    Some(Fullscreen::Exclusive(Self::get_video_mode(&winit_window, size))));
-            },
-            WindowMode::WindowedFullscreen => {
-                (winit_window, display) = builder.build(&event_loop);
-                winit_window.set_fullscreen(Some(Fullscreen::Borderless(None)));
-            },
-            WindowMode::Normal(size) => {
-                builder.set_winit(winit_builder.with_inner_size(winit::dpi::PhysicalSize::new(size[0], size[1])));
-                (winit_window, display) = builder.build(&event_loop);
-            },
-            WindowMode::Borderless(size) => {
-                builder.set_winit(winit_builder.with_transparent(true)
-                                                              .with_inner_size(winit::dpi::PhysicalSize::new(size[0], size[1]))
-                                                              .with_decorations(false)
-                                                              .with_resizable(true));
-                (winit_window, display) = builder.build(&event_loop);
-            }
+        if let WindowMode::Normal(size) | WindowMode::Borderless(size) = config.window_mode {
+            winit_builder = winit_builder.with_inner_size(PhysicalSize::new(size[0], size[1]));
+        }
+        // Transparency can only be chosen when the window is created, so it is kept when switching modes later.
+        if let WindowMode::Borderless(_) = config.window_mode {
+            winit_builder = winit_builder.with_transparent(true).with_decorations(false);
         }
 
-        let window = Window {
+        builder.set_winit(winit_builder);
+        let (winit_window, display) = builder.build(target, share_with);
+
+        let mut window = Window {
             window_mode: config.window_mode,
+            resizable: config.resizable.clone(),
+            movable: config.movable.clone(),
+            resolution: config.resolution,
+            refresh_rate: config.refresh_rate,
+            render_resolution: config.render_resolution,
+            upscale_filter: config.upscale_filter,
+            dynamic_resolution: config.dynamic_resolution,
+            anchor: winit_window.outer_position().ok(),
+            scale_factor: winit_window.scale_factor(),
+            monitor: config.monitor.and_then(|index| target.available_monitors().nth(index)),
             winit_window: Rc::new(winit_window),
-            display: display
+            display,
+            created_with: config,
+            drag_region: None,
+            cursor_position: None,
+            hovered_edge: None
         };
 
-        let any_window: Box<dyn AnyWindow> = match config.version {
-            Version::OpenGL(_, _) => Box::new(OpenglWindow { window: window }),
-            Version::Vulkan(_, _, _) => Box::new(VulkanWindow { window: window })
-        };
+        window.set_mode(window.window_mode);
+        if let Some(position) = window.anchor { window.keep_in_bounds(position); }
+
+        return window;
+    }
+
+    /// Switches between windowed, borderless and fullscreen on the selected monitor. The GL surface
+    /// belongs to the same native window in every mode, so it only needs to follow the new size.
+    pub fn set_mode(&mut self, window_mode: WindowMode) {
+        self.window_mode = window_mode;
+        let monitor = self.monitor();
 
-        event_loop.run(move |event, control_flow| {
-            match event {
-                winit::event::Event::AboutToWait => winit_window.request_redraw(),
-                winit::event::Event::WindowEvent { event, .. } => match event {
-                    winit::event::WindowEvent::CloseRequested => control_flow.exit(),
-                    winit::event::WindowEvent::Resized(window_size) => { display.resize(window_size.into()); },
-                    winit::event::WindowEvent::RedrawRequested => {
-                        any_window.render();
-                    },
-                    _ => ()
+        match window_mode {
+            WindowMode::Fullscreen => {
+                let Some(monitor) = monitor else { return; };
+                // Some platforms (Wayland) report no modes, borderless fullscreen is the closest match there.
+                self.winit_window.set_fullscreen(Some(match Self::get_video_mode(&monitor, self.resolution, self.refresh_rate) {
+                    Some(video_mode) => Fullscreen::Exclusive(video_mode),
+                    None => Fullscreen::Borderless(Some(monitor))
+                }));
+            },
+            WindowMode::WindowedFullscreen => self.winit_window.set_fullscreen(Some(Fullscreen::Borderless(monitor))),
+            WindowMode::Normal(size) | WindowMode::Borderless(size) => {
+                self.winit_window.set_fullscreen(None);
+                self.winit_window.set_decorations(matches!(window_mode, WindowMode::Normal(_)));
+                let _ = self.winit_window.request_inner_size(PhysicalSize::new(size[0], size[1]));
+
+                if let Some(monitor) = self.monitor.as_ref() {
+                    let position = monitor.position();
+                    let (monitor_size, outer_size) = (monitor.size(), self.winit_window.outer_size());
+                    self.anchor = Some(PhysicalPosition::new(
+                        position.x + (monitor_size.width as i32 - outer_size.width as i32) / 2,
+                        position.y + (monitor_size.height as i32 - outer_size.height as i32) / 2));
+                    self.winit_window.set_outer_position(self.anchor.unwrap());
                 }
-                _ => ()
             }
-        });
+        }
 
-        any_window.start();
+        self.apply_resize_limits();
+        self.display.resize(self.winit_window.inner_size().into());
+    }
 
-        return window;
+    /// Current settings of the window, for saving them when the player changes options.
+    pub fn config(&self) -> WindowConfig {
+        return WindowConfig {
+            window_mode: self.window_mode,
+            resizable: self.resizable.clone(),
+            movable: self.movable.clone(),
+            resolution: self.resolution,
+            refresh_rate: self.refresh_rate,
+            render_resolution: self.render_resolution,
+            upscale_filter: self.upscale_filter,
+            dynamic_resolution: self.dynamic_resolution,
+            monitor: self.monitor.as_ref().and_then(|monitor| self.monitors().iter().position(|available| available == monitor)),
+            ..self.created_with.clone()
+        };
     }
 
-    fn event_loop() {
+    /// Physical pixels per logical pixel, follows the monitor the window is on.
+    pub fn scale_factor(&self) -> f64 { self.scale_factor }
 
+    /// Size of the drawable area in pixels, what the display renders to.
+    pub fn physical_size(&self) -> [u32; 2] {
+        let size = self.winit_window.inner_size();
+        return [size.width, size.height];
     }
 
-    fn get_video_mode(window: &winit::window::Window, size: [u32; 2]) -> VideoMode {
-        let size = size[0]*size[1];
-        let video_modes: Rc<Vec<VideoMode>> = Rc::new(window.current_monitor().unwrap().video_modes().collect());
-        /*
-        let mut max_distance = 0;
-        video_modes.for_each(|mode| {
-            let current = mode.size().width*mode.size().height;
-            if current > max_distance { max_distance = current };
-        });
-        max_distance = max_distance-size;
-        */
-        let mut closest = video_modes.last().unwrap().clone();
-        let mut closest_distance: u32 = (i64::from(closest.size().width)*i64::from(closest.size().height)-i64::from(size)).abs().try_into().unwrap();
-
-        for mode in video_modes.to_vec() {
-            let current_distance = (i64::from(mode.size().width)*i64::from(mode.size().height)-i64::from(size)).abs().try_into().unwrap();
-            println!("{}, {}", mode.size().width, mode.size().height);
-            if closest_distance > current_distance {
-                closest = mode.clone();
-                closest_distance = current_distance;
+    /// Size of the drawable area in logical pixels, what UI and text are laid out in.
+    pub fn logical_size(&self) -> [f32; 2] {
+        let size: LogicalSize<f32> = self.winit_window.inner_size().to_logical(self.scale_factor);
+        return [size.width, size.height];
+    }
+
+    pub fn to_logical(&self, physical: [f32; 2]) -> [f32; 2] {
+        return [physical[0] / self.scale_factor as f32, physical[1] / self.scale_factor as f32];
+    }
+
+    pub fn to_physical(&self, logical: [f32; 2]) -> [f32; 2] {
+        return [logical[0] * self.scale_factor as f32, logical[1] * self.scale_factor as f32];
+    }
+
+    pub fn monitors(&self) -> Vec<MonitorHandle> { self.winit_window.available_monitors().collect() }
+
+    /// Monitor used for fullscreen, the one chosen with `set_monitor` or else the one the window is on.
+    pub fn monitor(&self) -> Option<MonitorHandle> {
+        return self.monitor.clone()
+            .or_else(|| self.winit_window.current_monitor())
+            .or_else(|| self.winit_window.primary_monitor());
+    }
+
+    /// Moves the window to `monitor`, `None` goes back to following the window's current monitor.
+    pub fn set_monitor(&mut self, monitor: Option<MonitorHandle>) {
+        self.monitor = monitor;
+        self.set_mode(self.window_mode);
+    }
+
+    fn is_windowed(&self) -> bool { matches!(self.window_mode, WindowMode::Normal(_) | WindowMode::Borderless(_)) }
+
+    fn is_borderless(&self) -> bool { matches!(self.window_mode, WindowMode::Borderless(_)) }
+
+    /// Locks the axes `resizable` doesn't allow to the size of the window mode, fullscreen has no limits.
+    pub fn apply_resize_limits(&self) {
+        let size = match self.window_mode {
+            WindowMode::Normal(size) | WindowMode::Borderless(size) => PhysicalSize::new(size[0], size[1]),
+            _ => {
+                self.winit_window.set_min_inner_size(None::<PhysicalSize<u32>>);
+                self.winit_window.set_max_inner_size(None::<PhysicalSize<u32>>);
+                return;
+            }
+        };
+        let (horizontal, vertical) = (self.resizable.horizontal(), self.resizable.vertical());
+
+        self.winit_window.set_resizable(horizontal || vertical);
+        self.winit_window.set_min_inner_size(Some(PhysicalSize::new(
+            if horizontal { 1 } else { size.width }, if vertical { 1 } else { size.height })));
+        self.winit_window.set_max_inner_size(Some(PhysicalSize::new(
+            if horizontal { MAX_WINDOW_SIZE } else { size.width }, if vertical { MAX_WINDOW_SIZE } else { size.height })));
+    }
+
+    /// Moves the window back inside the area allowed by `movable` if `position` is outside of it.
+    fn keep_in_bounds(&self, position: PhysicalPosition<i32>) {
+        if !self.is_windowed() { return; }
+
+        let Some(monitor) = self.winit_window.current_monitor() else { return; };
+        let target = match self.movable.bounds(monitor.position(), monitor.size()) {
+            Some((area_position, area_size)) => {
+                let size = self.winit_window.outer_size();
+                let clamp = |value: i32, start: i32, area: u32, window: u32| value.clamp(start, start.max(start + area as i32 - window as i32));
+
+                PhysicalPosition::new(
+                    clamp(position.x, area_position[0], area_size[0], size.width),
+                    clamp(position.y, area_position[1], area_size[1], size.height))
+            },
+            None => match self.anchor {
+                Some(anchor) => anchor,
+                None => return
             }
+        };
+
+        if target != position { self.winit_window.set_outer_position(target); }
+    }
+
+    fn resize_direction(&self, position: PhysicalPosition<f64>) -> Option<ResizeDirection> {
+        let size = self.winit_window.inner_size();
+        let border = RESIZE_BORDER * self.scale_factor;
+        let left = position.x < border && self.resizable.allows(ResizeSide::Left);
+        let right = position.x >= size.width as f64 - border && self.resizable.allows(ResizeSide::Right);
+        let top = position.y < border && self.resizable.allows(ResizeSide::Top);
+        let bottom = position.y >= size.height as f64 - border && self.resizable.allows(ResizeSide::Bottom);
+
+        return match (left, right, top, bottom) {
+            (true, _, true, _) => Some(ResizeDirection::NorthWest),
+            (_, true, true, _) => Some(ResizeDirection::NorthEast),
+            (true, _, _, true) => Some(ResizeDirection::SouthWest),
+            (_, true, _, true) => Some(ResizeDirection::SouthEast),
+            (true, _, _, _) => Some(ResizeDirection::West),
+            (_, true, _, _) => Some(ResizeDirection::East),
+            (_, _, true, _) => Some(ResizeDirection::North),
+            (_, _, _, true) => Some(ResizeDirection::South),
+            _ => None
+        };
+    }
+
+    fn in_drag_region(&self, position: PhysicalPosition<f64>) -> bool {
+        let Some((region_position, region_size)) = self.drag_region else { return true; };
+        let position = position.to_logical::<f64>(self.scale_factor);
+        return position.x >= region_position[0] as f64 && position.x < (region_position[0] + region_size[0]) as f64
+            && position.y >= region_position[1] as f64 && position.y < (region_position[1] + region_size[1]) as f64;
+    }
+
+    /// Applies the resize and move rules, borderless windows get their edges and drag region
+    /// handled here since there is no title bar or frame.
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::Moved(position) => self.keep_in_bounds(*position),
+            // winit suggests a size that keeps the logical size, the `Resized` that follows updates the mode.
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => self.scale_factor = *scale_factor,
+            WindowEvent::Resized(size) => match &mut self.window_mode {
+                WindowMode::Normal(mode_size) | WindowMode::Borderless(mode_size) => *mode_size = [size.width, size.height],
+                _ => ()
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(*position);
+                if !self.is_borderless() { return; }
+
+                let edge = self.resize_direction(*position);
+                if edge != self.hovered_edge {
+                    self.winit_window.set_cursor_icon(match edge {
+                        Some(ResizeDirection::East | ResizeDirection::West) => CursorIcon::EwResize,
+                        Some(ResizeDirection::North | ResizeDirection::South) => CursorIcon::NsResize,
+                        Some(ResizeDirection::NorthEast | ResizeDirection::SouthWest) => CursorIcon::NeswResize,
+                        Some(ResizeDirection::NorthWest | ResizeDirection::SouthEast) => CursorIcon::NwseResize,
+                        None => CursorIcon::Default
+                    });
+                    self.hovered_edge = edge;
+                }
+            },
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } => {
+                let Some(position) = self.cursor_position else { return; };
+                if !self.is_borderless() { return; }
+
+                if let Some(direction) = self.hovered_edge {
+                    let _ = self.winit_window.drag_resize_window(direction);
+                } else if !matches!(self.movable, WindowMove::None) && self.in_drag_region(position) {
+                    let _ = self.winit_window.drag_window();
+                }
+            },
+            _ => ()
         }
+    }
+
+    /// Every fullscreen mode of `monitor`, largest and fastest first, for settings menus.
+    pub fn video_modes(monitor: &MonitorHandle) -> Vec<VideoMode> {
+        let mut video_modes: Vec<VideoMode> = monitor.video_modes().collect();
+        video_modes.sort_by_key(|mode| std::cmp::Reverse((mode.size().width, mode.size().height, mode.refresh_rate_millihertz(), mode.bit_depth())));
+        video_modes.dedup();
+
+        return video_modes;
+    }
+
+    /// Picks the mode matching `size` exactly if there is one, otherwise the closest aspect ratio and
+    /// then the closest size. Ties go to `refresh_rate` in Hz, or the fastest mode, then the deepest color.
+    pub fn get_video_mode(monitor: &MonitorHandle, size: [u32; 2], refresh_rate: Option<u32>) -> Option<VideoMode> {
+        let aspect = size[0] as f64 / size[1].max(1) as f64;
+        let area = i64::from(size[0]) * i64::from(size[1]);
+
+        return monitor.video_modes().max_by_key(|mode| {
+            let mode_size = mode.size();
+            let mode_aspect = mode_size.width as f64 / mode_size.height.max(1) as f64;
+
+            let exact = mode_size.width == size[0] && mode_size.height == size[1];
+            let aspect_distance = ((mode_aspect - aspect).abs() * 1000.0) as i64;
+            let area_distance = (i64::from(mode_size.width) * i64::from(mode_size.height) - area).abs();
+            let refresh_score = match refresh_rate {
+                Some(refresh_rate) => -(i64::from(mode.refresh_rate_millihertz()) - i64::from(refresh_rate) * 1000).abs(),
+                None => i64::from(mode.refresh_rate_millihertz())
+            };
+
+            (exact, -aspect_distance, -area_distance, refresh_score, mode.bit_depth())
+        });
+    }
+}
 
-        return closest;
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.display.release();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIDES: [ResizeSide; 4] = [ResizeSide::Left, ResizeSide::Right, ResizeSide::Top, ResizeSide::Bottom];
+
+    fn allowed(resize: ResizeType) -> Vec<ResizeSide> { SIDES.into_iter().filter(|side| resize.allows(*side)).collect() }
+
+    /// A 1920x1080 monitor to the left of the primary one.
+    fn bounds(movable: WindowMove) -> Option<([i32; 2], [u32; 2])> {
+        return movable.bounds(PhysicalPosition::new(-1920, 0), PhysicalSize::new(1920, 1080));
+    }
+
+    #[test]
+    fn resize_sides() {
+        assert_eq!(allowed(ResizeType::All), SIDES);
+        assert!(allowed(ResizeType::None).is_empty());
+        assert_eq!(allowed(ResizeType::Horizontal), [ResizeSide::Left, ResizeSide::Right]);
+        assert_eq!(allowed(ResizeType::Vertical), [ResizeSide::Top, ResizeSide::Bottom]);
+        assert_eq!(allowed(ResizeType::Specific(vec![ResizeSide::Bottom, ResizeSide::Left])), [ResizeSide::Left, ResizeSide::Bottom]);
+
+        let bottom = ResizeType::Specific(vec![ResizeSide::Bottom]);
+        assert!(bottom.vertical() && !bottom.horizontal());
+        assert!(ResizeType::Horizontal.horizontal() && !ResizeType::Horizontal.vertical());
+    }
+
+    #[test]
+    fn move_bounds() {
+        assert_eq!(bounds(WindowMove::Full), Some(([-1920, 0], [1920, 1080])));
+        assert_eq!(bounds(WindowMove::None), None);
+        assert_eq!(bounds(WindowMove::CenterBox([800, 600])), Some(([-1360, 240], [800, 600])));
+        assert_eq!(bounds(WindowMove::Box([100, 50], [640, 480])), Some(([-1820, 50], [640, 480])));
+        assert_eq!(bounds(WindowMove::SideMargin([10, 20, 30, 40])), Some(([-1910, 30], [1890, 1010])));
+    }
+
+    #[test]
+    fn oversized_move_bounds() {
+        // A centered box larger than the monitor hangs over every edge by the same amount.
+        assert_eq!(bounds(WindowMove::CenterBox([2920, 1280])), Some(([-2420, -100], [2920, 1280])));
+        assert_eq!(bounds(WindowMove::CenterBox([u32::MAX, u32::MAX])), Some(([i32::MIN, -2147483107], [u32::MAX, u32::MAX])));
+
+        // Margins wider than the monitor leave nothing, even when their sum overflows.
+        assert_eq!(bounds(WindowMove::SideMargin([1000, 1000, 0, 0])), Some(([-920, 0], [0, 1080])));
+        assert_eq!(bounds(WindowMove::SideMargin([u32::MAX, u32::MAX, u32::MAX, 1])), Some(([i32::MAX, i32::MAX], [0, 0])));
+        assert_eq!(bounds(WindowMove::Box([u32::MAX, 0], [1, 1])), Some(([i32::MAX, 0], [1, 1])));
+    }
+}
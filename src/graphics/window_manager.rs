@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use winit::event::WindowEvent;
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::WindowId;
+
+use super::context::Display;
+use super::window::{Window, WindowConfig};
+
+/// Identifies a secondary window, it is known as soon as `WindowManager::open` returns even though
+/// the native window is only created when the event loop gets to it.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct WindowHandle(usize);
+
+/// Secondary windows (debug views, asset previews) next to the main window. They all share the main
+/// window's GL context, events are routed to them by `WindowId`.
+pub struct WindowManager {
+    share_with: Display,
+    windows: HashMap<WindowHandle, Window>,
+    ids: HashMap<WindowId, WindowHandle>,
+    pending: Vec<(WindowHandle, WindowConfig)>,
+    next_handle: usize
+}
+
+impl WindowManager {
+    pub fn new(share_with: Display) -> WindowManager {
+        return WindowManager { share_with, windows: HashMap::new(), ids: HashMap::new(), pending: Vec::new(), next_handle: 0 };
+    }
+
+    /// Queues a window, it is created before the next event is handled.
+    pub fn open(&mut self, config: WindowConfig) -> WindowHandle {
+        let handle = WindowHandle(self.next_handle);
+        self.next_handle += 1;
+        self.pending.push((handle, config));
+
+        return handle;
+    }
+
+    pub fn close(&mut self, handle: WindowHandle) {
+        self.pending.retain(|(pending, _)| *pending != handle);
+        if let Some(window) = self.windows.remove(&handle) {
+            self.ids.remove(&window.winit_window.id());
+        }
+    }
+
+    /// `false` once the window was closed, `true` while it is still waiting to be created.
+    pub fn is_open(&self, handle: WindowHandle) -> bool {
+        return self.windows.contains_key(&handle) || self.pending.iter().any(|(pending, _)| *pending == handle);
+    }
+
+    pub fn get(&self, handle: WindowHandle) -> Option<&Window> { self.windows.get(&handle) }
+
+    pub fn get_mut(&mut self, handle: WindowHandle) -> Option<&mut Window> { self.windows.get_mut(&handle) }
+
+    pub fn handle_of(&self, window_id: WindowId) -> Option<WindowHandle> { self.ids.get(&window_id).copied() }
+
+    pub fn iter(&self) -> impl Iterator<Item = (WindowHandle, &Window)> {
+        return self.windows.iter().map(|(handle, window)| (*handle, window));
+    }
+
+    pub(crate) fn open_pending<T>(&mut self, target: &EventLoopWindowTarget<T>) {
+        for (handle, config) in self.pending.drain(..) {
+            let window = Window::new(config, target, Some(&self.share_with));
+            self.ids.insert(window.winit_window.id(), handle);
+            self.windows.insert(handle, window);
+        }
+    }
+
+    pub(crate) fn handle_event(&mut self, handle: WindowHandle, event: &WindowEvent) {
+        let Some(window) = self.windows.get_mut(&handle) else { return; };
+
+        window.handle_event(event);
+        if let WindowEvent::Resized(size) = event { window.display.resize((*size).into()); }
+    }
+
+    pub(crate) fn request_redraw(&self) {
+        for window in self.windows.values() { window.winit_window.request_redraw(); }
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+use super::gamepad::{GamepadAxis, GamepadButton};
+
+/// A physical input an action or axis can be bound to, written as `{ Key = "Space" }`,
+/// `{ Mouse = "Left" }` or `{ Gamepad = "South" }` in the config file.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton)
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct AxisBinding {
+    #[serde(default)]
+    pub positive: Vec<Binding>,
+    #[serde(default)]
+    pub negative: Vec<Binding>,
+    #[serde(default)]
+    pub analog: Vec<GamepadAxis>
+}
+
+#[derive(Debug)]
+pub enum ActionMapError {
+    Io(std::io::Error),
+    Parse(toml::de::Error)
+}
+
+impl std::fmt::Display for ActionMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionMapError::Io(error) => write!(f, "failed to read action map: {}", error),
+            ActionMapError::Parse(error) => write!(f, "failed to parse action map: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for ActionMapError {}
+
+/// Named actions and axes bound to keys and buttons, queried through `Input`.
+///
+/// ```toml
+/// [actions]
+/// jump = [{ Key = "Space" }]
+/// fire = [{ Mouse = "Left" }, { Key = "KeyF" }]
+///
+/// [axes.move_x]
+/// positive = [{ Key = "KeyD" }, { Key = "ArrowRight" }]
+/// negative = [{ Key = "KeyA" }, { Key = "ArrowLeft" }]
+/// analog = ["LeftStickX"]
+/// ```
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct ActionMap {
+    #[serde(default)]
+    actions: HashMap<String, Vec<Binding>>,
+    #[serde(default)]
+    axes: HashMap<String, AxisBinding>
+}
+
+impl ActionMap {
+    pub fn load(path: impl AsRef<Path>) -> Result<ActionMap, ActionMapError> {
+        let source = std::fs::read_to_string(path).map_err(ActionMapError::Io)?;
+        return ActionMap::parse(&source);
+    }
+
+    pub fn parse(source: &str) -> Result<ActionMap, ActionMapError> {
+        return toml::from_str(source).map_err(ActionMapError::Parse);
+    }
+
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) { bindings.push(binding); }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) { bindings.retain(|bound| *bound != binding); }
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, positive: Binding, negative: Binding) {
+        let axis = self.axes.entry(axis.to_string()).or_default();
+        axis.positive.push(positive);
+        axis.negative.push(negative);
+    }
+
+    pub fn bind_analog(&mut self, axis: &str, analog: GamepadAxis) {
+        self.axes.entry(axis.to_string()).or_default().analog.push(analog);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        return self.actions.get(action).map(Vec::as_slice).unwrap_or(&[]);
+    }
+
+    pub fn axis(&self, axis: &str) -> Option<&AxisBinding> { self.axes.get(axis) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_shipped_config() {
+        let map = ActionMap::parse(include_str!("../../config/input.toml")).unwrap();
+        assert_eq!(map.bindings("pause"), [Binding::Key(KeyCode::Space), Binding::Mouse(MouseButton::Left), Binding::Gamepad(GamepadButton::South)]);
+        assert_eq!(map.bindings("quit"), [Binding::Key(KeyCode::Escape), Binding::Gamepad(GamepadButton::Start)]);
+        assert!(map.bindings("missing").is_empty());
+
+        let spin = map.axis("spin").unwrap();
+        assert_eq!(spin.positive, [Binding::Key(KeyCode::ArrowRight), Binding::Key(KeyCode::KeyD)]);
+        assert_eq!(spin.negative, [Binding::Key(KeyCode::ArrowLeft), Binding::Key(KeyCode::KeyA)]);
+        assert_eq!(spin.analog, [GamepadAxis::LeftStickX]);
+        assert!(map.axis("missing").is_none());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(ActionMap::parse("[actions]\njump = [{ Key = \"NoSuchKey\" }]"), Err(ActionMapError::Parse(_))));
+        assert!(matches!(ActionMap::parse("actions = 3"), Err(ActionMapError::Parse(_))));
+        assert!(ActionMap::parse("").unwrap().bindings("jump").is_empty());
+    }
+
+    #[test]
+    fn bind_and_unbind() {
+        let mut map = ActionMap::default();
+        map.bind("jump", Binding::Key(KeyCode::Space));
+        map.bind("jump", Binding::Gamepad(GamepadButton::South));
+        map.bind("jump", Binding::Key(KeyCode::Space));
+        assert_eq!(map.bindings("jump"), [Binding::Key(KeyCode::Space), Binding::Gamepad(GamepadButton::South)]);
+
+        map.unbind("jump", Binding::Key(KeyCode::Space));
+        map.unbind("missing", Binding::Key(KeyCode::Space));
+        assert_eq!(map.bindings("jump"), [Binding::Gamepad(GamepadButton::South)]);
+    }
+
+    #[test]
+    fn bind_axis_adds_to_both_sides() {
+        let mut map = ActionMap::default();
+        map.bind_axis("move_x", Binding::Key(KeyCode::KeyD), Binding::Key(KeyCode::KeyA));
+        map.bind_axis("move_x", Binding::Key(KeyCode::ArrowRight), Binding::Key(KeyCode::ArrowLeft));
+        map.bind_analog("move_x", GamepadAxis::LeftStickX);
+
+        let axis = map.axis("move_x").unwrap();
+        assert_eq!(axis.positive, [Binding::Key(KeyCode::KeyD), Binding::Key(KeyCode::ArrowRight)]);
+        assert_eq!(axis.negative, [Binding::Key(KeyCode::KeyA), Binding::Key(KeyCode::ArrowLeft)]);
+        assert_eq!(axis.analog, [GamepadAxis::LeftStickX]);
+    }
+}
@@ -0,0 +1,514 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use super::state::ButtonSet;
+
+/// Trigger value past which the trigger also counts as a pressed button.
+const TRIGGER_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Buttons use the position on the pad, `South` is A on Xbox and Cross on PlayStation layouts.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight
+}
+
+/// Sticks are in `-1.0..=1.0` with up and right positive, triggers are in `0.0..=1.0`.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger
+}
+
+impl GamepadAxis {
+    fn index(self) -> usize { self as usize }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Deadzone {
+    /// Radial deadzone applied to the stick as a whole, so diagonals are not snapped to an axis.
+    pub stick: f32,
+    pub trigger: f32
+}
+
+impl Default for Deadzone {
+    fn default() -> Deadzone {
+        return Deadzone { stick: 0.15, trigger: 0.05 };
+    }
+}
+
+/// Motor strengths in `0.0..=1.0`, the strong motor is the low frequency one.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rumble {
+    pub strong: f32,
+    pub weak: f32,
+    pub duration: Duration
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GamepadEvent {
+    Connected,
+    Disconnected,
+    ButtonPressed(GamepadButton),
+    ButtonReleased(GamepadButton),
+    AxisChanged(GamepadAxis, f32)
+}
+
+/// Source of gamepad events, gamepads are identified by a backend specific index.
+pub trait GamepadBackend {
+    fn poll(&mut self, events: &mut Vec<(usize, GamepadEvent)>);
+    fn rumble(&mut self, gamepad: usize, rumble: Rumble);
+}
+
+pub struct Gamepad {
+    pub buttons: ButtonSet<GamepadButton>,
+    pub deadzone: Deadzone,
+    axes: [f32; 6],
+    connected: bool
+}
+
+impl Default for Gamepad {
+    fn default() -> Gamepad {
+        return Gamepad { buttons: ButtonSet::default(), deadzone: Deadzone::default(), axes: [0.0; 6], connected: true };
+    }
+}
+
+impl Gamepad {
+    /// False for the one frame after a disconnect, in which its held buttons read as released.
+    pub fn connected(&self) -> bool { self.connected }
+
+    pub fn pressed(&self, button: GamepadButton) -> bool { self.buttons.pressed(button) }
+    pub fn held(&self, button: GamepadButton) -> bool { self.buttons.held(button) }
+    pub fn released(&self, button: GamepadButton) -> bool { self.buttons.released(button) }
+
+    /// Axis value before any deadzone is applied.
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 { self.axes[axis.index()] }
+
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        return match axis {
+            GamepadAxis::LeftStickX => self.left_stick()[0],
+            GamepadAxis::LeftStickY => self.left_stick()[1],
+            GamepadAxis::RightStickX => self.right_stick()[0],
+            GamepadAxis::RightStickY => self.right_stick()[1],
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger =>
+                apply_deadzone(self.raw_axis(axis), self.deadzone.trigger)
+        };
+    }
+
+    pub fn left_stick(&self) -> [f32; 2] {
+        return self.stick(GamepadAxis::LeftStickX, GamepadAxis::LeftStickY);
+    }
+
+    pub fn right_stick(&self) -> [f32; 2] {
+        return self.stick(GamepadAxis::RightStickX, GamepadAxis::RightStickY);
+    }
+
+    fn stick(&self, x: GamepadAxis, y: GamepadAxis) -> [f32; 2] {
+        let (x, y) = (self.raw_axis(x), self.raw_axis(y));
+        let magnitude = (x * x + y * y).sqrt();
+        if magnitude <= self.deadzone.stick { return [0.0, 0.0]; }
+
+        let scale = apply_deadzone(magnitude, self.deadzone.stick) / magnitude;
+        return [x * scale, y * scale];
+    }
+
+    fn handle_event(&mut self, event: GamepadEvent) {
+        match event {
+            GamepadEvent::ButtonPressed(button) => self.buttons.press(button),
+            GamepadEvent::ButtonReleased(button) => self.buttons.release(button),
+            GamepadEvent::AxisChanged(axis, value) => {
+                self.axes[axis.index()] = value.clamp(-1.0, 1.0);
+
+                let button = match axis {
+                    GamepadAxis::LeftTrigger => GamepadButton::LeftTrigger,
+                    GamepadAxis::RightTrigger => GamepadButton::RightTrigger,
+                    _ => return
+                };
+                if value > TRIGGER_PRESS_THRESHOLD { self.buttons.press(button); } else { self.buttons.release(button); }
+            },
+            GamepadEvent::Disconnected => {
+                self.buttons.release_all();
+                self.axes = [0.0; 6];
+                self.connected = false;
+            },
+            GamepadEvent::Connected => ()
+        }
+    }
+}
+
+/// Maps `deadzone..=1.0` back onto `0.0..=1.0` so there is no jump at the edge of the deadzone.
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() <= deadzone { return 0.0; }
+    return value.signum() * ((value.abs() - deadzone) / (1.0 - deadzone)).min(1.0);
+}
+
+/// Every connected gamepad, polled from the backend once per frame by the engine.
+pub struct Gamepads {
+    deadzone: Deadzone,
+    backend: Option<Box<dyn GamepadBackend>>,
+    gamepads: BTreeMap<usize, Gamepad>,
+    events: Vec<(usize, GamepadEvent)>
+}
+
+impl Default for Gamepads {
+    #[cfg(feature = "gilrs")]
+    fn default() -> Gamepads {
+        return match GilrsBackend::new() {
+            Some(backend) => Gamepads::new(Box::new(backend)),
+            None => Gamepads::disconnected()
+        };
+    }
+
+    #[cfg(not(feature = "gilrs"))]
+    fn default() -> Gamepads { Gamepads::disconnected() }
+}
+
+impl Gamepads {
+    pub fn new(backend: Box<dyn GamepadBackend>) -> Gamepads {
+        return Gamepads { backend: Some(backend), ..Gamepads::disconnected() };
+    }
+
+    /// No backend, for platforms without gamepad support.
+    pub fn disconnected() -> Gamepads {
+        return Gamepads { deadzone: Deadzone::default(), backend: None, gamepads: BTreeMap::new(), events: Vec::new() };
+    }
+
+    pub fn deadzone(&self) -> Deadzone { self.deadzone }
+
+    /// Used for gamepads connected from now on and applied to the ones already connected.
+    pub fn set_deadzone(&mut self, deadzone: Deadzone) {
+        self.deadzone = deadzone;
+        for gamepad in self.gamepads.values_mut() { gamepad.deadzone = deadzone; }
+    }
+
+    pub fn update(&mut self) {
+        let Some(backend) = self.backend.as_mut() else { return; };
+        backend.poll(&mut self.events);
+
+        for (id, event) in self.events.drain(..) {
+            match event {
+                GamepadEvent::Connected => {
+                    self.gamepads.insert(id, Gamepad { deadzone: self.deadzone, ..Default::default() });
+                },
+                _ => if let Some(gamepad) = self.gamepads.get_mut(&id) { gamepad.handle_event(event); }
+            }
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Gamepad> { self.gamepads.get(&id) }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Gamepad> { self.gamepads.get_mut(&id) }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Gamepad)> {
+        return self.gamepads.iter().map(|(id, gamepad)| (*id, gamepad));
+    }
+
+    pub fn rumble(&mut self, id: usize, rumble: Rumble) {
+        if !self.gamepads.get(&id).is_some_and(Gamepad::connected) { return; }
+        if let Some(backend) = self.backend.as_mut() { backend.rumble(id, rumble); }
+    }
+
+    pub fn pressed(&self, button: GamepadButton) -> bool { self.iter().any(|(_, gamepad)| gamepad.pressed(button)) }
+    pub fn held(&self, button: GamepadButton) -> bool { self.iter().any(|(_, gamepad)| gamepad.held(button)) }
+    pub fn released(&self, button: GamepadButton) -> bool { self.iter().any(|(_, gamepad)| gamepad.released(button)) }
+
+    /// Strongest deflection of `axis` over all connected gamepads.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        return self.iter().map(|(_, gamepad)| gamepad.axis(axis)).fold(0.0, |strongest, value| {
+            if value.abs() > strongest.abs() { value } else { strongest }
+        });
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.gamepads.retain(|_, gamepad| gamepad.connected);
+        for gamepad in self.gamepads.values_mut() { gamepad.buttons.end_frame(); }
+    }
+}
+
+#[derive(Default)]
+struct VirtualGamepadState {
+    events: Vec<(usize, GamepadEvent)>,
+    rumbles: Vec<(usize, Rumble)>
+}
+
+/// Scriptable backend for machines without a controller. Clones share state, so a test keeps
+/// one handle to drive input while `Gamepads` owns the other.
+#[derive(Clone, Default)]
+pub struct VirtualGamepad {
+    state: Rc<RefCell<VirtualGamepadState>>
+}
+
+impl VirtualGamepad {
+    pub fn new() -> VirtualGamepad { VirtualGamepad::default() }
+
+    /// Queues an event, it is applied on the next `Gamepads::update`.
+    pub fn send(&self, id: usize, event: GamepadEvent) {
+        self.state.borrow_mut().events.push((id, event));
+    }
+
+    pub fn connect(&self, id: usize) { self.send(id, GamepadEvent::Connected); }
+    pub fn disconnect(&self, id: usize) { self.send(id, GamepadEvent::Disconnected); }
+    pub fn press(&self, id: usize, button: GamepadButton) { self.send(id, GamepadEvent::ButtonPressed(button)); }
+    pub fn release(&self, id: usize, button: GamepadButton) { self.send(id, GamepadEvent::ButtonReleased(button)); }
+    pub fn set_axis(&self, id: usize, axis: GamepadAxis, value: f32) { self.send(id, GamepadEvent::AxisChanged(axis, value)); }
+
+    /// Rumble requests received so far, oldest first.
+    pub fn rumbles(&self) -> Vec<(usize, Rumble)> { self.state.borrow().rumbles.clone() }
+}
+
+impl GamepadBackend for VirtualGamepad {
+    fn poll(&mut self, events: &mut Vec<(usize, GamepadEvent)>) {
+        events.append(&mut self.state.borrow_mut().events);
+    }
+
+    fn rumble(&mut self, gamepad: usize, rumble: Rumble) {
+        self.state.borrow_mut().rumbles.push((gamepad, rumble));
+    }
+}
+
+#[cfg(feature = "gilrs")]
+pub struct GilrsBackend {
+    gilrs: gilrs::Gilrs,
+    effects: std::collections::HashMap<usize, gilrs::ff::Effect>
+}
+
+#[cfg(feature = "gilrs")]
+impl GilrsBackend {
+    pub fn new() -> Option<GilrsBackend> {
+        return gilrs::Gilrs::new().ok().map(|gilrs| GilrsBackend { gilrs, effects: Default::default() });
+    }
+
+    fn button(button: gilrs::Button) -> Option<GamepadButton> {
+        return Some(match button {
+            gilrs::Button::South => GamepadButton::South,
+            gilrs::Button::East => GamepadButton::East,
+            gilrs::Button::North => GamepadButton::North,
+            gilrs::Button::West => GamepadButton::West,
+            gilrs::Button::LeftTrigger => GamepadButton::LeftBumper,
+            gilrs::Button::RightTrigger => GamepadButton::RightBumper,
+            gilrs::Button::Select => GamepadButton::Select,
+            gilrs::Button::Start => GamepadButton::Start,
+            gilrs::Button::Mode => GamepadButton::Mode,
+            gilrs::Button::LeftThumb => GamepadButton::LeftStick,
+            gilrs::Button::RightThumb => GamepadButton::RightStick,
+            gilrs::Button::DPadUp => GamepadButton::DPadUp,
+            gilrs::Button::DPadDown => GamepadButton::DPadDown,
+            gilrs::Button::DPadLeft => GamepadButton::DPadLeft,
+            gilrs::Button::DPadRight => GamepadButton::DPadRight,
+            _ => return None
+        });
+    }
+
+    fn axis(axis: gilrs::Axis) -> Option<GamepadAxis> {
+        return Some(match axis {
+            gilrs::Axis::LeftStickX => GamepadAxis::LeftStickX,
+            gilrs::Axis::LeftStickY => GamepadAxis::LeftStickY,
+            gilrs::Axis::RightStickX => GamepadAxis::RightStickX,
+            gilrs::Axis::RightStickY => GamepadAxis::RightStickY,
+            _ => return None
+        });
+    }
+}
+
+#[cfg(feature = "gilrs")]
+impl GamepadBackend for GilrsBackend {
+    fn poll(&mut self, events: &mut Vec<(usize, GamepadEvent)>) {
+        use gilrs::{Button, EventType};
+
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let id = usize::from(id);
+            let event = match event {
+                EventType::Connected => GamepadEvent::Connected,
+                EventType::Disconnected => {
+                    self.effects.remove(&id);
+                    GamepadEvent::Disconnected
+                },
+                // Analog triggers report their value through `ButtonChanged`, the press is derived from it.
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => GamepadEvent::AxisChanged(GamepadAxis::LeftTrigger, value),
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => GamepadEvent::AxisChanged(GamepadAxis::RightTrigger, value),
+                EventType::ButtonPressed(button, _) => match Self::button(button) {
+                    Some(button) => GamepadEvent::ButtonPressed(button),
+                    None => continue
+                },
+                EventType::ButtonReleased(button, _) => match Self::button(button) {
+                    Some(button) => GamepadEvent::ButtonReleased(button),
+                    None => continue
+                },
+                EventType::AxisChanged(axis, value, _) => match Self::axis(axis) {
+                    Some(axis) => GamepadEvent::AxisChanged(axis, value),
+                    None => continue
+                },
+                _ => continue
+            };
+            events.push((id, event));
+        }
+    }
+
+    fn rumble(&mut self, gamepad: usize, rumble: Rumble) {
+        use gilrs::ff::{BaseEffect, BaseEffectType, EffectBuilder, Replay, Repeat, Ticks};
+
+        let Some((id, _)) = self.gilrs.gamepads().find(|(id, _)| usize::from(*id) == gamepad) else { return; };
+        if rumble.strong <= 0.0 && rumble.weak <= 0.0 {
+            self.effects.remove(&gamepad);
+            return;
+        }
+
+        let play_for = Ticks::from_ms(rumble.duration.as_millis().min(u32::MAX as u128) as u32);
+        let scheduling = Replay { play_for, ..Default::default() };
+        let magnitude = |strength: f32| (strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect { kind: BaseEffectType::Strong { magnitude: magnitude(rumble.strong) }, scheduling, ..Default::default() })
+            .add_effect(BaseEffect { kind: BaseEffectType::Weak { magnitude: magnitude(rumble.weak) }, scheduling, ..Default::default() })
+            .gamepads(&[id])
+            .repeat(Repeat::For(play_for))
+            .finish(&mut self.gilrs);
+
+        // Dropping an effect stops it, so it is kept until replaced or the gamepad disconnects.
+        if let Ok(effect) = effect {
+            if effect.play().is_ok() { self.effects.insert(gamepad, effect); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connected() -> (VirtualGamepad, Gamepads) {
+        let pad = VirtualGamepad::new();
+        let mut gamepads = Gamepads::new(Box::new(pad.clone()));
+        pad.connect(0);
+        gamepads.update();
+        return (pad, gamepads);
+    }
+
+    #[test]
+    fn deadzone_rescales_past_the_edge() {
+        assert_eq!(apply_deadzone(0.1, 0.2), 0.0);
+        assert_eq!(apply_deadzone(-0.2, 0.2), 0.0);
+        assert!((apply_deadzone(0.6, 0.2) - 0.5).abs() < 1e-6);
+        assert!((apply_deadzone(-0.6, 0.2) + 0.5).abs() < 1e-6);
+        assert_eq!(apply_deadzone(1.0, 0.2), 1.0);
+    }
+
+    #[test]
+    fn stick_deadzone_is_radial() {
+        let (pad, mut gamepads) = connected();
+        pad.set_axis(0, GamepadAxis::LeftStickX, 0.1);
+        pad.set_axis(0, GamepadAxis::LeftStickY, 0.1);
+        gamepads.update();
+        assert_eq!(gamepads.get(0).unwrap().left_stick(), [0.0, 0.0]);
+
+        pad.set_axis(0, GamepadAxis::LeftStickX, 0.5);
+        pad.set_axis(0, GamepadAxis::LeftStickY, 0.5);
+        gamepads.update();
+        let [x, y] = gamepads.get(0).unwrap().left_stick();
+        assert!(x > 0.0 && (x - y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn deadzone_changes_reach_connected_pads() {
+        let (pad, mut gamepads) = connected();
+        pad.set_axis(0, GamepadAxis::RightTrigger, 0.3);
+        gamepads.update();
+        assert!(gamepads.axis(GamepadAxis::RightTrigger) > 0.0);
+
+        gamepads.set_deadzone(Deadzone { stick: 0.15, trigger: 0.4 });
+        assert_eq!(gamepads.get(0).unwrap().deadzone.trigger, 0.4);
+        assert_eq!(gamepads.axis(GamepadAxis::RightTrigger), 0.0);
+    }
+
+    #[test]
+    fn axes_map_to_the_strongest_pad() {
+        let (pad, mut gamepads) = connected();
+        pad.connect(1);
+        pad.set_axis(0, GamepadAxis::RightStickY, 0.5);
+        pad.set_axis(1, GamepadAxis::RightStickY, -1.0);
+        pad.set_axis(1, GamepadAxis::LeftStickX, 3.0);
+        gamepads.update();
+
+        assert_eq!(gamepads.axis(GamepadAxis::RightStickY), -1.0);
+        assert_eq!(gamepads.axis(GamepadAxis::RightStickX), 0.0);
+        assert_eq!(gamepads.get(1).unwrap().raw_axis(GamepadAxis::LeftStickX), 1.0);
+    }
+
+    #[test]
+    fn buttons_press_hold_and_release() {
+        let (pad, mut gamepads) = connected();
+        pad.press(0, GamepadButton::South);
+        gamepads.update();
+        assert!(gamepads.pressed(GamepadButton::South) && gamepads.held(GamepadButton::South));
+        assert!(!gamepads.pressed(GamepadButton::East));
+
+        gamepads.end_frame();
+        assert!(!gamepads.pressed(GamepadButton::South) && gamepads.held(GamepadButton::South));
+
+        pad.release(0, GamepadButton::South);
+        gamepads.update();
+        assert!(gamepads.released(GamepadButton::South) && !gamepads.held(GamepadButton::South));
+    }
+
+    #[test]
+    fn triggers_count_as_buttons() {
+        let (pad, mut gamepads) = connected();
+        pad.set_axis(0, GamepadAxis::LeftTrigger, 0.8);
+        gamepads.update();
+        assert!(gamepads.pressed(GamepadButton::LeftTrigger));
+
+        pad.set_axis(0, GamepadAxis::LeftTrigger, 0.2);
+        gamepads.update();
+        assert!(gamepads.released(GamepadButton::LeftTrigger));
+    }
+
+    #[test]
+    fn disconnect_releases_held_buttons() {
+        let (pad, mut gamepads) = connected();
+        pad.press(0, GamepadButton::Start);
+        gamepads.update();
+        gamepads.end_frame();
+
+        pad.disconnect(0);
+        gamepads.update();
+        assert!(gamepads.released(GamepadButton::Start));
+        assert!(!gamepads.get(0).unwrap().connected());
+
+        gamepads.end_frame();
+        assert!(gamepads.get(0).is_none());
+    }
+
+    #[test]
+    fn rumble_reaches_connected_pads_only() {
+        let (pad, mut gamepads) = connected();
+        let rumble = Rumble { strong: 1.0, weak: 0.5, duration: Duration::from_millis(200) };
+        gamepads.rumble(0, rumble);
+        gamepads.rumble(3, rumble);
+
+        pad.disconnect(0);
+        gamepads.update();
+        gamepads.rumble(0, rumble);
+        assert_eq!(pad.rumbles(), vec![(0, rumble)]);
+    }
+}
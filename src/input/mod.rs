@@ -0,0 +1,3 @@
+pub mod action;
+pub mod gamepad;
+pub mod state;
@@ -0,0 +1,294 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use winit::event::{DeviceEvent, ElementState, Ime, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+use super::action::{ActionMap, Binding};
+use super::gamepad::Gamepads;
+
+/// Pixel deltas from touchpads are converted to lines so `scroll` has one unit.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+pub struct ButtonSet<T> {
+    held: HashSet<T>,
+    pressed: HashSet<T>,
+    released: HashSet<T>
+}
+
+impl<T> Default for ButtonSet<T> {
+    fn default() -> ButtonSet<T> {
+        return ButtonSet { held: HashSet::new(), pressed: HashSet::new(), released: HashSet::new() };
+    }
+}
+
+impl<T: Copy + Eq + Hash> ButtonSet<T> {
+    pub fn press(&mut self, button: T) {
+        if self.held.insert(button) { self.pressed.insert(button); }
+    }
+
+    pub fn release(&mut self, button: T) {
+        if self.held.remove(&button) { self.released.insert(button); }
+    }
+
+    /// Went down since the last frame.
+    pub fn pressed(&self, button: T) -> bool { self.pressed.contains(&button) }
+    /// Is down, including the frame it was pressed on.
+    pub fn held(&self, button: T) -> bool { self.held.contains(&button) }
+    /// Went up since the last frame.
+    pub fn released(&self, button: T) -> bool { self.released.contains(&button) }
+
+    pub fn release_all(&mut self) {
+        for button in self.held.drain() { self.released.insert(button); }
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
+
+/// Keyboard, mouse and gamepad state for the current frame, fed by the engine from window events.
+#[derive(Default)]
+pub struct Input {
+    pub keys: ButtonSet<KeyCode>,
+    pub mouse_buttons: ButtonSet<MouseButton>,
+    pub gamepads: Gamepads,
+    pub actions: ActionMap,
+    cursor_position: Option<[f32; 2]>,
+    cursor_delta: [f32; 2],
+    mouse_motion: [f32; 2],
+    scroll: [f32; 2],
+    text: String
+}
+
+impl Input {
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    match event.state {
+                        ElementState::Pressed => self.keys.press(key),
+                        ElementState::Released => self.keys.release(key)
+                    }
+                }
+                if event.state == ElementState::Pressed {
+                    if let Some(text) = &event.text {
+                        self.text.extend(text.chars().filter(|character| !character.is_control()));
+                    }
+                }
+            },
+            WindowEvent::Ime(Ime::Commit(text)) => self.text.push_str(text),
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => self.mouse_buttons.press(*button),
+                ElementState::Released => self.mouse_buttons.release(*button)
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = [position.x as f32, position.y as f32];
+                if let Some(last) = self.cursor_position {
+                    self.cursor_delta[0] += position[0] - last[0];
+                    self.cursor_delta[1] += position[1] - last[1];
+                }
+                self.cursor_position = Some(position);
+            },
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(position) =>
+                        (position.x as f32 / PIXELS_PER_LINE, position.y as f32 / PIXELS_PER_LINE)
+                };
+                self.scroll[0] += x;
+                self.scroll[1] += y;
+            },
+            WindowEvent::Focused(false) => {
+                self.keys.release_all();
+                self.mouse_buttons.release_all();
+            },
+            _ => ()
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.mouse_motion[0] += delta.0 as f32;
+            self.mouse_motion[1] += delta.1 as f32;
+        }
+    }
+
+    /// Clears everything that only lasts one frame, called by the engine after rendering.
+    pub fn end_frame(&mut self) {
+        self.keys.end_frame();
+        self.mouse_buttons.end_frame();
+        self.gamepads.end_frame();
+        self.cursor_delta = [0.0, 0.0];
+        self.mouse_motion = [0.0, 0.0];
+        self.scroll = [0.0, 0.0];
+        self.text.clear();
+    }
+
+    pub fn key_pressed(&self, key: KeyCode) -> bool { self.keys.pressed(key) }
+    pub fn key_held(&self, key: KeyCode) -> bool { self.keys.held(key) }
+    pub fn key_released(&self, key: KeyCode) -> bool { self.keys.released(key) }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool { self.mouse_buttons.pressed(button) }
+    pub fn mouse_held(&self, button: MouseButton) -> bool { self.mouse_buttons.held(button) }
+    pub fn mouse_released(&self, button: MouseButton) -> bool { self.mouse_buttons.released(button) }
+
+    /// Cursor position in physical pixels from the top left, `None` while outside the window.
+    /// `Window::to_logical` converts it for UI laid out in logical pixels.
+    pub fn cursor_position(&self) -> Option<[f32; 2]> { self.cursor_position }
+    /// How far the cursor moved over the window this frame.
+    pub fn cursor_delta(&self) -> [f32; 2] { self.cursor_delta }
+    /// Raw mouse movement this frame, keeps reporting while the cursor is grabbed or at the screen edge.
+    pub fn mouse_motion(&self) -> [f32; 2] { self.mouse_motion }
+    /// Scrolled lines this frame, positive y is away from the user.
+    pub fn scroll(&self) -> [f32; 2] { self.scroll }
+    /// Characters typed this frame, for text fields.
+    pub fn text(&self) -> &str { &self.text }
+
+    pub fn binding_pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.key_pressed(key),
+            Binding::Mouse(button) => self.mouse_pressed(button),
+            Binding::Gamepad(button) => self.gamepads.pressed(button)
+        }
+    }
+
+    pub fn binding_held(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.key_held(key),
+            Binding::Mouse(button) => self.mouse_held(button),
+            Binding::Gamepad(button) => self.gamepads.held(button)
+        }
+    }
+
+    pub fn binding_released(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.key_released(key),
+            Binding::Mouse(button) => self.mouse_released(button),
+            Binding::Gamepad(button) => self.gamepads.released(button)
+        }
+    }
+
+    pub fn action_pressed(&self, action: &str) -> bool {
+        return self.actions.bindings(action).iter().any(|binding| self.binding_pressed(*binding));
+    }
+
+    pub fn action_held(&self, action: &str) -> bool {
+        return self.actions.bindings(action).iter().any(|binding| self.binding_held(*binding));
+    }
+
+    pub fn action_released(&self, action: &str) -> bool {
+        return self.actions.bindings(action).iter().any(|binding| self.binding_released(*binding))
+            && !self.action_held(action);
+    }
+
+    /// Value of a named axis in `-1.0..=1.0`, opposing bindings held together cancel out
+    /// and analog gamepad axes are added on top.
+    pub fn axis(&self, axis: &str) -> f32 {
+        let Some(axis) = self.actions.axis(axis) else { return 0.0; };
+
+        let positive = axis.positive.iter().any(|binding| self.binding_held(*binding));
+        let negative = axis.negative.iter().any(|binding| self.binding_held(*binding));
+        let analog: f32 = axis.analog.iter().map(|analog| self.gamepads.axis(*analog)).sum();
+
+        return ((positive as i8 - negative as i8) as f32 + analog).clamp(-1.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::gamepad::{GamepadAxis, VirtualGamepad};
+
+    fn input_with_pad() -> (Input, VirtualGamepad) {
+        let pad = VirtualGamepad::new();
+        let mut input = Input { gamepads: Gamepads::new(Box::new(pad.clone())), ..Input::default() };
+        input.actions.bind_axis("move", Binding::Key(KeyCode::KeyD), Binding::Key(KeyCode::KeyA));
+        input.actions.bind_analog("move", GamepadAxis::LeftStickX);
+        pad.connect(0);
+        input.gamepads.update();
+        return (input, pad);
+    }
+
+    fn set_stick(input: &mut Input, pad: &VirtualGamepad, value: f32) {
+        pad.set_axis(0, GamepadAxis::LeftStickX, value);
+        input.gamepads.update();
+    }
+
+    #[test]
+    fn button_transitions() {
+        let mut buttons = ButtonSet::default();
+        buttons.press(KeyCode::KeyW);
+        assert!(buttons.pressed(KeyCode::KeyW) && buttons.held(KeyCode::KeyW) && !buttons.released(KeyCode::KeyW));
+
+        // Repeats while held don't press again.
+        buttons.end_frame();
+        buttons.press(KeyCode::KeyW);
+        assert!(!buttons.pressed(KeyCode::KeyW) && buttons.held(KeyCode::KeyW));
+
+        buttons.release(KeyCode::KeyW);
+        assert!(!buttons.held(KeyCode::KeyW) && buttons.released(KeyCode::KeyW));
+        buttons.end_frame();
+        assert!(!buttons.released(KeyCode::KeyW));
+
+        // Pressed and released within one frame still shows both.
+        buttons.press(KeyCode::KeyW);
+        buttons.release(KeyCode::KeyW);
+        assert!(buttons.pressed(KeyCode::KeyW) && buttons.released(KeyCode::KeyW) && !buttons.held(KeyCode::KeyW));
+
+        // Releasing what isn't held does nothing.
+        buttons.end_frame();
+        buttons.release(KeyCode::KeyS);
+        assert!(!buttons.released(KeyCode::KeyS));
+    }
+
+    #[test]
+    fn opposing_keys_cancel() {
+        let (mut input, _pad) = input_with_pad();
+        input.keys.press(KeyCode::KeyD);
+        assert_eq!(input.axis("move"), 1.0);
+        input.keys.press(KeyCode::KeyA);
+        assert_eq!(input.axis("move"), 0.0);
+        input.keys.release(KeyCode::KeyD);
+        assert_eq!(input.axis("move"), -1.0);
+        assert_eq!(input.axis("missing"), 0.0);
+    }
+
+    #[test]
+    fn analog_adds_on_top_and_clamps() {
+        let (mut input, pad) = input_with_pad();
+        // Half way past the 0.15 deadzone.
+        set_stick(&mut input, &pad, 0.575);
+        assert!((input.axis("move") - 0.5).abs() < 1e-5);
+
+        input.keys.press(KeyCode::KeyD);
+        assert_eq!(input.axis("move"), 1.0);
+        input.keys.release(KeyCode::KeyD);
+        input.keys.press(KeyCode::KeyA);
+        assert!((input.axis("move") + 0.5).abs() < 1e-5);
+
+        set_stick(&mut input, &pad, -1.0);
+        assert_eq!(input.axis("move"), -1.0);
+        input.keys.press(KeyCode::KeyD);
+        assert_eq!(input.axis("move"), -1.0);
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = Input::default();
+        input.actions.bind("fire", Binding::Mouse(MouseButton::Left));
+        input.keys.press(KeyCode::KeyW);
+        input.mouse_buttons.press(MouseButton::Left);
+        input.end_frame();
+
+        input.handle_event(&WindowEvent::Focused(false));
+        assert!(!input.key_held(KeyCode::KeyW) && input.key_released(KeyCode::KeyW));
+        assert!(!input.mouse_held(MouseButton::Left) && input.action_released("fire"));
+
+        input.end_frame();
+        assert!(!input.key_released(KeyCode::KeyW) && !input.action_held("fire"));
+        input.handle_event(&WindowEvent::Focused(true));
+        assert!(!input.key_held(KeyCode::KeyW));
+    }
+}
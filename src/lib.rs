@@ -0,0 +1,17 @@
+#![allow(clippy::needless_return)]
+
+#[macro_use]
+pub extern crate glium;
+
+#[macro_export]
+macro_rules! inner_path {
+    ($path:expr) => {
+        { std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join($path) }
+    };
+}
+
+pub use winit;
+
+pub mod engine;
+pub mod graphics;
+pub mod input;
@@ -1,15 +1,534 @@
-#[macro_use]
-extern crate glium;
+#![allow(clippy::needless_return)]
 
-macro_rules! inner_path {
-    ($path:expr) => {
-        { std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join($path) }
-    };
+use gameengine::engine::{App, Context, Engine};
+use gameengine::graphics::opengl::{get_position, Material};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use gameengine::graphics::animation::{AnimationClip, Animator, PlayMode};
+use gameengine::graphics::atlas::{Atlas, AtlasBuilder, AtlasRegion};
+use gameengine::graphics::camera::Camera2d;
+use gameengine::graphics::context::Display;
+use gameengine::graphics::debug_draw::{DebugDraw, DebugStyle};
+use gameengine::graphics::debug_ui::DebugUi;
+use gameengine::graphics::lighting::{DirectionalLight, Light, Lighting, PointLight, SpotLight};
+use gameengine::graphics::math::IDENTITY;
+use gameengine::graphics::pbr::{Environment, PbrMaterial, PbrRenderer, PbrScene};
+use gameengine::graphics::post::PostStack;
+use gameengine::graphics::shaders::shader::{prepend, LIGHTS};
+use gameengine::graphics::shadow::{ShadowFlags, ShadowSettings, ShadowView, Shadows};
+use gameengine::graphics::sprite::{Sprite, SpriteBatch, SpriteRenderer};
+use gameengine::graphics::text::{FontId, TextRenderer, TextStyle};
+use gameengine::graphics::tilemap::Tilemap;
+use gameengine::graphics::texture::{Texture, TextureFormat, TextureSettings};
+use gameengine::graphics::ui::{NodeId, Ui, Widget};
+use gameengine::graphics::types::{RenderVertex, Rotate};
+use gameengine::graphics::window::{WindowConfig, WindowMode};
+use gameengine::graphics::window_manager::WindowHandle;
+use gameengine::input::action::ActionMap;
+use gameengine::inner_path;
+use glium::{uniform, Surface};
+
+struct Scene {
+    program: glium::Program,
+    indices: glium::IndexBuffer<u16>,
+    atlas: Atlas,
+    sprites: SpriteRenderer,
+    flipbook: Rc<AnimationClip>,
+    text: TextRenderer,
+    font: FontId,
+    ui: DebugUi,
+    menu: PauseMenu,
+    debug: DebugDraw,
+    lighting: Lighting,
+    materials: [glium::uniforms::UniformBuffer<Material>; 3],
+    shapes: [Vec<RenderVertex>; 3],
+    pbr: PbrRenderer,
+    environment: Environment,
+    metal: Vec<RenderVertex>,
+    metal_material: PbrMaterial,
+    /// Backdrop that only receives shadows.
+    wall: Vec<RenderVertex>,
+    wall_material: PbrMaterial,
+    shadows: Shadows,
+    /// None if the map failed to load, the rest of the demo runs without it.
+    tilemap: Option<Tilemap>
+}
+
+/// Values the debug UI can change while the demo runs.
+struct Tuning {
+    speed: f32,
+    sprites: bool,
+    /// Bounds, pivots and axes of the quads.
+    debug_shapes: bool,
+    roughness: f32,
+    shadows: bool,
+    /// Lights shaded per frame, a slider so whole numbers are taken from it.
+    light_limit: f32,
+    caption: String
+}
+
+impl Default for Tuning {
+    fn default() -> Tuning {
+        return Tuning { speed: 0.5, sprites: true, debug_shapes: false, roughness: 0.3, shadows: true, light_limit: 3.0, caption: String::new() };
+    }
+}
+
+#[derive(Copy, Clone)]
+enum MenuCommand {
+    Resume,
+    ToggleSprites,
+    Quit
+}
+
+/// Menu shown while the demo is paused, its buttons queue commands for `update`.
+struct PauseMenu {
+    ui: Ui,
+    panel: NodeId,
+    resume: NodeId,
+    sprites: NodeId,
+    commands: Rc<RefCell<Vec<MenuCommand>>>
+}
+
+impl PauseMenu {
+    fn new(display: &Display) -> PauseMenu {
+        let mut ui = Ui::new(display);
+        ui.load_stylesheet(inner_path!("config/ui.toml")).unwrap();
+        let mut panel = AtlasBuilder::default();
+        panel.add_file(inner_path!("img/panel.png")).unwrap();
+        ui.set_atlas(Atlas::new(display, panel.build().unwrap(), &TextureSettings { mipmaps: false, .. TextureSettings::default() }).unwrap());
+
+        let root = ui.root();
+        let panel = ui.add(root, Widget::Panel);
+        ui.set_name(panel, "pause_menu");
+        let title = ui.add(panel, Widget::Label("Paused".to_string()));
+        ui.add_class(title, "title");
+
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let button = |ui: &mut Ui, text: &str, command: MenuCommand| {
+            let button = ui.add(panel, Widget::Button(text.to_string()));
+            let commands = commands.clone();
+            ui.on_click(button, move |_, _| commands.borrow_mut().push(command));
+            return button;
+        };
+        let resume = button(&mut ui, "Resume", MenuCommand::Resume);
+        let sprites = button(&mut ui, "Hide sprites", MenuCommand::ToggleSprites);
+        button(&mut ui, "Quit", MenuCommand::Quit);
+
+        ui.set_visible(panel, false);
+        return PauseMenu { ui, panel, resume, sprites, commands };
+    }
+}
+
+#[derive(Default)]
+struct Demo {
+    scene: Option<Scene>,
+    paused: bool,
+    windowed_mode: Option<WindowMode>,
+    preview: Option<WindowHandle>,
+    animator: Animator,
+    tuning: Tuning,
+    /// Milliseconds of the last frames, oldest first.
+    frame_times: VecDeque<f32>,
+    /// Seconds since the start, moves the point light.
+    time: f32
+}
+
+/// The quads start out facing the viewer, who looks down +z in clip space.
+const FRONT: [f32; 3] = [0.0, 0.0, -1.0];
+const CAMERA_POSITION: [f32; 3] = [0.0, 0.0, -3.0];
+
+/// Faces of a sky fading from a dark ground to blue overhead, with a sun behind the viewer for the metal
+/// quad to reflect.
+fn sky_faces() -> Vec<image::DynamicImage> {
+    const SIZE: u32 = 32;
+    let (ground, horizon, zenith) = ([0.15, 0.12, 0.1], [0.9, 0.9, 1.0], [0.25, 0.45, 0.9]);
+    let mix = |a: [f32; 3], b: [f32; 3], t: f32| std::array::from_fn::<f32, 3, _>(|channel| a[channel] + (b[channel] - a[channel]) * t);
+
+    return (0..6).map(|face| {
+        let image = image::Rgba32FImage::from_fn(SIZE, SIZE, |x, y| {
+            // Rows run from the top of the side faces down.
+            let height = 1.0 - (y as f32 + 0.5) / SIZE as f32 * 2.0;
+            let mut color = match face {
+                2 => zenith,
+                3 => ground,
+                _ if height >= 0.0 => mix(horizon, zenith, height),
+                _ => mix(horizon, ground, (-height * 4.0).min(1.0))
+            };
+            let sun = ((x as f32 - 20.0).powi(2) + (y as f32 - 10.0).powi(2)).sqrt();
+            if face == 5 && sun < 3.0 { color = [20.0, 18.0, 14.0]; }
+            return image::Rgba([color[0], color[1], color[2], 1.0]);
+        });
+        return image::DynamicImage::ImageRgba32F(image);
+    }).collect();
+}
+
+fn quad(offset: [f32; 2], region: &AtlasRegion) -> Vec<RenderVertex> {
+    let ([u0, v0], [u1, v1]) = (region.uv_min, region.uv_max);
+    return vec![
+        RenderVertex { position: [-0.5+offset[0], -0.5+offset[1], 0.0], texture_coords: [u0, v0], normal: FRONT },
+        RenderVertex { position: [-0.5+offset[0],  0.5+offset[1], 0.0], texture_coords: [u0, v1], normal: FRONT },
+        RenderVertex { position: [ 0.5+offset[0],  0.5+offset[1], 0.0], texture_coords: [u1, v1], normal: FRONT },
+        RenderVertex { position: [ 0.5+offset[0], -0.5+offset[1], 0.0], texture_coords: [u1, v0], normal: FRONT },
+    ];
 }
 
-pub mod graphics;
+impl App for Demo {
+    fn init(&mut self, context: &mut Context) {
+        context.input.actions = ActionMap::load(inner_path!("config/input.toml")).unwrap();
+        // Without the stack the scene is drawn as it is, untonemapped.
+        match PostStack::load(inner_path!("config/post.toml")) {
+            Ok(post) => context.post = post,
+            Err(error) => eprintln!("{}", error)
+        }
+
+        let display = &context.window.display;
+
+        const U32_INDICES: [u16; 6] = [0, 1, 2, 0, 3, 2];
+
+        let vertex_shader = std::fs::read_to_string(inner_path!("shaders/simple.vs")).unwrap();
+        let fragment_shader = std::fs::read_to_string(inner_path!("shaders/simple.fs")).unwrap();
+
+        let mut atlas = AtlasBuilder::default();
+        atlas.add_file(inner_path!("img/opengl_logo.png")).unwrap();
+        atlas.add_file(inner_path!("img/pngegg.png")).unwrap();
+        let atlas = Atlas::new(display, atlas.build().unwrap(), &TextureSettings::default()).unwrap();
+        let (logo, egg) = (atlas.get("opengl_logo").unwrap(), atlas.get("pngegg").unwrap());
+        let flipbook = Rc::new(AnimationClip::from_regions(&atlas, &["opengl_logo", "pngegg"], 0.5, PlayMode::PingPong).unwrap());
+        let shapes = [quad([0.0, 0.0], logo), quad([1.5, 0.0], logo), quad([0.0, 0.5], egg)];
+        let metal = quad([-1.0, -0.5], logo);
+        let wall = quad([0.0, 0.0], logo).into_iter()
+            .map(|vertex| RenderVertex { position: [vertex.position[0] * 4.0, vertex.position[1] * 4.0, 0.9], ..vertex })
+            .collect();
+
+        let pbr = PbrRenderer::new(display);
+        let sky = Texture::cubemap_from_images(display, &sky_faces(), &TextureSettings { format: TextureFormat::Hdr, ..Default::default() }).unwrap();
+        let environment = pbr.environment(display, &sky).unwrap();
+
+        let mut text = TextRenderer::new(display);
+        let font = text.load_font(display, inner_path!("fonts/DejaVuSansMono.ttf")).unwrap();
+
+        self.scene = Some(Scene {
+            program: glium::Program::from_source(display, vertex_shader.as_str(), prepend(&fragment_shader, &[LIGHTS]).as_str(), None).unwrap(),
+            indices: glium::IndexBuffer::new(display, glium::index::PrimitiveType::TrianglesList, &U32_INDICES).unwrap(),
+            atlas,
+            sprites: SpriteRenderer::new(display),
+            flipbook,
+            text,
+            font,
+            ui: DebugUi::new(display),
+            menu: PauseMenu::new(display),
+            debug: DebugDraw::new(display),
+            lighting: {
+                let mut lighting = Lighting::new(display);
+                lighting.lights = vec![
+                    Light::Directional(DirectionalLight { direction: [0.3, -0.5, 1.0], color: [1.0, 0.95, 0.85], intensity: 0.6, cast_shadows: true }),
+                    Light::Point(PointLight { position: [0.0, 0.0, -0.6], color: [0.3, 0.6, 1.0], intensity: 1.5, range: 2.0 }),
+                    Light::Spot(SpotLight {
+                        position: [-1.0, 1.0, -1.0],
+                        direction: [1.0, -1.0, 1.0],
+                        color: [1.0, 0.4, 0.2],
+                        intensity: 2.0,
+                        range: 4.0,
+                        inner_angle: 0.3,
+                        outer_angle: 0.5,
+                        cast_shadows: true
+                    })
+                ];
+                lighting
+            },
+            materials: [
+                glium::uniforms::UniformBuffer::new(display, Material { color_override: [1f32, 1f32, 1f32], shininess: 32.0, specular: 0.5 }).unwrap(),
+                glium::uniforms::UniformBuffer::new(display, Material { color_override: [1f32, 0f32, 0f32], shininess: 8.0, specular: 0.2 }).unwrap(),
+                glium::uniforms::UniformBuffer::new(display, Material { color_override: [-2f32, 0f32, 0f32], shininess: 64.0, specular: 1.0 }).unwrap()
+            ],
+            shapes,
+            pbr,
+            environment,
+            metal,
+            metal_material: PbrMaterial { base_color_factor: [1.0, 0.78, 0.34, 1.0], ..Default::default() },
+            wall,
+            wall_material: PbrMaterial {
+                base_color_factor: [0.5, 0.5, 0.55, 1.0],
+                metallic_factor: 0.0,
+                roughness_factor: 0.8,
+                shadows: ShadowFlags { cast: false, receive: true },
+                ..Default::default()
+            },
+            shadows: Shadows::new(display, ShadowSettings::default()),
+            tilemap: match Tilemap::load(display, inner_path!("maps/demo.tmx")) {
+                Ok(tilemap) => Some(tilemap),
+                Err(error) => { eprintln!("failed to load the map: {}", error); None }
+            }
+        });
+    }
+
+    fn update(&mut self, context: &mut Context, delta_time: f32) {
+        let scene = self.scene.as_mut().unwrap();
+        scene.ui.scale = context.window.scale_factor() as f32;
+        scene.menu.ui.scale = context.window.scale_factor() as f32;
+        scene.ui.begin(&context.input);
+        scene.menu.ui.update(&context.input);
+        // Clicks and typing that go to a UI don't also pause or quit the demo.
+        let game_input = !scene.ui.wants_mouse() && !scene.ui.wants_keyboard() && !scene.menu.ui.wants_mouse();
+
+        for command in scene.menu.commands.borrow_mut().drain(..) {
+            match command {
+                MenuCommand::Resume => self.paused = false,
+                MenuCommand::ToggleSprites => self.tuning.sprites = !self.tuning.sprites,
+                MenuCommand::Quit => context.exit()
+            }
+        }
+
+        if game_input && context.input.action_pressed("quit") { context.exit(); }
+        // Once paused the menu has the focus, so the pause keys press its buttons instead.
+        if game_input && !self.paused && context.input.action_pressed("pause") { self.paused = true; }
+        if context.input.action_pressed("debug_ui") { scene.ui.visible = !scene.ui.visible; }
+        if context.input.action_pressed("fullscreen") {
+            match self.windowed_mode.take() {
+                Some(window_mode) => context.window.set_mode(window_mode),
+                None => {
+                    self.windowed_mode = Some(context.window.window_mode);
+                    context.window.set_mode(WindowMode::WindowedFullscreen);
+                }
+            }
+            if let Err(error) = context.window.config().save(inner_path!("config/window.toml")) { eprintln!("{}", error); }
+        }
+        if context.input.action_pressed("preview") {
+            match self.preview.take() {
+                Some(handle) => context.windows.close(handle),
+                None => self.preview = Some(context.windows.open(WindowConfig {
+                    title: "Texture preview".to_string(),
+                    window_mode: WindowMode::Normal([400, 400]),
+                    .. WindowConfig::default()
+                }))
+            }
+        }
+        if context.input.action_pressed("record") {
+            if context.capture.is_recording() {
+                context.capture.stop_recording();
+            } else {
+                if let Err(error) = context.capture.start_recording(inner_path!("recordings"), 60) { eprintln!("failed to start recording: {}", error); }
+            }
+        }
+        for error in context.capture.take_errors() { eprintln!("{}", error); }
+        if let Some(handle) = self.preview {
+            if !context.windows.is_open(handle) { self.preview = None; }
+        }
+
+        self.frame_times.push_back(delta_time * 1000.0);
+        if self.frame_times.len() > 120 { self.frame_times.pop_front(); }
+
+        scene.ui.window("Tuning", |ui| {
+            ui.slider("rotation speed", &mut self.tuning.speed, 0.0..=5.0);
+            ui.slider("lights", &mut self.tuning.light_limit, 0.0..=3.0);
+            ui.checkbox("paused", &mut self.paused);
+            ui.checkbox("sprites", &mut self.tuning.sprites);
+            ui.checkbox("debug shapes", &mut self.tuning.debug_shapes);
+            ui.slider("metal roughness", &mut self.tuning.roughness, 0.0..=1.0);
+            ui.checkbox("shadows", &mut self.tuning.shadows);
+            ui.separator();
+            for pass in &mut context.post.passes { ui.checkbox(pass.effect.name(), &mut pass.enabled); }
+            ui.text_field("caption", &mut self.tuning.caption);
+            if ui.button("reset") { self.tuning = Tuning::default(); }
+            ui.separator();
+            ui.plot("frame ms", self.frame_times.iter().copied(), None);
+        });
+
+        let menu = &mut scene.menu;
+        if self.paused && !menu.ui.is_visible(menu.panel) { menu.ui.focus(Some(menu.resume)); }
+        menu.ui.set_visible(menu.panel, self.paused);
+        menu.ui.set_text(menu.sprites, if self.tuning.sprites { "Hide sprites" } else { "Show sprites" });
+
+        self.animator.play(&scene.flipbook);
+        self.animator.paused = self.paused;
+        self.animator.update(delta_time);
+
+        let spin = if game_input { context.input.axis("spin") } else { 0.0 };
+        let speed = if self.paused { 0.0 } else { self.tuning.speed } + spin;
+        let rotation = speed * delta_time;
+
+        let [shape, shape2, shape3] = &mut scene.shapes;
+        shape.rotate([0.0, rotation, 0.0].into(), get_position(shape).into());
+        shape2.rotate([0.0, rotation, 0.0].into(), [0.0, 0.0, 0.0].into());
+        shape3.rotate([rotation, 0.0, 0.0].into(), get_position(shape3).into());
+        scene.metal.rotate([0.0, rotation, 0.0].into(), get_position(&scene.metal).into());
+        scene.metal_material.roughness_factor = self.tuning.roughness;
+
+        scene.debug.enabled = self.tuning.debug_shapes;
+        scene.debug.update(delta_time);
+        for shape in &scene.shapes {
+            let (min, max) = shape.iter().fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), vertex| {
+                (std::array::from_fn(|axis| min[axis].min(vertex.position[axis])), std::array::from_fn(|axis| max[axis].max(vertex.position[axis])))
+            });
+            scene.debug.aabb(min, max, [1.0, 0.9, 0.2, 1.0]);
+            scene.debug.point(get_position(shape), 0.1, DebugStyle::from([1.0, 1.0, 1.0, 1.0]).on_top());
+        }
+        scene.debug.axes([0.0, 0.0, 0.0], 0.25);
+
+        self.time += delta_time;
+        scene.lighting.max_lights = self.tuning.light_limit.round() as usize;
+        for light in &mut scene.lighting.lights {
+            match light {
+                Light::Point(point) => {
+                    point.position = [self.time.cos() * 0.8, self.time.sin() * 0.8, -0.6];
+                    scene.debug.sphere(point.position, 0.05, [point.color[0], point.color[1], point.color[2], 1.0]);
+                },
+                Light::Spot(spot) => {
+                    let to = [spot.position[0] + spot.direction[0] * 0.3, spot.position[1] + spot.direction[1] * 0.3, spot.position[2] + spot.direction[2] * 0.3];
+                    scene.debug.arrow(spot.position, to, [spot.color[0], spot.color[1], spot.color[2], 1.0]);
+                    spot.cast_shadows = self.tuning.shadows;
+                },
+                Light::Directional(directional) => directional.cast_shadows = self.tuning.shadows
+            }
+        }
+    }
+
+    fn render<S: Surface>(&mut self, context: &mut Context, target: &mut S) {
+        let display = &context.window.display;
+        let scene = self.scene.as_mut().unwrap();
+
+        let params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: true,
+                .. Default::default()
+            },
+            blend: glium::draw_parameters::Blend::alpha_blending(),
+            .. Default::default()
+        };
+
+        target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
+
+        let (width, height) = target.get_dimensions();
+        let shapes: Vec<_> = scene.shapes.iter().map(|shape| glium::VertexBuffer::new(display, shape).unwrap()).collect();
+        let metal = glium::VertexBuffer::new(display, &scene.metal).unwrap();
+        let wall = glium::VertexBuffer::new(display, &scene.wall).unwrap();
+
+        scene.lighting.upload(CAMERA_POSITION);
+        // Clip space seen from CAMERA_POSITION, where the quads at z = 0 fill the height of the screen.
+        let view = ShadowView {
+            position: CAMERA_POSITION,
+            forward: [0.0, 0.0, 1.0],
+            up: [0.0, 1.0, 0.0],
+            fov_y: 2.0 * (1.0f32 / 3.0).atan(),
+            aspect: width as f32 / height as f32,
+            near: 1.0,
+            far: 6.0
+        };
+        scene.shadows.render(display, &scene.lighting, &view, |pass| {
+            for shape in &shapes { let _ = pass.draw(shape, &scene.indices, ShadowFlags::default()); }
+            let _ = pass.draw(&metal, &scene.indices, scene.metal_material.shadows);
+            let _ = pass.draw(&wall, &scene.indices, scene.wall_material.shadows);
+        });
+
+        let pbr_scene = PbrScene {
+            view_projection: IDENTITY,
+            camera_position: CAMERA_POSITION,
+            lighting: &scene.lighting,
+            environment: Some(&scene.environment),
+            shadows: Some(&scene.shadows)
+        };
+        let _ = scene.pbr.draw(target, &wall, &scene.indices, &scene.wall_material, &pbr_scene, &params);
+        for (shape, material) in shapes.iter().zip(&scene.materials) {
+            let uniforms = uniform! {
+                texture_2d: &scene.atlas.texture,
+                Material: material,
+                Lights: scene.lighting.buffer(),
+                Shadows: scene.shadows.buffer(),
+                cascade_maps: scene.shadows.cascade_maps(),
+                spot_maps: scene.shadows.spot_maps(),
+                receive_shadows: true,
+                view_projection: IDENTITY,
+                camera_position: CAMERA_POSITION
+            };
+
+            let _ = target.draw(shape, &scene.indices, &scene.program, &uniforms, &params);
+        }
+        let _ = scene.pbr.draw(target, &metal, &scene.indices, &scene.metal_material, &pbr_scene, &params);
+        // The quads are given in clip space, so there is no camera to draw the debug shapes with.
+        scene.debug.render(display, target, IDENTITY);
+
+        // The map twice as large in the top left corner of the screen.
+        if let Some(tilemap) = &scene.tilemap {
+            let zoom = 2.0;
+            let position = [(width as f32 / 2.0 - 8.0) / zoom, tilemap.map.pixel_size()[1] - (height as f32 / 2.0 - 8.0) / zoom];
+            tilemap.render(target, &Camera2d { position, zoom, rotation: 0.0 });
+        }
+
+        // A row of small sprites along the bottom of the screen, tinted from white to red.
+        let mut batch = SpriteBatch::new();
+        let row = if self.tuning.sprites { 16 } else { 0 };
+        for (index, region) in scene.atlas.regions.values().cycle().take(row).enumerate() {
+            let tint = index as f32 / 15.0;
+            batch.draw(&scene.atlas.texture, Sprite {
+                scale: [32.0 / region.width as f32, 32.0 / region.height as f32],
+                color: [1.0, 1.0 - tint, 1.0 - tint, 1.0],
+                origin: [0.0, 0.0],
+                .. Sprite::from_region([8.0 + index as f32 * 40.0, 8.0], region)
+            });
+        }
+
+        let mut flipbook = Sprite { origin: [1.0, 0.0], layer: 1, .. Sprite::new([width as f32 - 8.0, 8.0], [64.0, 64.0]) };
+        self.animator.apply(&mut flipbook);
+        batch.draw(&scene.atlas.texture, flipbook);
+
+        let camera = Camera2d::screen([width, height]);
+        scene.sprites.render(display, target, &camera, &mut batch);
+    }
+
+    /// Text and UIs go on top of the post-processed scene, so tonemapping doesn't dim them.
+    fn render_overlay<S: Surface>(&mut self, context: &mut Context, target: &mut S) {
+        let display = &context.window.display;
+        let scene = self.scene.as_mut().unwrap();
+        let (width, height) = target.get_dimensions();
+        let camera = Camera2d::screen([width, height]);
+
+        let status = if context.capture.is_recording() { "recording" } else if self.paused { "paused" } else { "" };
+        let help = format!("F1 debug UI  F2 preview  F9 record  F12 screenshot\n{}\n{}", status, self.tuning.caption);
+        scene.text.draw(scene.font, &help, [8.0, height as f32 - 8.0], &TextStyle::default());
+        scene.text.render(display, target, &camera);
+
+        scene.menu.ui.render(display, target);
+        scene.ui.render(display, target);
+    }
+
+    /// The preview window draws the atlas loaded through the main window's display.
+    fn render_window<S: Surface>(&mut self, context: &mut Context, _window: WindowHandle, target: &mut S) {
+        let display = &context.window.display;
+        let scene = self.scene.as_ref().unwrap();
+
+        target.clear_color_and_depth((0.1, 0.1, 0.1, 1.0), 1.0);
+
+        for (region, offset) in scene.atlas.regions.values().zip([[-0.5, 0.0], [0.5, 0.0]]) {
+            let vertex_buffer = glium::VertexBuffer::new(display, &quad(offset, region)).unwrap();
+            let uniforms = uniform! {
+                texture_2d: &scene.atlas.texture,
+                Material: &scene.materials[0],
+                Lights: scene.lighting.buffer(),
+                Shadows: scene.shadows.buffer(),
+                cascade_maps: scene.shadows.cascade_maps(),
+                spot_maps: scene.shadows.spot_maps(),
+                receive_shadows: false,
+                view_projection: IDENTITY,
+                camera_position: CAMERA_POSITION
+            };
+
+            let _ = target.draw(&vertex_buffer, &scene.indices, &scene.program, &uniforms, &Default::default());
+        }
+    }
+
+    fn shutdown(&mut self, context: &mut Context) {
+        for error in context.capture.take_errors() { eprintln!("{}", error); }
+    }
+}
 
 fn main() {
     std::env::set_var("RUST_BACKTRACE", "full");
-    graphics::window::Window::new(None);
-}
\ No newline at end of file
+
+    let mut config = WindowConfig::load_or_default(inner_path!("config/window.toml")).unwrap();
+    config.apply_args(std::env::args().skip(1)).unwrap();
+
+    Engine::run(config, Demo::default()).unwrap();
+}